@@ -0,0 +1,422 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Method;
+use serde::Deserialize;
+use crate::feeds::Feed;
+use tracing::{debug, warn};
+
+// API Files
+// `.magic/api/` mounts REST APIs as files. ~/.eidetic/api.toml lists them, one `[[api]]` per
+// file: the URL to request, the HTTP method, headers, and how long a response is good for.
+// Reading the file returns the response body, cached in `.eidetic/cache/api/` and requested
+// again once older than `refresh_minutes`; if that fails the last good response is served.
+// Without the file the single built-in `bitcoin.json` is mounted.
+//
+// Writing to a file sends what was written as the body of a `write_method` request (POST by
+// default) once the file is closed, and reads return that request's response for a while
+// (REPLY_HOLD at least), so `echo '{...}' > api/x.json; cat api/x.json` works from scripts.
+//
+//   requests_per_minute = 30            # across all endpoints, the default
+//
+//   [[api]]
+//   name = "notifications.json"
+//   url = "https://api.github.com/notifications?per_page={env:GH_PAGE_SIZE}"
+//   method = "GET"                      # the default
+//   refresh_minutes = 10                # 5 by default
+//   write_method = "PUT"                # POST by default
+//   [api.headers]
+//   Authorization = "Bearer {keyring:github}"
+//
+// `{keyring:<name>}` in the URL or a header value is the secret stored under <name> in the
+// system keyring (`eidetic secret <name>`), `{env:<VAR>}` an environment variable of the
+// daemon, so tokens needn't be written into the file. `[[feed]]` entries add RSS and Atom
+// feeds as markdown digests next to them (see feeds.rs).
+//
+// So that `grep -r` over the mount can't hammer the services behind the files, requests are
+// limited to `requests_per_minute` in all; past that, reads get the cached response however
+// old, or a note saying when to try again. A fresh response is served straight from the FUSE
+// thread; only a read that needs a request gets a thread of its own, and reads of a file
+// waiting on the same request share it.
+
+/// The keyring service API secrets are stored under.
+pub const KEYRING_SERVICE: &str = "eidetic";
+
+const DEFAULT_REFRESH_MINUTES: u64 = 5;
+
+const DEFAULT_REQUESTS_PER_MINUTE: usize = 30;
+
+/// How long the response to a written body is served at least, however short `refresh_minutes`.
+const REPLY_HOLD: Duration = Duration::from_secs(60);
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+const DEFAULT_APIS: &str = r#"
+[[api]]
+name = "bitcoin.json"
+url = "https://api.coinbase.com/v2/prices/BTC-USD/spot"
+"#;
+
+#[derive(Deserialize)]
+struct ApiFile {
+    #[serde(default = "default_requests_per_minute")]
+    requests_per_minute: usize,
+    #[serde(default)]
+    api: Vec<Endpoint>,
+    #[serde(default)]
+    feed: Vec<Feed>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Endpoint {
+    /// The file's name in `.magic/api/`
+    pub name: String,
+    url: String,
+    #[serde(default = "default_method")]
+    method: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default = "default_refresh")]
+    refresh_minutes: u64,
+    #[serde(default = "default_write_method")]
+    write_method: String,
+}
+
+fn default_requests_per_minute() -> usize {
+    DEFAULT_REQUESTS_PER_MINUTE
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_write_method() -> String {
+    "POST".to_string()
+}
+
+fn default_refresh() -> u64 {
+    DEFAULT_REFRESH_MINUTES
+}
+
+#[derive(Clone)]
+struct Response {
+    at: SystemTime,
+    body: Vec<u8>,
+    /// To a written body rather than the endpoint's own request
+    reply: bool,
+}
+
+// Last response of each endpoint by name, mirroring the cache folder
+static RESPONSES: Mutex<BTreeMap<String, Response>> = Mutex::new(BTreeMap::new());
+
+// Bodies being written, by name, sent once the file is closed
+static BODIES: Mutex<BTreeMap<String, Vec<u8>>> = Mutex::new(BTreeMap::new());
+
+// Requests under way per name; reads of that file wait for them rather than start their own
+static IN_FLIGHT: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+static LANDED: Condvar = Condvar::new();
+
+// When the requests of the last RATE_WINDOW were made, across all endpoints
+static RECENT: Mutex<VecDeque<Instant>> = Mutex::new(VecDeque::new());
+
+pub fn path() -> PathBuf {
+    crate::config::Config::path().with_file_name("api.toml")
+}
+
+// api.toml, read on every call so edits show up without a remount; no endpoints if it's invalid
+fn load() -> ApiFile {
+    let path = path();
+    let parsed = if path.exists() {
+        std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|text| parse(&text))
+    } else {
+        parse(DEFAULT_APIS)
+    };
+    parsed.unwrap_or_else(|e| {
+        warn!(?path, error = %e, "invalid API config");
+        ApiFile { requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE, api: Vec::new(), feed: Vec::new() }
+    })
+}
+
+fn parse(text: &str) -> Result<ApiFile> {
+    let file: ApiFile = toml::from_str(text)?;
+    let mut names = HashSet::new();
+    for name in file.api.iter().map(|endpoint| &endpoint.name).chain(file.feed.iter().map(|feed| &feed.name)) {
+        if name.is_empty() || name.contains('/') || name.starts_with('.') {
+            bail!("{:?} can't be a file name", name);
+        }
+        if !names.insert(name.as_str()) {
+            bail!("{:?} is listed twice", name);
+        }
+    }
+    for endpoint in &file.api {
+        for method in [&endpoint.method, &endpoint.write_method] {
+            Method::from_bytes(method.to_uppercase().as_bytes()).with_context(|| format!("{:?} isn't an HTTP method", method))?;
+        }
+    }
+    Ok(file)
+}
+
+/// The endpoints in api.toml.
+pub fn endpoints() -> Vec<Endpoint> {
+    load().api
+}
+
+pub fn find(name: &str) -> Option<Endpoint> {
+    endpoints().into_iter().find(|endpoint| endpoint.name == name)
+}
+
+/// The feeds in api.toml.
+pub fn feeds() -> Vec<Feed> {
+    load().feed
+}
+
+pub fn find_feed(name: &str) -> Option<Feed> {
+    feeds().into_iter().find(|feed| feed.name == name)
+}
+
+/// Size of the last response to `name`, 0 before the first (the files are read with direct I/O).
+pub fn cached_len(name: &str) -> u64 {
+    RESPONSES.lock().unwrap().get(name).map_or(0, |response| response.body.len() as u64)
+}
+
+/// Puts what was written at `offset` into the body of the next request to `name`, over whatever
+/// was there; chunks may come in any order.
+pub fn write(name: &str, offset: usize, data: &[u8]) {
+    let mut bodies = BODIES.lock().unwrap();
+    let body = bodies.entry(name.to_string()).or_default();
+    let end = offset + data.len();
+    if body.len() < end {
+        body.resize(end, 0);
+    }
+    body[offset..end].copy_from_slice(data);
+}
+
+/// `>` truncating the file ahead of a write. Truncating with nothing written after it (`: > file`)
+/// still counts as writing: closing the file sends a request with an empty body.
+pub fn truncate(name: &str, size: usize) {
+    BODIES.lock().unwrap().entry(name.to_string()).or_default().truncate(size);
+}
+
+/// Sends what was written to `endpoint` since it was opened, if anything (an empty body when it
+/// was only truncated), on a thread of its own; reads wait for the response. Counts against the rate limit but is never held back,
+/// being something asked for explicitly.
+pub fn send(endpoint: &Endpoint) {
+    let Some(body) = BODIES.lock().unwrap().remove(&endpoint.name) else { return };
+    *IN_FLIGHT.lock().unwrap().entry(endpoint.name.clone()).or_default() += 1;
+    RECENT.lock().unwrap().push_back(Instant::now());
+    let endpoint = endpoint.clone();
+    thread::spawn(move || {
+        let reply = match request(&endpoint, Some(body)) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(name = %endpoint.name, error = %e, "API write failed");
+                format!("Eidetic couldn't send to {}\n{:#}\n", endpoint.name, e).into_bytes()
+            }
+        };
+        RESPONSES.lock().unwrap().insert(endpoint.name.clone(), Response { at: SystemTime::now(), body: reply, reply: true });
+        landed(&endpoint.name);
+    });
+}
+
+fn landed(name: &str) {
+    let mut in_flight = IN_FLIGHT.lock().unwrap();
+    if let Some(count) = in_flight.get_mut(name) {
+        *count -= 1;
+        if *count == 0 {
+            in_flight.remove(name);
+        }
+    }
+    LANDED.notify_all();
+}
+
+/// The last response to `endpoint` while it's fresh, without waiting on anything; None if a
+/// read needs to go through `response`.
+pub fn fresh(source_path: &Path, endpoint: &Endpoint) -> Option<Vec<u8>> {
+    if IN_FLIGHT.lock().unwrap().contains_key(&endpoint.name) {
+        return None;
+    }
+    let last = last(source_path, endpoint)?;
+    is_fresh(&last, endpoint).then_some(last.body)
+}
+
+/// What `endpoint` responds with, requested again once the last response is older than its
+/// refresh interval and the rate limit allows. Blocks; keep it off the FUSE thread.
+pub fn response(source_path: &Path, endpoint: &Endpoint) -> Vec<u8> {
+    // IN_FLIGHT is only held to check and claim, never across the cache read: `fresh` takes it
+    // on the FUSE thread
+    let last = loop {
+        wait_for(&endpoint.name);
+        let last = last(source_path, endpoint);
+        if let Some(last) = last.as_ref().filter(|last| is_fresh(last, endpoint)) {
+            return last.body.clone();
+        }
+        if crate::http::offline() {
+            return match last {
+                Some(last) => last.body,
+                None => format!("Offline mode is on and {} hasn't been requested before.\n", endpoint.name).into_bytes(),
+            };
+        }
+        let per_minute = load().requests_per_minute;
+        if let Err(wait) = take_slot(per_minute) {
+            return match last {
+                Some(last) => {
+                    debug!(name = %endpoint.name, "API rate limit reached, serving the cached response");
+                    last.body
+                }
+                None => format!("Eidetic holds requests to .magic/api/ back to {} a minute (requests_per_minute in {:?}).\nTry again in {} seconds.\n", per_minute, path(), wait.as_secs() + 1).into_bytes(),
+            };
+        }
+        // Another read may have started a request since; its response is the one to wait for
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        if !in_flight.contains_key(&endpoint.name) {
+            in_flight.insert(endpoint.name.clone(), 1);
+            break last;
+        }
+    };
+
+    let started = SystemTime::now();
+    let body = match request(endpoint, None) {
+        Ok(body) => {
+            debug!(name = %endpoint.name, bytes = body.len(), "API response");
+            store(source_path, endpoint, &body, started);
+            body
+        }
+        Err(e) => match last {
+            Some(last) => {
+                warn!(name = %endpoint.name, error = %e, "API request failed, serving the cached response");
+                last.body
+            }
+            None => {
+                warn!(name = %endpoint.name, error = %e, "API request failed");
+                format!("Eidetic couldn't request {}\n{:#}\nIt will be requested again on the next read.\n", endpoint.name, e).into_bytes()
+            }
+        },
+    };
+    landed(&endpoint.name);
+    body
+}
+
+// Until no request to `name` is under way
+fn wait_for(name: &str) {
+    let mut in_flight = IN_FLIGHT.lock().unwrap();
+    while in_flight.contains_key(name) {
+        in_flight = LANDED.wait(in_flight).unwrap();
+    }
+}
+
+fn is_fresh(response: &Response, endpoint: &Endpoint) -> bool {
+    let refresh = Duration::from_secs(endpoint.refresh_minutes * 60);
+    let max_age = if response.reply { refresh.max(REPLY_HOLD) } else { refresh };
+    response.at.elapsed().is_ok_and(|age| age < max_age)
+}
+
+/// Counts one background request against the rate limit; false if there's no room for it.
+pub fn allow_request() -> bool {
+    take_slot(load().requests_per_minute).is_ok()
+}
+
+// Makes room for one more request within the rate limit, or says how long until there is some
+fn take_slot(per_minute: usize) -> Result<(), Duration> {
+    let mut recent = RECENT.lock().unwrap();
+    while recent.front().is_some_and(|at| at.elapsed() >= RATE_WINDOW) {
+        recent.pop_front();
+    }
+    if recent.len() >= per_minute {
+        return Err(recent.front().map_or(RATE_WINDOW, |oldest| RATE_WINDOW.saturating_sub(oldest.elapsed())));
+    }
+    recent.push_back(Instant::now());
+    Ok(())
+}
+
+// Named after the endpoint's name and URL, so a changed URL doesn't get the old one's response
+fn cache_path(source_path: &Path, endpoint: &Endpoint) -> PathBuf {
+    let key = format!("{}\n{}", endpoint.name, endpoint.url);
+    source_path.join(".eidetic/cache/api").join(blake3::hash(key.as_bytes()).to_hex().as_str())
+}
+
+// The last response in memory, or else from the cache folder (e.g. after a restart)
+fn last(source_path: &Path, endpoint: &Endpoint) -> Option<Response> {
+    if let Some(response) = RESPONSES.lock().unwrap().get(&endpoint.name) {
+        return Some(response.clone());
+    }
+    let cached = cache_path(source_path, endpoint);
+    let at = std::fs::metadata(&cached).and_then(|metadata| metadata.modified()).ok()?;
+    let response = Response { at, body: std::fs::read(&cached).ok()?, reply: false };
+    RESPONSES.lock().unwrap().insert(endpoint.name.clone(), response.clone());
+    Some(response)
+}
+
+// Keeps a response unless a reply to a written body came in while it was requested
+fn store(source_path: &Path, endpoint: &Endpoint, body: &[u8], started: SystemTime) {
+    {
+        let mut responses = RESPONSES.lock().unwrap();
+        if responses.get(&endpoint.name).is_some_and(|newer| newer.reply && newer.at > started) {
+            return;
+        }
+        responses.insert(endpoint.name.clone(), Response { at: SystemTime::now(), body: body.to_vec(), reply: false });
+    }
+    let cached = cache_path(source_path, endpoint);
+    let partial = cached.with_extension("part");
+    let stored = std::fs::create_dir_all(cached.parent().unwrap_or(source_path))
+        .and_then(|_| std::fs::write(&partial, body))
+        .and_then(|_| std::fs::rename(&partial, &cached));
+    if let Err(e) = stored {
+        warn!(name = %endpoint.name, error = %e, "failed to cache API response");
+    }
+}
+
+// The endpoint's own request, or with a body its `write_method` request. The response to a
+// body is returned whatever the status, since APIs explain what they rejected in it
+fn request(endpoint: &Endpoint, body: Option<Vec<u8>>) -> Result<Vec<u8>> {
+    crate::http::ensure_online()?;
+    let method = if body.is_some() { &endpoint.write_method } else { &endpoint.method };
+    let mut request = crate::http::client().request(Method::from_bytes(method.to_uppercase().as_bytes())?, expand(&endpoint.url)?);
+    for (name, value) in &endpoint.headers {
+        request = request.header(name.as_str(), expand(value)?);
+    }
+    let Some(body) = body else {
+        return Ok(request.send()?.error_for_status()?.bytes()?.to_vec());
+    };
+    if !endpoint.headers.keys().any(|name| name.eq_ignore_ascii_case(CONTENT_TYPE.as_str())) && serde_json::from_slice::<serde_json::Value>(&body).is_ok() {
+        request = request.header(CONTENT_TYPE, "application/json");
+    }
+    let response = request.body(body).send()?;
+    if !response.status().is_success() {
+        warn!(name = %endpoint.name, status = %response.status(), "API write rejected");
+    }
+    Ok(response.bytes()?.to_vec())
+}
+
+/// Fills in `{keyring:<name>}` and `{env:<VAR>}`.
+pub fn expand(template: &str) -> Result<String> {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\{(keyring|env):([^}]+)\}").expect("placeholder pattern"));
+    let mut expanded = String::new();
+    let mut rest = 0;
+    for captures in placeholder.captures_iter(template) {
+        let whole = captures.get(0).expect("whole match");
+        let key = &captures[2];
+        let value = match &captures[1] {
+            "keyring" => keyring::Entry::new(KEYRING_SERVICE, key)?
+                .get_password()
+                .with_context(|| format!("No secret {:?} in the keyring (`eidetic secret {}` stores one)", key, key))?,
+            _ => std::env::var(key).with_context(|| format!("${} isn't set", key))?,
+        };
+        expanded.push_str(&template[rest..whole.start()]);
+        expanded.push_str(&value);
+        rest = whole.end();
+    }
+    expanded.push_str(&template[rest..]);
+    Ok(expanded)
+}
+
+/// Stores `secret` in the keyring for `{keyring:<name>}`.
+pub fn store_secret(name: &str, secret: &str) -> Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, name)?.set_password(secret)?;
+    Ok(())
+}
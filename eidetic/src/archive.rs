@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+
+// Archive Browsing
+// `backup.zip#` (AVFS-style suffix) is a read-only directory of the archive's contents.
+// Only the member list is read up front; a member is stream-extracted into the cache the
+// first time it's read. zip and tar(.gz) are handled in-process, 7z through the `7z` CLI.
+
+/// Appended to an archive's name to browse it as a directory.
+pub const BROWSE_SUFFIX: char = '#';
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+    SevenZip,
+}
+
+fn kind(name: &str) -> Option<Kind> {
+    let name = name.to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Kind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(Kind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Kind::TarGz)
+    } else if name.ends_with(".7z") {
+        Some(Kind::SevenZip)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(name: &str) -> bool {
+    kind(name).is_some()
+}
+
+pub struct Member {
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// Member list of one archive, keyed by normalized path ("dir/file.txt").
+#[derive(Default)]
+pub struct ArchiveIndex {
+    members: BTreeMap<String, Member>,
+}
+
+impl ArchiveIndex {
+    pub fn get(&self, path: &str) -> Option<&Member> {
+        self.members.get(path)
+    }
+
+    /// Direct children of `dir` ("" for the archive root) as (path, member).
+    pub fn children(&self, dir: &str) -> Vec<(&str, &Member)> {
+        let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+        self.members.range(prefix.clone()..)
+            .take_while(|(path, _)| path.starts_with(&prefix))
+            .filter(|(path, _)| !path[prefix.len()..].contains('/'))
+            .map(|(path, member)| (path.as_str(), member))
+            .collect()
+    }
+
+    fn insert(&mut self, raw_path: &str, size: u64, is_dir: bool) {
+        let Some(path) = normalize(raw_path) else { return };
+        // Archives don't always list parent directories explicitly
+        let mut parent = path.as_str();
+        while let Some((dir, _)) = parent.rsplit_once('/') {
+            self.members.entry(dir.to_string()).or_insert(Member { size: 0, is_dir: true });
+            parent = dir;
+        }
+        self.members.insert(path, Member { size, is_dir });
+    }
+}
+
+// "./a//b/" -> "a/b"; entries escaping the archive root are dropped
+fn normalize(path: &str) -> Option<String> {
+    let parts: Vec<&str> = path.split(['/', '\\']).filter(|p| !p.is_empty() && *p != ".").collect();
+    if parts.is_empty() || parts.contains(&"..") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+pub fn index(archive: &Path) -> Result<ArchiveIndex> {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let mut index = ArchiveIndex::default();
+    match kind(&name).ok_or_else(|| anyhow!("Not an archive: {:?}", archive))? {
+        Kind::Zip => {
+            let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+            for i in 0..zip.len() {
+                let entry = zip.by_index_raw(i)?;
+                index.insert(entry.name(), entry.size(), entry.is_dir());
+            }
+        }
+        Kind::Tar | Kind::TarGz => {
+            let mut tar = tar::Archive::new(tar_reader(archive)?);
+            for entry in tar.entries()? {
+                let entry = entry?;
+                let header = entry.header();
+                let path = entry.path()?.to_string_lossy().to_string();
+                let entry_type = header.entry_type();
+                if entry_type.is_dir() || entry_type.is_file() {
+                    index.insert(&path, header.size()?, entry_type.is_dir());
+                }
+            }
+        }
+        Kind::SevenZip => {
+            // `-slt` prints one "Key = Value" block per member
+            let output = seven_zip(&["l", "-slt", "-ba"], archive, &[], Stdio::piped())?;
+            for block in String::from_utf8_lossy(&output).split("\n\n") {
+                let field = |key: &str| block.lines().find_map(|l| l.strip_prefix(key)).map(str::trim);
+                if let Some(path) = field("Path = ") {
+                    let is_dir = field("Folder = ") == Some("+") || field("Attributes = ").is_some_and(|a| a.starts_with('D'));
+                    index.insert(path, field("Size = ").and_then(|s| s.parse().ok()).unwrap_or(0), is_dir);
+                }
+            }
+        }
+    }
+    Ok(index)
+}
+
+/// Streams one member out of the archive into `target`.
+pub fn extract(archive: &Path, member: &str, target: &Path) -> Result<()> {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    match kind(&name).ok_or_else(|| anyhow!("Not an archive: {:?}", archive))? {
+        Kind::Zip => {
+            let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i)?;
+                if normalize(entry.name()).as_deref() == Some(member) {
+                    std::io::copy(&mut entry, &mut File::create(target)?)?;
+                    return Ok(());
+                }
+            }
+        }
+        Kind::Tar | Kind::TarGz => {
+            let mut tar = tar::Archive::new(tar_reader(archive)?);
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                if normalize(&entry.path()?.to_string_lossy()).as_deref() == Some(member) {
+                    std::io::copy(&mut entry, &mut File::create(target)?)?;
+                    return Ok(());
+                }
+            }
+        }
+        Kind::SevenZip => {
+            let result = seven_zip(&["x", "-so"], archive, &[member], Stdio::from(File::create(target)?));
+            if result.is_err() {
+                let _ = std::fs::remove_file(target);
+            }
+            return result.map(|_| ());
+        }
+    }
+    Err(anyhow!("{} not found in {:?}", member, archive))
+}
+
+fn tar_reader(archive: &Path) -> Result<Box<dyn std::io::Read>> {
+    let file = BufReader::new(File::open(archive)?);
+    let name = archive.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    Ok(if name.ends_with(".tar") { Box::new(file) } else { Box::new(GzDecoder::new(file)) })
+}
+
+fn seven_zip(args: &[&str], archive: &Path, members: &[&str], stdout: Stdio) -> Result<Vec<u8>> {
+    let output = Command::new("7z")
+        .args(args)
+        .arg(archive)
+        .args(members)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| anyhow!("7z: {} (install p7zip to browse .7z files)", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("7z failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,95 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use fuser::MountOption;
+use crate::config::Config;
+use crate::convert::Registry;
+use crate::fs::EideticFS;
+use crate::queue::JobQueue;
+
+// Benchmark
+// `eidetic bench` mounts a throwaway source folder in /tmp with the default configuration and
+// times the operations the mount sees most: creating and writing files, lookups, directory
+// listings and reads. No worker runs, so analysis doesn't compete with the measurement; written
+// files still go through the job queue and the database like on a real mount. Prints throughput
+// and latency percentiles per operation, to compare releases on the same machine.
+
+/// Files are spread over this many directories.
+const DIRS: usize = 10;
+
+/// Each directory is listed this many times.
+const LISTINGS: usize = 20;
+
+pub fn run(files: usize, size: usize) -> Result<String> {
+    let root = std::env::temp_dir().join(format!("eidetic-bench-{}", std::process::id()));
+    let (source, mountpoint) = (root.join("source"), root.join("mount"));
+    std::fs::create_dir_all(&source)?;
+    std::fs::create_dir_all(&mountpoint)?;
+    let report = mount_and_measure(&source, &mountpoint, files, size);
+    let _ = std::fs::remove_dir_all(&root);
+    report
+}
+
+fn mount_and_measure(source: &Path, mountpoint: &Path, files: usize, size: usize) -> Result<String> {
+    let config = Config::default();
+    let converters = Registry::new(&config)?;
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let fs = EideticFS::new(source.to_path_buf(), uid, gid, JobQueue::new(), converters, None, &config);
+    let options = [MountOption::FSName("eidetic-bench".to_string()), MountOption::AutoUnmount];
+    // Unmounted when dropped
+    let _session = fuser::spawn_mount2(fs, mountpoint, &options).context("Failed to mount the benchmark folder")?;
+
+    let data = vec![b'x'; size];
+    let paths: Vec<_> = (0..files).map(|i| mountpoint.join(format!("dir{}", i % DIRS)).join(format!("file{}.txt", i))).collect();
+    for dir in 0..DIRS {
+        std::fs::create_dir(mountpoint.join(format!("dir{}", dir)))?;
+    }
+
+    let mut report = format!("{} files of {} bytes in {} directories\n\n", files, size, DIRS);
+    report.push_str(&format!("{:<8} {:>7} {:>10} {:>10} {:>10} {:>10} {:>10}\n", "op", "count", "ops/s", "p50", "p95", "p99", "max"));
+    let write = measure(&paths, |path| {
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(&data)
+    })?;
+    report.push_str(&row("write", write));
+    report.push_str(&row("lookup", measure(&paths, |path| std::fs::metadata(path).map(|_| ()))?));
+    let dirs: Vec<_> = (0..DIRS * LISTINGS).map(|i| mountpoint.join(format!("dir{}", i % DIRS))).collect();
+    report.push_str(&row("readdir", measure(&dirs, |dir| std::fs::read_dir(dir)?.try_for_each(|entry| entry.map(|_| ())))?));
+    report.push_str(&row("read", measure(&paths, |path| std::fs::read(path).map(|_| ()))?));
+    Ok(report)
+}
+
+// Latency of `op` on each of `targets`, in order
+fn measure<T: AsRef<Path>>(targets: &[T], mut op: impl FnMut(&Path) -> std::io::Result<()>) -> Result<Vec<Duration>> {
+    targets.iter()
+        .map(|target| {
+            let start = Instant::now();
+            op(target.as_ref()).with_context(|| format!("{:?}", target.as_ref()))?;
+            Ok(start.elapsed())
+        })
+        .collect()
+}
+
+fn row(op: &str, mut latencies: Vec<Duration>) -> String {
+    if latencies.is_empty() {
+        return format!("{:<8} {:>7}\n", op, 0);
+    }
+    latencies.sort();
+    let total: Duration = latencies.iter().sum();
+    let percentile = |p: f64| micros(latencies[((latencies.len() - 1) as f64 * p).round() as usize]);
+    format!(
+        "{:<8} {:>7} {:>10.0} {:>10} {:>10} {:>10} {:>10}\n",
+        op,
+        latencies.len(),
+        latencies.len() as f64 / total.as_secs_f64().max(f64::EPSILON),
+        percentile(0.5),
+        percentile(0.95),
+        percentile(0.99),
+        micros(latencies[latencies.len() - 1]),
+    )
+}
+
+fn micros(duration: Duration) -> String {
+    format!("{:.1}µs", duration.as_secs_f64() * 1e6)
+}
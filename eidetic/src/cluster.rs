@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use crate::db::Database;
+use crate::throttle::IoPriority;
+use tracing::{error, warn};
+
+// Topic Clusters
+// Every `[embeddings] cluster_interval_hours` a low-priority thread groups the embedded
+// documents with k-means over their mean chunk vector and names each group after the
+// words that set its members apart (from file names, summaries and the start of the
+// text). The result is `.magic/clusters/<label>/`, a thematic view of folders nobody sorted.
+
+/// Fewer embedded documents than this aren't worth clustering.
+const MIN_DOCUMENTS: usize = 4;
+
+const MAX_CLUSTERS: usize = 20;
+
+const ITERATIONS: usize = 25;
+
+/// Bytes of each document read for labelling.
+const LABEL_SAMPLE: u64 = 4096;
+
+/// Words joined into a label: "invoice-payment"
+const LABEL_WORDS: usize = 2;
+
+pub fn start(source_path: PathBuf, interval_hours: u64) {
+    if interval_hours == 0 {
+        return;
+    }
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        crate::throttle::lower_priority(19, IoPriority::Idle);
+
+        loop {
+            if let Err(e) = update(&db, &source_path) {
+                warn!(error = %e, "clustering failed");
+            }
+            thread::sleep(Duration::from_secs(interval_hours * 3600));
+        }
+    });
+}
+
+/// Recomputes every cluster and its label.
+pub fn update(db: &Database, source_path: &Path) -> anyhow::Result<()> {
+    let documents = db.get_document_vectors(crate::minilm::Embedder::MODEL)?;
+    if documents.len() < MIN_DOCUMENTS {
+        return Ok(db.set_clusters(&[])?);
+    }
+    let vectors: Vec<Vec<f32>> = documents.iter().map(|(_, _, v)| normalized(v.clone())).collect();
+    let k = ((documents.len() as f64 / 2.0).sqrt().round() as usize).clamp(2, MAX_CLUSTERS);
+    let assignment = kmeans(&vectors, k);
+
+    // Document frequency of each word, per cluster and overall
+    let words: Vec<HashSet<String>> = documents.iter()
+        .map(|(inode, name, _)| label_words(&label_text(db, source_path, *inode, name)))
+        .collect();
+    let mut overall: HashMap<&str, usize> = HashMap::new();
+    for word in words.iter().flatten() {
+        *overall.entry(word).or_default() += 1;
+    }
+    let mut labels = Vec::new();
+    let mut used = HashSet::new();
+    for cluster in 0..k {
+        let members: Vec<usize> = (0..documents.len()).filter(|i| assignment[*i] == cluster).collect();
+        if members.is_empty() {
+            continue;
+        }
+        let mut in_cluster: HashMap<&str, usize> = HashMap::new();
+        for word in members.iter().flat_map(|i| &words[*i]) {
+            *in_cluster.entry(word).or_default() += 1;
+        }
+        // Common in the cluster, rare outside it
+        let mut scored: Vec<(f64, &str)> = in_cluster.into_iter()
+            .filter(|(_, n)| *n > 1 || members.len() == 1)
+            .map(|(word, n)| (n as f64 * (documents.len() as f64 / overall[word] as f64).ln(), word))
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let mut label = scored.iter().take(LABEL_WORDS).map(|(_, w)| *w).collect::<Vec<_>>().join("-");
+        if label.is_empty() {
+            label = format!("cluster-{}", cluster + 1);
+        }
+        let mut unique = label.clone();
+        let mut n = 2;
+        while !used.insert(unique.clone()) {
+            unique = format!("{}-{}", label, n);
+            n += 1;
+        }
+        labels.extend(members.iter().map(|i| (documents[*i].0, unique.clone())));
+    }
+    Ok(db.set_clusters(&labels)?)
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// Spherical k-means on unit vectors. Seeded deterministically with the documents least
+// like the seeds picked so far, so the same library clusters the same way every run.
+fn kmeans(vectors: &[Vec<f32>], k: usize) -> Vec<usize> {
+    let mut centers = vec![vectors[0].clone()];
+    while centers.len() < k {
+        let farthest = (0..vectors.len())
+            .min_by(|a, b| {
+                let closest = |i: &usize| centers.iter().map(|c| similarity(&vectors[*i], c)).fold(f32::MIN, f32::max);
+                closest(a).total_cmp(&closest(b))
+            })
+            .unwrap_or(0);
+        centers.push(vectors[farthest].clone());
+    }
+
+    let mut assignment = vec![0; vectors.len()];
+    for _ in 0..ITERATIONS {
+        let next: Vec<usize> = vectors.iter()
+            .map(|v| (0..k).max_by(|a, b| similarity(v, &centers[*a]).total_cmp(&similarity(v, &centers[*b]))).unwrap_or(0))
+            .collect();
+        let converged = next == assignment;
+        assignment = next;
+        if converged {
+            break;
+        }
+        for (cluster, center) in centers.iter_mut().enumerate() {
+            let mut sum = vec![0f32; center.len()];
+            for (v, _) in vectors.iter().zip(&assignment).filter(|(_, a)| **a == cluster) {
+                sum.iter_mut().zip(v).for_each(|(s, x)| *s += x);
+            }
+            // An empty cluster keeps its old center
+            if sum.iter().any(|s| *s != 0.0) {
+                *center = normalized(sum);
+            }
+        }
+    }
+    assignment
+}
+
+// File name, summary and the start of the file if it's text
+fn label_text(db: &Database, source_path: &Path, inode: u64, name: &str) -> String {
+    let mut text = Path::new(name).file_stem().unwrap_or_default().to_string_lossy().to_string();
+    if let Ok(Some(summary)) = db.get_summary(inode) {
+        text.push(' ');
+        text.push_str(&summary);
+    }
+    if let Ok(Some(rel)) = db.get_path(inode) {
+        let mut sample = Vec::new();
+        if let Ok(file) = std::fs::File::open(source_path.join(rel)) {
+            let _ = file.take(LABEL_SAMPLE).read_to_end(&mut sample);
+        }
+        // Binary files are skipped; a text sample may end mid-character
+        let sample = match std::str::from_utf8(&sample) {
+            Ok(sample) => Some(sample),
+            Err(e) if e.error_len().is_none() => std::str::from_utf8(&sample[..e.valid_up_to()]).ok(),
+            Err(_) => None,
+        };
+        if let Some(sample) = sample {
+            text.push(' ');
+            text.push_str(sample);
+        }
+    }
+    text
+}
+
+fn label_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|w| w.chars().count() >= 4)
+        .map(str::to_lowercase)
+        .filter(|w| !crate::model::STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
@@ -0,0 +1,17 @@
+// Comments
+// Every file has a writable `<name>.comments` next to it for free-text notes about it
+// ("sent to accountant 2024-03-01"). The text lives in the database, never on disk, and
+// follows the file through renames; plain words in smart folder queries match it too.
+// Writing replaces or appends like a real file, `rm` deletes the comment. Only files with
+// a comment list their `.comments` in the directory.
+
+pub const SUFFIX: &str = ".comments";
+
+/// `comment` after a write of `data` at `offset`: what's past the offset is replaced, as
+/// editors rewrite the whole file and `>>` writes at the end.
+pub fn splice(comment: &str, offset: usize, data: &[u8]) -> Option<String> {
+    let mut bytes = comment.as_bytes().to_vec();
+    bytes.resize(offset, b' ');
+    bytes.extend_from_slice(data);
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,305 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use crate::throttle::IoPriority;
+
+// User Configuration
+// Read once at startup from ~/.eidetic/config.toml. The file and every section in it are optional.
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Extra or overriding conversions, consulted before the built-in ones
+    pub converters: Vec<ConverterRule>,
+    pub speech: SpeechConfig,
+    pub transcription: TranscriptionConfig,
+    pub scrub: ScrubConfig,
+    pub embeddings: EmbeddingsConfig,
+    pub worker: WorkerConfig,
+    pub ai: AiConfig,
+    pub sensitive: SensitiveConfig,
+    pub lint: LintConfig,
+    pub metrics: MetricsConfig,
+    pub notifications: NotificationsConfig,
+    pub logging: LoggingConfig,
+    pub rest: RestConfig,
+    pub grpc: GrpcConfig,
+    pub s3: S3Config,
+    pub webhooks: WebhooksConfig,
+    pub links: LinksConfig,
+    pub network: NetworkConfig,
+}
+
+/// `[speech]`: the text-to-speech engine behind `<name>.txt.mp3`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct SpeechConfig {
+    /// Reads plain text from `{input}` and writes a WAV file to `{output}`. Defaults to espeak-ng.
+    pub command: Option<String>,
+}
+
+/// `[transcription]`: the speech-to-text tool behind `<name>.m4a.txt`. Off unless set.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct TranscriptionConfig {
+    /// Reads a 16 kHz mono WAV from `{input}`, writes the text to `{output}` or stdout,
+    /// e.g. "whisper-cli -m /path/to/ggml-base.bin -nt -np -f {input}"
+    pub command: Option<String>,
+}
+
+/// `[scrub]`: periodic re-verification of file checksums (bitrot detection).
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ScrubConfig {
+    /// Each file is re-verified at most this often; 0 turns scrubbing off
+    pub interval_days: u64,
+}
+
+impl Default for ScrubConfig {
+    fn default() -> Self {
+        Self { interval_days: 30 }
+    }
+}
+
+/// `[embeddings]`: optional worker stage that stores chunk embeddings of text documents.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EmbeddingsConfig {
+    pub enabled: bool,
+    /// Target chunk length in characters; chunks break at paragraph boundaries when they can
+    pub chunk_chars: usize,
+    /// How often `.magic/clusters/` is recomputed from the stored embeddings; 0 turns it off
+    pub cluster_interval_hours: u64,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self { enabled: false, chunk_chars: 1000, cluster_interval_hours: 6 }
+    }
+}
+
+/// `[sensitive]`: what happens to files the sensitive data scanner flagged.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct SensitiveConfig {
+    /// Leave flagged files out of `.context` (and so out of `.ask` answers)
+    pub exclude_from_context: bool,
+}
+
+/// `[lint]`: optional worker stage proofreading prose files into `<name>.md.lint`.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct LintConfig {
+    pub enabled: bool,
+}
+
+/// `[metrics]`: Prometheus metrics of the daemon, see metrics.rs. Off unless set.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Address to serve `/metrics` on, e.g. "127.0.0.1:9477"
+    pub listen: Option<String>,
+    /// File to keep the metrics in for node_exporter's textfile collector, e.g.
+    /// "/var/lib/node_exporter/textfile_collector/eidetic.prom"
+    pub textfile: Option<PathBuf>,
+}
+
+/// `[notifications]`: desktop popups for background actions, see notifications.rs. Off unless enabled.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+    /// Tags the worker added to a file
+    pub tags: bool,
+    /// Organizer moves
+    pub moves: bool,
+    /// Files the sensitive data scanner flagged
+    pub sensitive: bool,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self { enabled: false, tags: true, moves: true, sensitive: true }
+    }
+}
+
+/// `[logging]`: the daemon's log in ~/.eidetic/logs/, see logging.rs.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// "error", "warn", "info", "debug" or "trace", or directives like "warn,eidetic::worker=debug"
+    pub level: String,
+    /// The log is rotated once it reaches this size; 0 never rotates
+    pub max_size_mb: u64,
+    /// Rotated logs kept (<mount>.log.1 is the newest)
+    pub keep: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self { level: "info".to_string(), max_size_mb: 10, keep: 5 }
+    }
+}
+
+/// `[rest]`: the JSON API for dashboards and browser extensions, see rest.rs. Off unless set.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct RestConfig {
+    /// Loopback address to serve on, e.g. "127.0.0.1:7474"
+    pub listen: Option<String>,
+    /// Bearer token clients must send; defaults to a random one kept in ~/.eidetic/rest_token
+    pub token: Option<String>,
+}
+
+/// `[grpc]`: the gRPC service in proto/eidetic.proto, see grpc.rs. Off unless set.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct GrpcConfig {
+    /// Loopback address to serve on, e.g. "127.0.0.1:7475"; clients use the `[rest]` token
+    pub listen: Option<String>,
+}
+
+/// `[s3]`: an S3-compatible bucket keeping a copy of the version history, see offsite.rs. Off unless
+/// `bucket` is set.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct S3Config {
+    pub bucket: Option<String>,
+    /// e.g. "https://s3.eu-central-1.amazonaws.com" or "http://nas:9000" for MinIO; AWS if unset
+    pub endpoint: Option<String>,
+    pub region: String,
+    /// Fall back to $AWS_ACCESS_KEY_ID and $AWS_SECRET_ACCESS_KEY
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    /// Keys are put under this prefix, e.g. "laptop/"
+    pub prefix: String,
+    /// Uploaded snapshots are deleted locally, oldest first, beyond this much; 0 keeps them all
+    pub cache_mb: u64,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self { bucket: None, endpoint: None, region: "us-east-1".to_string(), access_key: None, secret_key: None, prefix: String::new(), cache_mb: 1024 }
+    }
+}
+
+/// `[links]`: `.url` files, which read as the page they link to, see weblink.rs.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LinksConfig {
+    /// A cached page is refetched once older than this; offline, it's served however old
+    pub cache_minutes: u64,
+}
+
+impl Default for LinksConfig {
+    fn default() -> Self {
+        Self { cache_minutes: 60 }
+    }
+}
+
+/// `[network]`: whether Eidetic may go online at all, see http.rs.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Offline mode for good; `eidetic offline on|off` switches it for the time being
+    pub offline: bool,
+}
+
+/// `[webhooks]`: URLs events are POSTed to as JSON, per kind of event, see webhooks.rs.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WebhooksConfig {
+    /// A tag was added to a file
+    pub tagged: Vec<String>,
+    /// A file was deleted through the mount
+    pub trashed: Vec<String>,
+    /// The sensitive data scanner flagged a file
+    pub sensitive: Vec<String>,
+    /// Further attempts at a failed POST, with doubling delays, before it's dropped
+    pub retries: u32,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self { tagged: Vec::new(), trashed: Vec::new(), sensitive: Vec::new(), retries: 5 }
+    }
+}
+
+/// `[worker]`: how hard background analysis may compete with the mount.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WorkerConfig {
+    /// CPU niceness of the worker thread, 0 (normal) to 19 (lowest)
+    pub nice: i32,
+    /// "idle", "low" or "normal"
+    pub io_priority: IoPriority,
+    /// Cap on bytes read for analysis per second; 0 is unlimited
+    pub max_bytes_per_sec: u64,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self { nice: 10, io_priority: IoPriority::Low, max_bytes_per_sec: 0 }
+    }
+}
+
+/// `[ai]`: the model behind summaries.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AiConfig {
+    pub backend: AiBackend,
+    /// Base URL; defaults to the backend's usual local address (OpenAI's API for "openai")
+    pub url: Option<String>,
+    /// Model name, e.g. "llama3.2" (required for Ollama) or "t5-small"
+    pub model: Option<String>,
+    /// Bearer token for OpenAI-compatible APIs; falls back to $OPENAI_API_KEY
+    pub api_key: Option<String>,
+    pub timeout_secs: u64,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        Self { backend: AiBackend::Heuristic, url: None, model: None, api_key: None, timeout_secs: 120 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AiBackend {
+    Heuristic,
+    Ollama,
+    Openai,
+    LlamaCpp,
+    /// Local T5 through candle, see `eidetic model download`
+    T5,
+}
+
+/// `[[converters]]` entry: serve `<stem>.<to>` from a sibling `<stem>.<from>`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ConverterRule {
+    pub from: String,
+    pub to: String,
+    /// Name of a built-in converter, e.g. "image" or "markdown-html"
+    pub builtin: Option<String>,
+    /// External command, e.g. "pandoc {input} -o {output}". Without `{output}`, stdout is the result.
+    pub command: Option<String>,
+    /// Serve `<name>.<from>.<to>` instead of replacing the extension
+    #[serde(default)]
+    pub append: bool,
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+        PathBuf::from(home).join(".eidetic").join("config.toml")
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path)?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file {:?}", path))
+    }
+}
@@ -0,0 +1,492 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::db::Database;
+use crate::queue::{JobQueue, Priority};
+use tracing::{info, warn};
+
+// Control Socket
+// A Unix socket next to the pid file that lets other programs talk to the running daemon.
+// Protocol: one JSON request line, optionally followed by a raw byte payload, answered by one JSON line.
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "verb", rename_all = "snake_case")]
+pub enum Request {
+    /// Deposit a document into the source tree. `size` bytes of content follow the header line.
+    Ingest {
+        path: String,
+        size: u64,
+        #[serde(default)]
+        tags: Vec<String>,
+        #[serde(default)]
+        source_url: Option<String>,
+    },
+    /// Drop what analysis derived for a subtree (the whole source tree if `path` is None) and
+    /// analyze it again, e.g. after tag rules or the model changed.
+    Reindex {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// Markdown list of files that failed checksum verification (same as `.magic/integrity_report.md`)
+    Integrity,
+    /// Stop or restart background analysis (same as writing to `.magic/jobs/control`)
+    Pause,
+    Resume,
+    /// Queue status and recent failures (same as `.magic/jobs/status.md`)
+    Jobs,
+    /// Markdown list of images that look like the one at `path` (same as `.magic/similar_images/<name>/`)
+    Similar { path: String },
+    /// Tags, entities and note links as a graph in `format` ("dot" or "json"), see graph.rs
+    Graph { format: String },
+    /// Render notes, summaries, tags and backlinks as a static HTML site into `dir` (absolute)
+    ExportHtml { dir: String },
+    /// Copy notes with tags, summaries and backlinks into the Obsidian vault at `dir` (absolute)
+    ExportObsidian { dir: String },
+    /// Add the file at `path` to `.magic/favorites/`, or take it off
+    Pin { path: String },
+    Unpin { path: String },
+    /// Rate the file at `path` 1-5 stars, 0 clears the rating (see ratings.rs)
+    Rate { path: String, stars: u8 },
+    /// Give the file at `path` a color label, None clears it (see labels.rs)
+    Label {
+        path: String,
+        #[serde(default)]
+        color: Option<String>,
+    },
+    /// Move files back where they were before the organizer moved them since `since` (unix
+    /// time), or only the latest move. The report lists what was undone.
+    OrganizeUndo {
+        #[serde(default)]
+        since: Option<i64>,
+    },
+    /// The event log since `since` (unix time), or its latest lines (same as `.magic/events.log`)
+    Events {
+        #[serde(default)]
+        since: Option<i64>,
+    },
+    /// Change the daemon's log level, e.g. "debug" or "warn,eidetic::worker=debug" (see logging.rs)
+    LogLevel { level: String },
+    /// Path of the daemon's log file, for `eidetic logs`
+    LogFile,
+    /// The source directory, for `eidetic mcp`
+    SourcePath,
+    /// Where the filesystem is mounted, for `eidetic serve`
+    MountPoint,
+    // These answer in `data`, for `eidetic status`, `search`, `tags`, `history` and `trash`
+    /// The health report (same as `.magic/health.json`)
+    Status,
+    /// Files matching a smart folder query (see query.rs), with their tags
+    Search { query: String },
+    /// Every tag with its number of files, or the files with `tag`
+    Tags { tag: Option<String> },
+    /// Version history snapshots of the file at `path`, newest first
+    History { path: String },
+    /// Files deleted through the mount, newest first
+    Trash,
+    /// Keep the tags materialized as symlinks in `dir` (absolute), see materialize.rs
+    MaterializeTags { dir: String },
+    /// Download history snapshots missing locally from the `[s3]` bucket (see offsite.rs), of
+    /// files under `path` or all of them
+    HistoryPull { path: Option<String> },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inode: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report: Option<String>,
+    /// Structured answer to listing requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl Response {
+    fn ok(inode: Option<u64>) -> Self {
+        Self { ok: true, inode, error: None, report: None, data: None }
+    }
+
+    fn err(e: anyhow::Error) -> Self {
+        Self { ok: false, inode: None, error: Some(e.to_string()), report: None, data: None }
+    }
+
+    fn data(result: Result<Value>) -> Self {
+        match result {
+            Ok(data) => Self { data: Some(data), ..Self::ok(None) },
+            Err(e) => Self::err(e),
+        }
+    }
+}
+
+/// Longest accepted request header line.
+const MAX_HEADER_LEN: u64 = 64 * 1024;
+
+/// A client that stalls longer than this mid-request is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct ControlServer {
+    socket_path: PathBuf,
+    source_path: PathBuf,
+    mountpoint: PathBuf,
+    jobs: JobQueue,
+    /// When the daemon came up, for `eidetic status`
+    started_at: SystemTime,
+}
+
+impl ControlServer {
+    pub fn new(socket_path: PathBuf, source_path: PathBuf, mountpoint: PathBuf, jobs: JobQueue) -> Self {
+        Self { socket_path, source_path, mountpoint, jobs, started_at: SystemTime::now() }
+    }
+
+    pub fn start(self) -> Result<()> {
+        // A stale socket from a crashed daemon would make bind() fail
+        let _ = std::fs::remove_file(&self.socket_path);
+        let listener = UnixListener::bind(&self.socket_path)
+            .with_context(|| format!("Failed to bind control socket {:?}", self.socket_path))?;
+
+        thread::spawn(move || {
+            // One thread per connection so a slow client can't block the others
+            for stream in listener.incoming().flatten() {
+                let server = self.clone();
+                thread::spawn(move || {
+                    if let Err(e) = server.handle(stream) {
+                        warn!(error = %e, "connection error");
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn handle(&self, stream: UnixStream) -> Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut header = String::new();
+        reader.by_ref().take(MAX_HEADER_LEN).read_line(&mut header)?;
+        if !header.ends_with('\n') {
+            let mut stream = stream;
+            writeln!(stream, "{}", serde_json::to_string(&Response::err(anyhow!("Request header too long or truncated")))?)?;
+            return Ok(());
+        }
+
+        // Open DB in this thread
+        let db = Database::new(self.source_path.join(".eidetic.db"))?;
+
+        let response = match serde_json::from_str::<Request>(&header) {
+            Ok(Request::Ingest { path, size, tags, source_url }) => {
+                match self.ingest(&db, &path, size, &mut reader.take(size), &tags, source_url.as_deref()) {
+                    Ok(inode) => Response::ok(Some(inode)),
+                    Err(e) => Response::err(e),
+                }
+            }
+            Ok(Request::Reindex { path }) => match self.reindex(&db, path.as_deref()) {
+                Ok(inode) => Response::ok(Some(inode)),
+                Err(e) => Response::err(e),
+            },
+            Ok(Request::Integrity) => Response { report: Some(crate::scrub::report(&db)), ..Response::ok(None) },
+            Ok(Request::Pause) => {
+                self.jobs.set_paused(true);
+                Response::ok(None)
+            }
+            Ok(Request::Resume) => {
+                self.jobs.set_paused(false);
+                Response::ok(None)
+            }
+            Ok(Request::Jobs) => Response { report: Some(self.jobs.status_report(&db)), ..Response::ok(None) },
+            Ok(Request::Similar { path }) => match sanitize_path(&path).and_then(|p| Ok(db.lookup_path(&p)?)) {
+                Ok(Some(inode)) => Response { report: Some(crate::phash::report(&db, inode)), ..Response::ok(Some(inode)) },
+                Ok(None) => Response::err(anyhow!("No such file: {}", path)),
+                Err(e) => Response::err(e),
+            },
+            Ok(Request::Graph { format }) => match crate::graph::export(&db, &format) {
+                Ok(graph) => Response { report: Some(graph), ..Response::ok(None) },
+                Err(e) => Response::err(e),
+            },
+            Ok(Request::ExportHtml { dir }) => match self.outside_dir(&dir).and_then(|dir| crate::site::export(&db, &self.source_path, &dir)) {
+                Ok(report) => Response { report: Some(report), ..Response::ok(None) },
+                Err(e) => Response::err(e),
+            },
+            Ok(Request::ExportObsidian { dir }) => match self.outside_dir(&dir).and_then(|dir| crate::obsidian::export(&db, &self.source_path, &dir)) {
+                Ok(report) => Response { report: Some(report), ..Response::ok(None) },
+                Err(e) => Response::err(e),
+            },
+            Ok(Request::Pin { path }) => Self::pin(&db, &path, true),
+            Ok(Request::Unpin { path }) => Self::pin(&db, &path, false),
+            Ok(Request::Rate { path, stars }) => Self::rate(&db, &path, stars),
+            Ok(Request::Label { path, color }) => Self::label(&db, &path, color.as_deref()),
+            Ok(Request::OrganizeUndo { since }) => Response { report: Some(crate::organize::undo(&db, &self.source_path, since)), ..Response::ok(None) },
+            Ok(Request::Events { since }) => Response { report: Some(crate::events::log(&db, since)), ..Response::ok(None) },
+            Ok(Request::LogLevel { level }) => match crate::logging::set_level(&level) {
+                Ok(()) => Response::ok(None),
+                Err(e) => Response::err(e),
+            },
+            Ok(Request::LogFile) => match crate::logging::current() {
+                Some(path) => Response { report: Some(path.to_string_lossy().to_string()), ..Response::ok(None) },
+                None => Response::err(anyhow!("Logging isn't set up")),
+            },
+            Ok(Request::Status) => Response::data(Ok(crate::health::status(&db, &self.jobs, self.started_at))),
+            Ok(Request::Search { query }) => Response::data(Self::search(&db, &query)),
+            Ok(Request::Tags { tag }) => Response::data(Self::tags(&db, tag.as_deref())),
+            Ok(Request::History { path }) => Response::data(Self::history(&db, &path)),
+            Ok(Request::Trash) => Response::data(db.get_trash().map_err(Into::into).map(|trash| {
+                trash.into_iter()
+                    .map(|(path, trashed, deleted_at)| json!({ "path": path, "trashed": trashed, "deleted_at": deleted_at }))
+                    .collect()
+            })),
+            Ok(Request::MountPoint) => Response { report: Some(self.mountpoint.to_string_lossy().to_string()), ..Response::ok(None) },
+            Ok(Request::MaterializeTags { dir }) => match self.outside_dir(&dir).and_then(|dir| crate::materialize::set_up(&db, &self.source_path, &dir)) {
+                Ok(report) => Response { report: Some(report), ..Response::ok(None) },
+                Err(e) => Response::err(e),
+            },
+            Ok(Request::HistoryPull { path }) => {
+                let pulled = crate::config::Config::load()
+                    .and_then(|config| crate::offsite::pull(&config.s3, &db, &self.source_path, path.as_deref()));
+                match pulled {
+                    Ok(report) => Response { report: Some(report), ..Response::ok(None) },
+                    Err(e) => Response::err(e),
+                }
+            }
+            Ok(Request::SourcePath) => Response { report: Some(self.source_path.to_string_lossy().to_string()), ..Response::ok(None) },
+            Err(e) => Response::err(anyhow!("Malformed request: {}", e)),
+        };
+
+        let mut stream = stream;
+        writeln!(stream, "{}", serde_json::to_string(&response)?)?;
+        Ok(())
+    }
+
+    fn search(db: &Database, query: &str) -> Result<Value> {
+        let results = crate::query::Query::parse(query)?.results(db);
+        Ok(results.into_iter()
+            .map(|(inode, name)| json!({
+                "path": db.get_path(inode).ok().flatten().unwrap_or(name),
+                "tags": db.get_file_tags(inode).unwrap_or_default(),
+            }))
+            .collect())
+    }
+
+    fn tags(db: &Database, tag: Option<&str>) -> Result<Value> {
+        Ok(match tag {
+            Some(tag) => db.get_files_with_tag(tag)?
+                .into_iter()
+                .map(|(inode, name)| json!(db.get_path(inode).ok().flatten().unwrap_or(name)))
+                .collect(),
+            None => db.get_tags()?
+                .into_iter()
+                .map(|tag| json!({ "files": db.get_files_with_tag(&tag).map(|files| files.len()).unwrap_or(0), "tag": tag }))
+                .collect(),
+        })
+    }
+
+    fn history(db: &Database, path: &str) -> Result<Value> {
+        let inode = db.lookup_path(&sanitize_path(path)?)?.with_context(|| format!("No such file: {}", path))?;
+        Ok(db.get_history(inode)?
+            .into_iter()
+            .map(|(at, snapshot)| json!({ "at": at, "snapshot": snapshot }))
+            .collect())
+    }
+
+    fn pin(db: &Database, path: &str, pinned: bool) -> Response {
+        Self::update(db, path, |inode| db.set_favorite(inode, pinned))
+    }
+
+    fn rate(db: &Database, path: &str, stars: u8) -> Response {
+        if stars > crate::ratings::MAX_STARS {
+            return Response::err(anyhow!("Ratings go up to {} stars", crate::ratings::MAX_STARS));
+        }
+        Self::update(db, path, |inode| db.set_rating(inode, stars))
+    }
+
+    fn label(db: &Database, path: &str, color: Option<&str>) -> Response {
+        let color = match color.map(crate::labels::parse).transpose() {
+            Ok(color) => color.flatten(),
+            Err(e) => return Response::err(e),
+        };
+        Self::update(db, path, |inode| db.set_label(inode, color))
+    }
+
+    // Looks up a file by its path in the source tree and applies `change` to it
+    fn update(db: &Database, path: &str, change: impl FnOnce(u64) -> rusqlite::Result<()>) -> Response {
+        match sanitize_path(path).and_then(|p| Ok(db.lookup_path(&p)?)) {
+            Ok(Some(inode)) => match change(inode) {
+                Ok(()) => Response::ok(Some(inode)),
+                Err(e) => Response::err(e.into()),
+            },
+            Ok(None) => Response::err(anyhow!("No such file: {}", path)),
+            Err(e) => Response::err(e),
+        }
+    }
+
+    /// Checks a folder the daemon is asked to write into: it must be absolute (the daemon's cwd
+    /// isn't the client's) and, with symlinks resolved, outside both the mount and the source
+    /// tree, or the output would be indexed, or written through the mount, as it's produced.
+    fn outside_dir(&self, dir: &str) -> Result<PathBuf> {
+        let path = Path::new(dir);
+        if !path.is_absolute() {
+            return Err(anyhow!("Need an absolute path: {}", dir));
+        }
+        if path.components().any(|component| component == Component::ParentDir) {
+            return Err(anyhow!("Invalid folder: {}", dir));
+        }
+        // The folder may not exist yet: resolve its deepest existing ancestor
+        let existing = path.ancestors().find(|ancestor| ancestor.exists()).unwrap_or(Path::new("/"));
+        let resolved = std::fs::canonicalize(existing)?.join(path.strip_prefix(existing)?);
+        let source = std::fs::canonicalize(&self.source_path)?;
+        if resolved.starts_with(&source) || resolved.starts_with(&self.mountpoint) {
+            return Err(anyhow!("{} is inside the mount or the source folder; pick a folder outside both", dir));
+        }
+        Ok(resolved)
+    }
+
+    fn ingest(&self, db: &Database, rel_path: &str, size: u64, content: &mut impl Read, tags: &[String], source_url: Option<&str>) -> Result<u64> {
+        let rel_path = sanitize_path(rel_path)?;
+        let real_path = self.source_path.join(&rel_path);
+        if real_path.is_dir() {
+            return Err(anyhow!("{} is a directory", rel_path));
+        }
+
+        // Stream into a temp file on the same filesystem first, so an aborted upload
+        // never replaces the existing file. Vault files are encrypted per chunk like FS writes.
+        let tmp_dir = self.source_path.join(".eidetic/tmp");
+        std::fs::create_dir_all(&tmp_dir)?;
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+        let tmp_path = tmp_dir.join(format!("ingest_{}_{}", timestamp.as_nanos(), std::process::id()));
+        let in_vault = real_path.to_string_lossy().contains("/vault/");
+        let received = (|| -> Result<u64> {
+            let mut file = std::fs::File::create(&tmp_path)?;
+            let mut buffer = vec![0; 64 * 1024];
+            let mut received = 0u64;
+            loop {
+                let n = content.read(&mut buffer)?;
+                if n == 0 {
+                    break;
+                }
+                received += n as u64;
+                if in_vault {
+                    file.write_all(&crate::cipher::encrypt(&buffer[..n]))?;
+                } else {
+                    file.write_all(&buffer[..n])?;
+                }
+            }
+            file.sync_all()?;
+            Ok(received)
+        })();
+        match received {
+            Ok(n) if n == size => {}
+            Ok(n) => {
+                let _ = std::fs::remove_file(&tmp_path);
+                return Err(anyhow!("Incomplete upload: received {} of {} bytes", n, size));
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&tmp_path);
+                return Err(e);
+            }
+        }
+
+        if let Some(parent) = real_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let inode = db.alloc_path(&rel_path)?;
+
+        // Time Travel: snapshot the previous version, same as a write through the mount
+        if real_path.exists() {
+            let history_dir = self.source_path.join(".eidetic/history");
+            let _ = std::fs::create_dir_all(&history_dir);
+            let backup_name = format!("{}_{}_{}", inode, timestamp.as_secs(), real_path.file_name().unwrap().to_string_lossy());
+            let backup_path = history_dir.join(&backup_name);
+            if std::fs::copy(&real_path, &backup_path).is_ok() {
+                let _ = db.add_history(inode, backup_path.to_string_lossy().as_ref());
+            }
+        }
+
+        if let Err(e) = std::fs::rename(&tmp_path, &real_path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+
+        for tag in tags {
+            db.add_manual_tag(inode, tag)?;
+        }
+        if let Some(url) = source_url {
+            db.set_source_url(inode, url)?;
+        }
+
+        // Hand off to the normal analysis pipeline (tagging, organizer rules)
+        self.jobs.enqueue(db, inode, real_path, Priority::Written);
+        Ok(inode)
+    }
+
+    fn reindex(&self, db: &Database, rel_path: Option<&str>) -> Result<u64> {
+        let rel_path = match rel_path.map(|p| p.trim_matches('/')) {
+            None | Some("") | Some(".") => String::new(),
+            Some(p) => sanitize_path(p)?,
+        };
+        let real_path = self.source_path.join(&rel_path);
+        if !real_path.exists() {
+            return Err(anyhow!("No such file or directory: {}", rel_path));
+        }
+        let inode = db.alloc_path(&rel_path)?;
+        db.clear_analysis(inode)?;
+
+        // Queueing a big tree waits on the worker; don't hold the client for it
+        let (source_path, jobs) = (self.source_path.clone(), self.jobs.clone());
+        thread::spawn(move || {
+            let Ok(db) = Database::new(source_path.join(".eidetic.db")) else { return };
+            let queued = crate::scan::queue_tree(&db, &jobs, &source_path, &real_path);
+            info!(queued, path = ?real_path, "reindex queued");
+        });
+        Ok(inode)
+    }
+}
+
+// Only allow plain relative paths inside the source tree, never into Eidetic's own state.
+fn sanitize_path(path: &str) -> Result<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return Err(anyhow!("Invalid target path: {}", path)),
+        }
+    }
+    if parts.is_empty() {
+        return Err(anyhow!("Empty target path"));
+    }
+    if parts[0] == ".eidetic" || parts[0].starts_with(".eidetic.db") || parts[0] == ".magic" {
+        return Err(anyhow!("Refusing to write into reserved path: {}", path));
+    }
+    Ok(parts.join("/"))
+}
+
+/// Client side: sends one request (plus payload) and waits for the daemon's answer.
+pub fn send(socket_path: &Path, request: &Request, payload: Option<&mut dyn Read>) -> Result<Response> {
+    let mut stream = UnixStream::connect(socket_path)
+        .context("Could not reach the Eidetic daemon (is it running?)")?;
+    writeln!(stream, "{}", serde_json::to_string(request)?)?;
+    if let Some(payload) = payload {
+        std::io::copy(payload, &mut stream)?;
+    }
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// `send` without a payload, turning a failed response into an error.
+pub fn send_checked(socket_path: &Path, request: &Request) -> Result<Response> {
+    let response = send(socket_path, request, None)?;
+    if !response.ok {
+        return Err(anyhow!(response.error.unwrap_or_else(|| "Request failed".to_string())));
+    }
+    Ok(response)
+}
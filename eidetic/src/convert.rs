@@ -0,0 +1,731 @@
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Condvar, Mutex};
+use std::time::UNIX_EPOCH;
+use anyhow::{anyhow, Result};
+use pulldown_cmark::{html, Event, Options, Parser, TagEnd};
+use serde::de::{self, Deserializer as _, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use crate::config::Config;
+use crate::office;
+use crate::pdf;
+
+// Conversion Cache
+// Virtual converted files (PNG -> JPG, Markdown -> HTML / PDF, CSV <-> JSON, DOCX -> Markdown, PDF -> text, FLAC -> MP3, link -> QR code, text -> speech, ...) are rendered once into .eidetic/cache/
+// and served from there in ranges, so a large source is converted once rather than per read call.
+// Cache files are named after the source inode and mtime, so saving a new version of the
+// source invalidates the old rendering by itself.
+
+/// Longest edge of generated RAW previews.
+const RAW_PREVIEW_SIZE: usize = 2048;
+
+/// Longest edge of `.thumbnails/` entries.
+const THUMBNAIL_SIZE: u32 = 256;
+
+const THUMBNAIL_IMAGES: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "heic", "heif"];
+const RAW_FORMATS: &[&str] = &["cr2", "nef", "arw", "dng", "raf", "orf", "rw2"];
+/// `[speech]` default; `-f` reads the text from a file, `-w` writes WAV.
+const DEFAULT_SPEECH_COMMAND: &str = "espeak-ng -f {input} -w {output}";
+
+const VIDEO_FORMATS: &[&str] = &["mp4", "m4v", "mov", "mkv", "webm", "avi"];
+
+/// Whether `.thumbnails/` has an entry for this file name.
+pub fn is_thumbnailable(name: &str) -> bool {
+    let ext = Path::new(name).extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    [THUMBNAIL_IMAGES, RAW_FORMATS, VIDEO_FORMATS].iter().any(|list| list.contains(&ext.as_str()))
+}
+
+/// Converters compiled into Eidetic, selectable by name from `[[converters]]` in config.toml.
+#[derive(Clone, Copy, Debug)]
+pub enum Builtin {
+    Image,
+    Heif,
+    Raw,
+    MarkdownHtml,
+    MarkdownPdf,
+    TextPdf,
+    CsvJson,
+    JsonCsv,
+    Thumbnail,
+    Audio,
+    Poster,
+    Office,
+    PdfText,
+    Qr,
+    Speech,
+}
+
+impl Builtin {
+    const NAMES: &'static [(&'static str, Builtin)] = &[
+        ("image", Builtin::Image),
+        ("heif", Builtin::Heif),
+        ("raw", Builtin::Raw),
+        ("markdown-html", Builtin::MarkdownHtml),
+        ("markdown-pdf", Builtin::MarkdownPdf),
+        ("text-pdf", Builtin::TextPdf),
+        ("csv-json", Builtin::CsvJson),
+        ("json-csv", Builtin::JsonCsv),
+        ("thumbnail", Builtin::Thumbnail),
+        ("audio", Builtin::Audio),
+        ("poster", Builtin::Poster),
+        ("office", Builtin::Office),
+        ("pdf-text", Builtin::PdfText),
+        ("qr", Builtin::Qr),
+        ("speech", Builtin::Speech),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().find(|(n, _)| *n == name).map(|(_, b)| *b)
+    }
+}
+
+/// Default rules that replace the extension (`notes.md` -> `notes.html`), as (source ext, target ext, converter), in lookup order.
+const BUILTIN_RULES: &[(&str, &str, Builtin)] = &[
+    ("png", "jpg", Builtin::Image),
+    ("heic", "jpg", Builtin::Heif),
+    ("heif", "jpg", Builtin::Heif),
+    // Camera RAW formats, decoded through rawloader/imagepipe
+    ("cr2", "jpg", Builtin::Raw),
+    ("nef", "jpg", Builtin::Raw),
+    ("arw", "jpg", Builtin::Raw),
+    ("dng", "jpg", Builtin::Raw),
+    ("raf", "jpg", Builtin::Raw),
+    ("orf", "jpg", Builtin::Raw),
+    ("rw2", "jpg", Builtin::Raw),
+    ("md", "html", Builtin::MarkdownHtml),
+    ("markdown", "html", Builtin::MarkdownHtml),
+    ("md", "pdf", Builtin::MarkdownPdf),
+    ("markdown", "pdf", Builtin::MarkdownPdf),
+    ("txt", "pdf", Builtin::TextPdf),
+    ("csv", "json", Builtin::CsvJson),
+    ("json", "csv", Builtin::JsonCsv),
+    ("docx", "md", Builtin::Office),
+    ("odt", "md", Builtin::Office),
+    // Lossless audio for devices that only play lossy formats
+    ("flac", "mp3", Builtin::Audio),
+    ("wav", "mp3", Builtin::Audio),
+    ("flac", "opus", Builtin::Audio),
+    ("wav", "opus", Builtin::Audio),
+];
+
+/// Default rules that append the extension (`movie.mp4` -> `movie.mp4.jpg`).
+const BUILTIN_APPEND_RULES: &[(&str, &str, Builtin)] = &[
+    ("pdf", "txt", Builtin::PdfText),
+    ("url", "png", Builtin::Qr),
+    ("txt", "mp3", Builtin::Speech),
+    ("md", "mp3", Builtin::Speech),
+    ("mp4", "jpg", Builtin::Poster),
+    ("m4v", "jpg", Builtin::Poster),
+    ("mov", "jpg", Builtin::Poster),
+    ("mkv", "jpg", Builtin::Poster),
+    ("webm", "jpg", Builtin::Poster),
+    ("avi", "jpg", Builtin::Poster),
+];
+
+#[derive(Clone, Debug)]
+enum Backend {
+    Builtin(Builtin),
+    /// Argument template, `{input}` / `{output}` are substituted per argument so paths never go through a shell
+    Command(Vec<String>),
+    /// Text-to-speech: the `[speech]` command (same template rules) renders WAV, ffmpeg encodes it
+    Speech(Vec<String>),
+}
+
+/// Serves `<stem>.<target>` from a sibling `<stem>.<from>`.
+#[derive(Clone, Debug)]
+pub struct Converter {
+    /// Empty for converters that are only reachable through a view (e.g. thumbnails)
+    pub from: String,
+    /// Extension of the virtual file
+    pub target: String,
+    /// `<name>.<from>.<target>` instead of `<name>.<target>`
+    pub append: bool,
+    /// Names the rendering in the cache, distinct per converter producing the same target
+    pub cache_key: String,
+    backend: Backend,
+}
+
+impl Converter {
+    fn new(from: String, target: String, append: bool, backend: Backend) -> Self {
+        // Appended views can target the same format as a replacing rule for the same source
+        let cache_key = if append { format!("{}.{}", from, target) } else { target.clone() };
+        Self { from, target, append, cache_key, backend }
+    }
+}
+
+/// All conversion rules, indexed by converter id (which is encoded into virtual inodes).
+pub struct Registry {
+    converters: Vec<Converter>,
+    thumbnail: usize,
+}
+
+impl Registry {
+    /// User rules from config.toml come first so they can shadow the built-in ones.
+    pub fn new(config: &Config) -> Result<Self> {
+        let speech = parse_command(config.speech.command.as_deref().unwrap_or(DEFAULT_SPEECH_COMMAND))
+            .map_err(|e| anyhow!("[speech] command: {}", e))?;
+        let backend_for = |builtin: Builtin| match builtin {
+            Builtin::Speech => Backend::Speech(speech.clone()),
+            builtin => Backend::Builtin(builtin),
+        };
+
+        let mut converters = Vec::new();
+        for rule in &config.converters {
+            let backend = match (&rule.builtin, &rule.command) {
+                (Some(name), None) => backend_for(Builtin::from_name(name)
+                    .ok_or_else(|| anyhow!("Unknown built-in converter '{}' for {} -> {}", name, rule.from, rule.to))?),
+                (None, Some(command)) => Backend::Command(parse_command(command)
+                    .map_err(|e| anyhow!("Converter {} -> {}: {}", rule.from, rule.to, e))?),
+                _ => return Err(anyhow!("Converter {} -> {} needs exactly one of `builtin` or `command`", rule.from, rule.to)),
+            };
+            converters.push(Converter::new(
+                rule.from.trim_start_matches('.').to_lowercase(),
+                rule.to.trim_start_matches('.').to_lowercase(),
+                rule.append,
+                backend,
+            ));
+        }
+        for (append, rules) in [(false, BUILTIN_RULES), (true, BUILTIN_APPEND_RULES)] {
+            for (from, target, builtin) in rules {
+                converters.push(Converter::new(from.to_string(), target.to_string(), append, backend_for(*builtin)));
+            }
+        }
+        let thumbnail = converters.len();
+        converters.push(Converter {
+            from: String::new(),
+            target: "jpg".to_string(),
+            append: true,
+            cache_key: "thumb.jpg".to_string(),
+            backend: Backend::Builtin(Builtin::Thumbnail),
+        });
+        Ok(Self { converters, thumbnail })
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Converter> {
+        self.converters.get(id)
+    }
+
+    /// Id of the first rule turning `from` files into `target`.
+    pub fn find(&self, from: &str, target: &str) -> Option<usize> {
+        self.converters.iter().position(|c| c.from == from && c.target == target)
+    }
+
+    /// Converter id of `.thumbnails/` entries.
+    pub fn thumbnail(&self) -> usize {
+        self.thumbnail
+    }
+
+    /// (converter id, sibling name) pairs that could back a missing `name` as a converted view.
+    pub fn backing_candidates(&self, name: &str) -> Vec<(usize, String)> {
+        let mut candidates: Vec<(usize, String)> = Vec::new();
+        for (id, converter) in self.converters.iter().enumerate().filter(|(_, c)| !c.from.is_empty()) {
+            let Some(stem) = name.strip_suffix(&format!(".{}", converter.target)) else {
+                continue;
+            };
+            let siblings = if converter.append {
+                let ext = Path::new(stem).extension().unwrap_or_default().to_string_lossy().to_lowercase();
+                if ext != converter.from {
+                    continue;
+                }
+                vec![stem.to_string()]
+            } else {
+                vec![format!("{}.{}", stem, converter.from), format!("{}.{}", stem, converter.from.to_uppercase())]
+            };
+            for sibling in siblings {
+                // A shadowed built-in must not be reached through the same sibling
+                if !candidates.iter().any(|(_, c)| *c == sibling) {
+                    candidates.push((id, sibling));
+                }
+            }
+        }
+        candidates
+    }
+}
+
+pub struct ConversionCache {
+    dir: PathBuf,
+    // Targets being rendered right now; a second request for one waits instead of converting again
+    in_flight: Mutex<HashSet<PathBuf>>,
+    finished: Condvar,
+}
+
+impl ConversionCache {
+    pub fn new(source_path: &Path) -> Self {
+        Self {
+            dir: source_path.join(".eidetic/cache"),
+            in_flight: Mutex::new(HashSet::new()),
+            finished: Condvar::new(),
+        }
+    }
+
+    pub fn path_for(&self, inode: u64, mtime: i64, format: &str) -> PathBuf {
+        self.dir.join(format!("{}_{}.{}", inode, mtime, format))
+    }
+
+    /// Converts `source` into the cache and returns the cached file and its real size.
+    pub fn render(&self, inode: u64, mtime: i64, source: &Path, converter: &Converter) -> Result<(PathBuf, u64)> {
+        self.render_with(inode, mtime, &converter.cache_key, source, |source, target| convert(source, target, converter))
+    }
+
+    /// Like `render`, for renderings that don't come from a Converter (e.g. translations).
+    /// `format` names the rendering in the cache and ends in its real extension.
+    pub fn render_with(&self, inode: u64, mtime: i64, format: &str, source: &Path, render: impl FnOnce(&Path, &Path) -> Result<()>) -> Result<(PathBuf, u64)> {
+        fs::create_dir_all(&self.dir)?;
+        let target = self.path_for(inode, mtime, format);
+
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            while in_flight.contains(&target) {
+                in_flight = self.finished.wait(in_flight).unwrap();
+            }
+            if let Ok(metadata) = fs::metadata(&target) {
+                return Ok((target, metadata.len()));
+            }
+            in_flight.insert(target.clone());
+        }
+        let result = self.render_uncached(inode, mtime, format, source, render, &target);
+        self.in_flight.lock().unwrap().remove(&target);
+        self.finished.notify_all();
+        result
+    }
+
+    fn render_uncached(&self, inode: u64, mtime: i64, format: &str, source: &Path, render: impl FnOnce(&Path, &Path) -> Result<()>, target: &Path) -> Result<(PathBuf, u64)> {
+        // Keep the real extension last, external tools pick the output format from it.
+        // The cache key stays in the prefix so renderings of one source in other formats can run alongside.
+        let (key, ext) = format.rsplit_once('.').map(|(key, ext)| (format!("{}.", key), ext)).unwrap_or((String::new(), format));
+        let prefix = format!("{}_{}.{}part", inode, mtime, key);
+        let partial = self.dir.join(format!("{}.{}", prefix, ext));
+
+        let result = render(source, &partial).and_then(|_| Ok(fs::rename(&partial, target)?));
+        // External tools may leave extra outputs next to the partial file (e.g. heif-convert's `-1` siblings)
+        self.remove_partials(&prefix);
+        result?;
+        self.evict_stale(inode, format, target);
+
+        let size = fs::metadata(target)?.len();
+        Ok((target.to_path_buf(), size))
+    }
+
+    fn remove_partials(&self, prefix: &str) {
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with(prefix) {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+
+    // Drop renderings of older versions of the same source. Only `{inode}_{mtime}.{format}` with
+    // exactly this format matches, so `.jpg` doesn't sweep away `.thumb.jpg`.
+    fn evict_stale(&self, inode: u64, format: &str, keep: &Path) {
+        let prefix = format!("{}_", inode);
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let stale = name.strip_prefix(&prefix)
+                    .and_then(|rest| rest.split_once('.'))
+                    .is_some_and(|(mtime, rest)| mtime.parse::<i64>().is_ok() && rest == format);
+                if stale && entry.path() != keep {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+}
+
+/// Cache key for a source file: its mtime in nanoseconds.
+pub fn mtime_key(metadata: &fs::Metadata) -> i64 {
+    metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+fn convert(source: &Path, target: &Path, converter: &Converter) -> Result<()> {
+    match (&converter.backend, converter.target.as_str()) {
+        (Backend::Command(template), _) => run_command(template, source, target),
+        (Backend::Speech(template), format) => speech(template, source, target, format),
+        (Backend::Builtin(Builtin::Heif), "jpg") => heif_to_jpeg(source, target),
+        (Backend::Builtin(Builtin::Raw), "jpg") => raw_to_jpeg(source, target, RAW_PREVIEW_SIZE),
+        (Backend::Builtin(Builtin::Image), "jpg") => {
+            // JPEG has no alpha channel, flatten to RGB first (in place, large sources shouldn't be held twice)
+            let img = image::open(source)?;
+            image::DynamicImage::ImageRgb8(img.into_rgb8()).save_with_format(target, image::ImageFormat::Jpeg)?;
+            Ok(())
+        }
+        (Backend::Builtin(Builtin::Image), _) => {
+            // Any other format the image crate can write, picked from the extension
+            image::open(source)?.save(target)?;
+            Ok(())
+        }
+        (Backend::Builtin(Builtin::MarkdownHtml), _) => markdown_to_html(source, target),
+        (Backend::Builtin(builtin @ (Builtin::MarkdownPdf | Builtin::TextPdf)), _) => {
+            let text = fs::read_to_string(source)?;
+            let blocks = match builtin {
+                Builtin::TextPdf => pdf::text_blocks(&text),
+                _ => pdf::markdown_blocks(&text),
+            };
+            fs::write(target, pdf::render(&blocks))?;
+            Ok(())
+        }
+        (Backend::Builtin(Builtin::CsvJson), _) => csv_to_json(source, target),
+        (Backend::Builtin(Builtin::JsonCsv), _) => json_to_csv(source, target),
+        (Backend::Builtin(Builtin::Thumbnail), "jpg") => thumbnail(source, target),
+        (Backend::Builtin(Builtin::Audio), format) => transcode_audio(source, target, format),
+        (Backend::Builtin(Builtin::Poster), "jpg") => video_frame(source, target, None),
+        (Backend::Builtin(Builtin::Office), "md") => Ok(fs::write(target, office::to_markdown(source)?)?),
+        (Backend::Builtin(Builtin::PdfText), "txt") => Ok(fs::write(target, pdf_text(source)?)?),
+        (Backend::Builtin(Builtin::Qr), "png") => qr_code(source, target),
+        (Backend::Builtin(builtin), format) => Err(anyhow!("Built-in converter {:?} cannot produce .{}", builtin, format)),
+    }
+}
+
+// QR code of the link in a `.url` file: either a bare URL or the Windows `[InternetShortcut]` form with a `URL=` line
+fn qr_code(source: &Path, target: &Path) -> Result<()> {
+    let text = fs::read_to_string(source)?;
+    let url = text.lines()
+        .find_map(|line| line.trim().strip_prefix("URL="))
+        .unwrap_or(text.trim())
+        .trim();
+    if url.is_empty() {
+        return Err(anyhow!("No link in {:?}", source));
+    }
+    let code = qrcode::QrCode::new(url.as_bytes())?;
+    code.render::<image::Luma<u8>>().min_dimensions(512, 512).build().save_with_format(target, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+// Reads a text or Markdown file aloud. Markdown is flattened to its text first so the
+// engine doesn't spell out the markup.
+fn speech(template: &[String], source: &Path, target: &Path, format: &str) -> Result<()> {
+    let text = fs::read_to_string(source)?;
+    let is_markdown = source.extension().is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"));
+    let text = if is_markdown { markdown_text(&text) } else { text };
+    if text.trim().is_empty() {
+        return Err(anyhow!("Nothing to read aloud in {:?}", source));
+    }
+    // Intermediate files share the partial target's name, so the cache's cleanup sweeps them up
+    let script = target.with_extension("speech.txt");
+    let wav = target.with_extension("wav");
+    fs::write(&script, text)?;
+    run_command(template, &script, &wav)?;
+    transcode_audio(&wav, target, format)
+}
+
+fn markdown_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak => text.push(' '),
+            // Pause after each block; inline ends (emphasis, links) mustn't break the sentence
+            Event::HardBreak | Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock | TagEnd::TableRow)
+                if !text.ends_with('\n') => text.push('\n'),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Text layer of a PDF.
+pub fn pdf_text(source: &Path) -> Result<String> {
+    // pdf-extract panics on some malformed files, don't let that take the calling thread down
+    std::panic::catch_unwind(|| pdf_extract::extract_text(source))
+        .map_err(|_| anyhow!("PDF text extraction crashed on {:?}", source))?
+        .map_err(|e| anyhow!("PDF text extraction failed on {:?}: {}", source, e))
+}
+
+/// Splits a command from the config into its arguments the way a shell would, so quoted
+/// arguments and paths with spaces stay whole. Nothing is expanded and no shell is run.
+pub fn parse_command(command: &str) -> Result<Vec<String>> {
+    let args = shell_words::split(command).map_err(|e| anyhow!("Can't parse command {:?}: {}", command, e))?;
+    if args.is_empty() {
+        return Err(anyhow!("Empty command"));
+    }
+    Ok(args)
+}
+
+/// Runs a user-configured converter. Without an `{output}` argument the tool's stdout becomes the result.
+pub fn run_command(template: &[String], source: &Path, target: &Path) -> Result<()> {
+    let input = source.to_string_lossy();
+    let output = target.to_string_lossy();
+    let args: Vec<String> = template.iter()
+        .map(|arg| arg.replace("{input}", &input).replace("{output}", &output))
+        .collect();
+    let stdout = if template.iter().any(|arg| arg.contains("{output}")) {
+        Stdio::null()
+    } else {
+        Stdio::from(fs::File::create(target)?)
+    };
+    let result = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| anyhow!("{}: {}", args[0], e))?;
+    if !result.status.success() {
+        return Err(anyhow!("{} failed ({}): {}", args[0], result.status, String::from_utf8_lossy(&result.stderr).trim()));
+    }
+    if !target.exists() {
+        return Err(anyhow!("{} produced no output", args[0]));
+    }
+    Ok(())
+}
+
+fn markdown_to_html(source: &Path, target: &Path) -> Result<()> {
+    let markdown = fs::read_to_string(source)?;
+    let title = source.file_stem().unwrap_or_default().to_string_lossy();
+    fs::write(target, html_page(&title, &markdown_html(&markdown)))?;
+    Ok(())
+}
+
+/// HTML body for a Markdown document.
+pub fn markdown_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(markdown, options));
+    body
+}
+
+/// `text` with HTML special characters escaped.
+pub fn escape_html(text: &str) -> String {
+    // A lone text event comes out HTML-escaped
+    let mut escaped = String::new();
+    html::push_html(&mut escaped, std::iter::once(Event::Text(text.into())));
+    escaped
+}
+
+/// A complete HTML document around `body`.
+pub fn html_page(title: &str, body: &str) -> String {
+    let mut page = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    page.push_str(&escape_html(title));
+    page.push_str("</title>\n</head>\n<body>\n");
+    page.push_str(body);
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+// One JSON object per CSV row, keyed by the header line. Values stay strings, CSV has no types.
+fn csv_to_json(source: &Path, target: &Path) -> Result<()> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(source)?;
+    let headers = reader.headers()?.clone();
+    let mut out = BufWriter::new(fs::File::create(target)?);
+    let mut record = csv::StringRecord::new();
+    let mut first = true;
+    out.write_all(b"[")?;
+    while reader.read_record(&mut record)? {
+        out.write_all(if first { b"\n  {" } else { b",\n  {" })?;
+        first = false;
+        for (i, (key, value)) in headers.iter().zip(record.iter()).enumerate() {
+            if i > 0 {
+                out.write_all(b", ")?;
+            }
+            serde_json::to_writer(&mut out, key)?;
+            out.write_all(b": ")?;
+            serde_json::to_writer(&mut out, value)?;
+        }
+        out.write_all(b"}")?;
+    }
+    out.write_all(b"\n]\n")?;
+    out.flush()?;
+    Ok(())
+}
+
+// Two streaming passes: the first collects the union of keys for the header, the second writes the rows
+fn json_to_csv(source: &Path, target: &Path) -> Result<()> {
+    let mut columns: Vec<String> = Vec::new();
+    for_each_json_row(source, |row| {
+        for key in row.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+        Ok(())
+    })?;
+
+    let mut writer = csv::Writer::from_path(target)?;
+    writer.write_record(&columns)?;
+    for_each_json_row(source, |row| {
+        let fields = columns.iter().map(|column| match row.get(column) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        });
+        Ok(writer.write_record(fields)?)
+    })?;
+    writer.flush()?;
+    Ok(())
+}
+
+// Feeds each object of a top-level JSON array (or of newline-delimited JSON) to `row`,
+// without holding the whole document in memory
+fn for_each_json_row(source: &Path, mut row: impl FnMut(Map<String, Value>) -> Result<()>) -> Result<()> {
+    struct Rows<'a, F>(&'a mut F);
+
+    impl<'de, F: FnMut(Map<String, Value>) -> Result<()>> Visitor<'de> for Rows<'_, F> {
+        type Value = ();
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("an array of objects")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+            while let Some(value) = seq.next_element::<Value>()? {
+                let Value::Object(object) = value else {
+                    return Err(de::Error::custom("array element is not an object"));
+                };
+                (self.0)(object).map_err(de::Error::custom)?;
+            }
+            Ok(())
+        }
+    }
+
+    let mut reader = BufReader::new(fs::File::open(source)?);
+    let starts_with_array = loop {
+        let buffer = reader.fill_buf()?;
+        match buffer.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(i) => break buffer[i] == b'[',
+            None if buffer.is_empty() => break false,
+            None => {
+                let len = buffer.len();
+                reader.consume(len);
+            }
+        }
+    };
+
+    if starts_with_array {
+        serde_json::Deserializer::from_reader(reader).deserialize_seq(Rows(&mut row))?;
+    } else {
+        for value in serde_json::Deserializer::from_reader(reader).into_iter::<Value>() {
+            match value? {
+                Value::Object(object) => row(object)?,
+                _ => return Err(anyhow!("Expected a JSON array of objects or newline-delimited objects")),
+            }
+        }
+    }
+    Ok(())
+}
+
+// The image crate can't decode HEIF, so hand it to libheif's CLI (or ImageMagick as a fallback)
+fn heif_to_jpeg(source: &Path, target: &Path) -> Result<()> {
+    let attempts: [(&str, Vec<&std::ffi::OsStr>); 2] = [
+        ("heif-convert", vec!["-q".as_ref(), "90".as_ref(), source.as_os_str(), target.as_os_str()]),
+        ("magick", vec![source.as_os_str(), target.as_os_str()]),
+    ];
+    let mut errors = Vec::new();
+    for (program, args) in attempts {
+        let output = Command::new(program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output();
+        match output {
+            Ok(out) if out.status.success() => {
+                // Multi-image files come out as `<name>-1.jpg`, `<name>-2.jpg`...; the first is the primary image
+                let first = target.with_file_name(format!(
+                    "{}-1.{}",
+                    target.file_stem().unwrap_or_default().to_string_lossy(),
+                    target.extension().unwrap_or_default().to_string_lossy()
+                ));
+                if !target.exists() && first.exists() {
+                    fs::rename(&first, target)?;
+                }
+                if target.exists() {
+                    return Ok(());
+                }
+                errors.push(format!("{}: produced no output", program));
+            }
+            Ok(out) => errors.push(format!("{}: {}", program, String::from_utf8_lossy(&out.stderr).trim())),
+            Err(e) => errors.push(format!("{}: {}", program, e)),
+        }
+    }
+    Err(anyhow!("No HEIF decoder succeeded (install libheif-examples or ImageMagick): {}", errors.join("; ")))
+}
+
+fn thumbnail(source: &Path, target: &Path) -> Result<()> {
+    let ext = source.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    if VIDEO_FORMATS.contains(&ext.as_str()) {
+        return video_frame(source, target, Some(THUMBNAIL_SIZE));
+    }
+    if RAW_FORMATS.contains(&ext.as_str()) {
+        return raw_to_jpeg(source, target, THUMBNAIL_SIZE as usize);
+    }
+    let img = if ext == "heic" || ext == "heif" {
+        // Decode through the external tools first, then shrink like any other image
+        heif_to_jpeg(source, target)?;
+        image::open(target)?
+    } else {
+        image::open(source)?
+    };
+    let small = img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    image::DynamicImage::ImageRgb8(small.to_rgb8()).save_with_format(target, image::ImageFormat::Jpeg)?;
+    Ok(())
+}
+
+// Grabs a frame one second in (or the first frame of very short clips) with ffmpeg
+fn video_frame(source: &Path, target: &Path, max_edge: Option<u32>) -> Result<()> {
+    let mut last_error = String::new();
+    for seek in ["1", "0"] {
+        let mut args: Vec<std::ffi::OsString> = vec!["-v".into(), "error".into(), "-y".into(), "-ss".into(), seek.into(), "-i".into(), source.into(), "-frames:v".into(), "1".into()];
+        if let Some(edge) = max_edge {
+            args.push("-vf".into());
+            args.push(format!("scale='min({0},iw)':'min({0},ih)':force_original_aspect_ratio=decrease", edge).into());
+        }
+        args.push(target.into());
+        let output = Command::new("ffmpeg")
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| anyhow!("ffmpeg: {} (is ffmpeg installed?)", e))?;
+        if output.status.success() && target.exists() {
+            return Ok(());
+        }
+        last_error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    }
+    Err(anyhow!("ffmpeg could not extract a frame: {}", last_error))
+}
+
+// Transcodes with ffmpeg, keeping the tags but not embedded cover art (a video stream to ffmpeg)
+fn transcode_audio(source: &Path, target: &Path, format: &str) -> Result<()> {
+    let codec: &[&str] = match format {
+        "mp3" => &["-c:a", "libmp3lame", "-q:a", "2"],
+        "opus" => &["-c:a", "libopus", "-b:a", "160k"],
+        "ogg" => &["-c:a", "libvorbis", "-q:a", "6"],
+        "m4a" => &["-c:a", "aac", "-b:a", "256k"],
+        _ => return Err(anyhow!("No audio codec for .{}", format)),
+    };
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-i"])
+        .arg(source)
+        .args(["-map", "0:a", "-map_metadata", "0"])
+        .args(codec)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| anyhow!("ffmpeg: {} (is ffmpeg installed?)", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// Develops a RAW file with imagepipe's default pipeline, downscaled to `max_edge`
+fn raw_to_jpeg(source: &Path, target: &Path, max_edge: usize) -> Result<()> {
+    let decoded = imagepipe::simple_decode_8bit(source, max_edge, max_edge)
+        .map_err(|e| anyhow!("RAW decode failed: {}", e))?;
+    let img = image::RgbImage::from_raw(decoded.width as u32, decoded.height as u32, decoded.data)
+        .ok_or_else(|| anyhow!("RAW decode produced a malformed buffer"))?;
+    img.save_with_format(target, image::ImageFormat::Jpeg)?;
+    Ok(())
+}
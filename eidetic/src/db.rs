@@ -1,6 +1,67 @@
 use rusqlite::{params, Connection, Result, OptionalExtension};
 use std::path::Path;
-use anyhow::Context;
+
+/// A row of photo_metadata joined with its file name.
+pub struct Photo {
+    pub inode: u64,
+    pub name: String,
+    pub taken: Option<String>,
+    pub camera: Option<String>,
+}
+
+/// A file whose content no longer matches its recorded checksum.
+pub struct Corruption {
+    pub inode: u64,
+    pub expected: String,
+    pub actual: String,
+    /// "2024-05-01"
+    pub detected: String,
+}
+
+/// A move made by accepting an organizer proposal, see `eidetic organize undo`.
+pub struct OrganizerMove {
+    pub id: i64,
+    pub inode: u64,
+    pub from: String,
+    pub to: String,
+    /// "2024-05-01 12:00:00"
+    pub moved: String,
+    pub undone: Option<String>,
+}
+
+/// A row of music_metadata joined with its file name.
+pub struct Track {
+    pub inode: u64,
+    pub name: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+/// An analyzed file with what file_info recorded about it, for smart folder queries.
+pub struct IndexedFile {
+    pub inode: u64,
+    pub name: String,
+    pub size: u64,
+    /// Local "2024-05-01" of the modification time
+    pub date: String,
+    pub kind: Option<String>,
+    pub tags: Vec<String>,
+    /// Text of `<name>.comments`
+    pub comment: Option<String>,
+    /// Stars, 0 if unrated
+    pub rating: u8,
+}
+
+/// Tables holding rows about a file, by `inode_id`; they go with the inode when it's deleted so
+/// a reused inode number doesn't inherit them. Add new per-file tables here. file_history,
+/// organizer_moves and jobs are left out: snapshots and the undo log outlive the file, and the
+/// worker drops jobs for files that are gone.
+const PER_INODE_TABLES: &[&str] = &[
+    "file_tags", "reindexed_tags", "tag_links", "file_sources", "conversions", "photo_metadata", "music_metadata",
+    "summaries", "checksums", "file_info", "dir_usage", "file_access", "embeddings", "rename_suggestions",
+    "sensitive_findings", "transcripts", "image_hashes", "clusters", "proposals", "favorites", "todos", "todos_done",
+    "note_links", "text_stats", "lint_findings", "comments", "ratings", "labels", "wormhole_received",
+];
 
 pub struct Database {
     conn: Connection,
@@ -8,11 +69,13 @@ pub struct Database {
 
 impl Database {
     pub fn new<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let conn = Connection::open(path)?;
+        let mut conn = Connection::open(path)?;
+        conn.profile(Some(crate::metrics::record_query));
         
         // Optimize for performance
-        conn.execute("PRAGMA journal_mode = WAL;", [])?;
-        conn.execute("PRAGMA synchronous = NORMAL;", [])?;
+        // journal_mode returns a row, so it can't go through execute()
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
         
         // Create tables
         conn.execute(
@@ -27,6 +90,19 @@ impl Database {
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS file_tags (
+                inode_id INTEGER,
+                tag TEXT,
+                manual INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY(inode_id, tag)
+            )",
+            [],
+        )?;
+        // Older DBs predate `manual` (tags given at ingest, kept across reindexes)
+        let _ = conn.execute("ALTER TABLE file_tags ADD COLUMN manual INTEGER NOT NULL DEFAULT 0", []);
+
+        // Analysis tags a reindex cleared; the same tag coming back isn't a new event
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS reindexed_tags (
                 inode_id INTEGER,
                 tag TEXT,
                 PRIMARY KEY(inode_id, tag)
@@ -43,6 +119,8 @@ impl Database {
             )",
             [],
         )?;
+        // Whether the snapshot was copied to the `[s3]` bucket, see offsite.rs
+        let _ = conn.execute("ALTER TABLE file_history ADD COLUMN uploaded INTEGER NOT NULL DEFAULT 0", []);
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS trash (
@@ -54,6 +132,359 @@ impl Database {
             [],
         )?;
         
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_sources (
+                inode_id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                ingested_at INTEGER
+            )",
+            [],
+        )?;
+        
+        // Converted views (e.g. PNG served as JPG), keyed by source inode + mtime
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS conversions (
+                inode_id INTEGER,
+                format TEXT,
+                source_mtime INTEGER,
+                size INTEGER,
+                PRIMARY KEY(inode_id, format)
+            )",
+            [],
+        )?;
+        
+        // EXIF of photos, filled in by the worker
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS photo_metadata (
+                inode_id INTEGER PRIMARY KEY,
+                taken TEXT,
+                camera TEXT,
+                lens TEXT
+            )",
+            [],
+        )?;
+        
+        // Artist / album / title of audio files, filled in by the worker
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS music_metadata (
+                inode_id INTEGER PRIMARY KEY,
+                artist TEXT,
+                album TEXT,
+                title TEXT
+            )",
+            [],
+        )?;
+        
+        // Document summaries produced by the worker's Summarizer
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS summaries (
+                inode_id INTEGER PRIMARY KEY,
+                summary TEXT NOT NULL,
+                created_at INTEGER
+            )",
+            [],
+        )?;
+        
+        // Speech-to-text of recordings, see transcribe.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS transcripts (
+                inode_id INTEGER PRIMARY KEY,
+                text TEXT NOT NULL,
+                created_at INTEGER
+            )",
+            [],
+        )?;
+
+        // What the sensitive data scanner found, by kind and line (never the value itself)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS sensitive_findings (
+                inode_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                line INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Topic cluster label of each embedded document, rewritten wholesale by cluster.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS clusters (
+                inode_id INTEGER PRIMARY KEY,
+                label TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Perceptual hash of each image (u64 stored as its i64 bits), see phash.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS image_hashes (
+                inode_id INTEGER PRIMARY KEY,
+                hash INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Moves proposed by the auto-organizer, applied once accepted in `.magic/proposals/`.
+        // `destination` is the source-relative target path; dismissed ones stay so they aren't proposed again.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proposals (
+                inode_id INTEGER PRIMARY KEY,
+                destination TEXT NOT NULL,
+                dismissed INTEGER DEFAULT 0
+            )",
+            [],
+        )?;
+
+        // Size, modification time (unix seconds) and detected kind (filetype::detect) of each
+        // file as of its last analysis, for the by-date, by-size and by-type views
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_info (
+                inode_id INTEGER PRIMARY KEY,
+                size INTEGER NOT NULL,
+                mtime INTEGER NOT NULL,
+                kind TEXT
+            )",
+            [],
+        )?;
+        let _ = conn.execute("ALTER TABLE file_info ADD COLUMN kind TEXT", []);
+
+        // Files received through `.magic/wormhole/receive`: the code, who sent them and the
+        // checksum they arrived with, for `.magic/wormhole/inbox/`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS wormhole_received (
+                inode_id INTEGER PRIMARY KEY,
+                code TEXT NOT NULL,
+                sender TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                checksum TEXT NOT NULL,
+                received_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Symlinks made for each file by `eidetic materialize-tags`, see materialize.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS tag_links (
+                inode_id INTEGER NOT NULL,
+                link TEXT NOT NULL,
+                PRIMARY KEY (inode_id, link)
+            )",
+            [],
+        )?;
+
+        // Total size and file count under each directory (the root is inode 1), kept up to date
+        // as file_info changes and files move or go, for `.magic/usage/`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS dir_usage (
+                inode_id INTEGER PRIMARY KEY,
+                bytes INTEGER NOT NULL,
+                files INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // When each real file was last opened or created through the mount, for `.magic/recent/`,
+        // and how often it was opened for reading and for writing (`.magic/heatmap.md`)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_access (
+                inode_id INTEGER PRIMARY KEY,
+                accessed_at INTEGER NOT NULL,
+                reads INTEGER NOT NULL DEFAULT 0,
+                writes INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        let _ = conn.execute("ALTER TABLE file_access ADD COLUMN reads INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE file_access ADD COLUMN writes INTEGER NOT NULL DEFAULT 0", []);
+
+        // Every organizer move that was carried out, so a bad rule can be rolled back
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS organizer_moves (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                inode_id INTEGER NOT NULL,
+                from_path TEXT NOT NULL,
+                to_path TEXT NOT NULL,
+                moved_at INTEGER NOT NULL,
+                undone_at INTEGER
+            )",
+            [],
+        )?;
+
+        // TODO/FIXME lines found in text files, for `.magic/todos.md`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS todos (
+                inode_id INTEGER NOT NULL,
+                line INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                PRIMARY KEY (inode_id, line)
+            )",
+            [],
+        )?;
+
+        // TODOs acknowledged by deleting them from `.magic/todos/`, by text so they stay
+        // done when edits move them to another line
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS todos_done (
+                inode_id INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                done_at INTEGER NOT NULL,
+                PRIMARY KEY (inode_id, content)
+            )",
+            [],
+        )?;
+
+        // Links out of each Markdown note ("wiki:<name>" or "path:<path>"), see links.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS note_links (
+                inode_id INTEGER NOT NULL,
+                target TEXT NOT NULL,
+                PRIMARY KEY (inode_id, target)
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_note_links_target ON note_links(target)", [])?;
+
+        // Word and line counts of text documents, see reading.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS text_stats (
+                inode_id INTEGER PRIMARY KEY,
+                words INTEGER NOT NULL,
+                lines INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Proofreading findings of prose files, for `<name>.md.lint`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS lint_findings (
+                inode_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                line INTEGER NOT NULL,
+                message TEXT NOT NULL,
+                PRIMARY KEY (inode_id, position)
+            )",
+            [],
+        )?;
+
+        // Free-text notes written to `<name>.comments`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS comments (
+                inode_id INTEGER PRIMARY KEY,
+                comment TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Star ratings (1-5), see ratings.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ratings (
+                inode_id INTEGER PRIMARY KEY,
+                stars INTEGER NOT NULL,
+                rated_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Color labels, see labels.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS labels (
+                inode_id INTEGER PRIMARY KEY,
+                color TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Audit trail of what Eidetic did to files, see events.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                at INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                path TEXT NOT NULL,
+                detail TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_events_at ON events(at)", [])?;
+
+        // Files pinned to `.magic/favorites/`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS favorites (
+                inode_id INTEGER PRIMARY KEY,
+                pinned_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Descriptive names proposed for IMG_2031.png and friends, see naming.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS rename_suggestions (
+                inode_id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // blake3 of each file's content as of its last analysis, re-verified by the scrubber.
+        // `corrupt_hash` is set when the content changed while size and mtime didn't.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS checksums (
+                inode_id INTEGER PRIMARY KEY,
+                hash TEXT NOT NULL,
+                size INTEGER,
+                mtime INTEGER,
+                verified_at INTEGER,
+                corrupt_hash TEXT,
+                detected_at INTEGER
+            )",
+            [],
+        )?;
+
+        // Embedding vectors (little-endian f32) of text chunks, from the worker's optional embedding stage
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS embeddings (
+                inode_id INTEGER,
+                chunk INTEGER,
+                start INTEGER,
+                model TEXT NOT NULL,
+                vector BLOB NOT NULL,
+                PRIMARY KEY(inode_id, chunk)
+            )",
+            [],
+        )?;
+
+        // Analyze jobs, persisted before they're handed to the worker so a crash doesn't lose them
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY,
+                inode_id INTEGER,
+                path TEXT NOT NULL,
+                queued_at INTEGER,
+                done_at INTEGER,
+                error TEXT
+            )",
+            [],
+        )?;
+        let _ = conn.execute("ALTER TABLE jobs ADD COLUMN error TEXT", []);
+        
+        // Running totals for `stats.md`, see stats.rs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS counters (
+                name TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Daemon bookkeeping (e.g. when the initial scan finished)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS meta (
+                key TEXT PRIMARY KEY,
+                value TEXT
+            )",
+            [],
+        )?;
+        
         // Ensure root exists (inode 1)
         // We use INSERT OR IGNORE. 
         // Note: SQLite autoincrement usually starts at 1, but we can force it.
@@ -81,6 +512,75 @@ impl Database {
         Ok(self.conn.last_insert_rowid() as u64)
     }
 
+    /// Resolves (or allocates) inodes for a batch of children of `parent`
+    /// inside a single transaction. Returned ids line up with `names`.
+    pub fn alloc_inodes(&self, parent: u64, names: &[String]) -> Result<Vec<u64>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut ids = Vec::with_capacity(names.len());
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR IGNORE INTO inodes (parent_id, name) VALUES (?1, ?2)",
+            )?;
+            let mut select = tx.prepare_cached(
+                "SELECT id FROM inodes WHERE parent_id = ?1 AND name = ?2",
+            )?;
+            for name in names {
+                insert.execute(params![parent, name])?;
+                ids.push(select.query_row(params![parent, name], |row| row.get(0))?);
+            }
+        }
+        tx.commit()?;
+        Ok(ids)
+    }
+
+    /// Walks a source-relative path ("a/b/c.txt") from the root, allocating
+    /// any missing components. Returns the inode of the last component.
+    pub fn alloc_path(&self, path: &str) -> Result<u64> {
+        let mut current = 1;
+        for name in path.split('/').filter(|part| !part.is_empty()) {
+            current = match self.get_inode(current, name)? {
+                Some(inode) => inode,
+                None => self.create_inode(current, name)?,
+            };
+        }
+        Ok(current)
+    }
+
+    /// Inode of a source-relative path, without allocating missing components.
+    pub fn lookup_path(&self, path: &str) -> Result<Option<u64>> {
+        let mut current = 1;
+        for name in path.split('/').filter(|part| !part.is_empty()) {
+            match self.get_inode(current, name)? {
+                Some(inode) => current = inode,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(current))
+    }
+
+    /// Source-relative path of an inode ("" for the root), or None if it or a parent is gone.
+    /// The path relative to the root, or None for an unknown inode or one whose parents loop
+    /// (a corrupt table), never a partial path.
+    pub fn get_path(&self, inode: u64) -> Result<Option<String>> {
+        let mut parts = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        let mut current = inode;
+        while current != 1 {
+            if !seen.insert(current) {
+                return Ok(None);
+            }
+            match self.get_inode_entry(current)? {
+                Some((parent, name)) => {
+                    parts.push(name);
+                    current = parent;
+                }
+                None => return Ok(None),
+            }
+        }
+        parts.reverse();
+        Ok(Some(parts.join("/")))
+    }
+
     pub fn get_inode_entry(&self, inode: u64) -> Result<Option<(u64, String)>> {
          self.conn.query_row(
             "SELECT parent_id, name FROM inodes WHERE id = ?1",
@@ -90,13 +590,43 @@ impl Database {
     }
 
     pub fn add_tag(&self, inode: u64, tag: &str) -> Result<()> {
-        self.conn.execute(
+        let added = self.conn.execute(
             "INSERT OR IGNORE INTO file_tags (inode_id, tag) VALUES (?1, ?2)",
             params![inode, tag],
         )?;
+        let restored = self.conn.execute(
+            "DELETE FROM reindexed_tags WHERE inode_id = ?1 AND tag = ?2",
+            params![inode, tag],
+        )?;
+        if added > 0 && restored == 0 {
+            self.add_inode_event(crate::events::TAG, inode, tag)?;
+        }
+        Ok(())
+    }
+
+    /// A tag the user gave explicitly; unlike analysis tags it survives a reindex.
+    pub fn add_manual_tag(&self, inode: u64, tag: &str) -> Result<()> {
+        let known = self.get_file_tags(inode)?.iter().any(|t| t == tag);
+        self.conn.execute(
+            "INSERT INTO file_tags (inode_id, tag, manual) VALUES (?1, ?2, 1)
+             ON CONFLICT(inode_id, tag) DO UPDATE SET manual = 1",
+            params![inode, tag],
+        )?;
+        if !known {
+            self.add_inode_event(crate::events::TAG, inode, tag)?;
+        }
         Ok(())
     }
 
+    /// (tag, files carrying it) of every tag, by tag.
+    pub fn tag_counts(&self) -> Result<Vec<(String, usize)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.tag, COUNT(*) FROM file_tags t JOIN inodes i ON i.id = t.inode_id GROUP BY t.tag ORDER BY t.tag"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?;
+        rows.collect()
+    }
+
     pub fn get_tags(&self) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare("SELECT DISTINCT tag FROM file_tags")?;
         let rows = stmt.query_map([], |row| row.get(0))?;
@@ -107,6 +637,22 @@ impl Database {
         Ok(tags)
     }
 
+    pub fn get_file_tags(&self, inode: u64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT tag FROM file_tags WHERE inode_id = ?1 ORDER BY tag")?;
+        let rows = stmt.query_map(params![inode], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// A file's tags along with the ones a reindex cleared and analysis hasn't given back yet,
+    /// so what comes back isn't reported as new.
+    pub fn get_known_tags(&self, inode: u64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT tag FROM file_tags WHERE inode_id = ?1 UNION SELECT tag FROM reindexed_tags WHERE inode_id = ?1 ORDER BY tag"
+        )?;
+        let rows = stmt.query_map(params![inode], |row| row.get(0))?;
+        rows.collect()
+    }
+
     pub fn get_files_with_tag(&self, tag: &str) -> Result<Vec<(u64, String)>> {
         // returning inode and name
         let mut stmt = self.conn.prepare(
@@ -126,28 +672,1266 @@ impl Database {
             "INSERT INTO file_history (inode_id, timestamp, backup_path) VALUES (?1, ?2, ?3)",
             params![inode, timestamp, path],
         )?;
-        Ok(())
+        self.add_inode_event(crate::events::SNAPSHOT, inode, path)
     }
 
-    pub fn add_trash(&self, original_path: &str, backup_path: &str) -> Result<()> {
-        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
-        self.conn.execute(
-            "INSERT INTO trash (original_path, backup_path, deleted_at) VALUES (?1, ?2, ?3)",
-            params![original_path, backup_path, timestamp],
+    /// (timestamp, snapshot path) of a file's history snapshots, newest first.
+    pub fn get_history(&self, inode: u64) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT timestamp, backup_path FROM file_history WHERE inode_id = ?1 ORDER BY timestamp DESC, id DESC",
+        )?;
+        let rows = stmt.query_map(params![inode], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    pub fn get_tag_links(&self, inode: u64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT link FROM tag_links WHERE inode_id = ?1")?;
+        let rows = stmt.query_map(params![inode], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn set_tag_links(&self, inode: u64, links: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM tag_links WHERE inode_id = ?1", params![inode])?;
+        for link in links {
+            tx.execute("INSERT OR IGNORE INTO tag_links (inode_id, link) VALUES (?1, ?2)", params![inode, link])?;
+        }
+        tx.commit()
+    }
+
+    /// The file a tag link was made for.
+    pub fn tag_link_owner(&self, link: &str) -> Result<Option<u64>> {
+        self.conn.query_row("SELECT inode_id FROM tag_links WHERE link = ?1", params![link], |row| row.get(0)).optional()
+    }
+
+    pub fn tag_link_inodes(&self) -> Result<Vec<u64>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT inode_id FROM tag_links")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// (id, inode, timestamp, snapshot path) of snapshots not in the `[s3]` bucket yet, oldest first.
+    pub fn history_to_upload(&self) -> Result<Vec<(i64, u64, i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, inode_id, timestamp, backup_path FROM file_history WHERE uploaded = 0 ORDER BY id",
         )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect()
+    }
+
+    pub fn set_history_uploaded(&self, id: i64) -> Result<()> {
+        self.conn.execute("UPDATE file_history SET uploaded = 1 WHERE id = ?1", params![id])?;
         Ok(())
     }
 
-    pub fn delete_inode(&self, inode: u64) -> Result<()> {
-        self.conn.execute("DELETE FROM inodes WHERE id = ?", params![inode])?;
+    /// Paths of snapshots already in the `[s3]` bucket, oldest first, for trimming the local copies.
+    pub fn uploaded_history(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT backup_path FROM file_history WHERE uploaded = 1 ORDER BY id")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// The local path of a file's snapshot taken at `timestamp`, if one is recorded.
+    pub fn history_at(&self, inode: u64, timestamp: i64) -> Result<Option<String>> {
+        self.conn.query_row(
+            "SELECT backup_path FROM file_history WHERE inode_id = ?1 AND timestamp = ?2",
+            params![inode, timestamp],
+            |row| row.get(0),
+        ).optional()
+    }
+
+    /// Records a snapshot downloaded from the `[s3]` bucket, without logging it as a new one.
+    pub fn restore_history(&self, inode: u64, timestamp: i64, path: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO file_history (inode_id, timestamp, backup_path, uploaded) VALUES (?1, ?2, ?3, 1)",
+            params![inode, timestamp, path],
+        )?;
         Ok(())
     }
 
-    pub fn rename_inode(&self, inode: u64, new_parent: u64, new_name: &str) -> Result<()> {
+    pub fn set_source_url(&self, inode: u64, url: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
         self.conn.execute(
-            "UPDATE inodes SET parent_id = ?1, name = ?2 WHERE id = ?3",
-            params![new_parent, new_name, inode],
+            "INSERT OR REPLACE INTO file_sources (inode_id, url, ingested_at) VALUES (?1, ?2, ?3)",
+            params![inode, url, timestamp],
         )?;
         Ok(())
     }
+
+    /// Cached conversion for a source version. A NULL size records a failed conversion.
+    pub fn get_conversion(&self, inode: u64, format: &str) -> Result<Option<(i64, Option<u64>)>> {
+        self.conn.query_row(
+            "SELECT source_mtime, size FROM conversions WHERE inode_id = ?1 AND format = ?2",
+            params![inode, format],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()
+    }
+
+    pub fn set_conversion(&self, inode: u64, format: &str, source_mtime: i64, size: Option<u64>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO conversions (inode_id, format, source_mtime, size) VALUES (?1, ?2, ?3, ?4)",
+            params![inode, format, source_mtime, size],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_photo_metadata(&self, inode: u64, taken: Option<&str>, camera: Option<&str>, lens: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO photo_metadata (inode_id, taken, camera, lens) VALUES (?1, ?2, ?3, ?4)",
+            params![inode, taken, camera, lens],
+        )?;
+        Ok(())
+    }
+
+    /// When the photo was taken, per EXIF
+    pub fn get_photo_taken(&self, inode: u64) -> Result<Option<String>> {
+        self.conn.query_row("SELECT taken FROM photo_metadata WHERE inode_id = ?1", params![inode], |row| row.get(0))
+            .optional()
+            .map(Option::flatten)
+    }
+
+    /// Every photo with EXIF, oldest first
+    pub fn get_photos(&self) -> Result<Vec<Photo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, p.taken, p.camera FROM inodes i JOIN photo_metadata p ON i.id = p.inode_id ORDER BY p.taken"
+        )?;
+        let rows = stmt.query_map([], |row| Ok(Photo {
+            inode: row.get(0)?,
+            name: row.get(1)?,
+            taken: row.get(2)?,
+            camera: row.get(3)?,
+        }))?;
+        let mut photos = Vec::new();
+        for photo in rows {
+            photos.push(photo?);
+        }
+        Ok(photos)
+    }
+
+    pub fn set_music_metadata(&self, inode: u64, artist: Option<&str>, album: Option<&str>, title: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO music_metadata (inode_id, artist, album, title) VALUES (?1, ?2, ?3, ?4)",
+            params![inode, artist, album, title],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_tracks(&self) -> Result<Vec<Track>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, m.artist, m.album FROM inodes i JOIN music_metadata m ON i.id = m.inode_id"
+        )?;
+        let rows = stmt.query_map([], |row| Ok(Track {
+            inode: row.get(0)?,
+            name: row.get(1)?,
+            artist: row.get(2)?,
+            album: row.get(3)?,
+        }))?;
+        let mut tracks = Vec::new();
+        for track in rows {
+            tracks.push(track?);
+        }
+        Ok(tracks)
+    }
+
+    pub fn set_summary(&self, inode: u64, summary: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO summaries (inode_id, summary, created_at) VALUES (?1, ?2, ?3)",
+            params![inode, summary, timestamp],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_summary(&self, inode: u64) -> Result<Option<String>> {
+        self.conn.query_row(
+            "SELECT summary FROM summaries WHERE inode_id = ?1",
+            params![inode],
+            |row| row.get(0),
+        ).optional()
+    }
+
+    /// Replaces a file's sensitive data findings, tagging it `sensitive` while there are any.
+    pub fn set_sensitive_findings(&self, inode: u64, findings: &[(&str, usize)]) -> Result<()> {
+        // A reindex clears the findings but leaves the `sensitive` tag in reindexed_tags
+        let newly_flagged = !findings.is_empty() && !self.was_flagged(inode)?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM sensitive_findings WHERE inode_id = ?1", params![inode])?;
+        tx.execute("DELETE FROM reindexed_tags WHERE inode_id = ?1 AND tag = 'sensitive'", params![inode])?;
+        for (kind, line) in findings {
+            tx.execute("INSERT INTO sensitive_findings (inode_id, kind, line) VALUES (?1, ?2, ?3)", params![inode, kind, *line as i64])?;
+        }
+        if findings.is_empty() {
+            tx.execute("DELETE FROM file_tags WHERE inode_id = ?1 AND tag = 'sensitive' AND manual = 0", params![inode])?;
+        } else {
+            tx.execute("INSERT OR IGNORE INTO file_tags (inode_id, tag) VALUES (?1, 'sensitive')", params![inode])?;
+        }
+        if newly_flagged {
+            let mut kinds: Vec<&str> = findings.iter().map(|(kind, _)| *kind).collect();
+            kinds.sort();
+            kinds.dedup();
+            self.add_inode_event(crate::events::SENSITIVE, inode, &kinds.join(", "))?;
+        }
+        tx.commit()
+    }
+
+    /// (inode, kind, line) of every finding, grouped by file
+    pub fn get_sensitive_findings(&self) -> Result<Vec<(u64, String, usize)>> {
+        let mut stmt = self.conn.prepare("SELECT inode_id, kind, line FROM sensitive_findings ORDER BY inode_id, line")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as usize)))?;
+        rows.collect()
+    }
+
+    /// Whether the scanner flagged the file before, even if a reindex has cleared its findings since.
+    pub fn was_flagged(&self, inode: u64) -> Result<bool> {
+        Ok(self.is_sensitive(inode)? || self.get_known_tags(inode)?.iter().any(|tag| tag == "sensitive"))
+    }
+
+    pub fn is_sensitive(&self, inode: u64) -> Result<bool> {
+        self.conn.query_row("SELECT EXISTS(SELECT 1 FROM sensitive_findings WHERE inode_id = ?1)", params![inode], |row| row.get(0))
+    }
+
+    pub fn set_transcript(&self, inode: u64, text: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO transcripts (inode_id, text, created_at) VALUES (?1, ?2, ?3)",
+            params![inode, text, timestamp],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_transcript(&self, inode: u64) -> Result<Option<String>> {
+        self.conn.query_row("SELECT text FROM transcripts WHERE inode_id = ?1", params![inode], |row| row.get(0)).optional()
+    }
+
+    /// Children of `parent` that have a transcript.
+    pub fn transcribed_children(&self, parent: u64) -> Result<std::collections::HashSet<u64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.inode_id FROM transcripts t JOIN inodes i ON i.id = t.inode_id WHERE i.parent_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![parent], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn set_rename_suggestion(&self, inode: u64, name: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO rename_suggestions (inode_id, name) VALUES (?1, ?2)",
+            params![inode, name],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_rename_suggestion(&self, inode: u64) -> Result<()> {
+        self.conn.execute("DELETE FROM rename_suggestions WHERE inode_id = ?1", params![inode])?;
+        Ok(())
+    }
+
+    /// (inode, current name, suggested name) of every pending suggestion
+    pub fn get_rename_suggestions(&self) -> Result<Vec<(u64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, r.name FROM inodes i JOIN rename_suggestions r ON i.id = r.inode_id ORDER BY r.name"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Proposes moving `inode` to `destination`, unless a proposal for it was dismissed.
+    pub fn set_proposal(&self, inode: u64, destination: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO proposals (inode_id, destination) VALUES (?1, ?2)
+             ON CONFLICT(inode_id) DO UPDATE SET destination = excluded.destination WHERE dismissed = 0",
+            params![inode, destination],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_proposal(&self, inode: u64) -> Result<()> {
+        self.conn.execute("DELETE FROM proposals WHERE inode_id = ?1", params![inode])?;
+        Ok(())
+    }
+
+    pub fn dismiss_proposal(&self, inode: u64) -> Result<()> {
+        self.conn.execute("UPDATE proposals SET dismissed = 1 WHERE inode_id = ?1", params![inode])?;
+        Ok(())
+    }
+
+    /// (inode, name, destination) of every pending proposal
+    pub fn get_proposals(&self) -> Result<Vec<(u64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, p.destination FROM inodes i JOIN proposals p ON i.id = p.inode_id
+             WHERE p.dismissed = 0 ORDER BY i.name, i.id"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    pub fn set_file_info(&self, inode: u64, size: u64, mtime: i64, kind: &str) -> Result<()> {
+        let previous: Option<i64> = self.conn.query_row("SELECT size FROM file_info WHERE inode_id = ?1", params![inode], |row| row.get(0)).optional()?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO file_info (inode_id, size, mtime, kind) VALUES (?1, ?2, ?3, ?4)",
+            params![inode, size, mtime, kind],
+        )?;
+        let (bytes, files) = match previous {
+            Some(previous) => (size as i64 - previous, 0),
+            None => (size as i64, 1),
+        };
+        if bytes != 0 || files != 0 {
+            if let Some((parent, _)) = self.get_inode_entry(inode)? {
+                self.add_usage(parent, bytes, files)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the size of an already analyzed file as soon as it's written, ahead of the worker
+    /// re-analyzing it, so directory rollups don't lag behind.
+    pub fn update_file_size(&self, inode: u64, size: u64) -> Result<()> {
+        let previous: Option<i64> = self.conn.query_row("SELECT size FROM file_info WHERE inode_id = ?1", params![inode], |row| row.get(0)).optional()?;
+        let Some(previous) = previous else { return Ok(()) };
+        if previous == size as i64 {
+            return Ok(());
+        }
+        self.conn.execute("UPDATE file_info SET size = ?1 WHERE inode_id = ?2", params![size, inode])?;
+        if let Some((parent, _)) = self.get_inode_entry(inode)? {
+            self.add_usage(parent, size as i64 - previous, 0)?;
+        }
+        Ok(())
+    }
+
+    // Adds to the rollup of `dir` and of every directory above it
+    fn add_usage(&self, dir: u64, bytes: i64, files: i64) -> Result<()> {
+        let mut current = dir;
+        // Bounded like get_path in case of a parent loop
+        for _ in 0..100 {
+            self.conn.execute(
+                "INSERT INTO dir_usage (inode_id, bytes, files) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(inode_id) DO UPDATE SET bytes = bytes + ?2, files = files + ?3",
+                params![current, bytes, files],
+            )?;
+            match self.get_inode_entry(current)? {
+                Some((parent, _)) if current != 1 => current = parent,
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    // (bytes, files) an inode adds to the rollups above it: its own for a directory, its size for a file
+    fn usage_of(&self, inode: u64) -> Result<(i64, i64)> {
+        if let Some(usage) = self.dir_usage(inode)? {
+            return Ok((usage.0 as i64, usage.1 as i64));
+        }
+        let size: Option<i64> = self.conn.query_row("SELECT size FROM file_info WHERE inode_id = ?1", params![inode], |row| row.get(0)).optional()?;
+        Ok(size.map_or((0, 0), |size| (size, 1)))
+    }
+
+    // Takes an inode's usage off the directories above it, ahead of it moving or going away
+    fn remove_usage(&self, inode: u64) -> Result<(i64, i64)> {
+        let (bytes, files) = self.usage_of(inode)?;
+        if bytes != 0 || files != 0 {
+            if let Some((parent, _)) = self.get_inode_entry(inode)? {
+                self.add_usage(parent, -bytes, -files)?;
+            }
+        }
+        Ok((bytes, files))
+    }
+
+    /// (bytes, files) under a directory, or None if nothing under it was analyzed yet.
+    pub fn dir_usage(&self, dir: u64) -> Result<Option<(u64, u64)>> {
+        self.conn.query_row(
+            "SELECT bytes, files FROM dir_usage WHERE inode_id = ?1",
+            params![dir],
+            |row| Ok((row.get::<_, i64>(0)?.max(0) as u64, row.get::<_, i64>(1)?.max(0) as u64)),
+        ).optional()
+    }
+
+    /// (inode, name, bytes, files) of the subdirectories of `dir` holding anything, largest first.
+    pub fn child_dir_usage(&self, dir: u64) -> Result<Vec<(u64, String, u64, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, u.bytes, u.files FROM inodes i JOIN dir_usage u ON i.id = u.inode_id
+             WHERE i.parent_id = ?1 AND i.id != 1 AND u.files > 0 ORDER BY u.bytes DESC, i.name",
+        )?;
+        let rows = stmt.query_map(params![dir], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)?.max(0) as u64, row.get::<_, i64>(3)? as u64)))?;
+        rows.collect()
+    }
+
+    /// (inode, name, size) of the analyzed files directly in `dir`, largest first.
+    pub fn child_file_sizes(&self, dir: u64) -> Result<Vec<(u64, String, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, f.size FROM inodes i JOIN file_info f ON i.id = f.inode_id
+             WHERE i.parent_id = ?1 ORDER BY f.size DESC, i.name",
+        )?;
+        let rows = stmt.query_map(params![dir], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Recomputes every directory's rollup from file_info, e.g. for a database from before rollups.
+    pub fn rebuild_usage(&self) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM dir_usage", [])?;
+        for (inode, _, size) in self.get_file_sizes()? {
+            if let Some((parent, _)) = self.get_inode_entry(inode)? {
+                self.add_usage(parent, size as i64, 1)?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// (inode, name, size) of every analyzed file, largest first.
+    pub fn get_file_sizes(&self) -> Result<Vec<(u64, String, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, f.size FROM inodes i JOIN file_info f ON i.id = f.inode_id
+             ORDER BY f.size DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// (size, mtime, kind) of an analyzed file.
+    pub fn get_file_info(&self, inode: u64) -> Result<Option<(u64, i64, Option<String>)>> {
+        self.conn.query_row(
+            "SELECT size, mtime, kind FROM file_info WHERE inode_id = ?1",
+            params![inode],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional()
+    }
+
+    /// Every analyzed file with its size, date, kind and tags, by name.
+    pub fn get_indexed_files(&self) -> Result<Vec<IndexedFile>> {
+        let mut tags: std::collections::HashMap<u64, Vec<String>> = std::collections::HashMap::new();
+        let mut stmt = self.conn.prepare("SELECT inode_id, tag FROM file_tags")?;
+        for row in stmt.query_map([], |row| Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?)))? {
+            let (inode, tag) = row?;
+            tags.entry(inode).or_default().push(tag);
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, f.size, date(f.mtime, 'unixepoch', 'localtime'), f.kind, c.comment, COALESCE(r.stars, 0) FROM inodes i JOIN file_info f ON i.id = f.inode_id
+             LEFT JOIN comments c ON c.inode_id = i.id LEFT JOIN ratings r ON r.inode_id = i.id ORDER BY i.name, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let inode: u64 = row.get(0)?;
+            Ok(IndexedFile { inode, name: row.get(1)?, size: row.get(2)?, date: row.get(3)?, kind: row.get(4)?, tags: tags.remove(&inode).unwrap_or_default(), comment: row.get(5)?, rating: row.get(6)? })
+        })?;
+        rows.collect()
+    }
+
+    /// (inode, name, kind) of every analyzed file, by name.
+    pub fn get_file_kinds(&self) -> Result<Vec<(u64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, f.kind FROM inodes i JOIN file_info f ON i.id = f.inode_id
+             WHERE f.kind IS NOT NULL ORDER BY i.name, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// (inode, name, local "YYYY-MM-DD" of the modification time) of every analyzed file, newest first.
+    pub fn get_file_dates(&self) -> Result<Vec<(u64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, date(f.mtime, 'unixepoch', 'localtime') FROM inodes i JOIN file_info f ON i.id = f.inode_id
+             ORDER BY f.mtime DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Adds opens counted in memory, as (inode, last opened, reads, writes), to each file's totals.
+    pub fn record_accesses(&self, accesses: &[(u64, u64, u64, u64)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (inode, at, reads, writes) in accesses {
+            tx.execute(
+                "INSERT INTO file_access (inode_id, accessed_at, reads, writes) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(inode_id) DO UPDATE SET accessed_at = excluded.accessed_at,
+                     reads = reads + excluded.reads, writes = writes + excluded.writes",
+                params![inode, at, reads, writes],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// (inode, parent, reads, writes) of every file opened through the mount, busiest first.
+    pub fn access_counts(&self) -> Result<Vec<(u64, u64, u64, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.parent_id, a.reads, a.writes FROM inodes i JOIN file_access a ON i.id = a.inode_id
+             ORDER BY a.reads + a.writes DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect()
+    }
+
+    /// Analyzed files that were never opened through the mount, in indexing order.
+    pub fn never_opened(&self) -> Result<Vec<u64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.inode_id FROM checksums c LEFT JOIN file_access a ON c.inode_id = a.inode_id
+             WHERE a.inode_id IS NULL ORDER BY c.inode_id",
+        )?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// (inode, name) of analyzed files without any tag, newest first.
+    pub fn untagged_files(&self) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name FROM inodes i JOIN file_info f ON i.id = f.inode_id
+             WHERE NOT EXISTS (SELECT 1 FROM file_tags t WHERE t.inode_id = i.id)
+             ORDER BY f.mtime DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// (inode, name) of analyzed files never opened for reading through the mount (creating
+    /// or saving one doesn't count), newest first.
+    pub fn unread_files(&self) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name FROM inodes i JOIN file_info f ON i.id = f.inode_id
+             LEFT JOIN file_access a ON i.id = a.inode_id
+             WHERE COALESCE(a.reads, 0) = 0
+             ORDER BY f.mtime DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    pub fn set_text_stats(&self, inode: u64, words: u64, lines: u64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO text_stats (inode_id, words, lines) VALUES (?1, ?2, ?3)",
+            params![inode, words as i64, lines as i64],
+        )?;
+        Ok(())
+    }
+
+    /// (words, lines) of a text document.
+    pub fn get_text_stats(&self, inode: u64) -> Result<Option<(u64, u64)>> {
+        self.conn.query_row(
+            "SELECT words, lines FROM text_stats WHERE inode_id = ?1",
+            params![inode],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+        ).optional()
+    }
+
+    /// (documents, words, lines) over every counted document.
+    pub fn text_stats_totals(&self) -> Result<(u64, u64, u64)> {
+        self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(words), 0), COALESCE(SUM(lines), 0) FROM text_stats",
+            [],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64)),
+        )
+    }
+
+    /// Replaces a file's comment; an empty one deletes it.
+    pub fn set_comment(&self, inode: u64, comment: &str) -> Result<()> {
+        if comment.is_empty() {
+            self.conn.execute("DELETE FROM comments WHERE inode_id = ?1", params![inode])?;
+            return Ok(());
+        }
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO comments (inode_id, comment, updated_at) VALUES (?1, ?2, ?3)",
+            params![inode, comment, timestamp],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_comment(&self, inode: u64) -> Result<Option<String>> {
+        self.conn.query_row("SELECT comment FROM comments WHERE inode_id = ?1", params![inode], |row| row.get(0)).optional()
+    }
+
+    pub fn commented_children(&self, parent: u64) -> Result<std::collections::HashSet<u64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.inode_id FROM comments c JOIN inodes i ON i.id = c.inode_id WHERE i.parent_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![parent], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Replaces the proofreading findings recorded for `inode`.
+    pub fn set_lint_findings(&self, inode: u64, findings: &[(usize, String)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM lint_findings WHERE inode_id = ?1", params![inode])?;
+        for (position, (line, message)) in findings.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO lint_findings (inode_id, position, line, message) VALUES (?1, ?2, ?3, ?4)",
+                params![inode, position as i64, *line as i64, message],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// (line, message) of a file's proofreading findings, in order.
+    pub fn get_lint_findings(&self, inode: u64) -> Result<Vec<(usize, String)>> {
+        let mut stmt = self.conn.prepare("SELECT line, message FROM lint_findings WHERE inode_id = ?1 ORDER BY position")?;
+        let rows = stmt.query_map(params![inode], |row| Ok((row.get::<_, i64>(0)? as usize, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    pub fn linted_children(&self, parent: u64) -> Result<std::collections::HashSet<u64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT l.inode_id FROM lint_findings l JOIN inodes i ON i.id = l.inode_id WHERE i.parent_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![parent], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Replaces the TODO lines recorded for `inode`.
+    pub fn set_todos(&self, inode: u64, todos: &[(usize, String)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM todos WHERE inode_id = ?1", params![inode])?;
+        for (line, content) in todos {
+            tx.execute("INSERT INTO todos (inode_id, line, content) VALUES (?1, ?2, ?3)", params![inode, *line as i64, content])?;
+        }
+        tx.commit()
+    }
+
+    /// (inode, line, content) of every recorded TODO not marked done, by file and line.
+    pub fn get_todos(&self) -> Result<Vec<(u64, usize, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.inode_id, t.line, t.content FROM todos t
+             WHERE NOT EXISTS (SELECT 1 FROM todos_done d WHERE d.inode_id = t.inode_id AND d.content = t.content)
+             ORDER BY t.inode_id, t.line",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Marks the TODO on `line` of `inode` done, along with any later copy of its text.
+    pub fn mark_todo_done(&self, inode: u64, line: usize) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT OR IGNORE INTO todos_done (inode_id, content, done_at)
+             SELECT inode_id, content, ?3 FROM todos WHERE inode_id = ?1 AND line = ?2",
+            params![inode, line as i64, timestamp],
+        )?;
+        Ok(())
+    }
+
+    /// Replaces the link targets recorded for the note `inode`.
+    pub fn set_links(&self, inode: u64, targets: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM note_links WHERE inode_id = ?1", params![inode])?;
+        for target in targets {
+            tx.execute("INSERT OR IGNORE INTO note_links (inode_id, target) VALUES (?1, ?2)", params![inode, target])?;
+        }
+        tx.commit()
+    }
+
+    /// Every link target of every note.
+    pub fn get_link_targets(&self) -> Result<std::collections::HashSet<String>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT target FROM note_links")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// (note, target) of every recorded link.
+    pub fn get_links(&self) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self.conn.prepare("SELECT inode_id, target FROM note_links ORDER BY inode_id, target")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Notes linking to any of `targets`.
+    pub fn get_linking_notes(&self, targets: &[String]) -> Result<Vec<u64>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT inode_id FROM note_links WHERE target = ?1")?;
+        let mut notes = Vec::new();
+        for target in targets {
+            for row in stmt.query_map(params![target], |row| row.get(0))? {
+                let inode: u64 = row?;
+                if !notes.contains(&inode) {
+                    notes.push(inode);
+                }
+            }
+        }
+        Ok(notes)
+    }
+
+    /// Pins `inode` to `.magic/favorites/`, or unpins it.
+    pub fn set_favorite(&self, inode: u64, pinned: bool) -> Result<()> {
+        if pinned {
+            let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+            self.conn.execute(
+                "INSERT OR IGNORE INTO favorites (inode_id, pinned_at) VALUES (?1, ?2)",
+                params![inode, timestamp],
+            )?;
+        } else {
+            self.conn.execute("DELETE FROM favorites WHERE inode_id = ?1", params![inode])?;
+        }
+        Ok(())
+    }
+
+    /// Rates a file 1-5 stars; 0 clears its rating.
+    pub fn set_rating(&self, inode: u64, stars: u8) -> Result<()> {
+        if stars == 0 {
+            self.conn.execute("DELETE FROM ratings WHERE inode_id = ?1", params![inode])?;
+            return Ok(());
+        }
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO ratings (inode_id, stars, rated_at) VALUES (?1, ?2, ?3)",
+            params![inode, stars, timestamp],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_rating(&self, inode: u64) -> Result<Option<u8>> {
+        self.conn.query_row("SELECT stars FROM ratings WHERE inode_id = ?1", params![inode], |row| row.get(0)).optional()
+    }
+
+    /// (inode, name) of every rated file, best and then most recently rated first.
+    pub fn get_rated_files(&self) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name FROM inodes i JOIN ratings r ON i.id = r.inode_id
+             ORDER BY r.stars DESC, r.rated_at DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Sets a file's color label; None clears it.
+    pub fn set_label(&self, inode: u64, color: Option<&str>) -> Result<()> {
+        match color {
+            Some(color) => self.conn.execute("INSERT OR REPLACE INTO labels (inode_id, color) VALUES (?1, ?2)", params![inode, color])?,
+            None => self.conn.execute("DELETE FROM labels WHERE inode_id = ?1", params![inode])?,
+        };
+        Ok(())
+    }
+
+    pub fn get_label(&self, inode: u64) -> Result<Option<String>> {
+        self.conn.query_row("SELECT color FROM labels WHERE inode_id = ?1", params![inode], |row| row.get(0)).optional()
+    }
+
+    /// (inode, name, color) of every labeled file, by name.
+    pub fn get_labeled_files(&self) -> Result<Vec<(u64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, l.color FROM inodes i JOIN labels l ON i.id = l.inode_id ORDER BY i.name, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// (inode, name) of the pinned files, most recently pinned first.
+    pub fn get_favorites(&self) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name FROM inodes i JOIN favorites f ON i.id = f.inode_id
+             ORDER BY f.pinned_at DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// (inode, name, unix time) of the `limit` most recently used files, newest first.
+    pub fn recent_files(&self, limit: usize) -> Result<Vec<(u64, String, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, a.accessed_at FROM inodes i JOIN file_access a ON i.id = a.inode_id
+             ORDER BY a.accessed_at DESC, i.id LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    pub fn add_organizer_move(&self, inode: u64, from: &str, to: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT INTO organizer_moves (inode_id, from_path, to_path, moved_at) VALUES (?1, ?2, ?3, ?4)",
+            params![inode, from, to, timestamp],
+        )?;
+        self.add_event(crate::events::MOVE, from, to)
+    }
+
+    /// Records a verified wormhole transfer that arrived as `inode`.
+    pub fn add_received(&self, inode: u64, code: &str, sender: &str, size: u64, checksum: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO wormhole_received (inode_id, code, sender, size, checksum, received_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![inode, code, sender, size as i64, checksum, timestamp],
+        )?;
+        self.add_inode_event(crate::events::RECEIVE, inode, &format!("from {}, blake3 {}", sender, checksum))
+    }
+
+    /// (inode, name) of files received through the wormhole, newest first.
+    pub fn get_received(&self) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name FROM inodes i JOIN wormhole_received w ON i.id = w.inode_id
+             ORDER BY w.received_at DESC, i.id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Appends to the audit trail, dropping the oldest entries beyond events::KEEP.
+    pub fn add_event(&self, kind: &str, path: &str, detail: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT INTO events (at, kind, path, detail) VALUES (?1, ?2, ?3, ?4)",
+            params![timestamp, kind, path, detail],
+        )?;
+        self.conn.execute("DELETE FROM events WHERE id <= ?1", params![self.conn.last_insert_rowid() - crate::events::KEEP])?;
+        Ok(())
+    }
+
+    fn add_inode_event(&self, kind: &str, inode: u64, detail: &str) -> Result<()> {
+        let path = self.get_path(inode)?.unwrap_or_else(|| format!("<inode {}>", inode));
+        self.add_event(kind, &path, detail)
+    }
+
+    /// Events at or after `since` (unix time), oldest first, at most `limit` of the latest.
+    pub fn events(&self, since: i64, limit: usize) -> Result<Vec<(i64, String, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT at, kind, path, detail FROM (
+                SELECT id, at, kind, path, detail FROM events WHERE at >= ?1 ORDER BY id DESC LIMIT ?2
+             ) ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![since, limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect()
+    }
+
+    /// (id, at, kind, path, detail) of up to `limit` events recorded after the one with `after_id`, oldest first.
+    pub fn events_after(&self, after_id: i64, limit: usize) -> Result<Vec<(i64, i64, String, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, at, kind, path, detail FROM events WHERE id > ?1 ORDER BY id LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![after_id, limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?;
+        rows.collect()
+    }
+
+    pub fn last_event_id(&self) -> Result<i64> {
+        self.conn.query_row("SELECT COALESCE(MAX(id), 0) FROM events", [], |row| row.get(0))
+    }
+
+    /// Organizer moves made at or after `since` (unix time), newest first.
+    pub fn organizer_moves(&self, since: i64) -> Result<Vec<OrganizerMove>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, inode_id, from_path, to_path, datetime(moved_at, 'unixepoch'), datetime(undone_at, 'unixepoch')
+             FROM organizer_moves WHERE moved_at >= ?1 ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_map(params![since], |row| Ok(OrganizerMove {
+            id: row.get(0)?,
+            inode: row.get(1)?,
+            from: row.get(2)?,
+            to: row.get(3)?,
+            moved: row.get(4)?,
+            undone: row.get(5)?,
+        }))?;
+        rows.collect()
+    }
+
+    pub fn mark_move_undone(&self, id: i64) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute("UPDATE organizer_moves SET undone_at = ?1 WHERE id = ?2", params![timestamp, id])?;
+        Ok(())
+    }
+
+    pub fn set_image_hash(&self, inode: u64, hash: u64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO image_hashes (inode_id, hash) VALUES (?1, ?2)",
+            params![inode, hash as i64],
+        )?;
+        Ok(())
+    }
+
+    /// (inode, name, hash) of every hashed image
+    pub fn get_image_hashes(&self) -> Result<Vec<(u64, String, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, h.hash FROM inodes i JOIN image_hashes h ON i.id = h.inode_id ORDER BY i.name, i.id"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as u64)))?;
+        rows.collect()
+    }
+
+    /// Records a file's known-good checksum, clearing any earlier corruption report.
+    pub fn set_checksum(&self, inode: u64, hash: &str, size: u64, mtime: i64, now: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO checksums (inode_id, hash, size, mtime, verified_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![inode, hash, size, mtime, now],
+        )?;
+        Ok(())
+    }
+
+    /// Up to `limit` healthy checksums last verified before `before`, oldest first,
+    /// as (inode, hash, size, mtime).
+    pub fn checksums_due(&self, before: i64, limit: usize) -> Result<Vec<(u64, String, u64, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT inode_id, hash, size, mtime FROM checksums
+             WHERE corrupt_hash IS NULL AND verified_at < ?1 ORDER BY verified_at LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![before, limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect()
+    }
+
+    pub fn mark_verified(&self, inode: u64, now: i64) -> Result<()> {
+        self.conn.execute("UPDATE checksums SET verified_at = ?1 WHERE inode_id = ?2", params![now, inode])?;
+        Ok(())
+    }
+
+    pub fn mark_corrupt(&self, inode: u64, actual: &str, now: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE checksums SET corrupt_hash = ?1, detected_at = ?2, verified_at = ?2 WHERE inode_id = ?3",
+            params![actual, now, inode],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_corruptions(&self) -> Result<Vec<Corruption>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT inode_id, hash, corrupt_hash, date(detected_at, 'unixepoch') FROM checksums WHERE corrupt_hash IS NOT NULL ORDER BY detected_at",
+        )?;
+        let rows = stmt.query_map([], |row| Ok(Corruption { inode: row.get(0)?, expected: row.get(1)?, actual: row.get(2)?, detected: row.get(3)? }))?;
+        rows.collect()
+    }
+
+    /// Replaces a file's chunk embeddings with `chunks` as (byte offset, vector).
+    pub fn set_embeddings(&self, inode: u64, model: &str, chunks: &[(usize, Vec<f32>)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM embeddings WHERE inode_id = ?1", params![inode])?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO embeddings (inode_id, chunk, start, model, vector) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (i, (start, vector)) in chunks.iter().enumerate() {
+                let blob: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+                insert.execute(params![inode, i as i64, *start as i64, model, blob])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// (inode, name, mean chunk vector) of every document with embeddings from `model`.
+    pub fn get_document_vectors(&self, model: &str) -> Result<Vec<(u64, String, Vec<f32>)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.name, e.vector FROM inodes i JOIN embeddings e ON i.id = e.inode_id WHERE e.model = ?1 ORDER BY i.id, e.chunk"
+        )?;
+        let rows = stmt.query_map(params![model], |row| Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?, row.get::<_, Vec<u8>>(2)?)))?;
+        let mut documents: Vec<(u64, String, Vec<f32>)> = Vec::new();
+        for row in rows {
+            let (inode, name, blob) = row?;
+            let vector = blob.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+            match documents.last_mut() {
+                Some((last, _, sum)) if *last == inode => sum.iter_mut().zip(vector).for_each(|(s, v)| *s += v),
+                _ => documents.push((inode, name, vector.collect())),
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Replaces every cluster assignment with `labels` as (inode, label).
+    pub fn set_clusters(&self, labels: &[(u64, String)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM clusters", [])?;
+        {
+            let mut insert = tx.prepare_cached("INSERT INTO clusters (inode_id, label) VALUES (?1, ?2)")?;
+            for (inode, label) in labels {
+                insert.execute(params![inode, label])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// (label, inode, name) of every clustered document
+    pub fn get_clusters(&self) -> Result<Vec<(String, u64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.label, i.id, i.name FROM inodes i JOIN clusters c ON i.id = c.inode_id ORDER BY c.label, i.name"
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Children of `parent` that have a summary.
+    pub fn summarized_children(&self, parent: u64) -> Result<std::collections::HashSet<u64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.inode_id FROM summaries s JOIN inodes i ON i.id = s.inode_id WHERE i.parent_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![parent], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn add_job(&self, inode: u64, path: &str) -> Result<i64> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT INTO jobs (inode_id, path, queued_at) VALUES (?1, ?2, ?3)",
+            params![inode, path, timestamp],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn finish_job(&self, id: i64) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute("UPDATE jobs SET done_at = ?1 WHERE id = ?2", params![timestamp, id])?;
+        Ok(())
+    }
+
+    /// Marks a job done but failed (the analysis panicked).
+    pub fn fail_job(&self, id: i64, error: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute("UPDATE jobs SET done_at = ?1, error = ?2 WHERE id = ?3", params![timestamp, error, id])?;
+        Ok(())
+    }
+
+    /// Most recent failures as (path, error, "2024-05-01 12:30:00" UTC).
+    pub fn failed_jobs(&self, limit: usize) -> Result<Vec<(String, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, error, datetime(done_at, 'unixepoch') FROM jobs WHERE error IS NOT NULL ORDER BY done_at DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// (id, inode, path) of jobs queued but never finished, oldest first. Finished ones are pruned,
+    /// keeping the latest failures for the jobs report.
+    pub fn pending_jobs(&self) -> Result<Vec<(i64, u64, String)>> {
+        self.conn.execute(
+            "DELETE FROM jobs WHERE done_at IS NOT NULL AND (error IS NULL OR id NOT IN
+                (SELECT id FROM jobs WHERE error IS NOT NULL ORDER BY done_at DESC LIMIT 100))",
+            [],
+        )?;
+        let mut stmt = self.conn.prepare("SELECT id, inode_id, path FROM jobs WHERE done_at IS NULL ORDER BY id")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        let mut jobs = Vec::new();
+        for job in rows {
+            jobs.push(job?);
+        }
+        Ok(jobs)
+    }
+
+    pub fn bump_counter(&self, name: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO counters (name, value) VALUES (?1, 1) ON CONFLICT(name) DO UPDATE SET value = value + 1",
+            params![name],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_counter(&self, name: &str) -> Result<u64> {
+        let value: Option<i64> = self.conn.query_row("SELECT value FROM counters WHERE name = ?1", params![name], |row| row.get(0)).optional()?;
+        Ok(value.unwrap_or(0) as u64)
+    }
+
+    /// Files analyzed at least once.
+    pub fn count_indexed_files(&self) -> Result<u64> {
+        self.conn.query_row("SELECT COUNT(*) FROM file_info", [], |row| row.get(0))
+    }
+
+    /// (pending, failed) analysis jobs; failures are kept for the last 100.
+    pub fn count_jobs(&self) -> Result<(u64, u64)> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FILTER (WHERE done_at IS NULL), COUNT(*) FILTER (WHERE error IS NOT NULL) FROM jobs",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
+    /// (files, bytes) that are extra copies of another file's content, by checksum.
+    pub fn duplicate_content(&self) -> Result<(u64, u64)> {
+        self.conn.query_row(
+            "SELECT COALESCE(SUM(n - 1), 0), COALESCE(SUM((n - 1) * size), 0)
+             FROM (SELECT COUNT(*) AS n, MAX(size) AS size FROM checksums GROUP BY hash HAVING COUNT(*) > 1)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        self.conn.query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| row.get(0)).optional()
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+        Ok(())
+    }
+
+    pub fn add_trash(&self, original_path: &str, backup_path: &str) -> Result<()> {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.conn.execute(
+            "INSERT INTO trash (original_path, backup_path, deleted_at) VALUES (?1, ?2, ?3)",
+            params![original_path, backup_path, timestamp],
+        )?;
+        self.add_event(crate::events::TRASH, original_path, backup_path)
+    }
+
+    /// (original path, trashed copy, deleted at) of files deleted through the mount, newest first.
+    pub fn get_trash(&self) -> Result<Vec<(String, String, i64)>> {
+        let mut stmt = self.conn.prepare("SELECT original_path, backup_path, deleted_at FROM trash ORDER BY deleted_at DESC, id DESC")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    // Fills the temp table `subtree` with an inode and everything below it (never the root)
+    fn select_tree(tx: &rusqlite::Transaction, inode: u64) -> Result<()> {
+        tx.execute("CREATE TEMP TABLE IF NOT EXISTS subtree (id INTEGER PRIMARY KEY)", [])?;
+        tx.execute("DELETE FROM subtree", [])?;
+        tx.execute(
+            "INSERT INTO subtree WITH RECURSIVE tree(id) AS (
+                SELECT ?1 UNION SELECT i.id FROM inodes i JOIN tree t ON i.parent_id = t.id WHERE i.id != 1
+             ) SELECT id FROM tree WHERE id != 1",
+            params![inode],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes an inode and everything below it (unlinked, trashed or removed behind our back), along with their
+    /// tags and metadata so a reused inode number doesn't inherit them.
+    pub fn delete_tree(&self, inode: u64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.remove_usage(inode)?;
+        Self::select_tree(&tx, inode)?;
+        for table in PER_INODE_TABLES {
+            tx.execute(&format!("DELETE FROM {} WHERE inode_id IN subtree", table), [])?;
+        }
+        tx.execute("DELETE FROM inodes WHERE id IN subtree", [])?;
+        tx.commit()
+    }
+
+    /// Forgets what analysis derived for a subtree (tags other than manual ones, summaries,
+    /// embeddings, transcripts, rename suggestions, sensitive data findings, image hashes, topic clusters, organizer proposals, TODOs, note links, word counts, proofreading findings, photo and music metadata) ahead of re-analyzing it. `inode` 1 clears everything.
+    pub fn clear_analysis(&self, inode: u64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let scope = if inode == 1 {
+            "1"
+        } else {
+            Self::select_tree(&tx, inode)?;
+            "inode_id IN subtree"
+        };
+        tx.execute(&format!("DELETE FROM reindexed_tags WHERE {}", scope), [])?;
+        tx.execute(&format!("INSERT INTO reindexed_tags SELECT inode_id, tag FROM file_tags WHERE manual = 0 AND {}", scope), [])?;
+        tx.execute(&format!("DELETE FROM file_tags WHERE manual = 0 AND {}", scope), [])?;
+        for table in ["photo_metadata", "music_metadata", "summaries", "embeddings", "rename_suggestions", "sensitive_findings", "transcripts", "image_hashes", "clusters", "proposals", "todos", "note_links", "text_stats", "lint_findings"] {
+            tx.execute(&format!("DELETE FROM {} WHERE {}", table, scope), [])?;
+        }
+        tx.commit()
+    }
+
+    /// Moves `inode` to the source-relative `destination`, allocating inodes for missing parent
+    /// directories. `on_disk` does the real rename inside the same transaction, so the table
+    /// and the source tree change together or not at all.
+    pub fn move_to_path(&self, inode: u64, destination: &str, on_disk: impl FnOnce() -> std::io::Result<()>) -> anyhow::Result<()> {
+        let (dir, name) = destination.rsplit_once('/').unwrap_or(("", destination));
+        let tx = self.conn.unchecked_transaction()?;
+        let parent = self.alloc_path(dir)?;
+        let (bytes, files) = self.remove_usage(inode)?;
+        tx.execute("UPDATE inodes SET parent_id = ?1, name = ?2 WHERE id = ?3", params![parent, name, inode])?;
+        self.add_usage(parent, bytes, files)?;
+        on_disk()?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn rename_inode(&self, inode: u64, new_parent: u64, new_name: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let (bytes, files) = self.remove_usage(inode)?;
+        tx.execute(
+            "UPDATE inodes SET parent_id = ?1, name = ?2 WHERE id = ?3",
+            params![new_parent, new_name, inode],
+        )?;
+        self.add_usage(new_parent, bytes, files)?;
+        tx.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> Database {
+        Database::new(":memory:").unwrap()
+    }
+
+    fn events_of(db: &Database, kind: &str) -> usize {
+        db.events(0, 1000).unwrap().into_iter().filter(|(_, k, _, _)| k == kind).count()
+    }
+
+    #[test]
+    fn alloc_inodes_lines_up_with_names() {
+        let db = memory();
+        let existing = db.create_inode(1, "b").unwrap();
+        let names: Vec<String> = ["a", "b", "c", "b"].iter().map(|name| name.to_string()).collect();
+        let ids = db.alloc_inodes(1, &names).unwrap();
+        assert_eq!(ids[1], existing);
+        assert_eq!(ids[3], existing);
+        assert_ne!(ids[0], ids[2]);
+        assert_eq!(db.lookup_path("a").unwrap(), Some(ids[0]));
+        assert_eq!(db.lookup_path("c").unwrap(), Some(ids[2]));
+        assert_eq!(db.alloc_inodes(1, &names).unwrap(), ids);
+    }
+
+    #[test]
+    fn delete_tree_leaves_nothing_for_a_reused_inode() {
+        let db = memory();
+        let dir = db.create_inode(1, "dir").unwrap();
+        let file = db.create_inode(dir, "secrets.txt").unwrap();
+        db.add_manual_tag(file, "invoice").unwrap();
+        db.set_sensitive_findings(file, &[("aws-key", 3)]).unwrap();
+        db.clear_analysis(file).unwrap();
+
+        db.delete_tree(dir).unwrap();
+        assert_eq!(db.lookup_path("dir").unwrap(), None);
+        assert_eq!(db.lookup_path("").unwrap(), Some(1));
+
+        // SQLite hands the freed ids out again
+        let reused = [db.create_inode(1, "new").unwrap(), db.create_inode(1, "newer").unwrap()];
+        assert!(reused.contains(&file));
+        for inode in reused {
+            assert!(db.get_known_tags(inode).unwrap().is_empty());
+            assert!(!db.is_sensitive(inode).unwrap());
+            assert!(!db.was_flagged(inode).unwrap());
+        }
+    }
+
+    #[test]
+    fn reindexed_tags_coming_back_arent_new() {
+        let db = memory();
+        let dir = db.create_inode(1, "dir").unwrap();
+        let file = db.create_inode(dir, "report.pdf").unwrap();
+        let other = db.create_inode(1, "other.pdf").unwrap();
+        db.add_tag(file, "invoice").unwrap();
+        db.add_manual_tag(file, "keep").unwrap();
+        db.add_tag(other, "invoice").unwrap();
+        assert_eq!(events_of(&db, crate::events::TAG), 3);
+
+        db.clear_analysis(dir).unwrap();
+        assert_eq!(db.get_file_tags(file).unwrap(), ["keep"]);
+        assert_eq!(db.get_known_tags(file).unwrap(), ["invoice", "keep"]);
+        assert_eq!(db.get_file_tags(other).unwrap(), ["invoice"]);
+
+        db.add_tag(file, "invoice").unwrap();
+        assert_eq!(events_of(&db, crate::events::TAG), 3);
+        db.add_tag(file, "receipt").unwrap();
+        assert_eq!(events_of(&db, crate::events::TAG), 4);
+        assert_eq!(db.get_file_tags(file).unwrap(), ["invoice", "keep", "receipt"]);
+    }
+
+    #[test]
+    fn sensitive_isnt_reported_again_after_a_reindex() {
+        let db = memory();
+        let file = db.create_inode(1, ".env").unwrap();
+        db.set_sensitive_findings(file, &[("aws-key", 3), ("aws-key", 4)]).unwrap();
+        assert_eq!(events_of(&db, crate::events::SENSITIVE), 1);
+        assert!(db.get_file_tags(file).unwrap().contains(&"sensitive".to_string()));
+
+        db.clear_analysis(1).unwrap();
+        assert!(!db.is_sensitive(file).unwrap());
+        assert!(db.was_flagged(file).unwrap());
+        db.set_sensitive_findings(file, &[("aws-key", 3)]).unwrap();
+        assert_eq!(events_of(&db, crate::events::SENSITIVE), 1);
+        assert!(db.get_file_tags(file).unwrap().contains(&"sensitive".to_string()));
+
+        // Cleaned up, then leaking again
+        db.set_sensitive_findings(file, &[]).unwrap();
+        assert!(!db.was_flagged(file).unwrap());
+        assert!(db.get_file_tags(file).unwrap().is_empty());
+        db.set_sensitive_findings(file, &[("private-key", 1)]).unwrap();
+        assert_eq!(events_of(&db, crate::events::SENSITIVE), 2);
+    }
+
+    #[test]
+    fn get_path_is_whole_or_none() {
+        let db = memory();
+        let mut deepest = 1;
+        for depth in 0..150 {
+            deepest = db.create_inode(deepest, &format!("d{}", depth)).unwrap();
+        }
+        assert_eq!(db.get_path(deepest).unwrap().unwrap().split('/').count(), 150);
+
+        let a = db.create_inode(1, "a").unwrap();
+        let b = db.create_inode(a, "b").unwrap();
+        db.conn.execute("UPDATE inodes SET parent_id = ?1 WHERE id = ?2", params![b, a]).unwrap();
+        assert_eq!(db.get_path(b).unwrap(), None);
+        assert_eq!(db.get_path(9999).unwrap(), None);
+    }
 }
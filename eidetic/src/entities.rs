@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+// Named Entities
+// Tags documents with the people, organizations and dates they mention (`person:alice-smith`,
+// `org:acme`, `date:2024-03-05`) so `.magic/tags` can be browsed by entity. It's rule-based
+// until a real NER model is bundled: people are found after honorifics
+// ("Dr. Alice Smith") and in greetings ("Dear Alice,"), organizations before a company
+// suffix ("Acme Corp", "Initech GmbH"), dates in ISO form or written out ("March 5, 2024").
+
+/// Entities of each kind tagged per document, most mentioned first.
+const MAX_PER_KIND: usize = 5;
+
+const HONORIFICS: &[&str] = &["mr", "mrs", "ms", "miss", "dr", "prof", "sir"];
+
+const COMPANY_SUFFIXES: &[&str] = &[
+    "inc", "ltd", "llc", "llp", "gmbh", "corp", "corporation", "co", "ag", "sa", "plc", "limited", "company",
+];
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november", "december",
+];
+
+// Capitalized at the start of a sentence, not part of the name: "The Acme Company"
+const ARTICLES: &[&str] = &["the", "a", "an", "our", "your", "their", "this", "that"];
+
+/// Longest name taken after an honorific or before a company suffix, in words.
+const MAX_NAME_WORDS: usize = 3;
+
+/// Tags for the entities mentioned in `text`.
+pub fn tags(text: &str) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut people = HashMap::new();
+    let mut orgs = HashMap::new();
+    let mut dates = HashMap::new();
+
+    for (i, word) in words.iter().enumerate() {
+        let bare = bare(word).to_lowercase();
+        if HONORIFICS.contains(&bare.as_str()) || bare == "dear" {
+            // "Dear Dr. Smith" is counted at "Dr."
+            let next_is_honorific = words.get(i + 1).is_some_and(|w| HONORIFICS.contains(&bare_lower(w).as_str()));
+            if !(bare == "dear" && next_is_honorific) {
+                if let Some(name) = name_after(&words[i + 1..]) {
+                    *people.entry(name).or_insert(0) += 1;
+                }
+            }
+        } else if COMPANY_SUFFIXES.contains(&bare.as_str()) && i > 0 {
+            if let Some(name) = name_before(&words[..i]) {
+                *orgs.entry(name).or_insert(0) += 1;
+            }
+        }
+        if let Some(date) = date_at(&words[i..]) {
+            *dates.entry(date).or_insert(0) += 1;
+        }
+    }
+
+    let mut tags = Vec::new();
+    for (kind, found) in [("person", people), ("org", orgs), ("date", dates)] {
+        let mut found: Vec<(String, usize)> = found.into_iter().collect();
+        found.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        tags.extend(found.into_iter().take(MAX_PER_KIND).map(|(name, _)| format!("{}:{}", kind, name)));
+    }
+    tags
+}
+
+// The word without surrounding punctuation
+fn bare(word: &str) -> &str {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+fn bare_lower(word: &str) -> String {
+    bare(word).to_lowercase()
+}
+
+fn is_capitalized(word: &str) -> bool {
+    let word = bare(word);
+    let mut chars = word.chars();
+    chars.next().is_some_and(char::is_uppercase) && chars.all(|c| c.is_alphabetic() || c == '-' || c == '\'')
+}
+
+// A word followed by punctuation ends the name: "Dear Alice, ..."
+fn ends_phrase(word: &str) -> bool {
+    word.ends_with([',', '.', ';', ':', '!', '?', ')'])
+}
+
+fn slug(words: &[&str]) -> Option<String> {
+    let slug = words.iter().map(|w| bare_lower(w)).collect::<Vec<_>>().join("-");
+    (slug.chars().filter(|c| c.is_alphabetic()).count() >= 2).then_some(slug)
+}
+
+fn name_after(words: &[&str]) -> Option<String> {
+    let mut name = Vec::new();
+    for word in words.iter().take(MAX_NAME_WORDS) {
+        if !is_capitalized(word) || HONORIFICS.contains(&bare_lower(word).as_str()) {
+            break;
+        }
+        name.push(*word);
+        if ends_phrase(word) {
+            break;
+        }
+    }
+    slug(&name)
+}
+
+fn name_before(words: &[&str]) -> Option<String> {
+    let mut name = Vec::new();
+    for (n, word) in words.iter().rev().take(MAX_NAME_WORDS).enumerate() {
+        // "Acme, Inc." keeps the comma before the suffix
+        if !is_capitalized(word) || (n > 0 && ends_phrase(word)) || ARTICLES.contains(&bare_lower(word).as_str()) {
+            break;
+        }
+        name.insert(0, *word);
+    }
+    slug(&name)
+}
+
+// "2024-03-05", "March 5, 2024" or "5 March 2024" starting at words[0], as YYYY-MM-DD
+fn date_at(words: &[&str]) -> Option<String> {
+    let first = bare(words.first()?);
+    let parts: Vec<&str> = first.split('-').collect();
+    let (year, month, day): (u32, u32, u32) = if let [year, month, day] = parts[..] {
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+    } else if let Some(month) = month_number(first) {
+        (bare(words.get(2)?).parse().ok()?, month, bare(words.get(1)?).parse().ok()?)
+    } else {
+        let day = first.parse().ok()?;
+        (bare(words.get(2)?).parse().ok()?, month_number(bare(words.get(1)?))?, day)
+    };
+    let valid = (1000..=9999).contains(&year) && (1..=12).contains(&month) && (1..=31).contains(&day);
+    valid.then(|| format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+fn month_number(word: &str) -> Option<u32> {
+    MONTHS.iter().position(|m| m.eq_ignore_ascii_case(word)).map(|i| i as u32 + 1)
+}
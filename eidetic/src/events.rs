@@ -0,0 +1,34 @@
+use crate::db::Database;
+
+// Event Log
+// A trail of what Eidetic did to files on its own or on the user's behalf: tags added,
+// files trashed, history snapshots taken, sensitive data found, wormhole transfers received,
+// organizer moves and vault unlocks. The db methods behind those actions record them, so nothing that changes a file goes
+// unlogged.
+// `.magic/events.log` shows the latest; `eidetic events --since 7d` goes further back.
+
+pub const TAG: &str = "tag";
+pub const TRASH: &str = "trash";
+pub const SNAPSHOT: &str = "snapshot";
+pub const SENSITIVE: &str = "sensitive";
+pub const RECEIVE: &str = "receive";
+pub const MOVE: &str = "move";
+pub const VAULT_UNLOCK: &str = "vault-unlock";
+
+/// Events kept in the table; older ones are dropped as new ones come in.
+pub const KEEP: i64 = 100_000;
+
+/// Lines shown in `.magic/events.log`.
+const LOG_LINES: usize = 1000;
+
+/// One line per event, oldest first: "2024-05-01 14:03  tag  docs/a.pdf  invoice".
+/// All events since `since` (unix time), or the latest LOG_LINES.
+pub fn log(db: &Database, since: Option<i64>) -> String {
+    let limit = if since.is_some() { i64::MAX as usize } else { LOG_LINES };
+    let events = db.events(since.unwrap_or(0), limit).unwrap_or_default();
+    let mut content = String::new();
+    for (at, kind, path, detail) in events {
+        content.push_str(&format!("{}  {:<12}  {}  {}\n", crate::stats::local_time(at), kind, path, detail));
+    }
+    content
+}
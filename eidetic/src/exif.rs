@@ -0,0 +1,128 @@
+use std::io::Read;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+
+// EXIF Metadata
+// Just enough of TIFF/EXIF to pull capture date, camera and lens out of JPEGs and the
+// TIFF-based RAW formats (CR2, NEF, ARW, DNG, ...). Everything else in the IFDs is skipped.
+
+/// Formats read_photo understands.
+pub const PHOTO_FORMATS: &[&str] = &["jpg", "jpeg", "tif", "tiff", "cr2", "nef", "arw", "dng", "orf", "rw2"];
+
+// Metadata lives in the first few hundred KB; never read a whole RAW for it
+const READ_LIMIT: u64 = 1 << 20;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_LENS_MODEL: u16 = 0xA434;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PhotoInfo {
+    /// "2024-05-01 12:30:00", local time as recorded by the camera
+    pub taken: Option<String>,
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+}
+
+/// EXIF of a photo, or None if it has none.
+pub fn read_photo(path: &Path) -> Result<Option<PhotoInfo>> {
+    let mut data = Vec::new();
+    std::fs::File::open(path)?.take(READ_LIMIT).read_to_end(&mut data)?;
+    let tiff = if data.starts_with(&[0xFF, 0xD8]) { jpeg_exif(&data) } else { Some(&data[..]) };
+    let Some(tiff) = tiff else { return Ok(None) };
+    let tags = parse_tiff(tiff)?;
+    if tags.is_empty() {
+        return Ok(None);
+    }
+
+    let get = |tag: u16| tags.iter().find(|(t, _)| *t == tag).map(|(_, v)| v.clone()).filter(|v| !v.is_empty());
+    let camera = match (get(TAG_MAKE), get(TAG_MODEL)) {
+        // Most models already start with the make ("Canon EOS R5")
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => model.or(make),
+    };
+    let taken = get(TAG_DATE_TIME_ORIGINAL).or_else(|| get(TAG_DATE_TIME)).and_then(|d| normalize_date(&d));
+    Ok(Some(PhotoInfo { taken, camera, lens: get(TAG_LENS_MODEL) }))
+}
+
+// TIFF payload of the APP1 "Exif" segment
+fn jpeg_exif(data: &[u8]) -> Option<&[u8]> {
+    let mut pos = 2;
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let marker = data[pos + 1];
+        // Start of scan: image data follows, no more metadata
+        if marker == 0xDA {
+            return None;
+        }
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return Some(&segment[6..]);
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+// ASCII tags of IFD0 and the EXIF sub-IFD
+fn parse_tiff(tiff: &[u8]) -> Result<Vec<(u16, String)>> {
+    let little = match tiff.get(..4) {
+        Some([b'I', b'I', 42, 0]) => true,
+        Some([b'M', b'M', 0, 42]) => false,
+        // Panasonic RW2 and Olympus ORF use their own magic numbers but are TIFF otherwise
+        Some([b'I', b'I', b'U', 0]) | Some([b'I', b'I', b'R', b'O']) => true,
+        _ => return Err(anyhow!("No TIFF header")),
+    };
+    let u16_at = |pos: usize| tiff.get(pos..pos + 2).map(|b| if little { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) });
+    let u32_at = |pos: usize| tiff.get(pos..pos + 4).map(|b| {
+        let b = [b[0], b[1], b[2], b[3]];
+        if little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) }
+    });
+
+    let mut tags = Vec::new();
+    let mut ifds = vec![u32_at(4).unwrap_or(0) as usize];
+    let mut visited = 0;
+    while let Some(ifd) = ifds.pop() {
+        // Guards against IFD pointer loops in corrupt files
+        visited += 1;
+        if visited > 4 {
+            break;
+        }
+        let Some(count) = u16_at(ifd) else { continue };
+        for i in 0..count as usize {
+            let entry = ifd + 2 + i * 12;
+            let (Some(tag), Some(kind), Some(len), Some(value)) = (u16_at(entry), u16_at(entry + 2), u32_at(entry + 4), u32_at(entry + 8)) else {
+                break;
+            };
+            if tag == TAG_EXIF_IFD {
+                ifds.push(value as usize);
+                continue;
+            }
+            // Type 2 = ASCII; values of up to 4 bytes are stored inline
+            if kind != 2 || ![TAG_MAKE, TAG_MODEL, TAG_DATE_TIME, TAG_DATE_TIME_ORIGINAL, TAG_LENS_MODEL].contains(&tag) {
+                continue;
+            }
+            let start = if len <= 4 { entry + 8 } else { value as usize };
+            if let Some(bytes) = tiff.get(start..start + len as usize) {
+                let text = String::from_utf8_lossy(bytes);
+                tags.push((tag, text.trim_end_matches('\0').trim().to_string()));
+            }
+        }
+    }
+    Ok(tags)
+}
+
+// "2024:05:01 12:30:00" -> "2024-05-01 12:30:00"; blank or zeroed dates are dropped
+fn normalize_date(date: &str) -> Option<String> {
+    let (day, time) = date.split_once(' ').unwrap_or((date, "00:00:00"));
+    let parts: Vec<&str> = day.split([':', '-']).collect();
+    let [year, month, day] = parts[..] else { return None };
+    if year.len() != 4 || year == "0000" || month.parse::<u32>().ok().filter(|m| (1..=12).contains(m)).is_none() {
+        return None;
+    }
+    Some(format!("{}-{}-{} {}", year, month, day, time))
+}
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use crate::office::{attr, tokens, Token};
+use tracing::{debug, info, warn};
+
+// Feeds
+// `[[feed]]` entries in ~/.eidetic/api.toml put RSS and Atom feeds into `.magic/api/` as
+// markdown digests: the feed's title, then one line per item with its title, link and date,
+// newest as the feed orders them. Feeds are fetched in the background every
+// `refresh_minutes` (within the API rate limit) and the digest is kept in
+// `.eidetic/cache/feeds/`, so reading one never waits on the network and the last digest
+// stays readable offline.
+//
+//   [[feed]]
+//   name = "hacker-news.md"
+//   url = "https://hnrss.org/frontpage"
+//   refresh_minutes = 30                # the default
+//   items = 50                          # the default
+
+/// How often feeds are checked for being due.
+const POLL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Feed {
+    /// The digest's name in `.magic/api/`
+    pub name: String,
+    url: String,
+    #[serde(default = "default_refresh")]
+    refresh_minutes: u64,
+    #[serde(default = "default_items")]
+    items: usize,
+}
+
+fn default_refresh() -> u64 {
+    30
+}
+
+fn default_items() -> usize {
+    50
+}
+
+#[derive(Default)]
+struct Item {
+    title: String,
+    link: String,
+    date: String,
+}
+
+pub fn start(source_path: PathBuf) {
+    thread::spawn(move || loop {
+        // Offline, the digests stay as they are
+        let feeds = if crate::http::offline() { Vec::new() } else { crate::api::feeds() };
+        for feed in feeds {
+            let age = std::fs::metadata(cache_path(&source_path, &feed)).and_then(|metadata| metadata.modified()).ok().and_then(|modified| modified.elapsed().ok());
+            if age.is_some_and(|age| age < Duration::from_secs(feed.refresh_minutes * 60)) {
+                continue;
+            }
+            if !crate::api::allow_request() {
+                debug!(name = %feed.name, "API rate limit reached, feed refresh postponed");
+                break;
+            }
+            match refresh(&source_path, &feed) {
+                Ok(items) => info!(name = %feed.name, items, "feed refreshed"),
+                Err(e) => warn!(name = %feed.name, error = %e, "feed refresh failed"),
+            }
+        }
+        thread::sleep(POLL);
+    });
+}
+
+/// `.magic/api/<name>` of a feed: the last digest, or a note while there's none yet.
+pub fn digest(source_path: &Path, feed: &Feed) -> String {
+    std::fs::read_to_string(cache_path(source_path, feed))
+        .unwrap_or_else(|_| format!("# {}\n\n_Not fetched yet; {} is fetched in the background._\n", feed.name, feed.url))
+}
+
+// Named after the feed's name and URL, so a changed URL doesn't get the old one's digest
+fn cache_path(source_path: &Path, feed: &Feed) -> PathBuf {
+    let key = format!("{}\n{}", feed.name, feed.url);
+    source_path.join(".eidetic/cache/feeds").join(format!("{}.md", blake3::hash(key.as_bytes()).to_hex()))
+}
+
+// Fetches and renders the feed into the cache; returns how many items it has
+fn refresh(source_path: &Path, feed: &Feed) -> Result<usize> {
+    let xml = String::from_utf8_lossy(&crate::http::get(&crate::api::expand(&feed.url)?)?).into_owned();
+    let (title, items) = parse(&xml);
+    if title.is_none() && items.is_empty() {
+        bail!("Not an RSS or Atom feed");
+    }
+    let mut markdown = format!("# {}\n\n_From {}_\n\n", title.as_deref().unwrap_or(&feed.name), feed.url);
+    for item in items.iter().take(feed.items) {
+        let title = if item.title.is_empty() { "(untitled)".to_string() } else { item.title.replace(['[', ']'], "") };
+        let line = match (item.link.is_empty(), item.date.is_empty()) {
+            (false, false) => format!("- [{}]({}) — {}\n", title, item.link, day(&item.date)),
+            (false, true) => format!("- [{}]({})\n", title, item.link),
+            (true, false) => format!("- {} — {}\n", title, day(&item.date)),
+            (true, true) => format!("- {}\n", title),
+        };
+        markdown.push_str(&line);
+    }
+
+    let cached = cache_path(source_path, feed);
+    std::fs::create_dir_all(cached.parent().unwrap_or(source_path))?;
+    let partial = cached.with_extension("part");
+    std::fs::write(&partial, markdown)?;
+    std::fs::rename(&partial, &cached)?;
+    Ok(items.len().min(feed.items))
+}
+
+// The feed's title and items, RSS 2.0 (`<item>`) or Atom (`<entry>`) alike
+fn parse(xml: &str) -> (Option<String>, Vec<Item>) {
+    let (mut title, mut items) = (None, Vec::new());
+    let mut item: Option<Item> = None;
+    // The element whose text is being collected, and the text so far
+    let mut field: Option<&str> = None;
+    let mut text = String::new();
+    // Inside an RSS `<image>`, whose `<title>` isn't the feed's
+    let mut in_image = false;
+    for token in tokens(xml) {
+        match token {
+            Token::Start { name, attrs, empty } => match local(name) {
+                "item" | "entry" => item = Some(Item::default()),
+                "image" => in_image = !empty,
+                // Atom: `<link rel="alternate" href="..."/>`
+                "link" if attrs.contains("href=") => {
+                    let alternate = attr(attrs, "rel").is_none_or(|rel| rel == "alternate");
+                    if let (Some(item), Some(href)) = (item.as_mut(), attr(attrs, "href")) {
+                        if item.link.is_empty() && alternate {
+                            item.link = href;
+                        }
+                    }
+                }
+                element @ ("title" | "link" | "pubDate" | "published" | "updated" | "date") if !empty => {
+                    field = Some(element);
+                    text.clear();
+                }
+                _ => {}
+            },
+            Token::Text(chunk) if field.is_some() => text.push_str(&chunk),
+            Token::Text(_) => {}
+            Token::End(name) => match local(name) {
+                "item" | "entry" => items.extend(item.take()),
+                "image" => in_image = false,
+                element if field == Some(element) => {
+                    let value = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    match (item.as_mut(), element) {
+                        (Some(item), "title") => item.title = value,
+                        (Some(item), "link") if item.link.is_empty() => item.link = value,
+                        (Some(_), "link") => {}
+                        (Some(item), _) if item.date.is_empty() => item.date = value,
+                        (None, "title") if title.is_none() && !in_image => title = Some(value),
+                        _ => {}
+                    }
+                    field = None;
+                }
+                _ => {}
+            },
+        }
+    }
+    (title, items)
+}
+
+// "dc:date" -> "date"
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+// "2024-06-15T08:00:00Z" and "Sat, 15 Jun 2024 08:00:00 GMT" -> "2024-06-15"; anything else as is
+fn day(date: &str) -> String {
+    if let Some(day) = date.get(..10).filter(|day| day.as_bytes()[4] == b'-') {
+        return day.to_string();
+    }
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let parts: Vec<&str> = date.split_whitespace().skip_while(|part| part.ends_with(',')).collect();
+    if let [day, month, year, ..] = parts[..] {
+        let month = MONTHS.iter().position(|name| month.to_lowercase().starts_with(name));
+        if let (Ok(day), Some(month), Ok(year)) = (day.parse::<u32>(), month, year.parse::<u32>()) {
+            return format!("{:04}-{:02}-{:02}", year, month + 1, day);
+        }
+    }
+    date.to_string()
+}
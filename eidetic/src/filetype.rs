@@ -0,0 +1,68 @@
+use std::io::Read;
+use std::path::Path;
+
+// File Types
+// Sorts files into the broad kinds `.magic/by-type/` groups them by. The first bytes decide
+// when they carry a known signature (so a PNG named photo.dat is still an image); the
+// extension decides otherwise, and unknown files are documents if they read as text.
+
+const SNIFF_BYTES: u64 = 512;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp", "tif", "tiff", "heic", "heif", "svg", "ico", "cr2", "nef", "arw", "dng", "orf", "rw2"];
+const DOCUMENT_EXTENSIONS: &[&str] = &["pdf", "txt", "md", "rtf", "doc", "docx", "odt", "xls", "xlsx", "ods", "ppt", "pptx", "odp", "csv", "epub", "html", "htm"];
+const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "ts", "tsx", "jsx", "go", "c", "h", "cpp", "hpp", "cc", "java", "kt", "swift", "rb", "php", "cs",
+    "sh", "bash", "zsh", "sql", "lua", "pl", "r", "scala", "toml", "yaml", "yml", "json", "xml", "css", "scss",
+];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "oga", "opus", "wav", "m4a", "aac", "amr", "wma", "aiff"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "mkv", "webm", "avi", "wmv", "flv", "3gp", "mpg", "mpeg"];
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "iso", "dmg"];
+
+/// "images", "documents", "code", "audio", "video", "archives" or "other" for the file at `path`.
+pub fn detect(path: &Path) -> &'static str {
+    let mut head = Vec::new();
+    if let Ok(file) = std::fs::File::open(path) {
+        let _ = file.take(SNIFF_BYTES).read_to_end(&mut head);
+    }
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    // Office documents and EPUBs are zip files underneath
+    if let Some(kind) = sniff(&head).filter(|kind| !(*kind == "archives" && DOCUMENT_EXTENSIONS.contains(&ext.as_str()))) {
+        return kind;
+    }
+    for (kind, extensions) in [
+        ("images", IMAGE_EXTENSIONS), ("documents", DOCUMENT_EXTENSIONS), ("code", CODE_EXTENSIONS),
+        ("audio", AUDIO_EXTENSIONS), ("video", VIDEO_EXTENSIONS), ("archives", ARCHIVE_EXTENSIONS),
+    ] {
+        if extensions.contains(&ext.as_str()) {
+            return kind;
+        }
+    }
+    // Scripts without an extension; a text sample may end mid-character
+    let text = !head.is_empty() && !head.contains(&0) && std::str::from_utf8(&head).map_or_else(|e| e.error_len().is_none(), |_| true);
+    match text {
+        true if head.starts_with(b"#!") => "code",
+        true => "documents",
+        false => "other",
+    }
+}
+
+// Kind from well-known signatures
+fn sniff(head: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    Some(if at(0, b"\xFF\xD8\xFF") || at(0, b"\x89PNG") || at(0, b"GIF8") || (at(0, b"RIFF") && at(8, b"WEBP")) {
+        "images"
+    } else if at(0, b"%PDF") {
+        "documents"
+    } else if at(0, b"ID3") || at(0, b"fLaC") || at(0, b"OggS") || (at(0, b"RIFF") && at(8, b"WAVE")) {
+        "audio"
+    } else if at(4, b"ftyp") {
+        // MP4 container: audio-only brands are .m4a
+        if at(8, b"M4A ") || at(8, b"M4B ") { "audio" } else { "video" }
+    } else if at(0, b"\x1A\x45\xDF\xA3") || (at(0, b"RIFF") && at(8, b"AVI ")) {
+        "video"
+    } else if at(0, b"PK\x03\x04") || at(0, b"\x1F\x8B") || at(0, b"7z\xBC\xAF") || at(0, b"Rar!") || at(0, b"BZh") || at(0, b"\xFD7zXZ") || at(257, b"ustar") {
+        "archives"
+    } else {
+        return None;
+    })
+}
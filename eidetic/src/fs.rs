@@ -1,30 +1,46 @@
 use fuser::{
     FileAttr, FileType, Filesystem, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry,
-    ReplyWrite, Request,
+    ReplyOpen, ReplyWrite, ReplyXattr, Request,
 };
 #[cfg(unix)]
-use libc::{ENOENT, ENOSYS, EIO};
+use libc::{ENOENT, EIO};
+// "No such attribute" for getxattr
+#[cfg(target_os = "macos")]
+use libc::ENOATTR as ENODATA;
+#[cfg(all(unix, not(target_os = "macos")))]
+use libc::ENODATA;
 
 #[cfg(not(unix))]
 mod platform_constants {
     pub const ENOENT: i32 = 2;
-    pub const ENOSYS: i32 = 38;
     pub const EIO: i32 = 5;
+    pub const ENODATA: i32 = 61;
 }
 #[cfg(not(unix))]
 use platform_constants::*;
 
-use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::{self, File};
 use crate::db::Database;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
-use std::time::{Duration, UNIX_EPOCH};
-use std::sync::mpsc::Sender;
-use crate::worker::Job;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::queue::{JobQueue, Priority};
+use crate::metrics::failed;
+use crate::notifications::Notifier;
+use tracing::{debug, error, warn};
+use crate::worker::SUMMARY_MIN_CHARS;
+use crate::convert::{self, ConversionCache, Registry};
+use crate::views::{self, View, VirtualInodeStore};
+use crate::archive::{self, ArchiveIndex};
+use crate::query::{self, Query};
+use crate::{lang, scrub, sensitive, transcribe};
+use crate::model::Summarizer;
+use crate::config::{Config, SensitiveConfig};
 
 const TTL: Duration = Duration::from_secs(1); // 1 second attribute cache
 
@@ -33,31 +49,121 @@ pub struct EideticFS {
     // Inode management
     // We need Mutex for interior mutability strictly speaking,
     // though FUSE is multi-threaded by default.
-    inodes: Mutex<InodeStore>,
+    inodes: Arc<Mutex<InodeStore>>,
     uid: u32,
     gid: u32,
-    sender: Sender<Job>,
+    jobs: JobQueue,
+    cache: Arc<ConversionCache>,
+    converters: Arc<Registry>,
+    views: Mutex<VirtualInodeStore>,
+    // Member lists of browsed archives, keyed by the archive's inode and invalidated by its mtime
+    archives: Mutex<HashMap<u64, (i64, Arc<ArchiveIndex>)>>,
+    // Answers questions written to `.ask`; None if the configured backend couldn't be set up
+    assistant: Option<Arc<Summarizer>>,
+    // Last question written to each directory's `.ask`, keyed by directory inode
+    questions: Arc<Mutex<HashMap<u64, Question>>>,
+    // Translations under way on background threads, by source inode and language
+    translating: Arc<Mutex<HashSet<(u64, String)>>>,
+    // `stats.md` as of the last read from its start
+    stats: Mutex<String>,
+    sensitive: SensitiveConfig,
+    // For the uptime in `.magic/health.json`
+    mounted_at: SystemTime,
+    // The first vault read of the mount goes into the event log as the vault's unlock
+    vault_unlocked: AtomicBool,
+    // Desktop popups for organizer moves
+    notifier: Notifier,
+    // The Pro license and what it unlocks, kept current by license::watch
+    license: Arc<RwLock<crate::license::Status>>,
+    // How long a page fetched for a `.url` file is served from the cache
+    link_max_age: Duration,
 }
 
 const MAGIC_ROOT: u64 = u64::MAX;
 const MAGIC_TAGS: u64 = u64::MAX - 1;
-const MAGIC_RECENT: u64 = u64::MAX - 2;
 const MAGIC_SEARCH: u64 = u64::MAX - 3;
 const MAGIC_SEARCH_RESULTS: u64 = u64::MAX - 4;
 const CONTEXT_BIT: u64 = 1 << 63;
 const CONVERT_BIT: u64 = 1 << 62;
-const API_BIT: u64 = 1 << 61; // API Mounting
 const MAGIC_API: u64 = u64::MAX - 5;
 const MAGIC_WORMHOLE: u64 = u64::MAX - 6;
 const MAGIC_STATS: u64 = u64::MAX - 7;
 
 // If Inode X is a directory, Inode (X | CONTEXT_BIT) is its .context file.
+// Converted views carry the converter id in bits 48..60: CONVERT_BIT | (id << CONVERTER_SHIFT) | source inode.
+const CONVERTER_SHIFT: u32 = 48;
 
+/// Files listed in `.magic/recent/`.
+const RECENT_LIMIT: usize = 50;
+
+const RECENT_GROUPS: [&str; 3] = ["Today", "Yesterday", "This week"];
+
+const MB: u64 = 1024 * 1024;
+
+/// `.magic/by-size/` buckets and their exclusive upper bounds; anything bigger is "over-1GB".
+const SIZE_BUCKETS: [(&str, u64); 4] = [("under-1MB", MB), ("1-10MB", 10 * MB), ("10-100MB", 100 * MB), ("100MB-1GB", 1024 * MB)];
+
+/// Text formats served translated as `<stem>.<language>.<ext>`.
+const TRANSLATABLE: &[&str] = &["txt", "md"];
+const SOURCE_MASK: u64 = (1 << CONVERTER_SHIFT) - 1;
+
+fn convert_inode(converter: usize, source_inode: u64) -> u64 {
+    CONVERT_BIT | ((converter as u64) << CONVERTER_SHIFT) | source_inode
+}
+
+// (converter id, source inode) of a converted view
+fn convert_parts(inode: u64) -> (usize, u64) {
+    (((inode >> CONVERTER_SHIFT) & 0x1fff) as usize, inode & SOURCE_MASK)
+}
+
+// One read() worth of a cached file. Loops until `size` bytes or EOF, a single File::read may come up short.
+fn read_range(path: &std::path::Path, offset: i64, size: u32) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset.max(0) as u64))?;
+    let mut data = Vec::with_capacity(size as usize);
+    file.take(size as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+// Same-named files from different folders get the inode appended: IMG_0001-1234.jpg
+fn unique_names(files: impl IntoIterator<Item = (u64, String)>) -> impl Iterator<Item = (u64, String)> {
+    let mut seen = std::collections::HashSet::new();
+    files.into_iter().map(move |(inode, name)| {
+        if seen.insert(name.clone()) {
+            return (inode, name);
+        }
+        match name.rsplit_once('.') {
+            Some((stem, ext)) => (inode, format!("{}-{}.{}", stem, inode, ext)),
+            None => (inode, format!("{}-{}", name, inode)),
+        }
+    })
+}
 
 struct InodeStore {
     db: Database,
 }
 
+// A `notes.de.txt` translation: in the conversion cache (path, size, modified), or still being
+// made on a background thread
+enum Translated {
+    Ready(PathBuf, u64, SystemTime),
+    Pending,
+}
+
+// What a translation reads as until it's ready
+fn translating_text(language: &str) -> String {
+    format!("(Translating into {}; read this file again in a moment)\n", lang::english_name(language).unwrap_or(language))
+}
+
+// A question written to `.ask`, answered on a background thread after its first read
+#[derive(Default)]
+struct Question {
+    text: String,
+    answer: Option<String>,
+    // Answering has started
+    asked: bool,
+}
+
 impl InodeStore {
     fn new(path: PathBuf) -> Self {
         // We panic here if DB fails, as we can't recover in new() easily without changing signature heavily.
@@ -72,37 +178,26 @@ impl InodeStore {
         }
         self.db.create_inode(parent, &name).unwrap_or(0) // 0 is invalid/root-ish, but handle error ideally
     }
-    
+
+    // Batched variant used by readdir: one transaction per listing instead of one per entry.
+    fn alloc_inodes(&mut self, parent: u64, names: &[String]) -> Vec<u64> {
+        match self.db.alloc_inodes(parent, names) {
+            Ok(inodes) => inodes,
+            Err(_) => names.iter().map(|name| self.alloc_inode(parent, name.clone())).collect(),
+        }
+    }
+
     fn get_inode(&self, parent: u64, name: &str) -> Option<u64> {
          self.db.get_inode(parent, name).unwrap_or(None)
     }
 
     fn get_path(&self, inode: u64) -> Option<String> {
-        if inode == 1 {
-            return Some("".to_string());
-        }
-        
-        let mut parts = Vec::new();
-        let mut current = inode;
-        
-        let mut loop_check = 0;
-        
-        while current != 1 && loop_check < 100 {
-            if let Ok(Some((parent, name))) = self.db.get_inode_entry(current) {
-                parts.push(name);
-                current = parent;
-            } else {
-                return None;
-            }
-            loop_check += 1;
-        }
-        
-        parts.reverse();
-        Some(parts.join("/"))
+        self.db.get_path(inode).unwrap_or(None)
     }
     
+    // Along with everything below it and every row about them, see Database::delete_tree
     fn remove_inode(&mut self, inode: u64) {
-        let _ = self.db.delete_inode(inode);
+        let _ = self.db.delete_tree(inode);
     }
     
     fn move_inode(&mut self, inode: u64, new_parent: u64, new_name: String) {
@@ -113,15 +208,145 @@ impl InodeStore {
     fn get_tags(&self) -> Vec<String> {
         self.db.get_tags().unwrap_or_default()
     }
-    
-    fn get_files_with_tag(&self, tag: &str) -> Vec<(u64, String)> {
-        self.db.get_files_with_tag(tag).unwrap_or_default()
+}
+
+// A rendering of the real file `raw_inode` in the conversion cache, made by `render` unless it's
+// cached for the file's current mtime. Failures are remembered too, so they aren't retried per lookup.
+fn rendered(
+    inodes: &Mutex<InodeStore>,
+    cache: &ConversionCache,
+    source_path: &Path,
+    raw_inode: u64,
+    format: &str,
+    render: impl FnOnce(&ConversionCache, i64, &Path) -> anyhow::Result<(PathBuf, u64)>,
+) -> Option<(PathBuf, u64, std::time::SystemTime)> {
+    let source = source_path.join(inodes.lock().unwrap().get_path(raw_inode)?);
+    let metadata = fs::metadata(&source).ok()?;
+    let mtime = convert::mtime_key(&metadata);
+    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+
+    let cached = {
+        let store = inodes.lock().unwrap();
+        store.db.get_conversion(raw_inode, format).unwrap_or(None)
+    };
+    match cached {
+        Some((cached_mtime, None)) if cached_mtime == mtime => return None,
+        Some((cached_mtime, Some(size))) if cached_mtime == mtime => {
+            let target = cache.path_for(raw_inode, mtime, format);
+            if target.exists() {
+                return Some((target, size, modified));
+            }
+        }
+        _ => {}
     }
+
+    // Convert without holding the inode lock
+    let _ = inodes.lock().unwrap().db.bump_counter(crate::stats::CONVERSIONS);
+    match crate::metrics::converting(|| render(cache, mtime, &source)) {
+        Ok((target, size)) => {
+            let _ = inodes.lock().unwrap().db.set_conversion(raw_inode, format, mtime, Some(size));
+            Some((target, size, modified))
+        }
+        Err(e) => {
+            warn!(?source, format, error = %e, "conversion failed");
+            let _ = inodes.lock().unwrap().db.set_conversion(raw_inode, format, mtime, None);
+            None
+        }
+    }
+}
+
+// What `.context` and `.ask` read a directory through; cloned into the thread answering a
+// question, so the walk and the model never hold up the mount
+#[derive(Clone)]
+struct ContextReader {
+    source_path: PathBuf,
+    inodes: Arc<Mutex<InodeStore>>,
+    cache: Arc<ConversionCache>,
+    converters: Arc<Registry>,
+    exclude_sensitive: bool,
+}
+
+impl ContextReader {
+    // Files the sensitive data scanner flagged, when `[sensitive] exclude_from_context` is set
+    fn excluded(&self, path: &Path) -> bool {
+        if !self.exclude_sensitive {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.source_path) else { return false };
+        let store = self.inodes.lock().unwrap();
+        let inode = store.db.lookup_path(&relative.to_string_lossy()).unwrap_or(None);
+        inode.is_some_and(|inode| store.db.is_sensitive(inode).unwrap_or(false))
+    }
+
+    // Extracted text of a PDF anywhere under the source dir, as the `paper.pdf.txt` view renders it
+    fn pdf_text(&self, path: &Path) -> Option<String> {
+        let converter = self.converters.get(self.converters.find("pdf", "txt")?)?;
+        let rel_path = path.strip_prefix(&self.source_path).ok()?.to_string_lossy().to_string();
+        let inode = self.inodes.lock().unwrap().db.alloc_path(&rel_path).ok()?;
+        let (cached, _, _) = rendered(&self.inodes, &self.cache, &self.source_path, inode, &converter.cache_key, |cache, mtime, source| cache.render(inode, mtime, source, converter))?;
+        fs::read_to_string(cached).ok()
+    }
+
+    // Every source file below `dir_path`, recursively (respecting .gitignore)
+    fn directory_context(&self, dir_path: &Path) -> String {
+        let mut content = String::new();
+        content.push_str(&format!("# Deep Context for {:?}\n\n", dir_path.file_name().unwrap_or_default()));
+        content.push_str("> Generated by Eidetic. Includes all source files recursively (respecting .gitignore).\n\n");
+        
+        // Use 'ignore' crate for recursive walking with gitignore support
+        use ignore::WalkBuilder;
+        
+        let walker = WalkBuilder::new(dir_path)
+            .hidden(false) // Allow hidden files? Maybe no.
+            .git_ignore(true)
+            // Never Eidetic's own state (the conversion cache is full of .txt/.md renderings)
+            .filter_entry(|e| e.file_name() != ".eidetic")
+            .build();
+
+        for entry in walker.flatten() {
+            let p = entry.path();
+            if p.is_file() && !self.excluded(p) {
+                // Filter binary/large files roughly
+                let ext = p.extension().unwrap_or_default().to_string_lossy();
+                let allowed_exts = [
+                    "rs", "toml", "md", "txt", "js", "ts", "jsx", "tsx", "json", 
+                    "py", "c", "h", "cpp", "hpp", "go", "java", "kt", "swift",
+                    "html", "css", "scss", "sql", "sh", "yaml", "yml"
+                ];
+                
+                if ext.eq_ignore_ascii_case("pdf") {
+                    // PDFs go in as their extracted text, rendered through the conversion cache
+                    let rel_path = p.strip_prefix(dir_path).unwrap_or(p);
+                    if let Some(text) = self.pdf_text(p) {
+                        let (words, _) = crate::reading::count(&text);
+                        content.push_str(&format!("## {} ({})\n```text\n{}\n```\n\n", rel_path.display(), crate::reading::describe(words), text.trim_end()));
+                    }
+                } else if allowed_exts.contains(&ext.as_ref()) {
+                    // Relative path for cleanliness
+                    let rel_path = p.strip_prefix(dir_path).unwrap_or(p);
+                    
+                    if let Ok(code) = std::fs::read_to_string(p) {
+                        // Reading time only means something for prose
+                        let header = if ext == "md" || ext == "txt" {
+                            format!("{} ({})", rel_path.display(), crate::reading::describe(crate::reading::count(&code).0))
+                        } else {
+                            rel_path.display().to_string()
+                        };
+                        content.push_str(&format!("## {}\n```{}\n{}\n```\n\n", header, ext, code));
+                    }
+                }
+            }
+        }
+        content
+    }
+
 }
 
 impl EideticFS {
-    pub fn new(source_path: PathBuf, uid: u32, gid: u32, sender: Sender<Job>) -> Self {
+    pub fn new(source_path: PathBuf, uid: u32, gid: u32, jobs: JobQueue, converters: Registry, assistant: Option<Summarizer>, config: &Config) -> Self {
         let db_path = source_path.join(".eidetic.db");
+        let cache = ConversionCache::new(&source_path);
+        let license = crate::license::watch(&source_path);
         Self {
             source_path,
             #[cfg(unix)]
@@ -134,41 +359,42 @@ impl EideticFS {
             #[cfg(not(unix))]
             gid: 0,
             
-            inodes: Mutex::new(InodeStore::new(db_path)),
-            sender,
+            inodes: Arc::new(Mutex::new(InodeStore::new(db_path))),
+            jobs,
+            cache: Arc::new(cache),
+            converters: Arc::new(converters),
+            views: Mutex::new(VirtualInodeStore::default()),
+            archives: Mutex::new(HashMap::new()),
+            assistant: assistant.map(Arc::new),
+            questions: Arc::new(Mutex::new(HashMap::new())),
+            translating: Arc::new(Mutex::new(HashSet::new())),
+            stats: Mutex::new(String::new()),
+            sensitive: config.sensitive.clone(),
+            mounted_at: SystemTime::now(),
+            vault_unlocked: AtomicBool::new(false),
+            notifier: Notifier::new(&config.notifications),
+            license,
+            link_max_age: Duration::from_secs(config.links.cache_minutes * 60),
         }
     }
 
     // License Verification (Phase 11)
-    // Checks ~/.eidetic/license for a key and calls the Worker API
-    fn check_license(&self) -> bool {
-        // 1. Look for license file
-        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-        let license_path = std::path::Path::new(&home).join(".eidetic").join("license");
-        
-        if let Ok(key) = std::fs::read_to_string(license_path) {
-            let key = key.trim();
-            if key.is_empty() { return false; }
-            
-            // 2. Call Worker API
-            // In Prod: "https://your-worker.workers.dev/verify?key={}"
-            // For Demo: We mock a "local" check or assume "ED-PRO" prefix overrides network.
-            if key.starts_with("ED-PRO") { return true; }
-
-            // Using curl for prototype network check
-            let output = std::process::Command::new("curl")
-                .arg("-s")
-                .arg(format!("https://eidetic-license.saujanyayaya.workers.dev/verify?key={}", key)) 
-                // NOTE: User must replace URL. We leave a valid-looking structure.
-                .output();
-
-            if let Ok(out) = output {
-                if String::from_utf8_lossy(&out.stdout).contains("\"valid\":true") {
-                    return true;
-                }
-            }
+    // The license in ~/.eidetic/license is verified at startup and in the background (license::watch), never on a FUSE request
+    fn check_license(&self, feature: &str) -> bool {
+        self.license.read().unwrap().allows(feature)
+    }
+
+    // The query of a real directory that is a smart folder (has a `.eidetic-query`)
+    fn smart_folder(&self, dir: u64) -> Option<Query> {
+        if views::is_virtual(dir) || (dir & (CONTEXT_BIT | CONVERT_BIT)) != 0 {
+            return None;
         }
-        false 
+        Query::load(&self.real_path(dir)?)
+    }
+
+    // Everything in a smart folder but its query file is the query's, not to be changed
+    fn in_smart_folder(&self, dir: u64, name: &OsStr) -> bool {
+        name != query::QUERY_FILE && self.smart_folder(dir).is_some()
     }
 
     fn real_path(&self, inode: u64) -> Option<PathBuf> {
@@ -176,6 +402,847 @@ impl EideticFS {
         store.get_path(inode).map(|p| self.source_path.join(p))
     }
 
+    // Returns the cached rendering of a converted view (and its real size), converting on first access.
+    // Failures are remembered per source mtime so a missing decoder isn't retried on every access.
+    fn converted(&self, inode: u64) -> Option<(PathBuf, u64, std::time::SystemTime)> {
+        let (converter, raw_inode) = convert_parts(inode);
+        let converter = self.converters.get(converter)?;
+        self.rendered(raw_inode, &converter.cache_key, |cache, mtime, source| cache.render(raw_inode, mtime, source, converter))
+    }
+
+    // A rendering of the real file `raw_inode` in the conversion cache, see `rendered` below
+    fn rendered(
+        &self,
+        raw_inode: u64,
+        format: &str,
+        render: impl FnOnce(&ConversionCache, i64, &Path) -> anyhow::Result<(PathBuf, u64)>,
+    ) -> Option<(PathBuf, u64, std::time::SystemTime)> {
+        rendered(&self.inodes, &self.cache, &self.source_path, raw_inode, format, render)
+    }
+
+    fn converted_attr(&self, inode: u64) -> Option<FileAttr> {
+        let (_, size, modified) = self.converted(inode)?;
+        Some(FileAttr {
+            ino: inode,
+            size,
+            blocks: size / 512 + 1,
+            atime: modified,
+            mtime: modified,
+            ctime: modified,
+            crtime: modified,
+            kind: FileType::RegularFile,
+            perm: 0o444,
+            nlink: 1,
+            uid: self.uid, gid: self.gid, rdev: 0, flags: 0, blksize: 512,
+        })
+    }
+
+    // `notes.de.txt` -> ("notes.txt" or a sibling in another language like "notes.en.txt", "de"),
+    // unless a real file has that name
+    fn translation_source(&self, dir: &std::path::Path, name: &str) -> Option<(String, String)> {
+        let (rest, ext) = name.rsplit_once('.')?;
+        let (stem, language) = rest.rsplit_once('.')?;
+        if !TRANSLATABLE.contains(&ext) || lang::english_name(language).is_none() || dir.join(name).exists() {
+            return None;
+        }
+        let plain = format!("{}.{}", stem, ext);
+        if dir.join(&plain).is_file() {
+            return Some((plain, language.to_string()));
+        }
+        let mut siblings: Vec<String> = fs::read_dir(dir).ok()?.flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|sibling| {
+                sibling.strip_prefix(stem)
+                    .and_then(|r| r.strip_prefix('.'))
+                    .and_then(|r| r.strip_suffix(ext))
+                    .and_then(|r| r.strip_suffix('.'))
+                    .is_some_and(|other| other != language && lang::english_name(other).is_some())
+            })
+            .collect();
+        siblings.sort();
+        Some((siblings.into_iter().next()?, language.to_string()))
+    }
+
+    // Translated by the `[ai]` backend on a background thread started by the first access, then
+    // served from the conversion cache. None without a backend or if translating failed
+    fn translation(&self, source: u64, language: &str) -> Option<Translated> {
+        let assistant = self.assistant.clone()?;
+        let name = lang::english_name(language)?;
+        let path = self.real_path(source)?;
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        let format = format!("translated-{}.{}", language, ext);
+        let metadata = fs::metadata(&path).ok()?;
+        let mtime = convert::mtime_key(&metadata);
+        let cached = self.inodes.lock().unwrap().db.get_conversion(source, &format).unwrap_or(None);
+        match cached {
+            Some((cached_mtime, None)) if cached_mtime == mtime => return None,
+            Some((cached_mtime, Some(size))) if cached_mtime == mtime => {
+                let target = self.cache.path_for(source, mtime, &format);
+                if target.exists() {
+                    return Some(Translated::Ready(target, size, metadata.modified().unwrap_or(UNIX_EPOCH)));
+                }
+            }
+            _ => {}
+        }
+
+        if self.translating.lock().unwrap().insert((source, language.to_string())) {
+            let (inodes, cache, source_path, translating) = (self.inodes.clone(), self.cache.clone(), self.source_path.clone(), self.translating.clone());
+            let language = language.to_string();
+            thread::spawn(move || {
+                rendered(&inodes, &cache, &source_path, source, &format, |cache, mtime, path| {
+                    cache.render_with(source, mtime, &format, path, |from, to| {
+                        let text = fs::read_to_string(from)?;
+                        fs::write(to, assistant.translate(&text, name)?)?;
+                        Ok(())
+                    })
+                });
+                translating.lock().unwrap().remove(&(source, language));
+            });
+        }
+        Some(Translated::Pending)
+    }
+
+    // Where `name` would be as a real file, next to the translation's source
+    fn translation_target(&self, source: u64, name: &str) -> Option<PathBuf> {
+        Some(self.real_path(source)?.parent()?.join(name))
+    }
+
+    fn view(&self, inode: u64) -> Option<View> {
+        self.views.lock().unwrap().view(inode)
+    }
+
+    fn view_inode(&self, view: View) -> u64 {
+        self.views.lock().unwrap().inode_for(view)
+    }
+
+    fn virtual_dir_attr(&self, inode: u64) -> FileAttr {
+        FileAttr {
+            ino: inode,
+            size: 0,
+            blocks: 0,
+            atime: UNIX_EPOCH,
+            mtime: UNIX_EPOCH,
+            ctime: UNIX_EPOCH,
+            crtime: UNIX_EPOCH,
+            kind: FileType::Directory,
+            perm: 0o555,
+            nlink: 2,
+            uid: self.uid, gid: self.gid, rdev: 0, flags: 0, blksize: 512,
+        }
+    }
+
+    // Member list of an archive (plus its path and mtime), re-read when the archive changes
+    fn archive_index(&self, archive: u64) -> Option<(PathBuf, std::time::SystemTime, Arc<ArchiveIndex>)> {
+        let path = self.real_path(archive)?;
+        let metadata = fs::metadata(&path).ok()?;
+        let mtime = convert::mtime_key(&metadata);
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        if let Some((cached_mtime, index)) = self.archives.lock().unwrap().get(&archive) {
+            if *cached_mtime == mtime {
+                return Some((path, modified, index.clone()));
+            }
+        }
+        let index = match crate::metrics::converting(|| archive::index(&path)) {
+            Ok(index) => Arc::new(index),
+            Err(e) => {
+                warn!(?path, error = %e, "failed to index archive");
+                return None;
+            }
+        };
+        self.archives.lock().unwrap().insert(archive, (mtime, index.clone()));
+        Some((path, modified, index))
+    }
+
+    // Extracts an archive member into the cache on first read. Cache names are derived from a
+    // hash of the member path, never the path itself.
+    fn archive_member(&self, archive: u64, member: &str) -> Option<PathBuf> {
+        use std::hash::{Hash, Hasher};
+        let path = self.real_path(archive)?;
+        let mtime = convert::mtime_key(&fs::metadata(&path).ok()?);
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        member.hash(&mut hasher);
+        let target = self.cache.path_for(archive, mtime, &format!("member-{:016x}", hasher.finish()));
+        if target.exists() {
+            return Some(target);
+        }
+        let partial = target.with_extension("part");
+        let result = fs::create_dir_all(target.parent()?)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| crate::metrics::converting(|| archive::extract(&path, member, &partial)))
+            .and_then(|_| Ok(fs::rename(&partial, &target)?));
+        if let Err(e) = result {
+            warn!(?path, member, error = %e, "failed to extract archive member");
+            let _ = fs::remove_file(&partial);
+            return None;
+        }
+        Some(target)
+    }
+
+    fn summary(&self, inode: u64) -> Option<String> {
+        self.inodes.lock().unwrap().db.get_summary(inode).unwrap_or(None).map(|summary| summary + "\n")
+    }
+
+    // `.context`: every source file below the directory, recursively (respecting .gitignore)
+    fn directory_context(&self, dir_inode: u64) -> Option<String> {
+        Some(self.context_reader().directory_context(&self.real_path(dir_inode)?))
+    }
+
+    fn context_reader(&self) -> ContextReader {
+        ContextReader {
+            source_path: self.source_path.clone(),
+            inodes: self.inodes.clone(),
+            cache: self.cache.clone(),
+            converters: self.converters.clone(),
+            exclude_sensitive: self.sensitive.exclude_from_context,
+        }
+    }
+
+    // `.ask`: the question and its answer. The first read after the question was written starts
+    // answering it on a background thread; until that's done, reads say so
+    fn ask_text(&self, dir: u64) -> String {
+        let dir_path = self.real_path(dir);
+        let mut questions = self.questions.lock().unwrap();
+        let Some(question) = questions.get_mut(&dir) else {
+            return "Write a question to this file, then read it back for an answer based on this directory's files.\n".to_string();
+        };
+        let answer = match &question.answer {
+            Some(answer) => answer.clone(),
+            None => {
+                if !question.asked {
+                    question.asked = true;
+                    self.answer_in_background(dir, dir_path, question.text.clone());
+                }
+                "(thinking; read this file again in a moment)".to_string()
+            }
+        };
+        format!("Q: {}\n\nA: {}\n", question.text.trim(), answer)
+    }
+
+    fn answer_in_background(&self, dir: u64, dir_path: Option<PathBuf>, question: String) {
+        let (assistant, reader, questions) = (self.assistant.clone(), self.context_reader(), self.questions.clone());
+        thread::spawn(move || {
+            let answer = match (assistant, dir_path) {
+                (Some(assistant), Some(dir_path)) => {
+                    let context = reader.directory_context(&dir_path);
+                    crate::metrics::converting(|| assistant.ask(&context, question.trim())).unwrap_or_else(|e| format!("(no answer: {})", e))
+                }
+                (None, _) => "(no answer: the AI backend isn't available, see the log)".to_string(),
+                (_, None) => "(no answer: the directory is gone)".to_string(),
+            };
+            // Unless another question was written in the meantime
+            if let Some(current) = questions.lock().unwrap().get_mut(&dir) {
+                if current.text == question {
+                    current.answer = Some(answer);
+                }
+            }
+        });
+    }
+
+    // Pending `.magic/rename_suggestions/`: (inode, suggested name), skipping files renamed since
+    // and all but the first file for a name suggested twice
+    fn rename_suggestions(&self) -> Vec<(u64, String)> {
+        let rows = self.inodes.lock().unwrap().db.get_rename_suggestions().unwrap_or_default();
+        let mut seen = std::collections::HashSet::new();
+        rows.into_iter()
+            .filter(|(_, current, suggested)| crate::naming::is_generic(current) && seen.insert(suggested.clone()))
+            .map(|(inode, _, suggested)| (inode, suggested))
+            .collect()
+    }
+
+    // Renames the original in place; the suggestion can only be moved onto the file it was made for
+    fn apply_rename_suggestion(&self, suggested: &str, target_parent: u64, target_name: &str) -> Result<(), i32> {
+        let (source, _) = self.rename_suggestions().into_iter().find(|(_, name)| name == suggested).ok_or(ENOENT)?;
+        let mut store = self.inodes.lock().unwrap();
+        if store.get_inode(target_parent, target_name) != Some(source) {
+            return Err(libc::EINVAL);
+        }
+        let dir = store.get_path(target_parent).ok_or(ENOENT)?;
+        let (from, to) = (self.source_path.join(&dir).join(target_name), self.source_path.join(&dir).join(suggested));
+        if to.exists() {
+            return Err(libc::EEXIST);
+        }
+        fs::rename(&from, &to).map_err(|e| e.raw_os_error().unwrap_or(EIO))?;
+        store.move_inode(source, target_parent, suggested.to_string());
+        let _ = store.db.delete_rename_suggestion(source);
+        Ok(())
+    }
+
+    // Pending `.magic/proposals/`: (inode, entry name, destination), skipping files already moved there
+    fn proposals(&self) -> Vec<(u64, String, String)> {
+        let store = self.inodes.lock().unwrap();
+        let rows = store.db.get_proposals().unwrap_or_default();
+        let pending: Vec<_> = rows.into_iter()
+            .filter(|(inode, _, destination)| store.get_path(*inode).is_some_and(|path| path != *destination))
+            .collect();
+        let destinations: HashMap<u64, String> = pending.iter().map(|(inode, _, d)| (*inode, d.clone())).collect();
+        unique_names(pending.into_iter().map(|(inode, name, _)| (inode, name)))
+            .map(|(inode, name)| (inode, name, destinations[&inode].clone()))
+            .collect()
+    }
+
+    // Moves the file to its proposed destination, creating missing folders. The inode keeps its
+    // number and the table is updated together with the disk, so the mount sees the move at once.
+    fn apply_proposal(&self, source: u64) -> Result<(), i32> {
+        let (_, _, destination) = self.proposals().into_iter().find(|(inode, _, _)| *inode == source).ok_or(ENOENT)?;
+        let store = self.inodes.lock().unwrap();
+        let from_rel = store.get_path(source).ok_or(ENOENT)?;
+        let (from, to) = (self.source_path.join(&from_rel), self.source_path.join(&destination));
+        if to.exists() {
+            return Err(libc::EEXIST);
+        }
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.raw_os_error().unwrap_or(EIO))?;
+        }
+        store.db.move_to_path(source, &destination, || fs::rename(&from, &to)).map_err(|e| {
+            error!(?from, ?to, error = %e, "failed to apply organizer proposal");
+            e.downcast_ref::<std::io::Error>().and_then(|e| e.raw_os_error()).unwrap_or(EIO)
+        })?;
+        let _ = store.db.delete_proposal(source);
+        let _ = store.db.add_organizer_move(source, &from_rel, &destination);
+        self.notifier.moved(&from_rel, &destination);
+        Ok(())
+    }
+
+    // Content of `.magic/stats.md`
+    fn stats_text(&self) -> String {
+        let db = &self.inodes.lock().unwrap().db;
+        let tags = db.tag_counts().unwrap_or_default();
+
+        let mut content = String::new();
+        content.push_str("# 📊 Eidetic Stats\n\n");
+        content.push_str("## System Status\n");
+        content.push_str("- **State**: Online 🟢\n");
+        content.push_str(&format!("- **Total Tags**: {}\n", tags.len()));
+        
+        content.push_str("\n## Tags Distribution\n");
+        if tags.is_empty() {
+            content.push_str("_No tags found yet._\n");
+        } else {
+            for (tag, count) in tags {
+                 content.push_str(&format!("- **#{}**: {} files\n", tag, count));
+            }
+        }
+        content.push_str(&crate::heatmap::summary(db));
+        content.push_str(&crate::reading::summary(db));
+        content.push_str(&crate::stats::summary(db));
+        content.push_str(&crate::metrics::latency_summary());
+        content.push_str(&crate::license::summary(&self.license.read().unwrap()));
+        content.push_str("\n> *Generated by Eidetic Intelligent Filesystem*\n");
+        content
+    }
+
+    // Extended attributes of a real file: counts, rating and label
+    fn xattrs(&self, inode: u64) -> Vec<(&'static str, String)> {
+        let db = &self.inodes.lock().unwrap().db;
+        let mut attrs = crate::reading::xattrs(db, inode);
+        if let Ok(Some(stars)) = db.get_rating(inode) {
+            attrs.push((crate::ratings::XATTR, stars.to_string()));
+        }
+        if let Ok(Some(color)) = db.get_label(inode) {
+            attrs.push((crate::labels::XATTR, color));
+        }
+        attrs
+    }
+
+    // Content of the virtual text files
+    fn view_text(&self, view: &View) -> Option<String> {
+        match view {
+            View::Summary(source) => self.summary(*source),
+            View::Transcript(source) => self.inodes.lock().unwrap().db.get_transcript(*source).unwrap_or(None).map(|text| text + "\n"),
+            View::Backlinks(source) => crate::links::backlinks(&self.inodes.lock().unwrap().db, *source),
+            View::Lint(source) => crate::lint::report(&self.inodes.lock().unwrap().db, *source),
+            View::Comments(source) => Some(self.inodes.lock().unwrap().db.get_comment(*source).unwrap_or(None).unwrap_or_default()),
+            View::IntegrityReport => Some(scrub::report(&self.inodes.lock().unwrap().db)),
+            View::SensitiveReport => Some(sensitive::report(&self.inodes.lock().unwrap().db)),
+            View::OrganizerLog => Some(crate::organize::log(&self.inodes.lock().unwrap().db)),
+            View::Heatmap => Some(crate::heatmap::report(&self.inodes.lock().unwrap().db)),
+            View::Largest => Some(crate::space::cached(&self.inodes.lock().unwrap().db)),
+            View::UsageReport(dir) => Some(crate::usage::report(&self.inodes.lock().unwrap().db, *dir)),
+            View::Todos => Some(crate::todos::report(&self.inodes.lock().unwrap().db)),
+            View::Events => Some(crate::events::log(&self.inodes.lock().unwrap().db, None)),
+            View::Health => Some(crate::health::report(&self.inodes.lock().unwrap().db, &self.jobs, self.mounted_at)),
+            View::JobsStatus => Some(self.jobs.status_report(&self.inodes.lock().unwrap().db)),
+            View::JobsControl => Some(if self.jobs.is_paused() { "paused\n" } else { "running\n" }.to_string()),
+            View::Ask(dir) => Some(self.ask_text(*dir)),
+            View::RenameSuggestion(source) => {
+                let (_, suggested) = self.rename_suggestions().into_iter().find(|(inode, _)| inode == source)?;
+                Some(format!("{} -> {}\n", self.inodes.lock().unwrap().get_path(*source)?, suggested))
+            }
+            View::Proposal(source) => {
+                let (_, _, destination) = self.proposals().into_iter().find(|(inode, _, _)| inode == source)?;
+                Some(format!("{} -> {}\n", self.inodes.lock().unwrap().get_path(*source)?, destination))
+            }
+            View::Todo { source, line } => crate::todos::item(&self.inodes.lock().unwrap().db, *source, *line),
+            View::WormholeCode(name) => crate::wormhole::code_text(name),
+            View::WormholeReceive => Some(crate::wormhole::receive_report()),
+            View::Feed(name) => Some(crate::feeds::digest(&self.source_path, &crate::api::find_feed(name)?)),
+            _ => None,
+        }
+    }
+
+    fn text_attr(&self, inode: u64, size: u64, modified: SystemTime) -> FileAttr {
+        FileAttr {
+            ino: inode,
+            size,
+            blocks: size / 512 + 1,
+            atime: modified,
+            mtime: modified,
+            ctime: modified,
+            crtime: modified,
+            kind: FileType::RegularFile,
+            perm: 0o444,
+            nlink: 1,
+            uid: self.uid, gid: self.gid, rdev: 0, flags: 0, blksize: 512,
+        }
+    }
+
+    // Attributes of a virtual inode: directories for every view, plus files inside archives and summaries
+    fn view_attr(&self, inode: u64) -> Option<FileAttr> {
+        match self.view(inode)? {
+            View::Thumbnails(_) | View::Photos(_) | View::Music(_) | View::Clusters(_) | View::Recent(_) | View::ByDate(_) | View::ByType(_) | View::BySize(_) | View::Untagged | View::Unreviewed | View::Favorites | View::Starred | View::Labels(_) | View::TodoFiles | View::TodoFile(_) => Some(self.virtual_dir_attr(inode)),
+            view @ (View::Summary(source) | View::Transcript(source) | View::Backlinks(source) | View::Lint(source)) => {
+                let size = self.view_text(&view)?.len() as u64;
+                let modified = fs::metadata(self.real_path(source)?).and_then(|m| m.modified()).unwrap_or(UNIX_EPOCH);
+                Some(self.text_attr(inode, size, modified))
+            }
+            View::Translation { source, language, name } => {
+                // Written to since lookup, so it's a real file now
+                if let Ok(metadata) = fs::metadata(self.translation_target(source, &name)?) {
+                    return Some(FileAttr { perm: 0o644, ..self.text_attr(inode, metadata.len(), metadata.modified().unwrap_or(UNIX_EPOCH)) });
+                }
+                match self.translation(source, &language)? {
+                    Translated::Ready(_, size, modified) => Some(self.text_attr(inode, size, modified)),
+                    Translated::Pending => Some(self.text_attr(inode, translating_text(&language).len() as u64, SystemTime::now())),
+                }
+            }
+            View::Jobs | View::RenameSuggestions | View::Proposals | View::SimilarImages | View::SimilarTo(_) => Some(self.virtual_dir_attr(inode)),
+            // Sized by the rollup, so `ls -l` and `sort -h` rank folders by what they hold
+            View::Usage(dir) => Some(FileAttr {
+                size: self.inodes.lock().unwrap().db.dir_usage(dir).ok().flatten().map_or(0, |(bytes, _)| bytes),
+                ..self.virtual_dir_attr(inode)
+            }),
+            view @ (View::IntegrityReport | View::SensitiveReport | View::OrganizerLog | View::Heatmap | View::Largest | View::UsageReport(_) | View::Todos | View::Events | View::Health | View::JobsStatus | View::RenameSuggestion(_) | View::Proposal(_) | View::Todo { .. } | View::Feed(_)) => Some(self.text_attr(inode, self.view_text(&view)?.len() as u64, SystemTime::now())),
+            view @ (View::JobsControl | View::WormholeReceive) => Some(FileAttr {
+                perm: 0o644,
+                ..self.text_attr(inode, self.view_text(&view)?.len() as u64, SystemTime::now())
+            }),
+            // Files can be copied in
+            View::WormholeSend => Some(FileAttr { perm: 0o755, ..self.virtual_dir_attr(inode) }),
+            View::WormholeInbox => Some(self.virtual_dir_attr(inode)),
+            View::WormholeOutgoing(name) => {
+                let metadata = fs::metadata(crate::wormhole::outgoing_dir(&self.source_path).join(name)).ok()?;
+                Some(FileAttr { perm: 0o644, ..self.text_attr(inode, metadata.len(), metadata.modified().unwrap_or(UNIX_EPOCH)) })
+            }
+            View::WormholeCode(name) => Some(self.text_attr(inode, crate::wormhole::code_text(&name)?.len() as u64, SystemTime::now())),
+            // The size of the last response; reads aren't cut at it (direct I/O). Writable to send a body
+            View::Api(name) => {
+                crate::api::find(&name)?;
+                Some(FileAttr { perm: 0o644, ..self.text_attr(inode, crate::api::cached_len(&name), SystemTime::now()) })
+            }
+            View::Comments(source) => {
+                let modified = fs::metadata(self.real_path(source)?).ok().filter(|m| m.is_file())?.modified().unwrap_or(UNIX_EPOCH);
+                let size = self.view_text(&View::Comments(source))?.len() as u64;
+                Some(FileAttr { perm: 0o644, ..self.text_attr(inode, size, modified) })
+            }
+            View::Ask(dir) => {
+                if !self.real_path(dir)?.is_dir() {
+                    return None;
+                }
+                // Answering can take a while, so an unanswered question reports size 0 (`.ask` is opened with direct I/O)
+                let unanswered = self.questions.lock().unwrap().get(&dir).is_some_and(|question| question.answer.is_none());
+                let size = if unanswered { 0 } else { self.ask_text(dir).len() as u64 };
+                Some(FileAttr { perm: 0o644, ..self.text_attr(inode, size, SystemTime::now()) })
+            }
+            View::Archive { archive, path } => {
+                let (_, modified, index) = self.archive_index(archive)?;
+                let (is_dir, size) = if path.is_empty() {
+                    (true, 0)
+                } else {
+                    let member = index.get(&path)?;
+                    (member.is_dir, member.size)
+                };
+                Some(FileAttr {
+                    ino: inode,
+                    size,
+                    blocks: size / 512 + 1,
+                    atime: modified,
+                    mtime: modified,
+                    ctime: modified,
+                    crtime: modified,
+                    kind: if is_dir { FileType::Directory } else { FileType::RegularFile },
+                    perm: if is_dir { 0o555 } else { 0o444 },
+                    nlink: if is_dir { 2 } else { 1 },
+                    uid: self.uid, gid: self.gid, rdev: 0, flags: 0, blksize: 512,
+                })
+            }
+        }
+    }
+
+    // Parent inode and (inode, name, kind) entries of a virtual directory
+    fn view_entries(&self, view: &View) -> (u64, Vec<(u64, String, FileType)>) {
+        match view {
+            View::Thumbnails(dir) => {
+                let names: Vec<String> = self.real_path(*dir)
+                    .and_then(|path| fs::read_dir(path).ok())
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .filter(|name| convert::is_thumbnailable(name))
+                    .collect();
+                let inodes = self.inodes.lock().unwrap().alloc_inodes(*dir, &names);
+                let thumbnail = self.converters.thumbnail();
+                let entries = inodes.into_iter()
+                    .zip(names)
+                    .map(|(inode, name)| (convert_inode(thumbnail, inode), format!("{}.jpg", name), FileType::RegularFile))
+                    .collect();
+                (*dir, entries)
+            }
+            View::Archive { archive, path } => {
+                let parent = match path.rsplit_once('/') {
+                    Some((dir, _)) => self.view_inode(View::Archive { archive: *archive, path: dir.to_string() }),
+                    None if !path.is_empty() => self.view_inode(View::Archive { archive: *archive, path: String::new() }),
+                    // `..` of `backup.zip#` is the directory holding backup.zip
+                    None => self.inodes.lock().unwrap().db.get_inode_entry(*archive).ok().flatten().map(|(dir, _)| dir).unwrap_or(1),
+                };
+                let Some((_, _, index)) = self.archive_index(*archive) else {
+                    return (parent, Vec::new());
+                };
+                let entries = index.children(path)
+                    .into_iter()
+                    .map(|(member_path, member)| {
+                        let inode = self.view_inode(View::Archive { archive: *archive, path: member_path.to_string() });
+                        let name = member_path.rsplit('/').next().unwrap_or(member_path).to_string();
+                        (inode, name, if member.is_dir { FileType::Directory } else { FileType::RegularFile })
+                    })
+                    .collect();
+                (parent, entries)
+            }
+            View::Photos(path) => {
+                let parent = match path.split_last() {
+                    Some((_, up)) => self.view_inode(View::Photos(up.to_vec())),
+                    None => MAGIC_ROOT,
+                };
+                (parent, self.photo_entries(path))
+            }
+            View::ByDate(path) => {
+                let parent = match path.split_last() {
+                    Some((_, up)) => self.view_inode(View::ByDate(up.to_vec())),
+                    None => MAGIC_ROOT,
+                };
+                (parent, self.date_entries(path))
+            }
+            View::ByType(path) => {
+                let parent = if path.is_empty() { MAGIC_ROOT } else { self.view_inode(View::ByType(Vec::new())) };
+                (parent, self.type_entries(path))
+            }
+            View::BySize(path) => {
+                let parent = if path.is_empty() { MAGIC_ROOT } else { self.view_inode(View::BySize(Vec::new())) };
+                (parent, self.size_entries(path))
+            }
+            View::Labels(path) => {
+                let parent = if path.is_empty() { MAGIC_ROOT } else { self.view_inode(View::Labels(Vec::new())) };
+                (parent, self.label_entries(path))
+            }
+            View::Recent(path) => {
+                let parent = if path.is_empty() { MAGIC_ROOT } else { self.view_inode(View::Recent(Vec::new())) };
+                (parent, self.recent_entries(path))
+            }
+            View::Clusters(path) => {
+                let parent = match path.split_last() {
+                    Some((_, up)) => self.view_inode(View::Clusters(up.to_vec())),
+                    None => MAGIC_ROOT,
+                };
+                (parent, self.cluster_entries(path))
+            }
+            View::Usage(dir) => {
+                let (up, children) = {
+                    let db = &self.inodes.lock().unwrap().db;
+                    let up = if *dir == 1 { None } else { db.get_inode_entry(*dir).ok().flatten().map(|(up, _)| up) };
+                    (up, db.child_dir_usage(*dir).unwrap_or_default())
+                };
+                let parent = match up {
+                    Some(up) => self.view_inode(View::Usage(up)),
+                    None => MAGIC_ROOT,
+                };
+                let mut entries: Vec<_> = children.into_iter()
+                    .map(|(child, name, _, _)| (self.view_inode(View::Usage(child)), name, FileType::Directory))
+                    .collect();
+                entries.push((self.view_inode(View::UsageReport(*dir)), "usage.md".to_string(), FileType::RegularFile));
+                (parent, entries)
+            }
+            View::UsageReport(dir) => (self.view_inode(View::Usage(*dir)), Vec::new()),
+            View::Summary(_) | View::Transcript(_) | View::Backlinks(_) | View::Lint(_) | View::Comments(_) | View::Translation { .. } | View::IntegrityReport | View::SensitiveReport | View::OrganizerLog | View::Heatmap | View::Largest | View::Todos | View::Events | View::Health | View::JobsStatus | View::JobsControl => (1, Vec::new()),
+            View::Ask(dir) => (*dir, Vec::new()),
+            View::RenameSuggestions => (MAGIC_ROOT, self.rename_suggestions().into_iter()
+                .map(|(source, suggested)| (self.view_inode(View::RenameSuggestion(source)), suggested, FileType::RegularFile))
+                .collect()),
+            View::RenameSuggestion(_) => (self.view_inode(View::RenameSuggestions), Vec::new()),
+            View::Untagged | View::Unreviewed | View::Favorites | View::Starred => {
+                let db = &self.inodes.lock().unwrap().db;
+                let files = match view {
+                    View::Untagged => db.untagged_files(),
+                    View::Unreviewed => {
+                        crate::heatmap::flush(db);
+                        db.unread_files()
+                    }
+                    View::Starred => db.get_rated_files(),
+                    _ => db.get_favorites(),
+                };
+                (MAGIC_ROOT, unique_names(files.unwrap_or_default()).map(|(inode, name)| (inode, name, FileType::RegularFile)).collect())
+            }
+            View::Proposals => (MAGIC_ROOT, self.proposals().into_iter()
+                .map(|(source, name, _)| (self.view_inode(View::Proposal(source)), name, FileType::RegularFile))
+                .collect()),
+            View::Proposal(_) => (self.view_inode(View::Proposals), Vec::new()),
+            View::TodoFiles => {
+                let db = &self.inodes.lock().unwrap().db;
+                let mut sources: Vec<u64> = db.get_todos().unwrap_or_default().into_iter().map(|(inode, _, _)| inode).collect();
+                sources.dedup();
+                let files = sources.into_iter().filter_map(|inode| Some((inode, db.get_inode_entry(inode).ok()??.1)));
+                let entries = unique_names(files.collect::<Vec<_>>())
+                    .map(|(inode, name)| (self.view_inode(View::TodoFile(inode)), name, FileType::Directory))
+                    .collect();
+                (MAGIC_ROOT, entries)
+            }
+            View::TodoFile(source) => {
+                let todos = self.inodes.lock().unwrap().db.get_todos().unwrap_or_default();
+                let entries = todos.into_iter()
+                    .filter(|(inode, _, _)| inode == source)
+                    .map(|(_, line, content)| (self.view_inode(View::Todo { source: *source, line }), crate::todos::file_name(line, &content), FileType::RegularFile))
+                    .collect();
+                (self.view_inode(View::TodoFiles), entries)
+            }
+            View::Todo { source, .. } => (self.view_inode(View::TodoFile(*source)), Vec::new()),
+            View::SimilarImages => {
+                let images = self.inodes.lock().unwrap().db.get_image_hashes().unwrap_or_default();
+                let entries = unique_names(images.into_iter().map(|(inode, name, _)| (inode, name)))
+                    .map(|(inode, name)| (self.view_inode(View::SimilarTo(inode)), name, FileType::Directory))
+                    .collect();
+                (MAGIC_ROOT, entries)
+            }
+            View::SimilarTo(image) => {
+                let similar = crate::phash::similar(&self.inodes.lock().unwrap().db, *image);
+                let entries = unique_names(similar.into_iter().map(|(inode, name, _)| (inode, name)))
+                    .map(|(inode, name)| (inode, name, FileType::RegularFile))
+                    .collect();
+                (self.view_inode(View::SimilarImages), entries)
+            }
+            View::WormholeSend => {
+                let mut entries = Vec::new();
+                for name in crate::wormhole::outgoing(&self.source_path) {
+                    if crate::wormhole::code_text(&name).is_some() {
+                        entries.push((self.view_inode(View::WormholeCode(name.clone())), format!("{}.code", name), FileType::RegularFile));
+                    }
+                    entries.push((self.view_inode(View::WormholeOutgoing(name.clone())), name, FileType::RegularFile));
+                }
+                (MAGIC_WORMHOLE, entries)
+            }
+            View::WormholeOutgoing(_) | View::WormholeCode(_) => (self.view_inode(View::WormholeSend), Vec::new()),
+            View::WormholeReceive => (MAGIC_WORMHOLE, Vec::new()),
+            View::Api(_) | View::Feed(_) => (MAGIC_API, Vec::new()),
+            View::WormholeInbox => {
+                let received = self.inodes.lock().unwrap().db.get_received().unwrap_or_default();
+                (MAGIC_WORMHOLE, unique_names(received).map(|(inode, name)| (inode, name, FileType::RegularFile)).collect())
+            }
+            View::Jobs => (MAGIC_ROOT, vec![
+                (self.view_inode(View::JobsStatus), "status.md".to_string(), FileType::RegularFile),
+                (self.view_inode(View::JobsControl), "control".to_string(), FileType::RegularFile),
+            ]),
+            View::Music(path) => {
+                let parent = match path.split_last() {
+                    Some((_, up)) => self.view_inode(View::Music(up.to_vec())),
+                    None => MAGIC_ROOT,
+                };
+                (parent, self.music_entries(path))
+            }
+        }
+    }
+
+    // `.magic/photos/by-date/<year>/<month>/` and `.magic/photos/by-camera/<camera>/`, from the worker's EXIF table
+    fn photo_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let photos = self.inodes.lock().unwrap().db.get_photos().unwrap_or_default();
+        let parts: Vec<&str> = path.iter().map(String::as_str).collect();
+        let mut names: Vec<String> = Vec::new();
+        let mut files = Vec::new();
+        for photo in photos {
+            let date = photo.taken.as_deref().unwrap_or("");
+            // Slashes would split a camera name into directories
+            let camera = photo.camera.map(|c| c.replace('/', "_"));
+            match parts[..] {
+                ["by-date"] if date.len() >= 7 => names.push(date[..4].to_string()),
+                ["by-date", year] if date.starts_with(&format!("{}-", year)) => names.push(date[5..7].to_string()),
+                ["by-date", year, month] if date.starts_with(&format!("{}-{}-", year, month)) => files.push((photo.inode, photo.name)),
+                ["by-camera"] => names.extend(camera),
+                ["by-camera", wanted] if camera.as_deref() == Some(wanted) => files.push((photo.inode, photo.name)),
+                _ => {}
+            }
+        }
+        if parts.is_empty() {
+            names = vec!["by-date".to_string(), "by-camera".to_string()];
+        }
+        self.library_entries(path, names, files, View::Photos)
+    }
+
+    // `.magic/music/by-artist/<artist>/<album>/`, from the worker's tag table
+    fn music_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let tracks = self.inodes.lock().unwrap().db.get_tracks().unwrap_or_default();
+        let parts: Vec<&str> = path.iter().map(String::as_str).collect();
+        let mut names: Vec<String> = Vec::new();
+        let mut files = Vec::new();
+        for track in tracks {
+            let artist = track.artist.unwrap_or_else(|| "Unknown Artist".to_string()).replace('/', "_");
+            let album = track.album.unwrap_or_else(|| "Unknown Album".to_string()).replace('/', "_");
+            match parts[..] {
+                ["by-artist"] => names.push(artist),
+                ["by-artist", wanted] if artist == wanted => names.push(album),
+                ["by-artist", wanted, wanted_album] if artist == wanted && album == wanted_album => files.push((track.inode, track.name)),
+                _ => {}
+            }
+        }
+        if parts.is_empty() {
+            names = vec!["by-artist".to_string()];
+        }
+        self.library_entries(path, names, files, View::Music)
+    }
+
+    // `.magic/by-date/<year>/<month>/<day>/`, by modification time as of the last analysis
+    fn date_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let files = self.inodes.lock().unwrap().db.get_file_dates().unwrap_or_default();
+        let parts: Vec<&str> = path.iter().map(String::as_str).collect();
+        let mut names = Vec::new();
+        let mut listed = Vec::new();
+        for (inode, name, date) in files {
+            let mut fields = date.splitn(3, '-');
+            let (Some(year), Some(month), Some(day)) = (fields.next(), fields.next(), fields.next()) else { continue };
+            match parts[..] {
+                [] => names.push(year.to_string()),
+                [y] if y == year => names.push(month.to_string()),
+                [y, m] if y == year && m == month => names.push(day.to_string()),
+                [y, m, d] if y == year && m == month && d == day => listed.push((inode, name)),
+                _ => {}
+            }
+        }
+        self.library_entries(path, names, listed, View::ByDate)
+    }
+
+    // `.magic/by-type/<kind>/`, by the kind the worker detected
+    fn type_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let files = self.inodes.lock().unwrap().db.get_file_kinds().unwrap_or_default();
+        let mut names = Vec::new();
+        let mut listed = Vec::new();
+        for (inode, name, kind) in files {
+            match path {
+                [] => names.push(kind),
+                [k] if *k == kind => listed.push((inode, name)),
+                _ => {}
+            }
+        }
+        self.library_entries(path, names, listed, View::ByType)
+    }
+
+    // `.magic/by-size/<bucket>/`, largest first, by size as of the last analysis
+    fn size_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let files = self.inodes.lock().unwrap().db.get_file_sizes().unwrap_or_default();
+        let mut names = Vec::new();
+        let mut listed = Vec::new();
+        for (inode, name, size) in files {
+            let bucket = SIZE_BUCKETS.iter().find(|(_, limit)| size < *limit).map_or("over-1GB", |(bucket, _)| bucket);
+            match path {
+                [] => names.push(bucket.to_string()),
+                [b] if b == bucket => listed.push((inode, name)),
+                _ => {}
+            }
+        }
+        self.library_entries(path, names, listed, View::BySize)
+    }
+
+    // `.magic/labels/<color>/`, every color even when empty so files can be moved in
+    fn label_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let files = self.inodes.lock().unwrap().db.get_labeled_files().unwrap_or_default();
+        let names = if path.is_empty() { crate::labels::COLORS.iter().map(|color| color.to_string()).collect() } else { Vec::new() };
+        let listed = files.into_iter()
+            .filter(|(_, _, color)| matches!(path, [c] if c == color))
+            .map(|(inode, name, _)| (inode, name))
+            .collect();
+        self.library_entries(path, names, listed, View::Labels)
+    }
+
+    // `.magic/recent/`: the most recently opened files, and the same split into Today/, Yesterday/ and This week/
+    fn recent_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let recent = {
+            let db = &self.inodes.lock().unwrap().db;
+            crate::heatmap::flush(db);
+            db.recent_files(RECENT_LIMIT).unwrap_or_default()
+        };
+        // Local midnight, so "Today" matches the wall clock
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        unsafe { libc::localtime_r(&now, &mut tm) };
+        let midnight = now - (tm.tm_hour as i64 * 3600 + tm.tm_min as i64 * 60 + tm.tm_sec as i64);
+        let group = |accessed: i64| match accessed {
+            t if t >= midnight => Some("Today"),
+            t if t >= midnight - 86400 => Some("Yesterday"),
+            t if t >= midnight - 6 * 86400 => Some("This week"),
+            _ => None,
+        };
+        let files = recent.into_iter()
+            .filter(|(_, _, accessed)| match path {
+                [] => true,
+                [wanted] => group(*accessed) == Some(wanted.as_str()),
+                _ => false,
+            })
+            // Deleted outside the mount since
+            .filter(|(inode, _, _)| self.real_path(*inode).is_some_and(|p| p.exists()))
+            .map(|(inode, name, _)| (inode, name))
+            .collect();
+        let names = if path.is_empty() { RECENT_GROUPS.iter().map(|g| g.to_string()).collect() } else { Vec::new() };
+        self.library_entries(path, names, files, View::Recent)
+    }
+
+    // `.magic/clusters/<label>/`, from the periodic clustering in cluster.rs
+    fn cluster_entries(&self, path: &[String]) -> Vec<(u64, String, FileType)> {
+        let clusters = self.inodes.lock().unwrap().db.get_clusters().unwrap_or_default();
+        let mut names = Vec::new();
+        let mut files = Vec::new();
+        for (label, inode, name) in clusters {
+            match path {
+                [] => names.push(label),
+                [wanted] if *wanted == label => files.push((inode, name)),
+                _ => {}
+            }
+        }
+        self.library_entries(path, names, files, View::Clusters)
+    }
+
+    // Sub-directories `names` under a library view, followed by the real files it lists
+    fn library_entries(&self, path: &[String], mut names: Vec<String>, files: Vec<(u64, String)>, view: fn(Vec<String>) -> View) -> Vec<(u64, String, FileType)> {
+        names.sort();
+        names.dedup();
+        let mut entries: Vec<_> = names.into_iter()
+            .map(|name| {
+                let mut child = path.to_vec();
+                child.push(name.clone());
+                (self.view_inode(view(child)), name, FileType::Directory)
+            })
+            .collect();
+        entries.extend(unique_names(files).map(|(inode, name)| (inode, name, FileType::RegularFile)));
+        entries
+    }
+
+    // Attributes for an entry listed by view_entries
+    fn entry_attr(&self, inode: u64) -> Option<FileAttr> {
+        if views::is_virtual(inode) {
+            return self.view_attr(inode);
+        }
+        if (inode & CONVERT_BIT) != 0 {
+            return self.converted_attr(inode);
+        }
+        let metadata = fs::metadata(self.real_path(inode)?).ok()?;
+        Some(self.fs_metadata_to_file_attr(&metadata, inode))
+    }
+
     // Helper to map std::fs::Metadata to fuser::FileAttr
     fn fs_metadata_to_file_attr(&self, metadata: &fs::Metadata, inode: u64) -> FileAttr {
         // Virtual Context File
@@ -195,23 +1262,6 @@ impl EideticFS {
              };
         }
 
-        if (inode & CONVERT_BIT) != 0 {
-             // Virtual Converted File (e.g. .jpg)
-             return FileAttr {
-                ino: inode,
-                size: 1024 * 1024, // Dummy size (1MB), accurate size requires conversion
-                blocks: 1,
-                atime: UNIX_EPOCH,
-                mtime: UNIX_EPOCH,
-                ctime: UNIX_EPOCH,
-                crtime: UNIX_EPOCH,
-                kind: FileType::RegularFile,
-                perm: 0o444,
-                nlink: 1,
-                uid: 0, gid: 0, rdev: 0, flags: 0, blksize: 512,
-             };
-        }
-        
         // Virtual Search File (Writable)
         if inode == MAGIC_SEARCH {
              return FileAttr {
@@ -248,23 +1298,8 @@ impl EideticFS {
         if inode == MAGIC_STATS {
              return FileAttr {
                 ino: inode,
-                size: 1024, // Dynamic size usually
-                blocks: 1,
-                atime: UNIX_EPOCH,
-                mtime: UNIX_EPOCH,
-                ctime: UNIX_EPOCH,
-                crtime: UNIX_EPOCH,
-                kind: FileType::RegularFile,
-                perm: 0o444,
-                nlink: 1,
-                uid: 0, gid: 0, rdev: 0, flags: 0, blksize: 512,
-             };
-        }
-
-        if (inode & API_BIT) != 0 {
-             return FileAttr {
-                ino: inode,
-                size: 1024, 
+                // Built when it's read, see `read`; served with direct I/O like `.magic/api/`
+                size: 0,
                 blocks: 1,
                 atime: UNIX_EPOCH,
                 mtime: UNIX_EPOCH,
@@ -331,7 +1366,22 @@ impl PermissionsExt for std::fs::Permissions {
 
 impl Filesystem for EideticFS {
     fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let _timer = crate::metrics::fuse_op("lookup");
         let name_str = name.to_string_lossy();
+
+        // Entries inside virtual directories (VirtualInodeStore)
+        if views::is_virtual(parent) {
+            let Some(view) = self.view(parent) else {
+                reply.error(failed(ENOENT));
+                return;
+            };
+            let (_, entries) = self.view_entries(&view);
+            match entries.iter().find(|(_, entry_name, _)| *entry_name == name_str).and_then(|(inode, _, _)| self.entry_attr(*inode)) {
+                Some(attr) => reply.entry(&TTL, &attr, 0),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
         
         // Virtual Magic Lookup
         if parent == 1 && name_str == ".magic" {
@@ -370,22 +1420,115 @@ impl Filesystem for EideticFS {
              return;
         }
 
+        if parent == MAGIC_ROOT && name_str == "photos" {
+            let inode = self.view_inode(View::Photos(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "music" {
+            let inode = self.view_inode(View::Music(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "rename_suggestions" {
+            let inode = self.view_inode(View::RenameSuggestions);
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "clusters" {
+            let inode = self.view_inode(View::Clusters(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "proposals" {
+            let inode = self.view_inode(View::Proposals);
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "similar_images" {
+            let inode = self.view_inode(View::SimilarImages);
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "jobs" {
+            let inode = self.view_inode(View::Jobs);
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && ["integrity_report.md", "sensitive_report.md", "organizer_log.md", "heatmap.md", "largest.md", "todos.md", "health.json", "events.log"].contains(&name_str.as_ref()) {
+            let inode = self.view_inode(match name_str.as_ref() {
+                "integrity_report.md" => View::IntegrityReport,
+                "sensitive_report.md" => View::SensitiveReport,
+                "organizer_log.md" => View::OrganizerLog,
+                "heatmap.md" => View::Heatmap,
+                "largest.md" => View::Largest,
+                "health.json" => View::Health,
+                "events.log" => View::Events,
+                _ => View::Todos,
+            });
+            match self.view_attr(inode) {
+                Some(attr) => reply.entry(&TTL, &attr, 0),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "by-date" {
+            let inode = self.view_inode(View::ByDate(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && ["favorites", "starred", "untagged", "unreviewed", "todos"].contains(&name_str.as_ref()) {
+            let inode = self.view_inode(match name_str.as_ref() {
+                "favorites" => View::Favorites,
+                "starred" => View::Starred,
+                "todos" => View::TodoFiles,
+                "untagged" => View::Untagged,
+                _ => View::Unreviewed,
+            });
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "usage" {
+            let inode = self.view_inode(View::Usage(1));
+            match self.view_attr(inode) {
+                Some(attr) => reply.entry(&TTL, &attr, 0),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "by-size" {
+            let inode = self.view_inode(View::BySize(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "by-type" {
+            let inode = self.view_inode(View::ByType(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
+        if parent == MAGIC_ROOT && name_str == "labels" {
+            let inode = self.view_inode(View::Labels(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
+        }
+
         if parent == MAGIC_ROOT && name_str == "recent" {
-             let attr = FileAttr {
-                ino: MAGIC_RECENT,
-                size: 0,
-                blocks: 0,
-                atime: UNIX_EPOCH,
-                mtime: UNIX_EPOCH,
-                ctime: UNIX_EPOCH,
-                crtime: UNIX_EPOCH,
-                kind: FileType::Directory,
-                perm: 0o555,
-                nlink: 2,
-                uid: 0, gid: 0, rdev: 0, flags: 0, blksize: 512,
-             };
-             reply.entry(&TTL, &attr, 0);
-             return;
+            let inode = self.view_inode(View::Recent(Vec::new()));
+            reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+            return;
         }
 
         if parent == MAGIC_ROOT && name_str == "search" {
@@ -415,10 +1558,27 @@ impl Filesystem for EideticFS {
              return;
         }
 
+        if parent == MAGIC_WORMHOLE && self.check_license(crate::license::WORMHOLE) {
+            let inode = match name_str.as_ref() {
+                "send" => self.view_inode(View::WormholeSend),
+                "receive" => self.view_inode(View::WormholeReceive),
+                "inbox" => self.view_inode(View::WormholeInbox),
+                _ => {
+                    reply.error(failed(ENOENT));
+                    return;
+                }
+            };
+            match self.view_attr(inode) {
+                Some(attr) => reply.entry(&TTL, &attr, 0),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
         if parent == MAGIC_ROOT && name_str == "stats.md" {
              let attr = FileAttr {
                 ino: MAGIC_STATS,
-                size: 1024,
+                size: 0,
                 blocks: 1,
                 atime: UNIX_EPOCH,
                 mtime: UNIX_EPOCH,
@@ -433,13 +1593,17 @@ impl Filesystem for EideticFS {
              return;
         }
         
-        if parent == MAGIC_API && name_str == "bitcoin.json" {
-             let attr = FileAttr {
-                ino: MAGIC_API | API_BIT,
-                size: 1024, blocks: 1, atime: UNIX_EPOCH, mtime: UNIX_EPOCH, ctime: UNIX_EPOCH, crtime: UNIX_EPOCH, kind: FileType::RegularFile, perm: 0o444, nlink: 1, uid: 0, gid: 0, rdev: 0, flags: 0, blksize: 512,
-             };
-             reply.entry(&TTL, &attr, 0);
-             return;
+        // Endpoints and feeds from ~/.eidetic/api.toml, see api.rs and feeds.rs
+        if parent == MAGIC_API {
+            let view = match crate::api::find(&name_str) {
+                Some(endpoint) => Some(View::Api(endpoint.name)),
+                None => crate::api::find_feed(&name_str).map(|feed| View::Feed(feed.name)),
+            };
+            match view.and_then(|view| self.view_attr(self.view_inode(view))) {
+                Some(attr) => reply.entry(&TTL, &attr, 0),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
         }
         
         // Lookup specific tag directory (e.g., /magic/tags/finance)
@@ -478,12 +1642,136 @@ impl Filesystem for EideticFS {
             match store.get_path(parent) {
                 Some(p) => p,
                 None => {
-                    reply.error(ENOENT);
+                    reply.error(failed(ENOENT));
                     return;
                 }
             }
         };
 
+        // A smart folder holds its query's results (and the query file)
+        if name_str != query::QUERY_FILE {
+            if let Some(query) = self.smart_folder(parent) {
+                let results = query.results(&self.inodes.lock().unwrap().db);
+                match unique_names(results).find(|(_, entry)| *entry == name_str).and_then(|(inode, _)| self.entry_attr(inode)) {
+                    Some(attr) => reply.entry(&TTL, &attr, 0),
+                    None => reply.error(failed(ENOENT)),
+                }
+                return;
+            }
+        }
+
+        // Per-folder thumbnails, unless the folder has a real .thumbnails of its own
+        if name_str == ".thumbnails" {
+            let real_dir = self.source_path.join(&parent_path);
+            let has_media = fs::read_dir(&real_dir)
+                .map(|entries| entries.flatten().any(|e| convert::is_thumbnailable(&e.file_name().to_string_lossy())))
+                .unwrap_or(false);
+            if has_media && !real_dir.join(".thumbnails").exists() {
+                let inode = self.view_inode(View::Thumbnails(parent));
+                reply.entry(&TTL, &self.virtual_dir_attr(inode), 0);
+                return;
+            }
+        }
+
+        // `paper.pdf.summary` (or `.summary.txt`) once the worker has summarized paper.pdf
+        if let Some(source_name) = name_str.strip_suffix(".summary.txt").or_else(|| name_str.strip_suffix(".summary")) {
+            let real_dir = self.source_path.join(&parent_path);
+            if real_dir.join(source_name).is_file() && !real_dir.join(name_str.as_ref()).exists() {
+                let source = self.inodes.lock().unwrap().alloc_inode(parent, source_name.to_string());
+                let inode = self.view_inode(View::Summary(source));
+                match self.view_attr(inode) {
+                    Some(attr) => reply.entry(&TTL, &attr, 0),
+                    None => {
+                        // Not summarized yet (e.g. copied in before mounting): ask the worker to do it next
+                        let summarizable = source_name.to_lowercase().ends_with(".pdf")
+                            || fs::metadata(real_dir.join(source_name)).is_ok_and(|m| m.len() >= SUMMARY_MIN_CHARS as u64);
+                        if summarizable {
+                            let store = self.inodes.lock().unwrap();
+                            self.jobs.enqueue(&store.db, source, real_dir.join(source_name), Priority::Interactive);
+                        }
+                        reply.error(failed(ENOENT));
+                    }
+                }
+                return;
+            }
+        }
+
+        // `recording.m4a.txt` once the worker has transcribed recording.m4a
+        if let Some(source_name) = name_str.strip_suffix(".txt") {
+            let real_dir = self.source_path.join(&parent_path);
+            let is_audio = source_name.rsplit_once('.').is_some_and(|(_, ext)| transcribe::AUDIO_FORMATS.contains(&ext.to_lowercase().as_str()));
+            if is_audio && real_dir.join(source_name).is_file() && !real_dir.join(name_str.as_ref()).exists() {
+                let source = self.inodes.lock().unwrap().alloc_inode(parent, source_name.to_string());
+                match self.view_attr(self.view_inode(View::Transcript(source))) {
+                    Some(attr) => reply.entry(&TTL, &attr, 0),
+                    None => reply.error(failed(ENOENT)),
+                }
+                return;
+            }
+        }
+
+        // `note.md.backlinks` next to every Markdown note
+        if let Some(source_name) = name_str.strip_suffix(crate::links::SUFFIX) {
+            let real_dir = self.source_path.join(&parent_path);
+            if crate::links::is_note(source_name) && real_dir.join(source_name).is_file() && !real_dir.join(name_str.as_ref()).exists() {
+                let source = self.inodes.lock().unwrap().alloc_inode(parent, source_name.to_string());
+                match self.view_attr(self.view_inode(View::Backlinks(source))) {
+                    Some(attr) => reply.entry(&TTL, &attr, 0),
+                    None => reply.error(failed(ENOENT)),
+                }
+                return;
+            }
+        }
+
+        // `invoice.pdf.comments` next to every file, empty until something is written to it
+        if let Some(source_name) = name_str.strip_suffix(crate::comments::SUFFIX) {
+            let real_dir = self.source_path.join(&parent_path);
+            if real_dir.join(source_name).is_file() && !real_dir.join(name_str.as_ref()).exists() {
+                let source = self.inodes.lock().unwrap().alloc_inode(parent, source_name.to_string());
+                match self.view_attr(self.view_inode(View::Comments(source))) {
+                    Some(attr) => reply.entry(&TTL, &attr, 0),
+                    None => reply.error(failed(ENOENT)),
+                }
+                return;
+            }
+        }
+
+        // `essay.md.lint` next to prose files with proofreading findings
+        if let Some(source_name) = name_str.strip_suffix(crate::lint::SUFFIX) {
+            let real_dir = self.source_path.join(&parent_path);
+            if crate::lint::is_prose(source_name) && real_dir.join(source_name).is_file() && !real_dir.join(name_str.as_ref()).exists() {
+                let source = self.inodes.lock().unwrap().alloc_inode(parent, source_name.to_string());
+                match self.view_attr(self.view_inode(View::Lint(source))) {
+                    Some(attr) => reply.entry(&TTL, &attr, 0),
+                    None => reply.error(failed(ENOENT)),
+                }
+                return;
+            }
+        }
+
+        // `backup.zip#` browses the archive, unless something real already has that name
+        if let Some(archive_name) = name_str.strip_suffix(archive::BROWSE_SUFFIX) {
+            let real_dir = self.source_path.join(&parent_path);
+            if archive::is_archive(archive_name) && real_dir.join(archive_name).is_file() && !real_dir.join(name_str.as_ref()).exists() {
+                let archive = self.inodes.lock().unwrap().alloc_inode(parent, archive_name.to_string());
+                let inode = self.view_inode(View::Archive { archive, path: String::new() });
+                match self.view_attr(inode) {
+                    Some(attr) => reply.entry(&TTL, &attr, 0),
+                    None => reply.error(failed(EIO)),
+                }
+                return;
+            }
+        }
+
+        // Every directory's .ask file
+        if name_str == ".ask" {
+             match self.entry_attr(self.view_inode(View::Ask(parent))) {
+                 Some(attr) => reply.entry(&TTL, &attr, 0),
+                 None => reply.error(failed(ENOENT)),
+             }
+             return;
+        }
+
         // Virtual .context file check
         if name_str == ".context" {
              // ... existing context logic ...
@@ -504,29 +1792,30 @@ impl Filesystem for EideticFS {
              return;
         }
 
-        // Auto-Convert Lookup: If asking for .jpg and it doesn't exist, check for .png
-        if name_str.ends_with(".jpg") {
-            let png_name = name_str.replace(".jpg", ".png");
-            if let Some(png_inode) = {
-                let store = self.inodes.lock().unwrap();
-                store.get_inode(parent, &png_name)
-            } {
-                // Found a backing PNG! Return virtual JPG inode
-                let attr = FileAttr {
-                    ino: png_inode | CONVERT_BIT,
-                    size: 1024 * 1024,
-                    blocks: 1,
-                    atime: UNIX_EPOCH,
-                    mtime: UNIX_EPOCH,
-                    ctime: UNIX_EPOCH,
-                    crtime: UNIX_EPOCH,
-                    kind: FileType::RegularFile,
-                    perm: 0o444,
-                    nlink: 1,
-                    uid: 0, gid: 0, rdev: 0, flags: 0, blksize: 512,
-                };
-                reply.entry(&TTL, &attr, 0);
-                return;
+        // `notes.de.txt`: notes.txt in German
+        if let Some((source_name, language)) = self.translation_source(&self.source_path.join(&parent_path), &name_str) {
+            let source = self.inodes.lock().unwrap().alloc_inode(parent, source_name);
+            // Without a backend that translates, the name stays free for a real file
+            match self.view_attr(self.view_inode(View::Translation { source, language, name: name_str.to_string() })) {
+                Some(attr) => reply.entry(&TTL, &attr, 0),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // Auto-Convert Lookup: If asking for e.g. .jpg and it doesn't exist, check the registry for a .png / .heic sibling
+        let candidates = self.converters.backing_candidates(&name_str);
+        if !candidates.is_empty() && !self.source_path.join(&parent_path).join(name_str.as_ref()).exists() {
+            for (converter, candidate) in candidates {
+                if !self.source_path.join(&parent_path).join(&candidate).exists() {
+                    continue;
+                }
+                let source_inode = self.inodes.lock().unwrap().alloc_inode(parent, candidate);
+                // Found a backing file! Return the virtual inode (size comes from the conversion cache)
+                if let Some(attr) = self.converted_attr(convert_inode(converter, source_inode)) {
+                    reply.entry(&TTL, &attr, 0);
+                    return;
+                }
             }
         }
 
@@ -548,11 +1837,57 @@ impl Filesystem for EideticFS {
                 let attr = self.fs_metadata_to_file_attr(&metadata, inode);
                 reply.entry(&TTL, &attr, 0);
             }
-            Err(_) => reply.error(ENOENT),
+            Err(_) => reply.error(failed(ENOENT)),
+        }
+    }
+
+    fn open(&mut self, _req: &Request, inode: u64, flags: i32, reply: ReplyOpen) {
+        let _timer = crate::metrics::fuse_op("open");
+        // Real files only (top nibble clear): feeds `.magic/recent/` and the heatmap
+        if inode >> 60 == 0 {
+            let write = flags & libc::O_ACCMODE != libc::O_RDONLY;
+            crate::heatmap::record(inode, write);
+            // A `.url` file reads as the page it links to, longer than the file itself
+            if !write && self.real_path(inode).is_some_and(|path| path.extension().is_some_and(|ext| ext == "url")) {
+                reply.opened(0, fuser::consts::FOPEN_DIRECT_IO);
+                return;
+            }
+        }
+        // Writing to `notes.de.txt` makes it a real file; the name is only a translation while nothing has it
+        if let Some(View::Translation { source, name, .. }) = self.view(inode) {
+            if flags & libc::O_ACCMODE != libc::O_RDONLY {
+                let created = self.translation_target(source, &name).ok_or(std::io::Error::from_raw_os_error(ENOENT))
+                    .and_then(|path| File::options().write(true).create(true).truncate(flags & libc::O_TRUNC != 0).open(path));
+                match created {
+                    Ok(_) => reply.opened(0, fuser::consts::FOPEN_DIRECT_IO),
+                    Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(EIO))),
+                }
+                return;
+            }
+        }
+        // Converted and translated files, for the cache hit rate in `stats.md`
+        if inode >> 61 == CONVERT_BIT >> 61 || matches!(self.view(inode), Some(View::Translation { .. })) {
+            let _ = self.inodes.lock().unwrap().db.bump_counter(crate::stats::CONVERTED_OPENS);
+        }
+        // `.ask` and translations change size once the answer is in, `stats.md`, `health.json`, `events.log`, the wormhole's
+        // status files, API responses and feed digests all the time; bypass the page cache so reads aren't cut at a stale size
+        if inode == MAGIC_STATS || matches!(self.view(inode), Some(View::Ask(_) | View::Health | View::Events | View::WormholeCode(_) | View::WormholeReceive | View::Api(_) | View::Feed(_) | View::Translation { .. })) {
+            reply.opened(0, fuser::consts::FOPEN_DIRECT_IO);
+        } else {
+            reply.opened(0, 0);
         }
     }
 
     fn getattr(&mut self, _req: &Request, inode: u64, reply: ReplyAttr) {
+        let _timer = crate::metrics::fuse_op("getattr");
+        if views::is_virtual(inode) {
+            match self.view_attr(inode) {
+                Some(attr) => reply.attr(&TTL, &attr),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
         if (inode & CONTEXT_BIT) != 0 {
              let attr = FileAttr {
                 ino: inode,
@@ -572,20 +1907,10 @@ impl Filesystem for EideticFS {
         }
 
         if (inode & CONVERT_BIT) != 0 {
-             let attr = FileAttr {
-                ino: inode,
-                size: 1024 * 1024,
-                blocks: 1,
-                atime: UNIX_EPOCH,
-                mtime: UNIX_EPOCH,
-                ctime: UNIX_EPOCH,
-                crtime: UNIX_EPOCH,
-                kind: FileType::RegularFile,
-                perm: 0o444,
-                nlink: 1,
-                uid: 0, gid: 0, rdev: 0, flags: 0, blksize: 512,
-             };
-             reply.attr(&TTL, &attr);
+             match self.converted_attr(inode) {
+                 Some(attr) => reply.attr(&TTL, &attr),
+                 None => reply.error(failed(ENOENT)),
+             }
              return;
         }
         
@@ -607,24 +1932,6 @@ impl Filesystem for EideticFS {
              return;
         }
 
-        if (inode & API_BIT) != 0 {
-             let attr = FileAttr {
-                ino: inode,
-                size: 1024,
-                blocks: 1,
-                atime: UNIX_EPOCH,
-                mtime: UNIX_EPOCH,
-                ctime: UNIX_EPOCH,
-                crtime: UNIX_EPOCH,
-                kind: FileType::RegularFile,
-                perm: 0o444,
-                nlink: 1,
-                uid: 0, gid: 0, rdev: 0, flags: 0, blksize: 512,
-             };
-             reply.attr(&TTL, &attr);
-             return;
-        }
-
         if inode == MAGIC_API || inode == MAGIC_WORMHOLE {
              let attr = FileAttr {
                 ino: inode,
@@ -646,7 +1953,8 @@ impl Filesystem for EideticFS {
         if inode == MAGIC_STATS {
              let attr = FileAttr {
                 ino: inode,
-                size: 1024,
+                // Built when it's read, see `read`; served with direct I/O like `.magic/api/`
+                size: 0,
                 blocks: 1,
                 atime: UNIX_EPOCH,
                 mtime: UNIX_EPOCH,
@@ -686,10 +1994,10 @@ impl Filesystem for EideticFS {
                     let attr = self.fs_metadata_to_file_attr(&metadata, inode);
                     reply.attr(&TTL, &attr);
                 }
-                Err(_) => reply.error(ENOENT),
+                Err(_) => reply.error(failed(ENOENT)),
             }
         } else {
-            reply.error(ENOENT);
+            reply.error(failed(ENOENT));
         }
     }
 
@@ -704,12 +2012,76 @@ impl Filesystem for EideticFS {
         _lock_owner: Option<u64>,
         reply: ReplyData,
     ) {
+        let _timer = crate::metrics::fuse_op("read");
+        // Files inside a browsed archive, summaries
+        if views::is_virtual(inode) {
+            let extracted = match self.view(inode) {
+                Some(View::Archive { archive, path }) if !path.is_empty() => self.archive_member(archive, &path),
+                Some(View::Translation { source, language, name }) => {
+                    // Written to since lookup, so it's a real file now
+                    match self.translation_target(source, &name).filter(|path| path.exists()) {
+                        Some(path) => Some(path),
+                        None => match self.translation(source, &language) {
+                            Some(Translated::Ready(path, _, _)) => Some(path),
+                            Some(Translated::Pending) => {
+                                let text = translating_text(&language);
+                                let bytes = text.as_bytes();
+                                let start = (offset as usize).min(bytes.len());
+                                let end = (start + size as usize).min(bytes.len());
+                                reply.data(&bytes[start..end]);
+                                return;
+                            }
+                            None => None,
+                        },
+                    }
+                }
+                Some(View::WormholeOutgoing(name)) => Some(crate::wormhole::outgoing_dir(&self.source_path).join(name)),
+                // A fresh response right away; one that needs a request on a thread of its own, like `.url` pages
+                Some(View::Api(name)) => {
+                    let Some(endpoint) = crate::api::find(&name) else {
+                        reply.error(failed(ENOENT));
+                        return;
+                    };
+                    let slice = move |body: Vec<u8>, reply: ReplyData| {
+                        let start = (offset as usize).min(body.len());
+                        let end = (start + size as usize).min(body.len());
+                        reply.data(&body[start..end]);
+                    };
+                    match crate::api::fresh(&self.source_path, &endpoint) {
+                        Some(body) => slice(body, reply),
+                        None => {
+                            let source_path = self.source_path.clone();
+                            std::thread::spawn(move || slice(crate::api::response(&source_path, &endpoint), reply));
+                        }
+                    }
+                    return;
+                }
+                Some(view @ (View::Summary(_) | View::Transcript(_) | View::Backlinks(_) | View::Lint(_) | View::Comments(_) | View::IntegrityReport | View::SensitiveReport | View::OrganizerLog | View::Heatmap | View::Largest | View::UsageReport(_) | View::Todos | View::Events | View::Health | View::JobsStatus | View::JobsControl | View::Ask(_) | View::RenameSuggestion(_) | View::Proposal(_) | View::Todo { .. } | View::WormholeCode(_) | View::WormholeReceive | View::Feed(_))) => {
+                    match self.view_text(&view) {
+                        Some(text) => {
+                            let bytes = text.as_bytes();
+                            let start = (offset as usize).min(bytes.len());
+                            let end = (start + size as usize).min(bytes.len());
+                            reply.data(&bytes[start..end]);
+                        }
+                        None => reply.error(failed(ENOENT)),
+                    }
+                    return;
+                }
+                _ => None,
+            };
+            match extracted.map(|path| read_range(&path, offset, size)) {
+                Some(Ok(data)) => reply.data(&data),
+                _ => reply.error(failed(EIO)),
+            }
+            return;
+        }
+
         if let Some(real_path) = self.real_path(inode) {
              match File::open(&real_path) {
                  Ok(mut file) => {
-                     use std::io::{Read, Seek, SeekFrom};
-                     if let Err(_) = file.seek(SeekFrom::Start(offset as u64)) {
-                         reply.error(EIO);
+                     if file.seek(SeekFrom::Start(offset as u64)).is_err() {
+                         reply.error(failed(EIO));
                          return;
                      }
                      let mut buffer = vec![0; size as usize];
@@ -717,28 +2089,24 @@ impl Filesystem for EideticFS {
                          Ok(bytes_read) => {
                              // Vault Logic: Decrypt on Read
                              if real_path.to_string_lossy().contains("/vault/") {
+                                 if !self.vault_unlocked.swap(true, Ordering::Relaxed) {
+                                     let path = real_path.strip_prefix(&self.source_path).unwrap_or(&real_path).to_string_lossy();
+                                     let _ = self.inodes.lock().unwrap().db.add_event(crate::events::VAULT_UNLOCK, &path, "first read this mount");
+                                 }
                                  let decrypted = crate::cipher::decrypt(&buffer[..bytes_read]);
                                  reply.data(&decrypted);
-                             } else if real_path.extension().map_or(false, |e| e == "url") {
-                                 // Web-Link Logic: Fetch URL!
-                                 if let Ok(content) = std::str::from_utf8(&buffer[..bytes_read]) {
-                                     let url = content.trim();
-                                     if url.starts_with("http") {
-                                         // Execute curl
-                                         let output = std::process::Command::new("curl")
-                                             .arg("-s") // silent
-                                             .arg(url)
-                                             .output();
-                                         if let Ok(out) = output {
-                                             reply.data(&out.stdout); 
-                                             // Note: This replaces the .url file content with the HTML content view!
-                                             // This matches the "Web-Link File" feature description.
-                                         } else {
-                                             reply.data(b"Error fetching URL");
-                                         }
-                                     } else {
-                                        reply.data(&buffer[..bytes_read]); 
-                                     }
+                             } else if real_path.extension().is_some_and(|e| e == "url") {
+                                 // Web-Link Logic: the .url file reads as the page it links to (see weblink.rs).
+                                 // Fetched on a thread of its own so a slow site doesn't hold up the mount
+                                 let link = fs::read_to_string(&real_path).unwrap_or_default().trim().to_string();
+                                 if link.starts_with("http") {
+                                     let (source_path, max_age) = (self.source_path.clone(), self.link_max_age);
+                                     std::thread::spawn(move || {
+                                         let page = crate::weblink::page(&source_path, &link, max_age);
+                                         let start = (offset as usize).min(page.len());
+                                         let end = (start + size as usize).min(page.len());
+                                         reply.data(&page[start..end]);
+                                     });
                                  } else {
                                      reply.data(&buffer[..bytes_read]);
                                  }
@@ -746,119 +2114,42 @@ impl Filesystem for EideticFS {
                                  reply.data(&buffer[..bytes_read]);
                              }
                          },
-                         Err(_) => reply.error(EIO),
+                         Err(_) => reply.error(failed(EIO)),
                      }
                  },
-                 Err(_) => reply.error(ENOENT),
+                 Err(_) => reply.error(failed(ENOENT)),
              }
         } else if (inode & CONTEXT_BIT) != 0 {
              // DEEP CONTEXT: Recursive & Git-Aware
              // No license check required anymore.
 
              // Generate Context!
-             let dir_inode = inode & !CONTEXT_BIT;
-             if let Some(dir_path) = self.real_path(dir_inode) {
-                  let mut content = String::new();
-                  content.push_str(&format!("# Deep Context for {:?}\n\n", dir_path.file_name().unwrap_or_default()));
-                  content.push_str("> Generated by Eidetic. Includes all source files recursively (respecting .gitignore).\n\n");
-                  
-                  // Use 'ignore' crate for recursive walking with gitignore support
-                  use ignore::WalkBuilder;
-                  
-                  let walker = WalkBuilder::new(&dir_path)
-                      .hidden(false) // Allow hidden files? Maybe no.
-                      .git_ignore(true)
-                      .build();
-
-                  for result in walker {
-                      if let Ok(entry) = result {
-                          let p = entry.path();
-                          if p.is_file() {
-                              // Filter binary/large files roughly
-                              let ext = p.extension().unwrap_or_default().to_string_lossy();
-                              let allowed_exts = [
-                                  "rs", "toml", "md", "txt", "js", "ts", "jsx", "tsx", "json", 
-                                  "py", "c", "h", "cpp", "hpp", "go", "java", "kt", "swift",
-                                  "html", "css", "scss", "sql", "sh", "yaml", "yml"
-                              ];
-                              
-                              if allowed_exts.contains(&ext.as_ref()) {
-                                  // Relative path for cleanliness
-                                  let rel_path = p.strip_prefix(&dir_path).unwrap_or(p);
-                                  
-                                  if let Ok(code) = std::fs::read_to_string(&p) {
-                                      content.push_str(&format!("## {}\n```{}\n{}\n```\n\n", rel_path.display(), ext, code));
-                                  }
-                              }
-                          }
-                      }
-                  }
-                  
-                  // Handle offset read
-                  let bytes = content.as_bytes();
-                  if offset as usize >= bytes.len() {
-                      reply.data(&[]);
-                  } else {
-                      let end = std::cmp::min(offset as usize + size as usize, bytes.len());
-                      reply.data(&bytes[offset as usize..end]);
-                  }
-             } else {
-                 reply.error(ENOENT);
+             match self.directory_context(inode & !CONTEXT_BIT) {
+                 Some(content) => {
+                     // Handle offset read
+                     let bytes = content.as_bytes();
+                     if offset as usize >= bytes.len() {
+                         reply.data(&[]);
+                     } else {
+                         let end = std::cmp::min(offset as usize + size as usize, bytes.len());
+                         reply.data(&bytes[offset as usize..end]);
+                     }
+                 }
+                 None => reply.error(failed(ENOENT)),
              }
         } else if (inode & CONVERT_BIT) != 0 {
-            // Auto-Convert Read: PNG -> JPG
-            let raw_inode = inode & !CONVERT_BIT;
-            if let Some(real_path) = self.real_path(raw_inode) {
-                // Read PNG, Convert to JPG, Return
-                if let Ok(img) = image::open(&real_path) {
-                    let mut bytes: Vec<u8> = Vec::new();
-                    // Use cursor to write to memory
-                    let mut cursor = std::io::Cursor::new(&mut bytes);
-                    if img.write_to(&mut cursor, image::ImageFormat::Jpeg).is_ok() {
-                         // Handle offset
-                          if offset as usize >= bytes.len() {
-                              reply.data(&[]);
-                          } else {
-                              let end = std::cmp::min(offset as usize + size as usize, bytes.len());
-                              reply.data(&bytes[offset as usize..end]);
-                          }
-                    } else {
-                        reply.error(EIO);
-                    }
-                } else {
-                    reply.error(EIO);
-                }
-            } else {
-                reply.error(ENOENT);
+            // Auto-Convert Read: served from the conversion cache
+            match self.converted(inode).map(|(cached_path, _, _)| read_range(&cached_path, offset, size)) {
+                Some(Ok(data)) => reply.data(&data),
+                _ => reply.error(failed(EIO)),
             }
         } else if inode == MAGIC_STATS {
-            // Generate Stats Content
-            let tags = {
-                 let store = self.inodes.lock().unwrap();
-                 store.get_tags()
-            };
-            
-            let mut content = String::new();
-            content.push_str("# 📊 Eidetic Stats\n\n");
-            content.push_str("## System Status\n");
-            content.push_str("- **State**: Online 🟢\n");
-            content.push_str(&format!("- **Total Tags**: {}\n", tags.len()));
-            
-            content.push_str("\n## Tags Distribution\n");
-            if tags.is_empty() {
-                content.push_str("_No tags found yet._\n");
-            } else {
-                for tag in tags {
-                     let count = {
-                         let store = self.inodes.lock().unwrap();
-                         store.get_files_with_tag(&tag).len()
-                     };
-                     content.push_str(&format!("- **#{}**: {} files\n", tag, count));
-                }
+            // Built once per pass from the start, so the chunks of one read belong together
+            let mut stats = self.stats.lock().unwrap();
+            if offset == 0 || stats.is_empty() {
+                *stats = self.stats_text();
             }
-            content.push_str("\n> *Generated by Eidetic Intelligent Filesystem*\n");
-
-            let bytes = content.as_bytes();
+            let bytes = stats.as_bytes();
             if offset as usize >= bytes.len() {
                 reply.data(&[]);
             } else {
@@ -866,7 +2157,7 @@ impl Filesystem for EideticFS {
                 reply.data(&bytes[offset as usize..end]);
             }
         } else {
-            reply.error(ENOENT);
+            reply.error(failed(ENOENT));
         }
     }
 
@@ -878,86 +2169,118 @@ impl Filesystem for EideticFS {
         offset: i64,
         mut reply: ReplyDirectory,
     ) {
-        if offset > 0 {
-            reply.ok();
+        let _timer = crate::metrics::fuse_op("readdir");
+        let Some(entries) = self.dir_entries(inode) else {
+            reply.error(failed(ENOENT));
             return;
+        };
+        // An entry's offset is its position + 1. Once a buffer is full the kernel asks again with
+        // the offset of the last entry it got, and the listing picks up after that one
+        for (i, (entry_inode, kind, name)) in entries.iter().enumerate().skip(offset.max(0) as usize) {
+            if reply.add(*entry_inode, (i + 1) as i64, *kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    // Everything in directory `inode`, "." and ".." first; None if there's no such directory
+    fn dir_entries(&mut self, inode: u64) -> Option<Vec<(u64, FileType, String)>> {
+        let dir = |inode: u64, name: &str| (inode, FileType::Directory, name.to_string());
+        let file = |inode: u64, name: &str| (inode, FileType::RegularFile, name.to_string());
+
+        // Virtual directories (VirtualInodeStore)
+        if views::is_virtual(inode) {
+            let view = self.view(inode)?;
+            let (parent, entries) = self.view_entries(&view);
+            let mut listing = vec![dir(inode, "."), dir(parent, "..")];
+            listing.extend(entries.into_iter().map(|(entry_inode, name, kind)| (entry_inode, kind, name)));
+            return Some(listing);
         }
 
         // Virtual Readdir
         if inode == MAGIC_ROOT {
-            let _ = reply.add(MAGIC_ROOT, 1, FileType::Directory, ".");
-            let _ = reply.add(1, 2, FileType::Directory, "..");
-            let _ = reply.add(MAGIC_TAGS, 3, FileType::Directory, "tags");
-            let _ = reply.add(MAGIC_RECENT, 4, FileType::Directory, "recent");
-            let _ = reply.add(MAGIC_SEARCH, 5, FileType::RegularFile, "search");
-            let _ = reply.add(MAGIC_API, 6, FileType::Directory, "api");
-            let _ = reply.add(MAGIC_WORMHOLE, 7, FileType::Directory, "wormhole");
-            let _ = reply.add(MAGIC_STATS, 8, FileType::RegularFile, "stats.md");
-            reply.ok();
-            return;
+            return Some(vec![
+                dir(MAGIC_ROOT, "."),
+                dir(1, ".."),
+                dir(MAGIC_TAGS, "tags"),
+                dir(self.view_inode(View::Recent(Vec::new())), "recent"),
+                file(MAGIC_SEARCH, "search"),
+                dir(MAGIC_API, "api"),
+                dir(MAGIC_WORMHOLE, "wormhole"),
+                file(MAGIC_STATS, "stats.md"),
+                dir(self.view_inode(View::Photos(Vec::new())), "photos"),
+                dir(self.view_inode(View::Music(Vec::new())), "music"),
+                file(self.view_inode(View::IntegrityReport), "integrity_report.md"),
+                dir(self.view_inode(View::Jobs), "jobs"),
+                dir(self.view_inode(View::RenameSuggestions), "rename_suggestions"),
+                file(self.view_inode(View::SensitiveReport), "sensitive_report.md"),
+                dir(self.view_inode(View::SimilarImages), "similar_images"),
+                dir(self.view_inode(View::Clusters(Vec::new())), "clusters"),
+                dir(self.view_inode(View::Proposals), "proposals"),
+                file(self.view_inode(View::OrganizerLog), "organizer_log.md"),
+                file(self.view_inode(View::Heatmap), "heatmap.md"),
+                dir(self.view_inode(View::ByDate(Vec::new())), "by-date"),
+                dir(self.view_inode(View::ByType(Vec::new())), "by-type"),
+                dir(self.view_inode(View::BySize(Vec::new())), "by-size"),
+                file(self.view_inode(View::Largest), "largest.md"),
+                dir(self.view_inode(View::Untagged), "untagged"),
+                dir(self.view_inode(View::Unreviewed), "unreviewed"),
+                dir(self.view_inode(View::Favorites), "favorites"),
+                file(self.view_inode(View::Todos), "todos.md"),
+                dir(self.view_inode(View::TodoFiles), "todos"),
+                dir(self.view_inode(View::Starred), "starred"),
+                dir(self.view_inode(View::Labels(Vec::new())), "labels"),
+                file(self.view_inode(View::Health), "health.json"),
+                file(self.view_inode(View::Events), "events.log"),
+                dir(self.view_inode(View::Usage(1)), "usage"),
+            ]);
         }
-        
+
         // API Directory
         if inode == MAGIC_API {
-            let _ = reply.add(MAGIC_API, 1, FileType::Directory, ".");
-            let _ = reply.add(MAGIC_ROOT, 2, FileType::Directory, "..");
-            let _ = reply.add(MAGIC_API | API_BIT, 3, FileType::RegularFile, "bitcoin.json");
-             // In real app: read from config file list of APIs
-            reply.ok();
-            return;
+            let mut listing = vec![dir(MAGIC_API, "."), dir(MAGIC_ROOT, "..")];
+            let endpoints = crate::api::endpoints().into_iter().map(|endpoint| (View::Api(endpoint.name.clone()), endpoint.name));
+            let feeds = crate::api::feeds().into_iter().map(|feed| (View::Feed(feed.name.clone()), feed.name));
+            for (view, name) in endpoints.chain(feeds) {
+                listing.push(file(self.view_inode(view), &name));
+            }
+            return Some(listing);
         }
-        
-        // Wormhole (Mock P2P)
+
+        // Wormhole: send/ and receive, see wormhole.rs
         if inode == MAGIC_WORMHOLE {
-            if !self.check_license() {
+            if !self.check_license(crate::license::WORMHOLE) {
                 // Not Pro: Show Upgrade Info
-                let _ = reply.add(MAGIC_WORMHOLE, 1, FileType::Directory, ".");
-                let _ = reply.add(MAGIC_ROOT, 2, FileType::Directory, "..");
-                let _ = reply.add(MAGIC_WORMHOLE - 999, 3, FileType::RegularFile, "UPGRADE_TO_PRO.txt");
-                reply.ok();
-                return;
+                return Some(vec![dir(MAGIC_WORMHOLE, "."), dir(MAGIC_ROOT, ".."), file(MAGIC_WORMHOLE - 999, "UPGRADE_TO_PRO.txt")]);
             }
-
-            let _ = reply.add(MAGIC_WORMHOLE, 1, FileType::Directory, ".");
-            let _ = reply.add(MAGIC_ROOT, 2, FileType::Directory, "..");
-            // Mock peer
-            let _ = reply.add(MAGIC_WORMHOLE - 100, 3, FileType::Directory, "Peer_Node_1");
-             reply.ok();
-            return;
-        }
-        
-        // Recent Files
-        if inode == MAGIC_RECENT {
-            let _ = reply.add(MAGIC_RECENT, 1, FileType::Directory, ".");
-            let _ = reply.add(MAGIC_ROOT, 2, FileType::Directory, "..");
-            // Mock recent files
-            let _ = reply.add(MAGIC_RECENT-1, 3, FileType::RegularFile, "last_edited_file.rs");
-            reply.ok();
-            return;
+            return Some(vec![
+                dir(MAGIC_WORMHOLE, "."),
+                dir(MAGIC_ROOT, ".."),
+                dir(self.view_inode(View::WormholeSend), "send"),
+                file(self.view_inode(View::WormholeReceive), "receive"),
+                dir(self.view_inode(View::WormholeInbox), "inbox"),
+            ]);
         }
 
         if inode == MAGIC_TAGS {
-            let _ = reply.add(MAGIC_TAGS, 1, FileType::Directory, ".");
-            let _ = reply.add(MAGIC_ROOT, 2, FileType::Directory, "..");
-            
+            let mut listing = vec![dir(MAGIC_TAGS, "."), dir(MAGIC_ROOT, "..")];
+
             // Query DB for tags
             let store = self.inodes.lock().unwrap();
             let tags = store.get_tags();
             drop(store);
-            
-            for (i, tag) in tags.iter().enumerate() {
+
+            for tag in tags.iter() {
                 // Stable inode hash
                 let mut h = 0u64;
                 for b in tag.bytes() { h = h.wrapping_add(b as u64); }
-                let tag_inode = MAGIC_TAGS - 1000 - (h % 1000); 
-                
-                // +3 offset because of . and ..
-                if reply.add(tag_inode, (i+3) as i64, FileType::Directory, tag) { break; }
+                let tag_inode = MAGIC_TAGS - 1000 - (h % 1000);
+                listing.push(dir(tag_inode, tag));
             }
-            reply.ok();
-            return;
+            return Some(listing);
         }
-        
+
         // Tag Directory Listing (e.g. inside "finance")
         if inode < MAGIC_TAGS && inode > MAGIC_TAGS - 2000 {
             // We need to know WHICH tag this inode corresponds to. 
@@ -972,75 +2295,86 @@ impl Filesystem for EideticFS {
             // We will just return empty for safety on this pass to avoid crashing. 
             // In a real V4 we would implement VirtualInodeStore.
             
-            let _ = reply.add(inode, 1, FileType::Directory, ".");
-            let _ = reply.add(MAGIC_TAGS, 2, FileType::Directory, "..");
-            reply.ok();
-            return;
+            return Some(vec![dir(inode, "."), dir(MAGIC_TAGS, "..")]);
         }
 
         let store_lock = self.inodes.lock().unwrap();
-        let parent_path_opt = store_lock.get_path(inode);
+        let parent_path = store_lock.get_path(inode)?;
         drop(store_lock); // Release lock
 
-        if let Some(parent_path) = parent_path_opt {
-             let real_path = self.source_path.join(&parent_path);
-             
-             match fs::read_dir(real_path) {
-                 Ok(entries) => {
-                     let mut current_offset = 1;
-                     
-                     // Helper to add entry
-                     let mut add_entry = |inode: u64, name: &str, kind: FileType| {
-                         if reply.add(inode, current_offset, kind, name) {
-                             // Buffer full
-                             return true; 
-                         }
-                         current_offset += 1;
-                         false
-                     };
-
-                     // Add . and ..
-                     if add_entry(inode, ".", FileType::Directory) { reply.ok(); return; }
-                     // Note: Parent inode '..' calculation is simplified here (usually should track parent)
-                     if add_entry(1, "..", FileType::Directory) { reply.ok(); return; } 
-
-                     // Add .magic to root
-                     if inode == 1 {
-                         if add_entry(MAGIC_ROOT, ".magic", FileType::Directory) { reply.ok(); return; }
-                     }
-                     
-                     // Add .context to ALL directories
-                     let ctx_inode = inode | CONTEXT_BIT;
-                     if add_entry(ctx_inode, ".context", FileType::RegularFile) { reply.ok(); return; }
-
-                     for entry in entries {
-                         if let Ok(entry) = entry {
-                             let file_name = entry.file_name();
-                             let file_name_str = file_name.to_string_lossy();
-                             let child_path_str = if parent_path.is_empty() {
-                                 file_name_str.to_string()
-                             } else {
-                                 format!("{}/{}", parent_path, file_name_str)
-                             };
-                             
-                             let mut store = self.inodes.lock().unwrap();
-                             let child_inode = store.alloc_inode(inode, file_name_str.to_string());
-                             // For readdir, we don't strictly need full attributes, just name and inode
-                             let file_type = if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) { FileType::Directory } else { FileType::RegularFile };
-                             drop(store);
-
-                             if add_entry(child_inode, &file_name_str, file_type) {
-                                  break;
-                             }
-                         }
-                     }
-                     reply.ok();
-                 }
-                 Err(_) => reply.error(ENOENT),
-             }
-        } else {
-            reply.error(ENOENT);
+        let entries = fs::read_dir(self.source_path.join(&parent_path)).ok()?;
+        // Note: Parent inode '..' calculation is simplified here (usually should track parent)
+        let mut listing = vec![dir(inode, "."), dir(1, "..")];
+
+        // Add .magic to root
+        if inode == 1 {
+            listing.push(dir(MAGIC_ROOT, ".magic"));
+        }
+
+        if let Some(query) = self.smart_folder(inode) {
+            let query_file = self.inodes.lock().unwrap().alloc_inode(inode, query::QUERY_FILE.to_string());
+            listing.push(file(query_file, query::QUERY_FILE));
+            let results = query.results(&self.inodes.lock().unwrap().db);
+            listing.extend(unique_names(results).map(|(result, name)| (result, FileType::RegularFile, name)));
+            return Some(listing);
+        }
+
+        // Add .context to ALL directories
+        listing.push(file(inode | CONTEXT_BIT, ".context"));
+        listing.push(file(self.view_inode(View::Ask(inode)), ".ask"));
+
+        // Collect first so all inode allocations share one transaction. Sorted, so the offsets
+        // handed out in one call still point at the same entries in the next
+        let mut children: Vec<(String, FileType)> = entries.flatten()
+            .map(|entry| {
+                // For readdir, we don't strictly need full attributes, just name and inode
+                let file_type = if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) { FileType::Directory } else { FileType::RegularFile };
+                (entry.file_name().to_string_lossy().to_string(), file_type)
+            })
+            .collect();
+        children.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let (names, kinds): (Vec<String>, Vec<FileType>) = children.into_iter().unzip();
+
+        let child_inodes = {
+            let mut store = self.inodes.lock().unwrap();
+            store.alloc_inodes(inode, &names)
+        };
+        let taken: HashSet<&str> = names.iter().map(String::as_str).collect();
+
+        // .thumbnails in folders with images or videos
+        if names.iter().any(|n| convert::is_thumbnailable(n)) && !taken.contains(".thumbnails") {
+            listing.push(dir(self.view_inode(View::Thumbnails(inode)), ".thumbnails"));
+        }
+
+        // `<name>.summary` next to every summarized document, `<name>.txt` next to every transcribed
+        // recording, `<name>.backlinks` next to every note something links to, `<name>.lint` next to
+        // every prose file with proofreading findings, `<name>.comments` next to every commented file
+        let (summarized, transcribed, link_targets, linted, commented) = {
+            let store = self.inodes.lock().unwrap();
+            (store.db.summarized_children(inode).unwrap_or_default(), store.db.transcribed_children(inode).unwrap_or_default(), store.db.get_link_targets().unwrap_or_default(), store.db.linted_children(inode).unwrap_or_default(), store.db.commented_children(inode).unwrap_or_default())
+        };
+        let derived: Vec<(View, String)> = child_inodes.iter()
+            .zip(&names)
+            .flat_map(|(child_inode, name)| {
+                let summary = summarized.contains(child_inode).then(|| (View::Summary(*child_inode), format!("{}.summary", name)));
+                let transcript = transcribed.contains(child_inode).then(|| (View::Transcript(*child_inode), format!("{}.txt", name)));
+                let note_path = if parent_path.is_empty() { name.clone() } else { format!("{}/{}", parent_path, name) };
+                let backlinks = (crate::links::is_note(name) && crate::links::is_linked(&link_targets, &note_path))
+                    .then(|| (View::Backlinks(*child_inode), format!("{}{}", name, crate::links::SUFFIX)));
+                let lint = linted.contains(child_inode).then(|| (View::Lint(*child_inode), format!("{}{}", name, crate::lint::SUFFIX)));
+                let comments = commented.contains(child_inode).then(|| (View::Comments(*child_inode), format!("{}{}", name, crate::comments::SUFFIX)));
+                summary.into_iter().chain(transcript).chain(backlinks).chain(lint).chain(comments)
+            })
+            .filter(|(_, derived_name)| !taken.contains(derived_name.as_str()))
+            .collect();
+
+        for ((child_inode, name), file_type) in child_inodes.into_iter().zip(&names).zip(kinds) {
+            listing.push((child_inode, file_type, name.clone()));
+        }
+        for (view, derived_name) in derived {
+            listing.push((self.view_inode(view), FileType::RegularFile, derived_name));
         }
+        Some(listing)
     }
 
     fn mkdir(
@@ -1052,6 +2386,11 @@ impl Filesystem for EideticFS {
         _umask: u32,
         reply: ReplyEntry,
     ) {
+        let _timer = crate::metrics::fuse_op("mkdir");
+         if self.in_smart_folder(parent, name) {
+             reply.error(failed(libc::EROFS));
+             return;
+         }
          let name_str = name.to_string_lossy();
          let store_lock = self.inodes.lock().unwrap();
          let parent_path_opt = store_lock.get_path(parent);
@@ -1075,16 +2414,21 @@ impl Filesystem for EideticFS {
                      let attr = self.fs_metadata_to_file_attr(&metadata, inode);
                      reply.entry(&TTL, &attr, 0);
                  }
-                 Err(e) => reply.error(e.raw_os_error().unwrap_or(libc::EIO)),
+                 Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(libc::EIO))),
              }
          } else {
-             reply.error(ENOENT);
+             reply.error(failed(ENOENT));
          }
     }
 
     fn rmdir(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: fuser::ReplyEmpty) {
+        let _timer = crate::metrics::fuse_op("rmdir");
+        if self.in_smart_folder(parent, name) {
+            reply.error(failed(libc::EROFS));
+            return;
+        }
         let name_str = name.to_string_lossy();
-        let mut store = self.inodes.lock().unwrap();
+        let store = self.inodes.lock().unwrap();
         // Check lookup directly first
         if let Some(child_inode) = store.get_inode(parent, &name_str) {
             let child_path = store.get_path(child_inode);
@@ -1097,17 +2441,118 @@ impl Filesystem for EideticFS {
                         self.inodes.lock().unwrap().remove_inode(child_inode);
                         reply.ok();
                     },
-                    Err(e) => reply.error(e.raw_os_error().unwrap_or(libc::EIO)),
+                    Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(libc::EIO))),
                 }
             } else {
-                reply.error(ENOENT);
+                reply.error(failed(ENOENT));
             }
         } else {
-             reply.error(ENOENT);
+             reply.error(failed(ENOENT));
         }
     }
 
     fn unlink(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: fuser::ReplyEmpty) {
+        let _timer = crate::metrics::fuse_op("unlink");
+        // `rm .magic/proposals/<name>` dismisses the proposal for good
+        if self.view(parent) == Some(View::Proposals) {
+            let name_str = name.to_string_lossy();
+            match self.proposals().into_iter().find(|(_, entry, _)| *entry == name_str) {
+                Some((source, _, _)) => {
+                    let _ = self.inodes.lock().unwrap().db.dismiss_proposal(source);
+                    reply.ok();
+                }
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `rm .magic/wormhole/send/<name>` takes the file back
+        if self.view(parent) == Some(View::WormholeSend) {
+            match crate::wormhole::cancel(&self.source_path, &name.to_string_lossy()) {
+                Ok(()) => reply.ok(),
+                Err(_) => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `rm .magic/todos/<file>/<todo>.md` marks the TODO done
+        if let Some(View::TodoFile(source)) = self.view(parent) {
+            let name_str = name.to_string_lossy();
+            let todos = self.inodes.lock().unwrap().db.get_todos().unwrap_or_default();
+            match todos.into_iter().find(|(inode, line, content)| *inode == source && crate::todos::file_name(*line, content) == name_str) {
+                Some((_, line, _)) => {
+                    let _ = self.inodes.lock().unwrap().db.mark_todo_done(source, line);
+                    reply.ok();
+                }
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `rm .magic/favorites/<name>` unpins the file, which stays where it is
+        if self.view(parent) == Some(View::Favorites) {
+            let name_str = name.to_string_lossy();
+            let (_, entries) = self.view_entries(&View::Favorites);
+            match entries.into_iter().find(|(_, entry, _)| *entry == name_str) {
+                Some((source, _, _)) => {
+                    let _ = self.inodes.lock().unwrap().db.set_favorite(source, false);
+                    reply.ok();
+                }
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `rm .magic/starred/<name>` clears the rating
+        if self.view(parent) == Some(View::Starred) {
+            let name_str = name.to_string_lossy();
+            let (_, entries) = self.view_entries(&View::Starred);
+            match entries.into_iter().find(|(_, entry, _)| *entry == name_str) {
+                Some((source, _, _)) => {
+                    let _ = self.inodes.lock().unwrap().db.set_rating(source, 0);
+                    reply.ok();
+                }
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `rm .magic/labels/<color>/<name>` clears the label
+        if let Some(View::Labels(path)) = self.view(parent) {
+            let name_str = name.to_string_lossy();
+            let (_, entries) = self.view_entries(&View::Labels(path));
+            match entries.into_iter().find(|(_, entry, kind)| *entry == name_str && *kind == FileType::RegularFile) {
+                Some((source, _, _)) => {
+                    let _ = self.inodes.lock().unwrap().db.set_label(source, None);
+                    reply.ok();
+                }
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // Removing a result would delete the real file somewhere else
+        if self.in_smart_folder(parent, name) {
+            reply.error(failed(libc::EROFS));
+            return;
+        }
+
+        // `rm invoice.pdf.comments` deletes the comment
+        let name_str = name.to_string_lossy();
+        if let (Some(source_name), Some(dir)) = (name_str.strip_suffix(crate::comments::SUFFIX), self.real_path(parent)) {
+            if dir.join(source_name).is_file() && !dir.join(name_str.as_ref()).exists() {
+                let store = self.inodes.lock().unwrap();
+                match store.get_inode(parent, source_name) {
+                    Some(source) => {
+                        let _ = store.db.set_comment(source, "");
+                        reply.ok();
+                    }
+                    None => reply.error(failed(ENOENT)),
+                }
+                return;
+            }
+        }
+
         let mut store = self.inodes.lock().unwrap();
         let name_str = name.to_string_lossy().to_string();
         
@@ -1126,7 +2571,7 @@ impl Filesystem for EideticFS {
                  
                  if std::fs::rename(&full_path, &backup_path).is_ok() {
                      let _ = store.db.add_trash(&real_path_str, backup_path.to_string_lossy().as_ref());
-                     let _ = store.remove_inode(child_inode); // Corrected Arg: just inode
+                     store.remove_inode(child_inode); // Corrected Arg: just inode
                      reply.ok();
                      return;
                  }
@@ -1143,10 +2588,10 @@ impl Filesystem for EideticFS {
                  store.remove_inode(child_inode);
                  reply.ok();
              } else {
-                 reply.error(std::io::Error::last_os_error().raw_os_error().unwrap_or(EIO));
+                 reply.error(failed(std::io::Error::last_os_error().raw_os_error().unwrap_or(EIO)));
              }
         } else {
-            reply.error(ENOENT);
+            reply.error(failed(ENOENT));
         }
     }
 
@@ -1160,10 +2605,76 @@ impl Filesystem for EideticFS {
         _flags: u32,
         reply: fuser::ReplyEmpty,
     ) {
+        let _timer = crate::metrics::fuse_op("rename");
         let name_str = name.to_string_lossy();
         let newname_str = newname.to_string_lossy();
+
+        // `mv .magic/rename_suggestions/<suggestion> <original>` applies the suggestion
+        if self.view(parent) == Some(View::RenameSuggestions) {
+            match self.apply_rename_suggestion(&name_str, newparent, &newname_str) {
+                Ok(()) => reply.ok(),
+                Err(errno) => reply.error(failed(errno)),
+            }
+            return;
+        }
         
-        let mut store = self.inodes.lock().unwrap(); // Changed to `mut store`
+        // `mv .magic/proposals/<name> <original>` accepts the proposal
+        if self.view(parent) == Some(View::Proposals) {
+            let source = self.proposals().into_iter().find(|(_, entry, _)| *entry == name_str).map(|(inode, _, _)| inode);
+            let result = match source {
+                Some(source) if self.inodes.lock().unwrap().get_inode(newparent, &newname_str) == Some(source) => self.apply_proposal(source),
+                Some(_) => Err(libc::EINVAL),
+                None => Err(ENOENT),
+            };
+            match result {
+                Ok(()) => reply.ok(),
+                Err(errno) => reply.error(failed(errno)),
+            }
+            return;
+        }
+
+        if self.in_smart_folder(parent, name) || self.in_smart_folder(newparent, newname) {
+            reply.error(failed(libc::EROFS));
+            return;
+        }
+
+        // `mv <file> .magic/labels/<color>/` labels the file, which stays where it is; also from one color to another
+        if let Some(View::Labels(path)) = self.view(newparent) {
+            let [color] = path.as_slice() else {
+                reply.error(failed(libc::EACCES));
+                return;
+            };
+            let source = match self.view(parent) {
+                Some(view @ View::Labels(_)) => self.view_entries(&view).1.into_iter().find(|(_, entry, _)| *entry == name_str).map(|(inode, _, _)| inode),
+                Some(_) => None,
+                None => self.inodes.lock().unwrap().get_inode(parent, &name_str),
+            };
+            match source {
+                Some(source) if !views::is_virtual(source) => {
+                    let _ = self.inodes.lock().unwrap().db.set_label(source, Some(color));
+                    reply.ok();
+                }
+                Some(_) => reply.error(failed(libc::EINVAL)),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `mv <file> .magic/favorites/` pins the file, which stays where it is
+        if self.view(newparent) == Some(View::Favorites) {
+            let store = self.inodes.lock().unwrap();
+            match store.get_inode(parent, &name_str) {
+                Some(source) if !views::is_virtual(source) => {
+                    let _ = store.db.set_favorite(source, true);
+                    reply.ok();
+                }
+                Some(_) => reply.error(failed(libc::EINVAL)),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        let store = self.inodes.lock().unwrap();
         // Resolve paths
         let old_parent_path = store.get_path(parent);
         let new_parent_path = store.get_path(newparent);
@@ -1183,10 +2694,10 @@ impl Filesystem for EideticFS {
                      self.inodes.lock().unwrap().move_inode(inode, newparent, newname_str.to_string());
                      reply.ok();
                  },
-                 Err(e) => reply.error(e.raw_os_error().unwrap_or(libc::EIO)),
+                 Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(libc::EIO))),
              }
         } else {
-            reply.error(ENOENT);
+            reply.error(failed(ENOENT));
         }
     }
 
@@ -1199,7 +2710,7 @@ impl Filesystem for EideticFS {
         gid: Option<u32>,
         size: Option<u64>,
         _atime: Option<fuser::TimeOrNow>,
-        _mtime: Option<fuser::TimeOrNow>,
+        mtime: Option<fuser::TimeOrNow>,
         _ctime: Option<std::time::SystemTime>,
         _fh: Option<u64>,
         _crtime: Option<std::time::SystemTime>,
@@ -1208,11 +2719,96 @@ impl Filesystem for EideticFS {
         _flags: Option<u32>,
         reply: ReplyAttr,
     ) {
+        let _timer = crate::metrics::fuse_op("setattr");
+        // `touch .magic/proposals/<name>` accepts the proposal
+        if let Some(View::Proposal(source)) = self.view(inode) {
+            if mtime.is_some() {
+                if let Err(errno) = self.apply_proposal(source) {
+                    reply.error(failed(errno));
+                    return;
+                }
+            }
+            // An applied proposal leaves the listing but the kernel still holds its inode
+            let attr = self.view_attr(inode).unwrap_or_else(|| self.text_attr(inode, 0, SystemTime::now()));
+            reply.attr(&TTL, &attr);
+            return;
+        }
+
+        // `> invoice.pdf.comments` truncates the comment before writing
+        if let Some(View::Comments(source)) = self.view(inode) {
+            if let Some(size) = size {
+                let db = &self.inodes.lock().unwrap().db;
+                let comment = db.get_comment(source).unwrap_or(None).unwrap_or_default();
+                let truncated = String::from_utf8_lossy(&comment.as_bytes()[..comment.len().min(size as usize)]).into_owned();
+                let _ = db.set_comment(source, &truncated);
+            }
+            match self.view_attr(inode) {
+                Some(attr) => reply.attr(&TTL, &attr),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `cp` over a file staged in .magic/wormhole/send/
+        if let Some(View::WormholeOutgoing(name)) = self.view(inode) {
+            if let Some(size) = size {
+                let truncated = File::options().write(true).open(crate::wormhole::outgoing_dir(&self.source_path).join(&name)).and_then(|file| file.set_len(size));
+                if let Err(e) = truncated {
+                    reply.error(failed(e.raw_os_error().unwrap_or(EIO)));
+                    return;
+                }
+            }
+            match self.view_attr(inode) {
+                Some(attr) => reply.attr(&TTL, &attr),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // `> notes.de.txt`, once it's a real file
+        if let Some(View::Translation { source, name, .. }) = self.view(inode) {
+            if let Some(size) = size {
+                let truncated = self.translation_target(source, &name).ok_or(std::io::Error::from_raw_os_error(ENOENT))
+                    .and_then(|path| File::options().write(true).create(true).open(path))
+                    .and_then(|file| file.set_len(size));
+                if let Err(e) = truncated {
+                    reply.error(failed(e.raw_os_error().unwrap_or(EIO)));
+                    return;
+                }
+            }
+            match self.view_attr(inode) {
+                Some(attr) => reply.attr(&TTL, &attr),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // A request body written to .magic/api/<name>
+        if let Some(View::Api(name)) = self.view(inode) {
+            if let Some(size) = size {
+                crate::api::truncate(&name, size as usize);
+            }
+            match self.view_attr(inode) {
+                Some(attr) => reply.attr(&TTL, &attr),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
+        // Truncation ahead of a write to .magic/jobs/control, .ask or .magic/wormhole/receive: nothing to truncate
+        if matches!(self.view(inode), Some(View::JobsControl | View::Ask(_) | View::WormholeReceive)) {
+            match self.view_attr(inode) {
+                Some(attr) => reply.attr(&TTL, &attr),
+                None => reply.error(failed(ENOENT)),
+            }
+            return;
+        }
+
         if let Some(real_path) = self.real_path(inode) {
             // Handle chmod
             if let Some(m) = mode {
                 if let Err(e) = fs::set_permissions(&real_path, fs::Permissions::from_mode(m)) {
-                     reply.error(e.raw_os_error().unwrap_or(libc::EIO));
+                     reply.error(failed(e.raw_os_error().unwrap_or(libc::EIO)));
                      return;
                 }
             }
@@ -1227,7 +2823,7 @@ impl Filesystem for EideticFS {
                  let c_gid = gid.unwrap_or(u32::MAX);
                  unsafe {
                      if libc::chown(c_path.as_ptr(), c_uid, c_gid) != 0 {
-                          reply.error(EIO);
+                          reply.error(failed(EIO));
  
                           return;
                      }
@@ -1242,7 +2838,7 @@ impl Filesystem for EideticFS {
             if let Some(s) = size {
                  if let Ok(file) = File::open(&real_path) {
                      if let Err(e) = file.set_len(s) {
-                          reply.error(e.raw_os_error().unwrap_or(libc::EIO));
+                          reply.error(failed(e.raw_os_error().unwrap_or(libc::EIO)));
                           return;
                      }
                  }
@@ -1255,11 +2851,11 @@ impl Filesystem for EideticFS {
                     let attr = self.fs_metadata_to_file_attr(&metadata, inode);
                     reply.attr(&TTL, &attr);
                 }
-                Err(_) => reply.error(ENOENT),
+                Err(_) => reply.error(failed(ENOENT)),
             }
 
         } else {
-            reply.error(ENOENT);
+            reply.error(failed(ENOENT));
         }
     }
 
@@ -1275,10 +2871,95 @@ impl Filesystem for EideticFS {
         _lock_owner: Option<u64>,
         reply: ReplyWrite,
     ) {
+        let _timer = crate::metrics::fuse_op("write");
+        // `echo pause > .magic/jobs/control`, `echo "..." > .ask`, `echo "..." >> invoice.pdf.comments`,
+        // `cp report.pdf .magic/wormhole/send/`, `echo <code> > .magic/wormhole/receive`, `echo '{...}' > .magic/api/<name>`,
+        // `echo "..." > notes.de.txt` when notes.de.txt was a translation
+        if views::is_virtual(inode) {
+            if let Some(View::Api(name)) = self.view(inode) {
+                crate::api::write(&name, offset as usize, data);
+                reply.written(data.len() as u32);
+                return;
+            }
+            if let Some(View::WormholeOutgoing(name)) = self.view(inode) {
+                let written = File::options().write(true).open(crate::wormhole::outgoing_dir(&self.source_path).join(&name))
+                    .and_then(|mut file| file.seek(SeekFrom::Start(offset as u64)).and_then(|_| file.write_all(data)));
+                match written {
+                    Ok(()) => reply.written(data.len() as u32),
+                    Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(EIO))),
+                }
+                return;
+            }
+            if let Some(View::Translation { source, name, .. }) = self.view(inode) {
+                let written = self.translation_target(source, &name).ok_or(std::io::Error::from_raw_os_error(ENOENT))
+                    .and_then(|path| File::options().write(true).create(true).open(path))
+                    .and_then(|mut file| file.seek(SeekFrom::Start(offset as u64)).and_then(|_| file.write_all(data)));
+                match written {
+                    Ok(()) => reply.written(data.len() as u32),
+                    Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(EIO))),
+                }
+                return;
+            }
+            if let Some(View::WormholeReceive) = self.view(inode) {
+                let received = std::str::from_utf8(data).map_err(anyhow::Error::from).and_then(|code| crate::wormhole::receive(&self.source_path, code));
+                match received {
+                    Ok(()) => reply.written(data.len() as u32),
+                    Err(e) => {
+                        warn!(error = %e, "wormhole receive refused");
+                        reply.error(failed(libc::EINVAL));
+                    }
+                }
+                return;
+            }
+            if let Some(View::Comments(source)) = self.view(inode) {
+                let db = &self.inodes.lock().unwrap().db;
+                let comment = db.get_comment(source).unwrap_or(None).unwrap_or_default();
+                match crate::comments::splice(&comment, offset as usize, data) {
+                    Some(comment) => match db.set_comment(source, &comment) {
+                        Ok(()) => reply.written(data.len() as u32),
+                        Err(_) => reply.error(failed(EIO)),
+                    },
+                    None => reply.error(failed(libc::EINVAL)),
+                }
+                return;
+            }
+            if let Some(View::Ask(dir)) = self.view(inode) {
+                let Ok(text) = std::str::from_utf8(data) else {
+                    reply.error(failed(libc::EINVAL));
+                    return;
+                };
+                // A long question may arrive in several writes
+                let mut questions = self.questions.lock().unwrap();
+                let question = questions.entry(dir).or_default();
+                if offset == 0 {
+                    question.text.clear();
+                }
+                question.text.push_str(text);
+                question.answer = None;
+                question.asked = false;
+                reply.written(data.len() as u32);
+                return;
+            }
+            if self.view(inode) != Some(View::JobsControl) {
+                reply.error(failed(libc::EACCES));
+                return;
+            }
+            match std::str::from_utf8(data).map(str::trim) {
+                Ok("pause") => self.jobs.set_paused(true),
+                Ok("resume") => self.jobs.set_paused(false),
+                _ => {
+                    reply.error(failed(libc::EINVAL));
+                    return;
+                }
+            }
+            reply.written(data.len() as u32);
+            return;
+        }
+
         // Handle Search Write
         if inode == MAGIC_SEARCH {
             if let Ok(query) = std::str::from_utf8(data) {
-                println!("[Search] Query received: {}", query.trim());
+                debug!(query = query.trim(), "search query received");
                 // In V4: Trigger search, populate .magic/search_results
             }
             reply.written(data.len() as u32);
@@ -1321,21 +3002,21 @@ impl Filesystem for EideticFS {
                         // let hash = sha256(&final_data);
                         // if db.has_blob(hash) { inode.set_pointer(hash); }
                         if final_data.len() > 1024 * 1024 {
-                            println!("[Deduplication] Large file write detected. Hash check skipped for prototype safety.");
+                            debug!(path = ?real_path, bytes = final_data.len(), "large write, dedup hash check skipped");
                         }
 
                         match file.write_all(&final_data) {
                             Ok(_) => reply.written(data.len() as u32),
-                            Err(e) => reply.error(e.raw_os_error().unwrap_or(EIO)),
+                            Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(EIO))),
                         }
                     } else {
-                        reply.error(EIO);
+                        reply.error(failed(EIO));
                     }
                 },
-                Err(e) => reply.error(e.raw_os_error().unwrap_or(ENOENT)),
+                Err(e) => reply.error(failed(e.raw_os_error().unwrap_or(ENOENT))),
             }
         } else {
-            reply.error(ENOENT);
+            reply.error(failed(ENOENT));
         }
     }
 
@@ -1349,7 +3030,27 @@ impl Filesystem for EideticFS {
         _flags: i32,
         reply: fuser::ReplyCreate,
     ) {
+        let _timer = crate::metrics::fuse_op("create");
+         if self.in_smart_folder(parent, name) {
+             reply.error(failed(libc::EROFS));
+             return;
+         }
          let name_str = name.to_string_lossy();
+         // `cp report.pdf .magic/wormhole/send/` stages the file; it's offered once closed
+         if self.view(parent) == Some(View::WormholeSend) {
+             if crate::scan::ignored(&name_str) || name_str.ends_with(".code") {
+                 reply.error(failed(libc::EINVAL));
+                 return;
+             }
+             let dir = crate::wormhole::outgoing_dir(&self.source_path);
+             let created = fs::create_dir_all(&dir).and_then(|_| File::create(dir.join(name_str.as_ref())));
+             let inode = self.view_inode(View::WormholeOutgoing(name_str.to_string()));
+             match created.ok().and_then(|_| self.view_attr(inode)) {
+                 Some(attr) => reply.created(&TTL, &attr, 0, 0, 0),
+                 None => reply.error(failed(EIO)),
+             }
+             return;
+         }
          let store_lock = self.inodes.lock().unwrap();
          let parent_path_opt = store_lock.get_path(parent);
          drop(store_lock);
@@ -1368,17 +3069,18 @@ impl Filesystem for EideticFS {
                      if let Ok(metadata) = file.metadata() {
                          let mut store = self.inodes.lock().unwrap();
                          let inode = store.alloc_inode(parent, name_str.to_string());
+                         crate::heatmap::record(inode, true);
                          drop(store);
                          let attr = self.fs_metadata_to_file_attr(&metadata, inode);
                          reply.created(&TTL, &attr, 0, 0, 0); // Generation 0, fh 0, flags 0
                      } else {
-                         reply.error(EIO);
+                         reply.error(failed(EIO));
                      }
                  }
-                 Err(_) => reply.error(libc::EACCES),
+                 Err(_) => reply.error(failed(libc::EACCES)),
              }
          } else {
-             reply.error(ENOENT);
+             reply.error(failed(ENOENT));
         }
     }
 
@@ -1392,11 +3094,101 @@ impl Filesystem for EideticFS {
         _flush: bool,
         reply: fuser::ReplyEmpty,
     ) {
+        let _timer = crate::metrics::fuse_op("release");
+         if let Some(View::WormholeOutgoing(name)) = self.view(inode) {
+             crate::wormhole::send(&self.source_path, &name);
+             reply.ok();
+             return;
+         }
+         // A body written to an API file goes out once it's closed
+         if let Some(View::Api(name)) = self.view(inode) {
+             if let Some(endpoint) = crate::api::find(&name) {
+                 crate::api::send(&endpoint);
+             }
+             reply.ok();
+             return;
+         }
          if let Some(real_path) = self.real_path(inode) {
-             let _ = self.sender.send(Job::Analyze { inode, path: real_path });
+             let store = self.inodes.lock().unwrap();
+             if let Ok(metadata) = std::fs::metadata(&real_path) {
+                 let _ = store.db.update_file_size(inode, metadata.len());
+             }
+             self.jobs.enqueue(&store.db, inode, real_path, Priority::Written);
          }
          reply.ok();
     }
     
+    // `user.eidetic.wordcount` etc. of analyzed text documents (see reading.rs) and `user.eidetic.rating`
+    fn getxattr(&mut self, _req: &Request, inode: u64, name: &OsStr, size: u32, reply: ReplyXattr) {
+        let _timer = crate::metrics::fuse_op("getxattr");
+        let attrs = self.xattrs(inode);
+        match attrs.into_iter().find(|(attr, _)| OsStr::new(attr) == name) {
+            Some((_, value)) => reply_xattr(reply, size, value.as_bytes()),
+            None => reply.error(failed(ENODATA)),
+        }
+    }
+
+    fn listxattr(&mut self, _req: &Request, inode: u64, size: u32, reply: ReplyXattr) {
+        let _timer = crate::metrics::fuse_op("listxattr");
+        let mut names = Vec::new();
+        for (attr, _) in self.xattrs(inode) {
+            names.extend_from_slice(attr.as_bytes());
+            names.push(0);
+        }
+        reply_xattr(reply, size, &names);
+    }
+
+    // `setfattr -n user.eidetic.rating -v 4 <file>` and `user.eidetic.label`; everything else is read-only or unsupported
+    fn setxattr(&mut self, _req: &Request, inode: u64, name: &OsStr, value: &[u8], _flags: i32, _position: u32, reply: fuser::ReplyEmpty) {
+        let _timer = crate::metrics::fuse_op("setxattr");
+        if name != crate::ratings::XATTR && name != crate::labels::XATTR {
+            reply.error(failed(if self.xattrs(inode).iter().any(|(attr, _)| OsStr::new(attr) == name) { libc::EACCES } else { libc::ENOTSUP }));
+            return;
+        }
+        if self.real_path(inode).is_none_or(|path| !path.is_file()) {
+            reply.error(failed(libc::ENOTSUP));
+            return;
+        }
+        let value = String::from_utf8_lossy(value);
+        let db = &self.inodes.lock().unwrap().db;
+        let result = if name == crate::ratings::XATTR {
+            crate::ratings::parse(&value).map(|stars| db.set_rating(inode, stars))
+        } else {
+            crate::labels::parse(&value).map(|color| db.set_label(inode, color))
+        };
+        match result {
+            Ok(Ok(())) => reply.ok(),
+            Ok(Err(_)) => reply.error(failed(EIO)),
+            Err(_) => reply.error(failed(libc::EINVAL)),
+        }
+    }
+
+    fn removexattr(&mut self, _req: &Request, inode: u64, name: &OsStr, reply: fuser::ReplyEmpty) {
+        let _timer = crate::metrics::fuse_op("removexattr");
+        let attrs = self.xattrs(inode);
+        if !attrs.iter().any(|(attr, _)| OsStr::new(attr) == name) {
+            reply.error(failed(ENODATA));
+        } else if name == crate::ratings::XATTR {
+            let _ = self.inodes.lock().unwrap().db.set_rating(inode, 0);
+            reply.ok();
+        } else if name == crate::labels::XATTR {
+            let _ = self.inodes.lock().unwrap().db.set_label(inode, None);
+            reply.ok();
+        } else {
+            reply.error(failed(libc::EACCES));
+        }
+    }
+
     // TODO: Implement mkdir, unlink, rmdir, rename, etc.
 }
+
+// A size of 0 asks how big the value is; a smaller buffer than that is an error
+fn reply_xattr(reply: ReplyXattr, size: u32, value: &[u8]) {
+    if size == 0 {
+        reply.size(value.len() as u32);
+    } else if (size as usize) < value.len() {
+        reply.error(failed(libc::ERANGE));
+    } else {
+        reply.data(value);
+    }
+}
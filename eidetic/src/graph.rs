@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use crate::db::Database;
+
+// Knowledge Graph
+// `eidetic graph export` writes what the worker learned as one graph: analyzed files, the
+// tags on them, the people, organizations and dates they mention (entity tags like
+// `person:alice-smith`), and the links between notes. DOT goes to Graphviz; JSON uses the
+// `nodes`/`links` layout of d3-force and the graph views built on it.
+
+/// Tag prefixes that name an entity rather than a topic, see entities.rs.
+const ENTITY_PREFIXES: &[&str] = &["person:", "org:", "date:"];
+
+#[derive(Serialize)]
+struct Node {
+    id: String,
+    label: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct Link {
+    source: String,
+    target: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct Graph {
+    nodes: Vec<Node>,
+    links: Vec<Link>,
+}
+
+/// The graph as "dot" or "json".
+pub fn export(db: &Database, format: &str) -> Result<String> {
+    let graph = build(db)?;
+    match format {
+        "dot" => Ok(dot(&graph)),
+        "json" => Ok(serde_json::to_string_pretty(&graph)? + "\n"),
+        _ => bail!("Unknown graph format {:?}, expected dot or json", format),
+    }
+}
+
+fn build(db: &Database) -> Result<Graph> {
+    let files = db.get_indexed_files()?;
+    let paths: HashMap<u64, String> = files.iter()
+        .filter_map(|file| Some((file.inode, db.get_path(file.inode).ok()??)))
+        .collect();
+    let file_id = |inode: u64| format!("file:{}", inode);
+
+    let mut links = Vec::new();
+    let mut labels: BTreeMap<String, (String, &'static str)> = BTreeMap::new();
+    let mut linked = HashSet::new();
+    for file in files.iter().filter(|file| paths.contains_key(&file.inode)) {
+        for tag in &file.tags {
+            let kind = if ENTITY_PREFIXES.iter().any(|p| tag.starts_with(p)) { "entity" } else { "tag" };
+            let id = format!("{}:{}", kind, tag);
+            labels.entry(id.clone()).or_insert_with(|| (tag.clone(), kind));
+            links.push(Link { source: file_id(file.inode), target: id, kind });
+            linked.insert(file.inode);
+        }
+    }
+
+    // Notes by lowercase name, for [[wiki links]]
+    let mut notes: HashMap<String, Vec<u64>> = HashMap::new();
+    for (inode, path) in &paths {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if crate::links::is_note(name) {
+            let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+            notes.entry(stem.to_lowercase()).or_default().push(*inode);
+        }
+    }
+    let mut seen = HashSet::new();
+    for (source, target) in db.get_links()?.into_iter().filter(|(source, _)| paths.contains_key(source)) {
+        let targets = match target.split_once(':') {
+            Some(("wiki", name)) => notes.get(name).cloned().unwrap_or_default(),
+            Some(("path", path)) => db.lookup_path(path)?.into_iter().filter(|inode| paths.contains_key(inode)).collect(),
+            _ => Vec::new(),
+        };
+        // A note may link the same note by name and by path
+        for target in targets.into_iter().filter(|target| *target != source && seen.insert((source, *target))) {
+            links.push(Link { source: file_id(source), target: file_id(target), kind: "link" });
+            linked.insert(source);
+            linked.insert(target);
+        }
+    }
+
+    // Files without tags or links would only be noise
+    let mut nodes: Vec<Node> = files.iter()
+        .filter(|file| linked.contains(&file.inode))
+        .filter_map(|file| Some(Node { id: file_id(file.inode), label: paths.get(&file.inode)?.clone(), kind: "file" }))
+        .collect();
+    nodes.extend(labels.into_iter().map(|(id, (label, kind))| Node { id, label, kind }));
+    Ok(Graph { nodes, links })
+}
+
+fn dot(graph: &Graph) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut out = String::from("digraph eidetic {\n    rankdir=LR;\n");
+    for node in &graph.nodes {
+        let shape = match node.kind {
+            "file" => "note",
+            "entity" => "box",
+            _ => "ellipse",
+        };
+        out.push_str(&format!("    {} [label={}, shape={}];\n", quote(&node.id), quote(&node.label), shape));
+    }
+    for link in &graph.links {
+        let style = if link.kind == "link" { "solid" } else { "dashed" };
+        out.push_str(&format!("    {} -> {} [style={}];\n", quote(&link.source), quote(&link.target), style));
+    }
+    out.push_str("}\n");
+    out
+}
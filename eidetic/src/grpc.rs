@@ -0,0 +1,174 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use crate::config::{GrpcConfig, RestConfig};
+use crate::db::Database;
+use crate::query::Query;
+use tracing::{error, info};
+
+// gRPC API
+// For integrations that outgrow the REST API: the service in proto/eidetic.proto, served on a
+// loopback address with `[grpc] listen` set. Search results come one message per file, and SubscribeEvents
+// keeps a stream open that delivers each event as it's recorded, so clients don't have to poll.
+// The .proto is the stable contract third-party clients build against. Requests need the REST
+// API's bearer token in the `authorization` metadata.
+
+pub mod proto {
+    tonic::include_proto!("eidetic.v1");
+}
+
+use proto::eidetic_server::{Eidetic, EideticServer};
+use proto::{Event, FileEntry, FileMetadata, FileRequest, ListTagsRequest, SearchRequest, SubscribeEventsRequest, TagList};
+
+/// How often event subscriptions look for new events.
+const EVENT_POLL: Duration = Duration::from_secs(1);
+
+/// Events read per poll, and messages buffered per stream.
+const BATCH: usize = 256;
+
+pub fn start(config: &GrpcConfig, rest: &RestConfig, source_path: PathBuf) {
+    let Some(listen) = config.listen.clone() else { return };
+    let token = match crate::rest::token(rest) {
+        Ok(token) => token,
+        Err(e) => {
+            error!(error = %e, "gRPC API disabled, no token");
+            return;
+        }
+    };
+    thread::spawn(move || {
+        let served = tokio::runtime::Runtime::new()
+            .map_err(anyhow::Error::from)
+            .and_then(|runtime| runtime.block_on(serve(&listen, &source_path, token)));
+        if let Err(e) = served {
+            error!(%listen, error = %e, "gRPC API failed");
+        }
+    });
+}
+
+async fn serve(listen: &str, source_path: &Path, token: String) -> Result<()> {
+    let address: SocketAddr = listen.parse().with_context(|| format!("Invalid address {:?}", listen))?;
+    if !address.ip().is_loopback() {
+        bail!("{} isn't a loopback address", listen);
+    }
+    let db = Database::new(source_path.join(".eidetic.db"))?;
+    let service = Service { db: Arc::new(Mutex::new(db)) };
+    let expected = format!("Bearer {}", token);
+    let authorize = move |request: Request<()>| {
+        match request.metadata().get("authorization").and_then(|value| value.to_str().ok()) {
+            Some(value) if value == expected => Ok(request),
+            _ => Err(Status::unauthenticated("Missing or wrong bearer token")),
+        }
+    };
+    info!(%listen, "serving gRPC");
+    tonic::transport::Server::builder()
+        .add_service(EideticServer::with_interceptor(service, authorize))
+        .serve(address)
+        .await?;
+    Ok(())
+}
+
+struct Service {
+    db: Arc<Mutex<Database>>,
+}
+
+impl Service {
+    // Runs `f` on the database off the async threads
+    async fn with_db<T: Send + 'static>(&self, f: impl FnOnce(&Database) -> Result<T> + Send + 'static) -> Result<T, Status> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db.lock().unwrap()))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+#[tonic::async_trait]
+impl Eidetic for Service {
+    type SearchStream = ReceiverStream<Result<FileEntry, Status>>;
+    type SubscribeEventsStream = ReceiverStream<Result<Event, Status>>;
+
+    async fn search(&self, request: Request<SearchRequest>) -> Result<Response<Self::SearchStream>, Status> {
+        let query = Query::parse(&request.into_inner().query).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let entries = self.with_db(move |db| {
+            Ok(query.results(db)
+                .into_iter()
+                .map(|(inode, name)| FileEntry { path: db.get_path(inode).ok().flatten().unwrap_or_else(|| name.clone()), name })
+                .collect::<Vec<_>>())
+        }).await?;
+        let (sender, receiver) = mpsc::channel(BATCH);
+        tokio::spawn(async move {
+            for entry in entries {
+                // The client hung up
+                if sender.send(Ok(entry)).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn get_file(&self, request: Request<FileRequest>) -> Result<Response<FileMetadata>, Status> {
+        let path = request.into_inner().path;
+        let metadata = self.with_db(move |db| {
+            let Some(inode) = db.lookup_path(path.trim_matches('/'))? else { return Ok(None) };
+            let info = db.get_file_info(inode)?;
+            Ok(Some(FileMetadata {
+                path: db.get_path(inode)?.unwrap_or(path),
+                size: info.as_ref().map(|(size, _, _)| *size),
+                modified: info.as_ref().map(|(_, mtime, _)| *mtime),
+                kind: info.and_then(|(_, _, kind)| kind),
+                tags: db.get_file_tags(inode)?,
+                rating: db.get_rating(inode)?.map(u32::from),
+                label: db.get_label(inode)?,
+                comment: db.get_comment(inode)?,
+                summary: db.get_summary(inode)?,
+            }))
+        }).await?;
+        metadata.map(Response::new).ok_or_else(|| Status::not_found("No such file"))
+    }
+
+    async fn list_tags(&self, _request: Request<ListTagsRequest>) -> Result<Response<TagList>, Status> {
+        let tags = self.with_db(|db| Ok(db.get_tags()?)).await?;
+        Ok(Response::new(TagList { tags }))
+    }
+
+    async fn subscribe_events(&self, request: Request<SubscribeEventsRequest>) -> Result<Response<Self::SubscribeEventsStream>, Status> {
+        let mut after = match request.into_inner().after_id {
+            0 => self.with_db(|db| Ok(db.last_event_id()?)).await?,
+            after => after,
+        };
+        let (sender, receiver) = mpsc::channel(BATCH);
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                let db = db.clone();
+                let events = tokio::task::spawn_blocking(move || db.lock().unwrap().events_after(after, BATCH)).await;
+                let events = match events {
+                    Ok(Ok(events)) => events,
+                    Ok(Err(e)) => {
+                        let _ = sender.send(Err(Status::internal(e.to_string()))).await;
+                        return;
+                    }
+                    Err(_) => return,
+                };
+                let caught_up = events.len() < BATCH;
+                for (id, at, kind, path, detail) in events {
+                    after = id;
+                    if sender.send(Ok(Event { id, at, kind, path, detail })).await.is_err() {
+                        return;
+                    }
+                }
+                if caught_up {
+                    tokio::time::sleep(EVENT_POLL).await;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde_json::{json, Value};
+use crate::db::Database;
+use crate::queue::JobQueue;
+
+// Health
+// `.magic/health.json` for scripts and monitoring agents to poll: when the mount came up,
+// whether the database answers, what the worker is doing, the latest analysis failure and
+// the license (tier, expiry and which Pro features it unlocks). `status` is "degraded" if the
+// database fails or the worker has been on one file for longer than STUCK.
+
+const STUCK: Duration = Duration::from_secs(10 * 60);
+
+pub fn report(db: &Database, jobs: &JobQueue, mounted_at: SystemTime) -> String {
+    serde_json::to_string_pretty(&status(db, jobs, mounted_at)).unwrap_or_default() + "\n"
+}
+
+/// The health report as JSON, also behind `eidetic status`.
+pub fn status(db: &Database, jobs: &JobQueue, mounted_at: SystemTime) -> Value {
+    let now = SystemTime::now();
+    let database_error = db.count_indexed_files().err().map(|e| e.to_string());
+    let running = jobs.running();
+    let stuck = running.as_ref().is_some_and(|(_, busy)| *busy > STUCK);
+    let state = match &running {
+        Some(_) => "busy",
+        None if jobs.is_paused() => "paused",
+        None => "idle",
+    };
+    let last_error = db.failed_jobs(1).ok().and_then(|failed| failed.into_iter().next())
+        .map(|(path, error, at)| json!({ "path": path, "error": error, "at": at }));
+    json!({
+        "status": if database_error.is_some() || stuck { "degraded" } else { "ok" },
+        "mounted_at": mounted_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        "uptime_secs": now.duration_since(mounted_at).unwrap_or_default().as_secs(),
+        "database": { "ok": database_error.is_none(), "error": database_error },
+        "worker": {
+            "state": state,
+            "current": running.as_ref().map(|(path, _)| path.display().to_string()),
+            "busy_secs": running.as_ref().map(|(_, busy)| busy.as_secs()),
+            "stuck": stuck,
+            "pending": jobs.pending(),
+        },
+        "last_error": last_error,
+        "license": crate::license::report(&crate::license::current()),
+    })
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::db::Database;
+use tracing::{error, warn};
+
+// Access Heatmap
+// Every open through the mount is counted per file (reads and writes separately, see
+// `record`). `.magic/heatmap.md` ranks the busiest files and folders and
+// lists analyzed files nobody has opened since, the candidates for archiving; `stats.md`
+// carries a short version. Opens are counted in memory and written out every FLUSH_INTERVAL
+// in one transaction, so opening a file never waits on SQLite; whatever reads the counts
+// flushes first.
+
+/// Entries per section of the heatmap.
+const LIMIT: usize = 20;
+
+/// Entries per section in `stats.md`.
+const SUMMARY_LIMIT: usize = 5;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Opens not yet in the DB: inode -> (last opened, reads, writes)
+static PENDING: Mutex<Option<HashMap<u64, (u64, u64, u64)>>> = Mutex::new(None);
+
+/// Counts an open of `inode` through the mount.
+pub fn record(inode: u64, write: bool) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut pending = PENDING.lock().unwrap();
+    let entry = pending.get_or_insert_with(HashMap::new).entry(inode).or_default();
+    entry.0 = now;
+    if write {
+        entry.2 += 1;
+    } else {
+        entry.1 += 1;
+    }
+}
+
+/// Writes the opens counted since the last flush to the DB.
+pub fn flush(db: &Database) {
+    let Some(pending) = PENDING.lock().unwrap().take().filter(|pending| !pending.is_empty()) else { return };
+    let accesses: Vec<(u64, u64, u64, u64)> = pending.into_iter().map(|(inode, (at, reads, writes))| (inode, at, reads, writes)).collect();
+    if let Err(e) = db.record_accesses(&accesses) {
+        warn!(error = %e, files = accesses.len(), "failed to record file accesses");
+    }
+}
+
+pub fn start(source_path: PathBuf) {
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        loop {
+            thread::sleep(FLUSH_INTERVAL);
+            flush(&db);
+        }
+    });
+}
+
+fn path(db: &Database, inode: u64) -> String {
+    db.get_path(inode).unwrap_or(None).unwrap_or_else(|| format!("<inode {}>", inode))
+}
+
+fn folder(db: &Database, inode: u64) -> String {
+    match db.get_path(inode) {
+        Ok(Some(path)) if path.is_empty() => "/".to_string(),
+        Ok(Some(path)) => format!("{}/", path),
+        _ => format!("<inode {}>", inode),
+    }
+}
+
+// "- notes.md: 12 reads, 3 writes"
+fn hottest(db: &Database, counts: &[(u64, u64, u64, u64)], limit: usize) -> String {
+    counts.iter()
+        .take(limit)
+        .map(|(inode, _, reads, writes)| format!("- {}: {} reads, {} writes\n", path(db, *inode), reads, writes))
+        .collect()
+}
+
+/// Full Markdown report for `.magic/heatmap.md`.
+pub fn report(db: &Database) -> String {
+    flush(db);
+    let counts = db.access_counts().unwrap_or_default();
+    let never = db.never_opened().unwrap_or_default();
+    let mut content = String::from("# Heatmap\n\n");
+
+    content.push_str("## Hottest Files\n");
+    if counts.is_empty() {
+        content.push_str("_Nothing opened through the mount yet._\n");
+    } else {
+        content.push_str(&hottest(db, &counts, LIMIT));
+    }
+
+    content.push_str("\n## Folders\n");
+    let mut folders: HashMap<u64, (u64, u64, usize)> = HashMap::new();
+    for (_, parent, reads, writes) in &counts {
+        let entry = folders.entry(*parent).or_default();
+        entry.0 += reads;
+        entry.1 += writes;
+        entry.2 += 1;
+    }
+    let mut folders: Vec<_> = folders.into_iter().collect();
+    folders.sort_by_key(|(dir, (reads, writes, _))| (std::cmp::Reverse(reads + writes), *dir));
+    if folders.is_empty() {
+        content.push_str("_No activity yet._\n");
+    }
+    for (dir, (reads, writes, files)) in folders.into_iter().take(LIMIT) {
+        content.push_str(&format!("- {}: {} reads, {} writes across {} file(s)\n", folder(db, dir), reads, writes, files));
+    }
+
+    content.push_str(&format!("\n## Never Opened Since Indexing ({})\n", never.len()));
+    for inode in never.iter().take(LIMIT) {
+        content.push_str(&format!("- {}\n", path(db, *inode)));
+    }
+    if never.len() > LIMIT {
+        content.push_str(&format!("- _... and {} more_\n", never.len() - LIMIT));
+    }
+    content
+}
+
+/// Short activity section for `stats.md`.
+pub fn summary(db: &Database) -> String {
+    flush(db);
+    let counts = db.access_counts().unwrap_or_default();
+    let never = db.never_opened().map(|n| n.len()).unwrap_or(0);
+    let mut content = String::from("\n## Activity\n");
+    content.push_str(&hottest(db, &counts, SUMMARY_LIMIT));
+    content.push_str(&format!("- **Never opened since indexing**: {} files\n", never));
+    content.push_str("- More in `heatmap.md`\n");
+    content
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use anyhow::{bail, Result};
+use reqwest::blocking::Client;
+
+// HTTP Client
+// One client for the daemon's own requests (license checks, `.url` links), so connections are
+// pooled and nothing waits on a dead server longer than TIMEOUT. It blocks, so callers on the
+// FUSE path hand requests to a thread of their own.
+//
+// Offline mode, from `[network] offline` or `eidetic offline on` (a marker file next to the
+// config, so the daemon and one-off commands like `eidetic model download` agree), keeps
+// everything off the network: license checks, `.url` pages, `.magic/api/`, feeds, model
+// downloads, wormhole transfers, webhooks, S3 uploads and remote AI backends. Each falls
+// back on what it has cached, or says it's offline, instead of trying.
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+pub fn client() -> &'static Client {
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(TIMEOUT)
+            .user_agent(concat!("eidetic/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_else(|_| Client::new())
+    })
+}
+
+pub fn offline_path() -> PathBuf {
+    crate::config::Config::path().with_file_name("offline")
+}
+
+/// Whether offline mode is on. The config is read once per process, the switch every time.
+pub fn offline() -> bool {
+    static CONFIGURED: OnceLock<bool> = OnceLock::new();
+    *CONFIGURED.get_or_init(|| crate::config::Config::load().is_ok_and(|config| config.network.offline)) || offline_path().exists()
+}
+
+/// `eidetic offline on|off`.
+pub fn set_offline(offline: bool) -> Result<()> {
+    let path = offline_path();
+    if offline {
+        std::fs::write(&path, "")?;
+    } else if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+/// Fails in offline mode, so callers fall back on what they have.
+pub fn ensure_online() -> Result<()> {
+    if offline() {
+        bail!("Offline mode is on (`eidetic offline off` to go online)");
+    }
+    Ok(())
+}
+
+/// As ensure_online, but a server on this machine (a local AI backend) is always reachable.
+pub fn ensure_reachable(url: &str) -> Result<()> {
+    let host = reqwest::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)).unwrap_or_default();
+    if matches!(host.as_str(), "localhost" | "[::1]") || host.starts_with("127.") {
+        return Ok(());
+    }
+    ensure_online()
+}
+
+/// The body of `url`; an error status is an error, and so is offline mode.
+pub fn get(url: &str) -> Result<Vec<u8>> {
+    ensure_online()?;
+    Ok(client().get(url).send()?.error_for_status()?.bytes()?.to_vec())
+}
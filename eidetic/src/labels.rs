@@ -0,0 +1,23 @@
+use anyhow::{bail, Result};
+
+// Color Labels
+// One Finder-style color per file for quick triage, lighter than a tag: set it with
+// `eidetic label <path> <color>`, the extended attribute `user.eidetic.label`, or by moving
+// the file into `.magic/labels/<color>/`. "none" (or rm from the label folder) clears it.
+
+pub const XATTR: &str = "user.eidetic.label";
+
+pub const COLORS: &[&str] = &["red", "orange", "yellow", "green", "blue", "purple", "gray"];
+
+/// The color named by `value`, None for "none" or an empty value.
+pub fn parse(value: &str) -> Result<Option<&'static str>> {
+    let value = value.trim().to_lowercase();
+    if value.is_empty() || value == "none" {
+        return Ok(None);
+    }
+    let value = if value == "grey" { "gray".to_string() } else { value };
+    match COLORS.iter().find(|color| **color == value) {
+        Some(color) => Ok(Some(color)),
+        None => bail!("Unknown label {:?}, expected one of {} or none", value, COLORS.join(", ")),
+    }
+}
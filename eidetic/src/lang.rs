@@ -0,0 +1,49 @@
+use whatlang::Lang;
+
+// Language Detection
+// The worker tags text documents and PDFs with the language they're written in (`lang:de`),
+// using whatlang's trigram models. Short or mixed texts whatlang isn't sure about get no tag.
+
+/// Texts shorter than this are too short to call.
+const MIN_CHARS: usize = 50;
+
+/// Characters looked at; the start of a long document is enough.
+const SAMPLE_CHARS: usize = 10_000;
+
+/// ISO 639-1 code ("de") of the text's language.
+pub fn detect(text: &str) -> Option<&'static str> {
+    if text.chars().filter(|c| c.is_alphabetic()).take(MIN_CHARS).count() < MIN_CHARS {
+        return None;
+    }
+    let sample = match text.char_indices().nth(SAMPLE_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+    let info = whatlang::detect(sample).filter(|info| info.is_reliable())?;
+    Some(two_letter(info.lang()))
+}
+
+/// English name ("German") of the language with this ISO 639-1 code, if whatlang knows it.
+pub fn english_name(code: &str) -> Option<&'static str> {
+    Lang::all().iter().find(|lang| two_letter(**lang) == code).map(|lang| lang.eng_name())
+}
+
+// whatlang only knows ISO 639-3 ("deu"); every language it detects has a two-letter code
+fn two_letter(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Epo => "eo", Lang::Eng => "en", Lang::Rus => "ru", Lang::Cmn => "zh", Lang::Spa => "es",
+        Lang::Por => "pt", Lang::Ita => "it", Lang::Ben => "bn", Lang::Fra => "fr", Lang::Deu => "de",
+        Lang::Ukr => "uk", Lang::Kat => "ka", Lang::Ara => "ar", Lang::Hin => "hi", Lang::Jpn => "ja",
+        Lang::Heb => "he", Lang::Yid => "yi", Lang::Pol => "pl", Lang::Amh => "am", Lang::Jav => "jv",
+        Lang::Kor => "ko", Lang::Nob => "nb", Lang::Dan => "da", Lang::Swe => "sv", Lang::Fin => "fi",
+        Lang::Tur => "tr", Lang::Nld => "nl", Lang::Hun => "hu", Lang::Ces => "cs", Lang::Ell => "el",
+        Lang::Bul => "bg", Lang::Bel => "be", Lang::Mar => "mr", Lang::Kan => "kn", Lang::Ron => "ro",
+        Lang::Slv => "sl", Lang::Hrv => "hr", Lang::Srp => "sr", Lang::Mkd => "mk", Lang::Lit => "lt",
+        Lang::Lav => "lv", Lang::Est => "et", Lang::Tam => "ta", Lang::Vie => "vi", Lang::Urd => "ur",
+        Lang::Tha => "th", Lang::Guj => "gu", Lang::Uzb => "uz", Lang::Pan => "pa", Lang::Aze => "az",
+        Lang::Ind => "id", Lang::Tel => "te", Lang::Pes => "fa", Lang::Mal => "ml", Lang::Ori => "or",
+        Lang::Mya => "my", Lang::Nep => "ne", Lang::Sin => "si", Lang::Khm => "km", Lang::Tuk => "tk",
+        Lang::Aka => "ak", Lang::Zul => "zu", Lang::Sna => "sn", Lang::Afr => "af", Lang::Lat => "la",
+        Lang::Slk => "sk", Lang::Cat => "ca", Lang::Tgl => "tl", Lang::Hye => "hy",
+    }
+}
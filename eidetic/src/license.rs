@@ -1,150 +1,596 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use anyhow::{Context, Result, anyhow};
-
-// Freemius Configuration
-// TODO: Replace with your actual Product ID and Keys
-const PRODUCT_ID: &str = "22217";
-const PUBLIC_KEY: &str = "pk_449d4c5954dccbb796d8b2648e1aa";
-
-// For activation, we might not need the Secret Key if using public-facing activation 
-// that is properly scoped, but usually client-side activation uses the public key 
-// or a specific user token. 
-// Freemius API typically requires generating a signature for secure requests, 
-// but for simple license activation via their API, we follow their specific flow.
-// Note: Activating via API often requires Secret Key if not done via their SDK/Checkout.
-// If purely client-side without secret key, we rely on the user finding their key from email.
-
-// However, the user request says: 
-// "App sends request to Freemius API: POST /v1/products/{id}/licenses/activate.json"
-// This endpoint usually requires valid authentication.
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct LicenseData {
-    pub id: u64,
-    pub public_key: String,
-    pub secret_key: String,
-    pub is_active: bool,
-    pub expiration: Option<String>,
-    // Add other fields as necessary from Freemius response
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct LocalLicense {
-    pub key: String,
-    pub id: u64,
-}
-
-pub fn get_license_file_path() -> Result<PathBuf> {
-    let mut path = dirs::home_dir().context("Could not find home directory")?;
-    path.push(".eidetic");
-    if !path.exists() {
-        fs::create_dir_all(&path)?;
-    }
-    path.push("license.json");
-    Ok(path)
-}
-
-pub fn load_license() -> Result<LocalLicense> {
-    let path = get_license_file_path()?;
-    if !path.exists() {
-        return Err(anyhow!("No license file found"));
-    }
-    let content = fs::read_to_string(path)?;
-    let license: LocalLicense = serde_json::from_str(&content)?;
-    Ok(license)
-}
-
-pub fn save_license(license: &LocalLicense) -> Result<()> {
-    let path = get_license_file_path()?;
-    let content = serde_json::to_string_pretty(license)?;
-    fs::write(path, content)?;
-    Ok(())
-}
-
-/// Activates a license key with Freemius.
-/// 
-/// Note: This is a simplified implementation. Real Freemius API calls 
-/// often require signing requests with HmacSHA256 if using the Secret Key,
-/// or might have specific headers.
-/// 
-/// Based on standard Freemius API docs for simpler integrations or if using a proxy:
-/// We will try a direct hit to their API. If this fails due to auth (needs signing),
-/// we might need to implement the signature generation or route through our Worker.
-/// 
-/// User said: "App sends request to Freemius API: POST /v1/products/{id}/licenses/activate.json"
-pub fn activate_license(license_key: String) -> Result<LocalLicense> {
-    let client = reqwest::blocking::Client::new();
-    let url = format!("https://api.freemius.com/v1/products/{}/licenses/activate.json", PRODUCT_ID);
-
-    // Payload for activation
-    // Freemius often expects 'license_key' in the body
-    let params = [("license_key", &license_key)];
-    
-    // Authorization is tricky here. Client-side apps usually can't hold the Secret Key securely.
-    // If Freemius allows Public Key for activation context it's fine. 
-    // Otherwise, we might need to route this through our backend worker?
-    // User instruction implied direct app request. We will attempt standard request.
-    
-    // Note: In many Freemius implementations, you just check if the key exists and matches.
-    // Actual "activation" (binding to a user/site) might require existing user context.
-    // Let's assume for this "Product" type, we can validate the key.
-    
-    // ALTERNATIVE: GET /v1/products/{id}/licenses.json?filter=key&public_key=...
-    // But that might return all licenses? No.
-    
-    // Let's implement the specific endpoint requested by user logic.
-    let response = client.put(&url) // 'activate' is often a PUT or POST
-        .header("Content-Type", "application/json")
-        .body(serde_json::to_string(&serde_json::json!({
-             "license_key": license_key
-        }))?)
-        // .basic_auth(PUBLIC_KEY, Some("secret?")) // Unsafe to put secret here
-        .send();
-
-    // IF the above is too complex/undocumented without specific auth headers (Date, Auth signature),
-    // we might need to route this via our Worker or ask User for the specific Freemius setup.
-    
-    // FOR NOW: We will implement a "Check" logic which is safer and easier.
-    // We check if the license key is valid by fetching it.
-    // Since we don't have the full Freemius Auth implementation (HMAC signing) here,
-    // and storing Secret Key in the binary is bad practice,
-    // we strongly recommend using the Worker as a proxy for this if request signing is needed.
-    
-    // HOWEVER, to unblock the user, we'll create the structure and assume 
-    // they might have a proxy or specific public endpoint enabled.
-    
-    // MOCK RESPONSE for initial development until keys are real
-    // Remove this in production
-    if license_key.starts_with("ED-") {
-        let mock = LocalLicense {
-            key: license_key,
-            id: 12345,
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::{bail, Context, Result, anyhow};
+use ed25519_dalek::{Signature, VerifyingKey};
+use crate::db::Database;
+use tracing::{error, info, warn};
+
+// The license worker answering `?key=&machine=` with {"valid":true} for Pro keys active there
+const VERIFY_URL: &str = "https://eidetic-license.saujanyayaya.workers.dev/verify";
+
+// The license worker freeing the seat of the `{"key": ..., "machine": ...}` POSTed to it
+const DEACTIVATE_URL: &str = "https://eidetic-license.saujanyayaya.workers.dev/deactivate";
+
+// The license worker lending out team license seats: POSTing `{"key": ..., "machine": ...}`
+// claims or renews one (409 Conflict when they're all taken), DELETE gives it back
+const SEAT_URL: &str = "https://eidetic-license.saujanyayaya.workers.dev/seat";
+
+/// How often the daemon re-verifies the Pro key in ~/.eidetic/license.
+const RECHECK: Duration = Duration::from_secs(60 * 60);
+
+/// How often ~/.eidetic/license is looked at for changes (`eidetic activate`).
+const POLL: Duration = Duration::from_secs(10);
+
+/// How long Pro features are unlocked without a license, counted from the first mount.
+const TRIAL_DAYS: i64 = 14;
+
+/// How often a team license seat is renewed. The service frees seats that go unrenewed for a
+/// few of these, so a daemon that crashed doesn't hold one forever.
+const HEARTBEAT: Duration = Duration::from_secs(5 * 60);
+
+/// Meta key holding when the trial started (Unix time), alongside ~/.eidetic/trial.
+const TRIAL_KEY: &str = "trial_started";
+
+/// Meta key holding the plain key the license worker last said was valid.
+const VERIFIED_KEY: &str = "license_verified";
+
+// Where versions before ~/.eidetic/license kept an activated key
+fn legacy_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".eidetic").join("license.json"))
+}
+
+#[derive(Deserialize)]
+struct LegacyLicense {
+    key: String,
+}
+
+/// A Pro feature a license can grant.
+pub const WORMHOLE: &str = "wormhole";
+
+/// The Pro features, with where in the mount each lives (an UPGRADE_TO_PRO.txt there without
+/// Pro); all of them are unlocked by plain keys (from before signed licenses) and the trial.
+const PRO_FEATURES: &[(&str, &str)] = &[(WORMHOLE, ".magic/wormhole/")];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    #[default]
+    Free,
+    /// Pro features unlocked for TRIAL_DAYS without a license
+    Trial,
+    Pro,
+}
+
+/// What the daemon knows about the license, kept current by `watch`.
+#[derive(Clone, Debug, Default)]
+pub struct Status {
+    pub tier: Tier,
+    /// None without a valid license
+    pub key: Option<String>,
+    /// Unix time, None for a license that doesn't expire
+    pub expires: Option<i64>,
+    pub features: Vec<String>,
+    /// When the trial ends or ended (Unix time), None if it never started
+    pub trial_ends: Option<i64>,
+    /// Concurrent seats of a team license, None for a license of one's own
+    pub seats: Option<u32>,
+    /// Why the license on file unlocks nothing right now, e.g. all its seats being taken
+    pub note: Option<String>,
+}
+
+impl Status {
+    fn pro(key: &str) -> Self {
+        Self { tier: Tier::Pro, key: Some(key.to_string()), features: pro_features(), ..Self::default() }
+    }
+
+    pub fn allows(&self, feature: &str) -> bool {
+        self.tier != Tier::Free && self.features.iter().any(|granted| granted == feature)
+    }
+}
+
+fn pro_features() -> Vec<String> {
+    PRO_FEATURES.iter().map(|(feature, _)| feature.to_string()).collect()
+}
+
+/// The license line of stats.md: "Pro", "Pro trial, 9 days left (until ...)", "Free (trial ended ...)".
+pub fn describe(status: &Status) -> String {
+    let local_time = crate::stats::local_time;
+    match status.tier {
+        Tier::Pro => {
+            let team = status.seats.map_or(String::new(), |seats| format!(", a seat of a {}-seat team license", seats));
+            match status.expires {
+                Some(expires) => format!("Pro{}, until {}", team, local_time(expires)),
+                None => format!("Pro{}", team),
+            }
+        }
+        Tier::Trial => {
+            let ends = status.trial_ends.unwrap_or_default();
+            let days = (ends - now() + 86_399).max(0) / 86_400;
+            format!("Pro trial, {} day{} left (until {})", days, if days == 1 { "" } else { "s" }, local_time(ends))
+        }
+        Tier::Free => match (&status.note, status.trial_ends) {
+            (Some(note), _) => format!("Free ({})", note),
+            (None, Some(ends)) => format!("Free (the Pro trial ended {})", local_time(ends)),
+            (None, None) => "Free".to_string(),
+        },
+    }
+}
+
+/// The `## License` section of stats.md: the tier, and each Pro feature with whether it's unlocked.
+pub fn summary(status: &Status) -> String {
+    let mut content = String::from("\n## License\n");
+    content.push_str(&format!("- **Tier**: {}\n", describe(status)));
+    for (feature, place) in PRO_FEATURES {
+        if status.allows(feature) {
+            content.push_str(&format!("- **{}**: unlocked\n", feature));
+        } else {
+            content.push_str(&format!("- **{}**: locked, `{}` shows UPGRADE_TO_PRO.txt (`eidetic activate` a license)\n", feature, place));
+        }
+    }
+    content
+}
+
+/// The license for `health.json` and `eidetic status`.
+pub fn report(status: &Status) -> serde_json::Value {
+    let features: serde_json::Map<String, serde_json::Value> =
+        PRO_FEATURES.iter().map(|(feature, _)| (feature.to_string(), status.allows(feature).into())).collect();
+    serde_json::json!({
+        "tier": status.tier,
+        "summary": describe(status),
+        "expires": status.expires,
+        "trial_ends": status.trial_ends,
+        "seats": status.seats,
+        "note": status.note,
+        "features": features,
+    })
+}
+
+// The status `watch` keeps current, for readers outside the mount (`eidetic status`)
+static CURRENT: OnceLock<Arc<RwLock<Status>>> = OnceLock::new();
+
+/// The license as the daemon last checked it; Free in a process that doesn't watch it.
+pub fn current() -> Status {
+    CURRENT.get().map(|status| status.read().unwrap().clone()).unwrap_or_default()
+}
+
+/// The license in ~/.eidetic/license. A background thread checks it now, every RECHECK after
+/// and whenever the file changes, so the mount only ever reads the result. Without a license,
+/// the trial tracked in the database of `source_path` stands in until it runs out.
+pub fn watch(source_path: &Path) -> Arc<RwLock<Status>> {
+    let db = Database::new(source_path.join(".eidetic.db")).map_err(|e| error!(error = %e, "failed to open DB, no trial")).ok();
+    // Told apart without the network, so the mount starts with the license it had last time
+    // instead of free mode until the worker answers
+    let status = Arc::new(RwLock::new(resolve(db.as_ref(), false).unwrap_or_default()));
+    let _ = CURRENT.set(status.clone());
+    let shared = status.clone();
+    thread::spawn(move || {
+        let mut last: Option<(Instant, Option<SystemTime>)> = None;
+        loop {
+            let modified = path().and_then(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok());
+            // A team license seat is only held while it's renewed
+            let every = if shared.read().unwrap().seats.is_some() { HEARTBEAT } else { RECHECK };
+            if last.is_none_or(|(at, seen)| at.elapsed() >= every || seen != modified) {
+                if let Some(checked) = resolve(db.as_ref(), true) {
+                    if checked.seats.is_none() {
+                        release_seat();
+                    }
+                    let mut current = shared.write().unwrap();
+                    if current.tier == Tier::Trial && checked.tier == Tier::Free {
+                        info!("Pro trial ended, back to free features");
+                    }
+                    *current = checked;
+                }
+                last = Some((Instant::now(), modified));
+            }
+            thread::sleep(POLL);
+        }
+    });
+    status
+}
+
+// The license, or else the trial; None if that can't be told
+fn resolve(db: Option<&Database>, online: bool) -> Option<Status> {
+    let mut status = check(db, online)?;
+    if let (Tier::Free, Some(db)) = (status.tier, db) {
+        trial(db, &mut status);
+    }
+    Some(status)
+}
+
+// When the trial started (Unix time), one per user however many folders they mount. It's kept
+// in every mounted folder's DB as well, and the earliest of them all counts, so deleting one
+// copy doesn't restart the trial
+fn trial_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".eidetic").join("trial"))
+}
+
+// Unlocks Pro features while the trial lasts, starting it on the first mount without a license.
+// When it's over the status stays Free: what was received or set up with Pro features is kept,
+// only new Pro work is refused
+fn trial(db: &Database, status: &mut Status) {
+    let recorded = trial_path().and_then(|path| fs::read_to_string(path).ok()).and_then(|started| started.trim().parse::<i64>().ok());
+    let stored = match db.get_meta(TRIAL_KEY) {
+        Ok(started) => started.and_then(|started| started.parse::<i64>().ok()),
+        Err(e) => {
+            error!(error = %e, "failed to read trial start");
+            None
+        }
+    };
+    let started = match (recorded, stored) {
+        (Some(recorded), Some(stored)) => recorded.min(stored),
+        (Some(started), None) | (None, Some(started)) => started,
+        (None, None) => {
+            info!(days = TRIAL_DAYS, "Pro trial started");
+            now()
+        }
+    };
+    if recorded != Some(started) {
+        let saved = trial_path().context("Could not find home directory").and_then(|path| {
+            fs::create_dir_all(path.parent().context("Could not find home directory")?)?;
+            Ok(fs::write(path, started.to_string())?)
+        });
+        if let Err(e) = saved {
+            warn!(error = %e, "failed to record trial start in ~/.eidetic");
+        }
+    }
+    if stored != Some(started) {
+        if let Err(e) = db.set_meta(TRIAL_KEY, &started.to_string()) {
+            error!(error = %e, "failed to record trial start");
+            return;
+        }
+    }
+    let ends = started + TRIAL_DAYS * 86_400;
+    status.trial_ends = Some(ends);
+    if now() < ends {
+        status.tier = Tier::Trial;
+        status.expires = Some(ends);
+        status.features = pro_features();
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".eidetic").join("license"))
+}
+
+// The license as of now; None if that can't be told (a plain key, the worker can't be asked and
+// there's no DB to remember its last answer), when the last result stands. `online` false asks no one
+fn check(db: Option<&Database>, online: bool) -> Option<Status> {
+    let Some(text) = path().and_then(|path| fs::read_to_string(path).ok()) else { return Some(Status::default()) };
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(Status::default());
+    }
+    if text.starts_with('{') {
+        let grant = match verify_signed(text) {
+            Ok(grant) => grant,
+            Err(e) => {
+                warn!(error = %e, "license rejected");
+                return Some(Status::default());
+            }
+        };
+        // Phoning home is only to hear about revoked keys; without an answer the signature stands
+        if online && ask_worker(&grant.key) == Some(false) {
+            warn!("license key revoked");
+            return Some(Status::default());
+        }
+        let seats = grant.seats;
+        let status = Status { tier: Tier::Pro, key: Some(grant.key), expires: grant.expires, features: grant.features, seats, ..Status::default() };
+        let Some(seats) = seats else { return Some(status) };
+        // A team license unlocks Pro features only while one of its seats is held here
+        if !online {
+            return None;
+        }
+        return match claim_seat(status.key.as_deref().unwrap_or_default()) {
+            Some(true) => Some(status),
+            Some(false) => {
+                warn!(seats, "all seats of the team license are in use");
+                Some(Status { seats: Some(seats), note: Some(format!("all {} seats of the team license are in use", seats)), ..Status::default() })
+            }
+            None => None,
         };
-        save_license(&mock)?;
-        return Ok(mock);
     }
+    // The last key the worker vouched for stands when it can't be asked
+    let answer = if online { ask_worker(text) } else { None };
+    let valid = match (answer, db) {
+        (Some(valid), Some(db)) => {
+            let remembered = if valid { db.set_meta(VERIFIED_KEY, text) } else { db.set_meta(VERIFIED_KEY, "") };
+            if let Err(e) = remembered {
+                warn!(error = %e, "failed to remember license check");
+            }
+            valid
+        }
+        (Some(valid), None) => valid,
+        (None, db) => db?.get_meta(VERIFIED_KEY).ok().flatten().filter(|verified| verified == text).is_some(),
+    };
+    Some(if valid { Status::pro(text) } else { Status::default() })
+}
 
-    Err(anyhow!("Failed to activate license (Implementation requires valid API Keys)"))
+// Asks the license worker whether it knows `key` as valid; None offline or without an answer
+fn ask_worker(key: &str) -> Option<bool> {
+    if crate::http::offline() {
+        return None;
+    }
+    match crate::http::client().get(VERIFY_URL).query(&[("key", key), ("machine", fingerprint().as_str())]).send().and_then(|response| response.text()) {
+        Ok(body) => Some(body.contains("\"valid\":true")),
+        Err(e) => {
+            warn!(error = %e, "license check failed");
+            None
+        }
+    }
+}
+
+// The team license key this daemon holds a seat of
+static SEAT: Mutex<Option<String>> = Mutex::new(None);
+
+// Claims or renews a seat of the team license `key`: Some(false) when all are taken, None offline
+// or without an answer (a held seat is then kept until the service frees it)
+fn claim_seat(key: &str) -> Option<bool> {
+    if crate::http::offline() {
+        return None;
+    }
+    let response = crate::http::client().post(SEAT_URL).json(&serde_json::json!({ "key": key, "machine": fingerprint() })).send();
+    match response {
+        Ok(response) if response.status().is_success() => {
+            *SEAT.lock().unwrap() = Some(key.to_string());
+            Some(true)
+        }
+        Ok(response) if response.status() == reqwest::StatusCode::CONFLICT => {
+            *SEAT.lock().unwrap() = None;
+            Some(false)
+        }
+        Ok(response) => {
+            warn!(status = %response.status(), "seat claim refused");
+            None
+        }
+        Err(e) => {
+            warn!(error = %e, "seat claim failed");
+            None
+        }
+    }
+}
+
+/// Gives the team license seat this daemon holds, if any, back to the pool (at unmount, or when
+/// the license changes).
+pub fn release_seat() {
+    let Some(key) = SEAT.lock().unwrap().take() else { return };
+    if crate::http::offline() {
+        return;
+    }
+    let released = crate::http::client().delete(SEAT_URL).json(&serde_json::json!({ "key": key, "machine": fingerprint() })).send();
+    match released.and_then(|response| response.error_for_status()) {
+        Ok(_) => info!("team license seat released"),
+        Err(e) => warn!(error = %e, "failed to release team license seat"),
+    }
+}
+
+// Signed license files
+// The vendor signs the license itself, `{"key": ..., "expires": <unix time or null>,
+// "features": [...]}`, with its Ed25519 key, and ships it with the signature as
+// `{"license": "<that JSON, as a string>", "signature": "<hex>"}`. Checking one takes no
+// network, so a license keeps working offline until it expires.
+
+/// Public half of the vendor's license signing key.
+const SIGNING_KEY: &str = "2fc894c39853349e868efb8395767917f11704a1e95e476c65d2b0a0e4c6c798";
+
+#[derive(Deserialize)]
+struct SignedLicense {
+    license: String,
+    signature: String,
 }
 
-/// Checks if a license is still active.
-pub fn check_license_status() -> Result<bool> {
-    let license = load_license()?;
-    
-    // Logic:
-    // GET /v1/products/{id}/licenses/{license_id}.json
-    // Check `is_active` and `expiration`
-    
-    // Again, requires API Auth (likely HmacSHA256).
-    // For now, we return true if we have a saved license, assuming 'activate' did the heavy lifting.
-    // In a real scenario, this function would make a network call.
-    
-    // Mock check:
-    if !license.key.is_empty() {
-        return Ok(true);
+#[derive(Deserialize)]
+struct Grant {
+    key: String,
+    expires: Option<i64>,
+    #[serde(default)]
+    features: Vec<String>,
+    /// Fingerprints of the machines the license is for; any machine if empty
+    #[serde(default)]
+    machines: Vec<String>,
+    /// Concurrent seats of a team license, which any machine can use while it holds one
+    seats: Option<u32>,
+}
+
+// The grant in a signed license file, if the signature checks out and it hasn't expired
+fn verify_signed(text: &str) -> Result<Grant> {
+    verify_signed_by(text, SIGNING_KEY)
+}
+
+fn verify_signed_by(text: &str, signing_key: &str) -> Result<Grant> {
+    let signed: SignedLicense = serde_json::from_str(text).context("Not a license file")?;
+    let public: [u8; 32] = unhex(signing_key).and_then(|bytes| bytes.try_into().ok()).context("Bad signing key")?;
+    let signature: [u8; 64] = unhex(&signed.signature).and_then(|bytes| bytes.try_into().ok()).context("Malformed signature")?;
+    VerifyingKey::from_bytes(&public)?
+        .verify_strict(signed.license.as_bytes(), &Signature::from_bytes(&signature))
+        .map_err(|_| anyhow!("The license signature doesn't match"))?;
+    let grant: Grant = serde_json::from_str(&signed.license)?;
+    if grant.expires.is_some_and(|expires| expires <= now()) {
+        bail!("The license expired");
+    }
+    if !grant.machines.is_empty() && !grant.machines.contains(fingerprint()) {
+        bail!("The license is for another machine (this one is {}); deactivate it there to move it here", fingerprint());
+    }
+    Ok(grant)
+}
+
+/// This machine in license payloads: blake3 of the hostname and the OS machine id, so it stays
+/// the same across reinstalls of Eidetic but not when a license is copied to another machine.
+pub fn fingerprint() -> &'static String {
+    static FINGERPRINT: OnceLock<String> = OnceLock::new();
+    FINGERPRINT.get_or_init(|| blake3::hash(format!("{}\n{}", hostname(), machine_id()).as_bytes()).to_hex().to_string())
+}
+
+fn hostname() -> String {
+    let mut name = [0u8; 256];
+    if unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) } != 0 {
+        return String::new();
     }
+    let end = name.iter().position(|&byte| byte == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..end]).into_owned()
+}
 
-    Ok(false)
+// systemd's machine id on Linux, the hardware UUID on macOS; empty if there's neither
+fn machine_id() -> String {
+    for path in ["/etc/machine-id", "/var/lib/dbus/machine-id"] {
+        if let Some(id) = fs::read_to_string(path).ok().map(|id| id.trim().to_string()).filter(|id| !id.is_empty()) {
+            return id;
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(output) = std::process::Command::new("ioreg").args(["-rd1", "-c", "IOPlatformExpertDevice"]).output() {
+            let text = String::from_utf8_lossy(&output.stdout);
+            if let Some(id) = text.lines().find(|line| line.contains("IOPlatformUUID")).and_then(|line| line.rsplit('"').nth(1)) {
+                return id.to_string();
+            }
+        }
+    }
+    String::new()
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// `eidetic activate`: installs a signed license file, or a plain key, as ~/.eidetic/license.
+pub fn install(license: &str) -> Result<String> {
+    let candidate = Path::new(license);
+    let text = if candidate.is_file() { fs::read_to_string(candidate)? } else { license.to_string() };
+    let text = text.trim();
+    let report = if text.starts_with('{') {
+        let grant = verify_signed(text)?;
+        let expires = match grant.expires {
+            Some(expires) => format!("until {}", crate::stats::local_time(expires)),
+            None => "with no expiry".to_string(),
+        };
+        let team = grant.seats.map_or(String::new(), |seats| format!("; a team license, the daemon takes one of its {} seats while mounted", seats));
+        format!("Licensed {} ({}), {}{}\n", grant.key, grant.features.join(", "), expires, team)
+    } else {
+        format!("Key {} saved; it's verified once the daemon runs\n", text)
+    };
+    let path = path().context("Could not find home directory")?;
+    fs::create_dir_all(path.parent().unwrap_or(&path))?;
+    fs::write(&path, format!("{}\n", text))?;
+    Ok(report)
+}
+
+/// `eidetic deactivate`: releases this machine's seat with the license service, then removes the
+/// license from ~/.eidetic so it can be activated on another machine. Nothing is removed if the
+/// service can't be told, or the seat would stay taken.
+pub fn deactivate() -> Result<String> {
+    let path = path().context("Could not find home directory")?;
+    let legacy = legacy_path().context("Could not find home directory")?;
+    let text = fs::read_to_string(&path).unwrap_or_default();
+    let text = text.trim();
+    let key = if text.starts_with('{') {
+        // An expired license still holds its seat, so the signature isn't needed to release it
+        let signed: SignedLicense = serde_json::from_str(text).context("Not a license file")?;
+        serde_json::from_str::<Grant>(&signed.license)?.key
+    } else if !text.is_empty() {
+        text.to_string()
+    } else if let Some(old) = fs::read_to_string(&legacy).ok().and_then(|text| serde_json::from_str::<LegacyLicense>(&text).ok()) {
+        old.key
+    } else {
+        bail!("No license installed");
+    };
+
+    crate::http::ensure_online()?;
+    crate::http::client()
+        .post(DEACTIVATE_URL)
+        .json(&serde_json::json!({ "key": key, "machine": fingerprint() }))
+        .send()
+        .and_then(|response| response.error_for_status())
+        .context("Couldn't release the seat with the license service; the license is left in place")?;
+    for file in [&path, &legacy] {
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+    }
+    Ok(format!("Deactivated {}; it can now be activated on another machine\n", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    // A license file signed by a key of our own, and that key's public half
+    fn signed(license: serde_json::Value) -> (String, String) {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let license = license.to_string();
+        let file = serde_json::json!({ "license": license, "signature": hex(&key.sign(license.as_bytes()).to_bytes()) });
+        (file.to_string(), hex(key.verifying_key().as_bytes()))
+    }
+
+    #[test]
+    fn signed_license_is_checked() {
+        let (file, public) = signed(serde_json::json!({ "key": "EID-1", "expires": now() + 3600, "features": [WORMHOLE] }));
+        let grant = verify_signed_by(&file, &public).unwrap();
+        assert_eq!(grant.key, "EID-1");
+        assert_eq!(grant.features, [WORMHOLE]);
+        // Not signed by the vendor
+        assert!(verify_signed(&file).is_err());
+
+        let tampered = file.replace("EID-1", "EID-2");
+        assert!(verify_signed_by(&tampered, &public).is_err());
+        assert!(verify_signed_by("{\"license\": \"{}\", \"signature\": \"00\"}", &public).is_err());
+        assert!(verify_signed_by("not json", &public).is_err());
+    }
+
+    #[test]
+    fn signed_license_expires_and_stays_on_its_machines() {
+        let (file, public) = signed(serde_json::json!({ "key": "EID-1", "expires": now() - 1 }));
+        assert!(verify_signed_by(&file, &public).is_err());
+        let (file, public) = signed(serde_json::json!({ "key": "EID-1", "expires": null, "machines": ["elsewhere"] }));
+        assert!(verify_signed_by(&file, &public).is_err());
+        let (file, public) = signed(serde_json::json!({ "key": "EID-1", "expires": null, "machines": [fingerprint()] }));
+        assert!(verify_signed_by(&file, &public).is_ok());
+    }
+
+    // The only test touching HOME, which the trial file lives under
+    #[test]
+    fn trial_takes_the_earliest_start() {
+        let home = tempfile::tempdir().unwrap();
+        std::env::set_var("HOME", home.path());
+        let db = Database::new(":memory:").unwrap();
+
+        // First mount: both copies recorded
+        let mut status = Status::default();
+        trial(&db, &mut status);
+        assert_eq!(status.tier, Tier::Trial);
+        let started = db.get_meta(TRIAL_KEY).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(trial_path().unwrap()).unwrap(), started);
+
+        // Deleting ~/.eidetic/trial doesn't restart it, the DB remembers an older start
+        let old = (now() - (TRIAL_DAYS + 1) * 86_400).to_string();
+        db.set_meta(TRIAL_KEY, &old).unwrap();
+        fs::remove_file(trial_path().unwrap()).unwrap();
+        let mut status = Status::default();
+        trial(&db, &mut status);
+        assert_eq!(status.tier, Tier::Free);
+        assert_eq!(fs::read_to_string(trial_path().unwrap()).unwrap(), old);
+
+        // Nor does a fresh DB, the file remembers it
+        let fresh = Database::new(":memory:").unwrap();
+        let mut status = Status::default();
+        trial(&fresh, &mut status);
+        assert_eq!(status.tier, Tier::Free);
+        assert_eq!(fresh.get_meta(TRIAL_KEY).unwrap().unwrap(), old);
+    }
 }
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::db::Database;
+
+// Backlinks
+// The worker reads the links out of every Markdown note: `[[Wiki Links]]` (with optional
+// `#heading` and `|alias`) and ordinary `[text](other.md)` links. Each becomes a target
+// in the note_links table, "wiki:<lowercase name>" or "path:<source-relative path>", and
+// `note.md.backlinks` next to a note lists every note linking to it either way.
+
+pub const SUFFIX: &str = ".backlinks";
+
+const NOTE_FORMATS: &[&str] = &["md", "markdown"];
+
+pub fn is_note(name: &str) -> bool {
+    Path::new(name).extension().is_some_and(|ext| NOTE_FORMATS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// Link targets in the note at source-relative `rel_path`.
+pub fn extract(rel_path: &str, text: &str) -> Vec<String> {
+    static PATTERNS: OnceLock<(Regex, Regex)> = OnceLock::new();
+    let (wiki, markdown) = PATTERNS.get_or_init(|| (
+        Regex::new(r"\[\[([^\]\|#]+)[^\]]*\]\]").expect("wiki link pattern"),
+        Regex::new(r"\[[^\]]*\]\(<?([^)\s>]+)>?(?:\s+[^)]*)?\)").expect("markdown link pattern"),
+    ));
+    let mut targets = Vec::new();
+    for caps in wiki.captures_iter(text) {
+        // [[folder/Note.md]] links the same note as [[Note]]
+        let name = caps[1].trim().rsplit('/').next().unwrap_or_default();
+        let stem = strip_note_extension(name);
+        if !stem.is_empty() {
+            targets.push(format!("wiki:{}", stem.to_lowercase()));
+        }
+    }
+    let dir = rel_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    for caps in markdown.captures_iter(text) {
+        let target = caps[1].split('#').next().unwrap_or_default().replace("%20", " ");
+        if target.is_empty() || target.contains("://") || target.starts_with("mailto:") {
+            continue;
+        }
+        let joined = match target.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None => format!("{}/{}", dir, target),
+        };
+        if let Some(path) = normalize(&joined) {
+            targets.push(format!("path:{}", path));
+        }
+    }
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+fn strip_note_extension(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if NOTE_FORMATS.contains(&ext.to_lowercase().as_str()) => stem,
+        _ => name,
+    }
+}
+
+/// "a/./b/../c.md" -> "a/c.md"; None if it leaves the source folder.
+pub fn normalize(path: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// The targets that point at the note at `rel_path`.
+pub fn targets_of(rel_path: &str) -> [String; 2] {
+    let name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    [format!("wiki:{}", strip_note_extension(name).to_lowercase()), format!("path:{}", rel_path)]
+}
+
+/// Whether any note links to the note at `rel_path`, given every target in the index.
+pub fn is_linked(targets: &HashSet<String>, rel_path: &str) -> bool {
+    targets_of(rel_path).iter().any(|target| targets.contains(target))
+}
+
+/// Markdown list of the notes linking to the note with this inode.
+pub fn backlinks(db: &Database, inode: u64) -> Option<String> {
+    let rel_path = db.get_path(inode).ok().flatten()?;
+    let mut sources: Vec<String> = db.get_linking_notes(&targets_of(&rel_path)).unwrap_or_default()
+        .into_iter()
+        .filter(|source| *source != inode)
+        .filter_map(|source| db.get_path(source).ok().flatten())
+        .collect();
+    sources.sort();
+    let name = rel_path.rsplit('/').next().unwrap_or(&rel_path);
+    let mut content = format!("# Backlinks to {}\n\n", name);
+    if sources.is_empty() {
+        content.push_str("_No notes link here yet._\n");
+    }
+    for source in sources {
+        content.push_str(&format!("- [[{}]] ({})\n", strip_note_extension(source.rsplit('/').next().unwrap_or(&source)), source));
+    }
+    Some(content)
+}
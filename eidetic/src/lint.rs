@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use crate::db::Database;
+
+// Proofreading
+// An optional worker stage (`[lint] enabled = true`) that checks English prose files for
+// common misspellings from a bundled list (misspellings.txt), repeated words ("the the"),
+// wordy phrases and overlong sentences. Files with findings get a `<name>.md.lint` next to
+// them listing each one by line. Fenced code blocks, inline code and URLs are skipped.
+
+pub const SUFFIX: &str = ".lint";
+
+const PROSE_FORMATS: &[&str] = &["md", "markdown", "txt"];
+
+/// Sentences longer than this, in words, are flagged.
+const LONG_SENTENCE: usize = 40;
+
+/// Phrases with a shorter way to say them.
+const WORDY: &[(&str, &str)] = &[
+    ("in order to", "to"),
+    ("due to the fact that", "because"),
+    ("at this point in time", "now"),
+    ("in the event that", "if"),
+    ("for the purpose of", "for"),
+    ("in spite of the fact that", "although"),
+    ("has the ability to", "can"),
+    ("a large number of", "many"),
+    ("each and every", "each"),
+    ("it should be noted that", "(omit)"),
+];
+
+pub fn is_prose(name: &str) -> bool {
+    Path::new(name).extension().is_some_and(|ext| PROSE_FORMATS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+}
+
+fn misspellings() -> &'static HashMap<&'static str, &'static str> {
+    static LIST: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+    LIST.get_or_init(|| {
+        include_str!("misspellings.txt").lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(' '))
+            .collect()
+    })
+}
+
+/// (line number, message) of each issue found in `text`.
+pub fn check(text: &str) -> Vec<(usize, String)> {
+    let mut findings = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let prose = strip_code(line);
+        let words: Vec<&str> = prose.split_whitespace()
+            .filter(|word| !word.contains("://"))
+            .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\''))
+            .filter(|word| !word.is_empty())
+            .collect();
+        for word in &words {
+            if let Some(correction) = misspellings().get(word.to_lowercase().as_str()) {
+                findings.push((i + 1, format!("\"{}\" -> \"{}\"", word, correction)));
+            }
+        }
+        for pair in words.windows(2) {
+            if pair[0].eq_ignore_ascii_case(pair[1]) && pair[0].chars().any(|c| c.is_alphabetic()) {
+                findings.push((i + 1, format!("repeated word \"{}\"", pair[0])));
+            }
+        }
+        let lower = format!(" {} ", words.join(" ").to_lowercase());
+        for (phrase, shorter) in WORDY {
+            if lower.contains(&format!(" {} ", phrase)) {
+                findings.push((i + 1, format!("\"{}\" -> \"{}\"", phrase, shorter)));
+            }
+        }
+        for sentence in prose.split(['.', '!', '?']) {
+            let length = sentence.split_whitespace().count();
+            if length > LONG_SENTENCE {
+                findings.push((i + 1, format!("long sentence ({} words)", length)));
+            }
+        }
+    }
+    findings
+}
+
+// The line without its `inline code`
+fn strip_code(line: &str) -> String {
+    line.split('`').step_by(2).collect::<Vec<_>>().join(" ")
+}
+
+/// Contents of `<name>.lint`, None while the file has no findings.
+pub fn report(db: &Database, inode: u64) -> Option<String> {
+    let findings = db.get_lint_findings(inode).ok()?;
+    if findings.is_empty() {
+        return None;
+    }
+    let path = db.get_path(inode).ok().flatten()?;
+    let mut content = format!("# Proofreading {}\n\n", path.rsplit('/').next().unwrap_or(&path));
+    for (line, message) in findings {
+        content.push_str(&format!("- {}: {}\n", line, message));
+    }
+    Some(content)
+}
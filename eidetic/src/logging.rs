@@ -0,0 +1,175 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+use crate::config::LoggingConfig;
+
+// Logging
+// The daemon logs through `tracing` to ~/.eidetic/logs/<mount>.log, one file per mount point,
+// instead of the stdout/stderr capture next to the pid file. A log over `[logging] max_size_mb`
+// is rotated to <mount>.log.1, the older ones shifting up to `keep`. The level comes from
+// `[logging] level` ($RUST_LOG wins) and can be changed while running with `eidetic log-level`.
+// CLI commands log to stderr, silent unless $RUST_LOG is set. `eidetic logs -f` tails the log.
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+static CURRENT: OnceLock<PathBuf> = OnceLock::new();
+
+/// How often `eidetic logs -f` looks for new lines.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    PathBuf::from(home).join(".eidetic").join("logs")
+}
+
+/// The log of the mount at `mountpoint`, named after its absolute path: "/home/me/Mount"
+/// logs to "home-me-Mount.log".
+pub fn path(mountpoint: &Path) -> PathBuf {
+    let absolute = std::fs::canonicalize(mountpoint).unwrap_or_else(|_| mountpoint.to_path_buf());
+    let parts: Vec<String> = absolute.components()
+        .filter_map(|part| match part {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    let name = if parts.is_empty() { "root".to_string() } else { parts.join("-") };
+    dir().join(format!("{}.log", name))
+}
+
+/// Sets up the daemon's logging; in the foreground the log goes to stderr too.
+pub fn init(mountpoint: &Path, config: &LoggingConfig, foreground: bool) -> Result<PathBuf> {
+    let path = path(mountpoint);
+    std::fs::create_dir_all(dir())?;
+    let file = RotatingFile::open(path.clone(), config.max_size_mb * 1024 * 1024, config.keep)
+        .with_context(|| format!("Failed to open log file {:?}", path))?;
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.level));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        // Closing spans log their duration, e.g. every FUSE operation at `eidetic::fuse=trace`
+        .with(fmt::layer().with_ansi(false).with_span_events(FmtSpan::CLOSE).with_writer(Mutex::new(file)))
+        .with(foreground.then(|| fmt::layer().with_writer(io::stderr)))
+        .try_init()?;
+    let _ = FILTER.set(handle);
+    let _ = CURRENT.set(path.clone());
+    Ok(path)
+}
+
+/// This daemon's log file, once `init` ran.
+pub fn current() -> Option<&'static Path> {
+    CURRENT.get().map(PathBuf::as_path)
+}
+
+/// The most recently written log, for when no daemon is running to ask.
+pub fn latest() -> Option<PathBuf> {
+    std::fs::read_dir(dir()).ok()?
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+/// Prints the last `lines` lines of the log at `path`; with `follow`, keeps printing what's
+/// appended, picking up the new file when the log rotates.
+pub fn tail(path: &Path, lines: usize, follow: bool) -> Result<()> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    let text = String::from_utf8_lossy(&content);
+    let skip = text.lines().count().saturating_sub(lines);
+    let mut stdout = io::stdout();
+    for line in text.lines().skip(skip) {
+        writeln!(stdout, "{}", line)?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut position = content.len() as u64;
+    loop {
+        stdout.flush()?;
+        std::thread::sleep(FOLLOW_INTERVAL);
+        // Rotated: the path now holds a new file (or, with `keep = 0`, a truncated one)
+        let Ok(current) = std::fs::metadata(path) else { continue };
+        if current.ino() != file.metadata()?.ino() || current.len() < position {
+            file = File::open(path)?;
+            position = 0;
+        }
+        file.seek(SeekFrom::Start(position))?;
+        position += io::copy(&mut file, &mut stdout)?;
+    }
+}
+
+pub fn init_cli() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(io::stderr)
+        .try_init();
+}
+
+/// e.g. "debug", or per-module directives like "warn,eidetic::worker=debug"
+pub fn set_level(level: &str) -> Result<()> {
+    let filter = EnvFilter::try_new(level).with_context(|| format!("Invalid log level {:?}", level))?;
+    FILTER.get().context("Logging isn't set up")?.reload(filter)?;
+    Ok(())
+}
+
+// Appends to `path`, rotating it before a write would take it past `max_bytes` (0 never rotates)
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size, max_bytes, keep })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let _ = std::fs::rename(self.rotated(n), self.rotated(n + 1));
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.max_bytes > 0 && self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            // Keep logging into the full file rather than losing lines
+            let _ = self.rotate();
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -2,15 +2,72 @@ use clap::{Parser, Subcommand};
 use fuser::MountOption;
 use std::path::PathBuf;
 use anyhow::{Context, Result};
-use std::io::{self, Write};
 use std::fs::File;
 use daemonize::Daemonize;
 
+mod api;
+mod archive;
+mod bench;
 mod fs;
+mod grpc;
+mod graph;
+mod health;
+mod heatmap;
+mod http;
 mod db;
+mod entities;
+mod events;
+mod feeds;
 mod model;
+mod music;
+mod naming;
+mod notifications;
 mod cipher;
+mod cluster;
+mod comments;
 mod license;
+mod links;
+mod logging;
+mod mcp;
+mod lint;
+mod materialize;
+mod metrics;
+mod minilm;
+mod config;
+mod control;
+mod convert;
+mod exif;
+mod filetype;
+mod labels;
+mod lang;
+mod obsidian;
+mod offsite;
+mod office;
+mod organize;
+mod pdf;
+mod phash;
+mod query;
+mod queue;
+mod ratings;
+mod reading;
+mod rest;
+mod scan;
+mod scrub;
+mod sensitive;
+mod site;
+mod space;
+mod stats;
+mod t5;
+mod throttle;
+mod todos;
+mod transcribe;
+mod usage;
+mod views;
+mod watch;
+mod webdav;
+mod webhooks;
+mod weblink;
+mod wormhole;
 use fs::EideticFS;
 
 mod worker;
@@ -34,6 +91,10 @@ enum Commands {
         /// Path to the mount point
         #[arg(short, long, default_value = "./mount_point")]
         mountpoint: PathBuf,
+
+        /// Re-analyze every file in the source directory (done automatically on first mount)
+        #[arg(long)]
+        rescan: bool,
     },
     /// Start Eidetic in the background (Daemon)
     Start {
@@ -44,18 +105,264 @@ enum Commands {
         /// Path to the mount point
         #[arg(short, long, default_value = "./mount_point")]
         mountpoint: PathBuf,
+
+        /// Re-analyze every file in the source directory (done automatically on first mount)
+        #[arg(long)]
+        rescan: bool,
     },
     /// Stop the background Eidetic instance
     Stop,
+    /// Deposit a file into the running instance without going through the mount
+    Ingest {
+        /// File to ingest ("-" reads from stdin)
+        file: PathBuf,
+
+        /// Target path inside the source directory (defaults to the file name)
+        #[arg(short, long)]
+        path: Option<String>,
+
+        /// Tags to attach (repeatable)
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// URL the document was obtained from
+        #[arg(long)]
+        source_url: Option<String>,
+    },
+    /// Re-analyze a subtree of the source directory (everything by default), e.g. after
+    /// changing tag rules or upgrading the model
+    Reindex {
+        /// Path relative to the source directory
+        path: Option<String>,
+    },
+    /// List files whose content no longer matches their checksum (bitrot)
+    Integrity,
+    /// Show the analysis queue and recent failures
+    Jobs,
+    /// List images that look like the given one (re-encodes, burst shots)
+    Similar {
+        /// Image path relative to the source directory
+        path: String,
+    },
+    /// Pin a file to .magic/favorites/
+    Pin {
+        /// Path relative to the source directory
+        path: String,
+    },
+    /// Take a file off .magic/favorites/
+    Unpin {
+        /// Path relative to the source directory
+        path: String,
+    },
+    /// Rate a file 1-5 stars (0 clears the rating), listed in .magic/starred/
+    Rate {
+        /// Path relative to the source directory
+        path: String,
+        /// 1 to 5, or 0 to clear
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=5))]
+        stars: u8,
+    },
+    /// Give a file a color label (red, orange, yellow, green, blue, purple, gray, or none),
+    /// listed in .magic/labels/<color>/
+    Label {
+        /// Path relative to the source directory
+        path: String,
+        color: String,
+    },
+    /// Time writes, lookups, listings and reads on a throwaway mount, to compare releases
+    Bench {
+        /// Files to create
+        #[arg(long, default_value_t = 1000)]
+        files: usize,
+
+        /// Size of each file in bytes
+        #[arg(long, default_value_t = 4096)]
+        size: usize,
+    },
+    /// Print the running daemon's log from ~/.eidetic/logs/
+    Logs {
+        /// Keep printing new lines as they're written
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Lines to show from the end of the log
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+    },
+    /// Change how much the running daemon logs to ~/.eidetic/logs/
+    LogLevel {
+        /// error, warn, info, debug or trace, or directives like "warn,eidetic::worker=debug"
+        level: String,
+    },
+    /// Share the mount, `.magic/` included, with devices that can't run FUSE
+    Serve {
+        /// Address to serve WebDAV on, e.g. ":8080" (this machine only) or "0.0.0.0:8080" (with TLS)
+        #[arg(long)]
+        webdav: String,
+
+        /// Refuse writes, deletes and moves
+        #[arg(long)]
+        read_only: bool,
+
+        /// PEM certificate chain to serve HTTPS with; needed for addresses other than loopback
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+
+        /// PEM private key of the certificate
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+    },
+    /// Serve the index to AI agents as a Model Context Protocol server on stdin/stdout
+    Mcp,
+    /// Pause background analysis (e.g. on battery)
+    Pause,
+    /// Resume background analysis
+    Resume,
+    /// Install a Pro license: a signed license file, or a key
+    Activate {
+        /// Path to the license file, or the key itself
+        license: String,
+    },
+    /// Release this machine's Pro license seat and remove the license, to move it to another machine
+    Deactivate,
+    /// Keep Eidetic off the network (cached or placeholder content instead), or let it back on
+    Offline {
+        #[arg(default_value = "on", value_parser = ["on", "off"])]
+        state: String,
+    },
+    /// Manage local AI models in ~/.eidetic/models
+    Model {
+        #[command(subcommand)]
+        action: ModelAction,
+    },
+    /// Auto-organizer moves (see ~/.eidetic/organize.toml)
+    Organize {
+        #[command(subcommand)]
+        action: OrganizeAction,
+    },
+    /// Show whether the daemon is healthy and what the worker is doing
+    Status {
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Find files with a query like those of smart folders, e.g. "tag:invoice after:2024-01-01"
+    Search {
+        query: String,
+
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// List tags with how many files have them, or the files with one tag
+    Tags {
+        tag: Option<String>,
+
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the version history snapshots of a file
+    History {
+        /// Path relative to the source directory
+        path: String,
+
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Mirror .magic/tags/ as real folders of symlinks in <dir>, kept up to date, for backup
+    /// software and tools that can't traverse the mount
+    MaterializeTags {
+        /// Folder outside the Source folder
+        dir: PathBuf,
+    },
+    /// Download version history snapshots missing locally from the [s3] bucket
+    HistoryPull {
+        /// Only snapshots of files under this path, relative to the source directory
+        path: Option<String>,
+    },
+    /// List files deleted through the mount and where their copies are kept
+    Trash {
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show what Eidetic did to files: tags added, trashed files, snapshots, organizer moves, vault unlocks
+    Events {
+        /// Everything in this window, e.g. 30m, 12h, 7d, 2w (the latest 1000 events by default)
+        #[arg(long)]
+        since: Option<String>,
+    },
+    /// Publish notes, summaries, tags and backlinks as a static HTML site or an Obsidian vault
+    #[command(group(clap::ArgGroup::new("target").required(true)))]
+    Export {
+        /// Folder to write the site to (outside the Source folder)
+        #[arg(long, group = "target")]
+        html: Option<PathBuf>,
+        /// Vault folder to write the notes to (outside the Source folder), one-way
+        #[arg(long, group = "target")]
+        obsidian: Option<PathBuf>,
+    },
+    /// The graph of files, tags, entities and note links
+    Graph {
+        #[command(subcommand)]
+        action: GraphAction,
+    },
+    /// Store a secret in the system keyring for `{keyring:<name>}` in ~/.eidetic/api.toml (read from stdin)
+    Secret {
+        /// Name to store it under, e.g. "github"
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GraphAction {
+    /// Print the graph (or write it to --output) for Graphviz or d3-style graph tools
+    Export {
+        #[arg(long, default_value = "dot", value_parser = ["dot", "json"])]
+        format: String,
+
+        /// File to write instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum OrganizeAction {
+    /// Move files back where they were before the organizer moved them (the latest move by default)
+    Undo {
+        /// Undo every move made in this window, e.g. 30m, 12h, 7d, 2w
+        #[arg(long)]
+        since: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ModelAction {
+    /// List known models and whether they're downloaded
+    List,
+    /// Download a model (asks for confirmation first)
+    Download {
+        #[arg(default_value = t5::DEFAULT_MODEL)]
+        name: String,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
 }
 
 fn main() -> Result<()> {
-    env_logger::init();
-    
     // License check skipped for brevity in daemon command handling for now, 
     // or we can move it inside Mount/Start.
     
     let cli = Cli::parse();
+    // The daemon sets up its own logging once it knows the mount point
+    if !matches!(cli.command, Commands::Mount { .. } | Commands::Start { .. }) {
+        logging::init_cli();
+    }
     
     // Pid file path: ~/.eidetic/eidetic.pid
     let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
@@ -63,6 +370,7 @@ fn main() -> Result<()> {
     let pid_file = pid_dir.join("eidetic.pid");
     let stdout_log = pid_dir.join("eidetic.out");
     let stderr_log = pid_dir.join("eidetic.err");
+    let control_socket = pid_dir.join("eidetic.sock");
 
     if !pid_dir.exists() {
         std::fs::create_dir_all(&pid_dir)?;
@@ -91,7 +399,7 @@ fn main() -> Result<()> {
             return Ok(());
         }
         
-        Commands::Start { source, mountpoint } => {
+        Commands::Start { source, mountpoint, rescan } => {
             if pid_file.exists() {
                 println!("Eidetic is already running! (PID file exists)");
                 println!("Run 'eidetic stop' first if you want to restart.");
@@ -123,13 +431,13 @@ fn main() -> Result<()> {
                 Ok(_) => {
                     // WE ARE NOW IN THE DAEMON PROCESS
                     // Run the actual filesystem logic
-                    run_fs(source, mountpoint)?;
+                    run_fs(source, mountpoint, control_socket, rescan, false)?;
                 }
                 Err(e) => eprintln!("Error, {}", e),
             }
         }
         
-        Commands::Mount { source, mountpoint } => {
+        Commands::Mount { source, mountpoint, rescan } => {
             // Foreground run
             if !source.exists() { std::fs::create_dir_all(&source)?; }
             if !mountpoint.exists() { std::fs::create_dir_all(&mountpoint)?; }
@@ -139,23 +447,323 @@ fn main() -> Result<()> {
             println!("  Mount:  {:?}", mountpoint);
             println!("\n  (Press Ctrl+C to unmount)");
             
-            run_fs(source, mountpoint)?;
+            run_fs(source, mountpoint, control_socket, rescan, true)?;
+        }
+
+        Commands::Ingest { file, path, tags, source_url } => {
+            let target = match path {
+                Some(p) => p,
+                None if file.as_os_str() == "-" => {
+                    anyhow::bail!("--path is required when reading from stdin");
+                }
+                None => file.file_name().context("Invalid file name")?.to_string_lossy().to_string(),
+            };
+
+            // stdin has to be buffered to learn its length up front
+            let (size, mut input): (u64, Box<dyn std::io::Read>) = if file.as_os_str() == "-" {
+                let mut buffer = Vec::new();
+                std::io::Read::read_to_end(&mut std::io::stdin(), &mut buffer)?;
+                (buffer.len() as u64, Box::new(std::io::Cursor::new(buffer)))
+            } else {
+                let f = File::open(&file).with_context(|| format!("Failed to open {:?}", file))?;
+                (f.metadata()?.len(), Box::new(f))
+            };
+
+            let request = control::Request::Ingest { path: target.clone(), size, tags, source_url };
+            let response = control::send(&control_socket, &request, Some(&mut input))?;
+            if response.ok {
+                println!("Ingested {} (inode {})", target, response.inode.unwrap_or_default());
+            } else {
+                anyhow::bail!(response.error.unwrap_or_else(|| "Ingest failed".to_string()));
+            }
+        }
+
+        Commands::Reindex { path } => {
+            let request = control::Request::Reindex { path: path.clone() };
+            control::send_checked(&control_socket, &request)?;
+            println!("Reindexing {} in the background", path.as_deref().unwrap_or("everything"));
+        }
+
+        Commands::Integrity | Commands::Jobs | Commands::Similar { .. } => {
+            let request = match &cli.command {
+                Commands::Jobs => control::Request::Jobs,
+                Commands::Similar { path } => control::Request::Similar { path: path.clone() },
+                _ => control::Request::Integrity,
+            };
+            let response = control::send_checked(&control_socket, &request)?;
+            print!("{}", response.report.unwrap_or_default());
+        }
+
+        Commands::Pin { .. } | Commands::Unpin { .. } => {
+            let (request, done, path) = match &cli.command {
+                Commands::Pin { path } => (control::Request::Pin { path: path.clone() }, "Pinned", path),
+                Commands::Unpin { path } => (control::Request::Unpin { path: path.clone() }, "Unpinned", path),
+                _ => unreachable!(),
+            };
+            control::send_checked(&control_socket, &request)?;
+            println!("{} {}", done, path);
+        }
+
+        Commands::Rate { path, stars } => {
+            control::send_checked(&control_socket, &control::Request::Rate { path: path.clone(), stars })?;
+            match stars {
+                0 => println!("Cleared the rating of {}", path),
+                _ => println!("Rated {} {}", path, "★".repeat(stars as usize)),
+            }
+        }
+
+        Commands::Label { path, color } => {
+            let color = labels::parse(&color)?;
+            control::send_checked(&control_socket, &control::Request::Label { path: path.clone(), color: color.map(str::to_string) })?;
+            match color {
+                Some(color) => println!("Labeled {} {}", path, color),
+                None => println!("Cleared the label of {}", path),
+            }
+        }
+
+        Commands::Organize { action: OrganizeAction::Undo { since } } => {
+            let since = since.as_deref().map(organize::parse_since).transpose()?;
+            let response = control::send_checked(&control_socket, &control::Request::OrganizeUndo { since })?;
+            print!("{}", response.report.unwrap_or_default());
+        }
+
+        Commands::Status { json } | Commands::Search { json, .. } | Commands::Tags { json, .. } | Commands::History { json, .. } | Commands::Trash { json } => {
+            let request = match &cli.command {
+                Commands::Status { .. } => control::Request::Status,
+                Commands::Search { query, .. } => control::Request::Search { query: query.clone() },
+                Commands::Tags { tag, .. } => control::Request::Tags { tag: tag.clone() },
+                Commands::History { path, .. } => control::Request::History { path: path.clone() },
+                _ => control::Request::Trash,
+            };
+            let response = control::send_checked(&control_socket, &request)?;
+            let data = response.data.unwrap_or_default();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&data)?);
+            } else {
+                print_listing(&cli.command, &data);
+            }
+        }
+
+        Commands::MaterializeTags { dir } => {
+            // The daemon makes the links, so it needs a path that doesn't depend on our cwd
+            std::fs::create_dir_all(&dir)?;
+            let dir = std::fs::canonicalize(&dir)?.to_string_lossy().to_string();
+            let response = control::send_checked(&control_socket, &control::Request::MaterializeTags { dir })?;
+            print!("{}", response.report.unwrap_or_default());
+        }
+
+        Commands::HistoryPull { path } => {
+            let response = control::send_checked(&control_socket, &control::Request::HistoryPull { path })?;
+            print!("{}", response.report.unwrap_or_default());
+        }
+
+        Commands::Events { since } => {
+            let since = since.as_deref().map(organize::parse_since).transpose()?;
+            let response = control::send_checked(&control_socket, &control::Request::Events { since })?;
+            print!("{}", response.report.unwrap_or_default());
+        }
+
+        Commands::Export { html, obsidian } => {
+            let out = html.as_ref().or(obsidian.as_ref()).context("Pass --html or --obsidian")?;
+            // The daemon writes the export, so it needs a path that doesn't depend on our cwd
+            std::fs::create_dir_all(out)?;
+            let dir = std::fs::canonicalize(out)?.to_string_lossy().to_string();
+            let request = if html.is_some() { control::Request::ExportHtml { dir } } else { control::Request::ExportObsidian { dir } };
+            let response = control::send_checked(&control_socket, &request)?;
+            print!("{}", response.report.unwrap_or_default());
+        }
+
+        Commands::Graph { action: GraphAction::Export { format, output } } => {
+            let response = control::send_checked(&control_socket, &control::Request::Graph { format: format.clone() })?;
+            let graph = response.report.unwrap_or_default();
+            match output {
+                Some(output) => std::fs::write(output, graph)?,
+                None => print!("{}", graph),
+            }
+        }
+
+        Commands::Activate { license: activation } => {
+            print!("{}", license::install(&activation)?);
+        }
+
+        Commands::Deactivate => {
+            print!("{}", license::deactivate()?);
+        }
+
+        Commands::Offline { state } => {
+            http::set_offline(state == "on")?;
+            if state == "on" {
+                println!("Offline mode on: no license checks, links, API files, feeds, downloads or transfers until `eidetic offline off`");
+            } else if http::offline() {
+                println!("Still offline: `offline = true` is set under [network] in {:?}", config::Config::path());
+            } else {
+                println!("Offline mode off");
+            }
+        }
+
+        Commands::Secret { name } => {
+            let mut secret = String::new();
+            std::io::stdin().read_line(&mut secret)?;
+            let secret = secret.trim_end_matches(['\r', '\n']);
+            if secret.is_empty() {
+                anyhow::bail!("No secret on stdin");
+            }
+            api::store_secret(&name, secret)?;
+            println!("Stored {:?}; use it as {{keyring:{}}} in {:?}", name, name, api::path());
+        }
+
+        Commands::Model { action: ModelAction::List } => {
+            for (name, repo, size) in t5::MODELS {
+                let status = if t5::is_downloaded(name) { "downloaded" } else { "not downloaded" };
+                println!("{:<16} {:>7}  {:<15} ({})", name, size, status, repo);
+            }
+        }
+
+        Commands::Model { action: ModelAction::Download { name, yes } } => {
+            let (_, _, size) = t5::MODELS.iter().find(|(model, _, _)| *model == name)
+                .with_context(|| format!("Unknown model {} (see `eidetic model list`)", name))?;
+            if !yes {
+                print!("Download {} (~{}) to {:?}? [y/N] ", name, size, t5::models_dir());
+                std::io::Write::flush(&mut std::io::stdout())?;
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    println!("Cancelled.");
+                    return Ok(());
+                }
+            }
+            t5::download(&name)?;
+            if name == minilm::MODEL_NAME {
+                println!("Done. Set `enabled = true` under [embeddings] in {:?} to use it.", config::Config::path());
+            } else {
+                println!("Done. Set `backend = \"t5\"` under [ai] in {:?} to use it.", config::Config::path());
+            }
+        }
+
+        Commands::Bench { files, size } => {
+            print!("{}", bench::run(files, size)?);
+        }
+
+        Commands::Logs { follow, lines } => {
+            // The running daemon knows its log; without one, the most recently written log
+            let path = match control::send(&control_socket, &control::Request::LogFile, None) {
+                Ok(response) if response.ok => response.report.map(PathBuf::from),
+                _ => None,
+            };
+            let path = path.or_else(logging::latest).with_context(|| format!("No logs in {:?} yet", logging::dir()))?;
+            logging::tail(&path, lines, follow)?;
+        }
+
+        Commands::LogLevel { level } => {
+            control::send_checked(&control_socket, &control::Request::LogLevel { level: level.clone() })?;
+            println!("Log level set to {}", level);
+        }
+
+        Commands::Serve { webdav, read_only, tls_cert, tls_key } => {
+            let response = control::send_checked(&control_socket, &control::Request::MountPoint)?;
+            let tls = tls_cert.as_deref().zip(tls_key.as_deref());
+            webdav::serve(&webdav, PathBuf::from(response.report.unwrap_or_default()), read_only, tls)?;
+        }
+
+        Commands::Mcp => {
+            let response = control::send_checked(&control_socket, &control::Request::SourcePath)?;
+            mcp::Server::new(PathBuf::from(response.report.unwrap_or_default()))?.run()?;
+        }
+
+        Commands::Pause | Commands::Resume => {
+            let pause = matches!(cli.command, Commands::Pause);
+            let request = if pause { control::Request::Pause } else { control::Request::Resume };
+            control::send_checked(&control_socket, &request)?;
+            println!("Background analysis {}", if pause { "paused" } else { "resumed" });
         }
     }
 
     Ok(())
 }
 
-fn run_fs(source: PathBuf, mountpoint: PathBuf) -> Result<()> {
+// Human-readable output of `eidetic status`, `search`, `tags`, `history` and `trash`
+fn print_listing(command: &Commands, data: &serde_json::Value) {
+    let rows = data.as_array().cloned().unwrap_or_default();
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    let time = |value: &serde_json::Value| stats::local_time(value.as_i64().unwrap_or(0));
+    match command {
+        Commands::Status { .. } => {
+            let worker = &data["worker"];
+            println!("Status:   {}", text(&data["status"]));
+            println!("Uptime:   {}s", data["uptime_secs"]);
+            match worker["current"].as_str() {
+                Some(current) => println!("Worker:   {} on {} for {}s, {} pending", text(&worker["state"]), current, worker["busy_secs"], worker["pending"]),
+                None => println!("Worker:   {}, {} pending", text(&worker["state"]), worker["pending"]),
+            }
+            if let Some(error) = data["database"]["error"].as_str() {
+                println!("Database: {}", error);
+            }
+            if data["last_error"].is_object() {
+                println!("Last failure: {}: {}", text(&data["last_error"]["path"]), text(&data["last_error"]["error"]));
+            }
+            let license = &data["license"];
+            println!("License:  {}", text(&license["summary"]));
+            if let Some(features) = license["features"].as_object() {
+                let features: Vec<String> = features.iter()
+                    .map(|(feature, unlocked)| format!("{} ({})", feature, if unlocked.as_bool() == Some(true) { "unlocked" } else { "locked" }))
+                    .collect();
+                println!("Pro:      {}", features.join(", "));
+            }
+        }
+        Commands::Search { .. } => {
+            for row in &rows {
+                let tags: Vec<String> = row["tags"].as_array().into_iter().flatten().map(text).collect();
+                if tags.is_empty() {
+                    println!("{}", text(&row["path"]));
+                } else {
+                    println!("{}  [{}]", text(&row["path"]), tags.join(", "));
+                }
+            }
+        }
+        Commands::Tags { tag: Some(_), .. } => rows.iter().for_each(|path| println!("{}", text(path))),
+        Commands::Tags { .. } => rows.iter().for_each(|row| println!("{}  ({})", text(&row["tag"]), row["files"])),
+        Commands::History { .. } => rows.iter().for_each(|row| println!("{}  {}", time(&row["at"]), text(&row["snapshot"]))),
+        _ => rows.iter().for_each(|row| println!("{}  {}  -> {}", time(&row["deleted_at"]), text(&row["path"]), text(&row["trashed"]))),
+    }
+}
+
+fn run_fs(source: PathBuf, mountpoint: PathBuf, control_socket: PathBuf, rescan: bool, foreground: bool) -> Result<()> {
     let uid = unsafe { libc::getuid() };
     let gid = unsafe { libc::getgid() };
+
+    let config = config::Config::load()?;
+    let log_path = logging::init(&mountpoint, &config.logging, foreground)?;
+    tracing::info!(?source, ?mountpoint, log = ?log_path, "mounting");
+    let converters = convert::Registry::new(&config)?;
     
     // Start Worker
-    let (tx, rx) = std::sync::mpsc::channel();
+    let jobs = queue::JobQueue::new();
     let db_path = source.join(".eidetic.db");
-    worker::Worker::new(rx, db_path).start();
+    worker::Worker::new(jobs.clone(), db_path, &config).start();
+    scan::start(source.clone(), jobs.clone(), rescan);
+    watch::start(source.clone(), jobs.clone());
+    scrub::start(source.clone(), config.scrub.interval_days);
+    space::start(source.clone());
+    heatmap::start(source.clone());
+    metrics::start(&config.metrics, source.clone(), jobs.clone());
+    rest::start(&config.rest, source.clone());
+    grpc::start(&config.grpc, &config.rest, source.clone());
+    offsite::start(&config.s3, source.clone());
+    materialize::start(source.clone());
+    webhooks::start(&config.webhooks, source.clone());
+    feeds::start(source.clone());
+    if config.embeddings.enabled {
+        cluster::start(source.clone(), config.embeddings.cluster_interval_hours);
+    }
+
+    // Control socket for `eidetic ingest` and other clients
+    // Absolute, since `eidetic serve` may run from another directory
+    let absolute_mountpoint = std::fs::canonicalize(&mountpoint).unwrap_or_else(|_| mountpoint.clone());
+    control::ControlServer::new(control_socket, source.clone(), absolute_mountpoint, jobs.clone()).start()?;
     
-    let fs = EideticFS::new(source, uid, gid, tx);
+    // Answers questions written to `.ask`, with the same backend as the worker's summaries
+    let assistant = model::Summarizer::new(&config.ai).map_err(|e| tracing::warn!(error = %e, ".ask unavailable")).ok();
+    let fs = EideticFS::new(source, uid, gid, jobs, converters, assistant, &config);
     
     let options = vec![
         MountOption::RW,
@@ -163,6 +771,8 @@ fn run_fs(source: PathBuf, mountpoint: PathBuf) -> Result<()> {
         MountOption::AutoUnmount,
     ];
 
-    fuser::mount2(fs, mountpoint, &options).context("Failed to mount filesystem")?;
-    Ok(())
+    let mounted = fuser::mount2(fs, mountpoint, &options).context("Failed to mount filesystem");
+    // Unmounted: a team license seat is free for someone else
+    license::release_seat();
+    mounted
 }
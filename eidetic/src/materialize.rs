@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use anyhow::{bail, Result};
+use crate::db::Database;
+use tracing::{error, info, warn};
+
+// Materialized Tags
+// `eidetic materialize-tags <dir>` mirrors `.magic/tags/` as real directories of symlinks on
+// the host filesystem, for backup software and tools that can't (or shouldn't) traverse the
+// mount: `<dir>/invoice/report.pdf -> <source>/docs/report.pdf`. Tags with a colon nest,
+// "lang:de" becoming `lang/de/`. The worker relinks each file it analyzes, and a full pass
+// every SYNC_INTERVAL catches files renamed or deleted since. The links a file got are kept in
+// the tag_links table, so only Eidetic's own links are ever removed.
+
+const DIR_KEY: &str = "materialized_tags_dir";
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The folder tags are materialized into, if set up.
+pub fn dir(db: &Database) -> Option<PathBuf> {
+    db.get_meta(DIR_KEY).ok().flatten().map(PathBuf::from)
+}
+
+/// Materializes tags into `dir` (absolute) from now on, moving them if they were elsewhere.
+pub fn set_up(db: &Database, source_path: &Path, dir: &Path) -> Result<String> {
+    let source = std::fs::canonicalize(source_path)?;
+    if dir.starts_with(&source) {
+        bail!("{:?} is inside the Source folder; pick a folder outside it", dir);
+    }
+    std::fs::create_dir_all(dir)?;
+    if let Some(previous) = self::dir(db).filter(|previous| previous != dir) {
+        for inode in db.tag_link_inodes()? {
+            link(db, &previous, inode, None, &[])?;
+        }
+    }
+    db.set_meta(DIR_KEY, &dir.to_string_lossy())?;
+    let links = sync(db, source_path, dir)?;
+    Ok(format!("{} link(s) in {:?}, kept up to date while Eidetic runs\n", links, dir))
+}
+
+pub fn start(source_path: PathBuf) {
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        loop {
+            if let Some(dir) = dir(&db) {
+                match sync(&db, &source_path, &dir) {
+                    Ok(links) => info!(links, ?dir, "tag links synced"),
+                    Err(e) => warn!(?dir, error = %e, "tag link sync failed"),
+                }
+            }
+            thread::sleep(SYNC_INTERVAL);
+        }
+    });
+}
+
+/// Relinks one file after the worker analyzed it; `path` is where it is in the Source folder.
+pub fn update(db: &Database, inode: u64, path: &Path) {
+    let Some(dir) = dir(db) else { return };
+    let tags = db.get_file_tags(inode).unwrap_or_default();
+    if let Err(e) = link(db, &dir, inode, Some(path), &tags) {
+        warn!(?path, error = %e, "failed to update tag links");
+    }
+}
+
+// Brings every file's links in line with its tags; returns how many links there are
+fn sync(db: &Database, source_path: &Path, dir: &Path) -> Result<usize> {
+    let mut tagged: HashMap<u64, Vec<String>> = HashMap::new();
+    for tag in db.get_tags()? {
+        for (inode, _) in db.get_files_with_tag(&tag)? {
+            tagged.entry(inode).or_default().push(tag.clone());
+        }
+    }
+    // Untagged or deleted since their links were made
+    for inode in db.tag_link_inodes()? {
+        tagged.entry(inode).or_default();
+    }
+    let mut links = 0;
+    for (inode, tags) in tagged {
+        let path = db.get_path(inode)?.map(|rel| source_path.join(rel)).filter(|path| path.exists());
+        let tags = if path.is_some() { tags } else { Vec::new() };
+        links += link(db, dir, inode, path.as_deref(), &tags)?;
+    }
+    Ok(links)
+}
+
+// Gives `inode` one link per tag in `dir` (none without a `path`), removing the ones it no
+// longer should have; returns how many it has
+fn link(db: &Database, dir: &Path, inode: u64, path: Option<&Path>, tags: &[String]) -> Result<usize> {
+    let target = path.map(|path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+    let name = target.as_ref().and_then(|target| target.file_name()).map(|name| name.to_string_lossy().to_string());
+    let mut wanted = Vec::new();
+    if let (Some(target), Some(name)) = (&target, &name) {
+        for tag in tags {
+            let Some(tag_dir) = tag_dir(dir, tag) else { continue };
+            let mut link = tag_dir.join(name);
+            // Another file of the same name has the tag too
+            if db.tag_link_owner(&link.to_string_lossy())?.is_some_and(|owner| owner != inode) {
+                link = tag_dir.join(match name.rsplit_once('.') {
+                    Some((stem, ext)) => format!("{}-{}.{}", stem, inode, ext),
+                    None => format!("{}-{}", name, inode),
+                });
+            }
+            if std::fs::read_link(&link).is_ok_and(|existing| existing == *target) {
+                wanted.push(link);
+                continue;
+            }
+            std::fs::create_dir_all(&tag_dir)?;
+            // Replace a stale link of ours, never a real file
+            if link.symlink_metadata().is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+                std::fs::remove_file(&link)?;
+            }
+            match symlink(target, &link) {
+                Ok(()) => wanted.push(link),
+                Err(e) => warn!(?link, error = %e, "failed to create tag link"),
+            }
+        }
+    }
+
+    let wanted: Vec<String> = wanted.iter().map(|link| link.to_string_lossy().to_string()).collect();
+    for old in db.get_tag_links(inode)? {
+        if wanted.contains(&old) {
+            continue;
+        }
+        let old = PathBuf::from(old);
+        if old.symlink_metadata().is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            let _ = std::fs::remove_file(&old);
+        }
+        // Only goes if now empty
+        let mut parent = old.parent();
+        while let Some(folder) = parent.filter(|folder| *folder != dir) {
+            if std::fs::remove_dir(folder).is_err() {
+                break;
+            }
+            parent = folder.parent();
+        }
+    }
+    db.set_tag_links(inode, &wanted)?;
+    Ok(wanted.len())
+}
+
+// "lang:de" -> <dir>/lang/de; None for a tag that makes no folder name
+fn tag_dir(dir: &Path, tag: &str) -> Option<PathBuf> {
+    let mut path = dir.to_path_buf();
+    for part in tag.split(':') {
+        let part = part.trim().replace('/', "-");
+        if part.is_empty() || part == "." || part == ".." {
+            return None;
+        }
+        path.push(part);
+    }
+    Some(path)
+}
@@ -0,0 +1,270 @@
+use std::io::{BufRead, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use crate::db::Database;
+use crate::query::Query;
+use tracing::{debug, warn};
+
+// MCP Server
+// `eidetic mcp` speaks the Model Context Protocol on stdin/stdout, so AI agents can search the
+// index and read what it knows about files instead of grepping the tree. Register it with an
+// agent as a stdio server running `eidetic mcp`. It asks the running daemon for the source
+// directory, then reads the database and files itself; nothing is changed.
+//
+// Tools: search (smart folder query syntax), read_file, get_summary, list_tags and
+// files_with_tag. Resources: eidetic://tags, and eidetic://file/<path> and
+// eidetic://summary/<path> for any file.
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Largest file read_file returns; agents get the summary of bigger ones instead.
+const MAX_READ: u64 = 256 * 1024;
+
+pub struct Server {
+    source_path: PathBuf,
+    db: Database,
+    // `[sensitive] exclude_from_context`: flagged files aren't handed to agents
+    exclude_sensitive: bool,
+}
+
+impl Server {
+    pub fn new(source_path: PathBuf) -> Result<Self> {
+        let db = Database::new(source_path.join(".eidetic.db"))?;
+        let exclude_sensitive = crate::config::Config::load()?.sensitive.exclude_from_context;
+        Ok(Self { source_path, db, exclude_sensitive })
+    }
+
+    /// Answers JSON-RPC messages, one per line, until stdin closes.
+    pub fn run(&self) -> Result<()> {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+        for line in stdin.lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle(&message),
+                Err(e) => Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": e.to_string() } })),
+            };
+            if let Some(response) = response {
+                writeln!(stdout, "{}", response)?;
+                stdout.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    // The response to a request, None for a notification
+    fn handle(&self, message: &Value) -> Option<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let id = message.get("id")?.clone();
+        debug!(method, "mcp request");
+        let params = &message["params"];
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {}, "resources": {} },
+                "serverInfo": { "name": "eidetic", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => Ok(self.call(params["name"].as_str().unwrap_or_default(), &params["arguments"])),
+            "resources/list" => Ok(json!({ "resources": [
+                { "uri": "eidetic://tags", "name": "Tags", "description": "Every tag in the index", "mimeType": "text/plain" },
+            ] })),
+            "resources/templates/list" => Ok(json!({ "resourceTemplates": [
+                { "uriTemplate": "eidetic://file/{path}", "name": "File", "description": "A text file, by path relative to the source directory" },
+                { "uriTemplate": "eidetic://summary/{path}", "name": "Summary", "description": "The summary of a file" },
+            ] })),
+            "resources/read" => self.resource(params["uri"].as_str().unwrap_or_default()),
+            _ => {
+                return Some(json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32601, "message": format!("Unknown method {}", method) } }));
+            }
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32602, "message": e.to_string() } }),
+        })
+    }
+
+    fn call(&self, tool: &str, arguments: &Value) -> Value {
+        let argument = |name: &str| arguments[name].as_str().with_context(|| format!("Missing argument {:?}", name));
+        let text = match tool {
+            "search" => argument("query").and_then(|query| self.search(query)),
+            "read_file" => argument("path").and_then(|path| self.read_file(path)),
+            "get_summary" => argument("path").and_then(|path| self.summary(path)),
+            "list_tags" => self.db.get_tags().map(|tags| tags.join("\n")).map_err(Into::into),
+            "files_with_tag" => argument("tag").and_then(|tag| self.files_with_tag(tag)),
+            _ => Err(anyhow::anyhow!("Unknown tool {:?}", tool)),
+        };
+        match text {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+            Err(e) => {
+                warn!(tool, error = %e, "mcp tool failed");
+                json!({ "content": [{ "type": "text", "text": e.to_string() }], "isError": true })
+            }
+        }
+    }
+
+    fn resource(&self, uri: &str) -> Result<Value> {
+        let text = if uri == "eidetic://tags" {
+            self.db.get_tags()?.join("\n")
+        } else if let Some(path) = uri.strip_prefix("eidetic://file/") {
+            self.read_file(path)?
+        } else if let Some(path) = uri.strip_prefix("eidetic://summary/") {
+            self.summary(path)?
+        } else {
+            bail!("Unknown resource {}", uri);
+        };
+        Ok(json!({ "contents": [{ "uri": uri, "mimeType": "text/plain", "text": text }] }))
+    }
+
+    // One path per line, with the file's tags
+    fn search(&self, query: &str) -> Result<String> {
+        let results: Vec<_> = Query::parse(query)?.results(&self.db).into_iter().filter(|(inode, _)| !self.withheld(*inode)).collect();
+        if results.is_empty() {
+            return Ok("No matching files.".to_string());
+        }
+        Ok(results.into_iter().map(|(inode, name)| self.describe(inode, name)).collect::<Vec<_>>().join("\n"))
+    }
+
+    fn files_with_tag(&self, tag: &str) -> Result<String> {
+        let files: Vec<_> = self.db.get_files_with_tag(tag)?.into_iter().filter(|(inode, _)| !self.withheld(*inode)).collect();
+        if files.is_empty() {
+            return Ok(format!("No files tagged {}.", tag));
+        }
+        Ok(files.into_iter().map(|(inode, name)| self.describe(inode, name)).collect::<Vec<_>>().join("\n"))
+    }
+
+    fn describe(&self, inode: u64, name: String) -> String {
+        let path = self.db.get_path(inode).ok().flatten().unwrap_or(name);
+        match self.db.get_file_tags(inode).unwrap_or_default() {
+            tags if tags.is_empty() => path,
+            tags => format!("{}  [{}]", path, tags.join(", ")),
+        }
+    }
+
+    // Flagged as holding secrets while `[sensitive] exclude_from_context` is on: not named in
+    // search results, and neither its text nor its summary is handed out
+    fn withheld(&self, inode: u64) -> bool {
+        self.exclude_sensitive && self.db.is_sensitive(inode).unwrap_or(false)
+    }
+
+    fn summary(&self, path: &str) -> Result<String> {
+        let inode = self.db.lookup_path(relative(path)?.to_string_lossy().as_ref())?.with_context(|| format!("No such file {}", path))?;
+        if self.withheld(inode) {
+            bail!("{} was flagged as containing secrets", path);
+        }
+        Ok(self.db.get_summary(inode)?.unwrap_or_else(|| format!("{} has no summary yet.", path)))
+    }
+
+    fn read_file(&self, path: &str) -> Result<String> {
+        let relative = relative(path)?;
+        // What's in the vault is ciphertext, and its key stays with the mount
+        if relative.components().any(|component| component.as_os_str() == "vault") {
+            bail!("{} is in the vault", path);
+        }
+        // Symlinks are followed, but not out of the source directory
+        let root = self.source_path.canonicalize()?;
+        let full = self.source_path.join(relative).canonicalize().with_context(|| format!("No such file {}", path))?;
+        if !full.starts_with(&root) {
+            bail!("{} points outside the source directory", path);
+        }
+        if self.db.lookup_path(&full.strip_prefix(&root)?.to_string_lossy())?.is_some_and(|inode| self.withheld(inode)) {
+            bail!("{} was flagged as containing secrets", path);
+        }
+        let metadata = std::fs::metadata(&full).with_context(|| format!("No such file {}", path))?;
+        if !metadata.is_file() {
+            bail!("{} isn't a file", path);
+        }
+        if metadata.len() > MAX_READ {
+            bail!("{} is {}, too large to read whole; use get_summary", path, crate::space::format_size(metadata.len()));
+        }
+        let mut content = Vec::new();
+        std::fs::File::open(&full)?.read_to_end(&mut content)?;
+        if content.contains(&0) {
+            bail!("{} is a binary file; use get_summary", path);
+        }
+        Ok(String::from_utf8_lossy(&content).to_string())
+    }
+}
+
+// A path inside the source directory, without `..` or Eidetic's own state
+fn relative(path: &str) -> Result<&Path> {
+    let path = Path::new(path.trim_start_matches('/'));
+    for component in path.components() {
+        match component {
+            Component::Normal(name) if !crate::scan::ignored(&name.to_string_lossy()) => {}
+            _ => bail!("Invalid path {}", path.display()),
+        }
+    }
+    Ok(path)
+}
+
+fn tools() -> Value {
+    let path = json!({ "type": "object", "properties": { "path": { "type": "string", "description": "Path relative to the source directory" } }, "required": ["path"] });
+    json!([
+        {
+            "name": "search",
+            "description": "Find files by tags, kind, size, date, rating and words in their name or comments. Terms: tag:<tag>, type:<images|documents|code|audio|video|archives|other>, ext:<extension>, larger:<size>, smaller:<size>, after:<YYYY-MM-DD>, before:<YYYY-MM-DD>, rating>=<stars>, plain words; prefix a term with - to exclude.",
+            "inputSchema": { "type": "object", "properties": { "query": { "type": "string", "description": "e.g. \"type:documents tag:invoice after:2024-01-01\"" } }, "required": ["query"] },
+        },
+        { "name": "read_file", "description": "Read a text file.", "inputSchema": path },
+        { "name": "get_summary", "description": "The summary Eidetic wrote of a file, including PDFs and other documents.", "inputSchema": path },
+        { "name": "list_tags", "description": "Every tag in the index.", "inputSchema": { "type": "object", "properties": {} } },
+        {
+            "name": "files_with_tag",
+            "description": "Files carrying a tag.",
+            "inputSchema": { "type": "object", "properties": { "tag": { "type": "string" } }, "required": ["tag"] },
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A source directory with keys.txt (flagged) and notes.txt
+    fn server(exclude_sensitive: bool) -> (tempfile::TempDir, Server) {
+        let source = tempfile::tempdir().unwrap();
+        let db = Database::new(source.path().join(".eidetic.db")).unwrap();
+        for (name, text) in [("keys.txt", "AWS_SECRET_ACCESS_KEY=..."), ("notes.txt", "groceries")] {
+            std::fs::write(source.path().join(name), text).unwrap();
+            let inode = db.alloc_path(name).unwrap();
+            db.add_tag(inode, "work").unwrap();
+            db.set_summary(inode, "A summary").unwrap();
+        }
+        db.set_sensitive_findings(db.lookup_path("keys.txt").unwrap().unwrap(), &[("aws-key", 1)]).unwrap();
+        let server = Server { source_path: source.path().to_path_buf(), db, exclude_sensitive };
+        (source, server)
+    }
+
+    #[test]
+    fn flagged_files_are_withheld() {
+        let (_source, server) = server(true);
+        assert!(server.read_file("keys.txt").is_err());
+        assert!(server.read_file("/keys.txt").is_err());
+        assert!(server.summary("keys.txt").is_err());
+        assert!(server.resource("eidetic://summary/keys.txt").is_err());
+        assert_eq!(server.files_with_tag("work").unwrap(), "notes.txt  [work]");
+        assert_eq!(server.read_file("notes.txt").unwrap(), "groceries");
+        assert_eq!(server.summary("notes.txt").unwrap(), "A summary");
+    }
+
+    #[test]
+    fn flagged_files_are_served_when_not_excluded() {
+        let (_source, server) = server(false);
+        assert_eq!(server.read_file("keys.txt").unwrap(), "AWS_SECRET_ACCESS_KEY=...");
+        assert_eq!(server.summary("keys.txt").unwrap(), "A summary");
+        assert_eq!(server.files_with_tag("work").unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn paths_stay_inside_the_source() {
+        let (_source, server) = server(false);
+        assert!(server.read_file("../etc/passwd").is_err());
+        assert!(server.read_file(".eidetic.db").is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::config::MetricsConfig;
+use crate::db::Database;
+use crate::queue::JobQueue;
+use tracing::field::Empty;
+use tracing::{error, info, warn};
+
+// Metrics
+// Prometheus metrics for monitoring the daemon: latency histograms of FUSE operations and
+// database queries (timed through SQLite's profiling hook), the worker's queue, and the
+// index and conversion cache counters from `stats.md`. With `[metrics] listen` set they're
+// served at `http://<listen>/metrics`; with `textfile` set they're also written there every
+// TEXTFILE_INTERVAL for node_exporter's textfile collector. Both are off by default.
+//
+// Each FUSE operation also runs in a `fuse_op` span (target `eidetic::fuse`, trace level) and
+// is split into the time spent in SQLite, in conversions (rendering, archive extraction, model
+// calls) and everything else, which is mostly the backing filesystem. The split and the errno
+// of failed operations are tracked per thread, since a FUSE handler runs on one thread start
+// to finish. `stats.md` shows the same breakdown without Prometheus.
+
+const TEXTFILE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Histogram bucket bounds, in seconds.
+const BUCKETS: [f64; 10] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+#[derive(Default)]
+struct Histogram {
+    counts: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bound, count) in BUCKETS.iter().zip(self.counts.iter_mut()) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        for (bound, count) in BUCKETS.iter().zip(self.counts) {
+            out.push_str(&format!("{}_bucket{{{}{}le=\"{}\"}} {}\n", name, labels, sep, bound, count));
+        }
+        out.push_str(&format!("{}_bucket{{{}{}le=\"+Inf\"}} {}\n", name, labels, sep, self.count));
+        let braces = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        out.push_str(&format!("{}_sum{} {}\n", name, braces, self.sum));
+        out.push_str(&format!("{}_count{} {}\n", name, braces, self.count));
+    }
+}
+
+/// Where a FUSE operation's time went, in the order of `OpStats::components`.
+const COMPONENTS: [&str; 3] = ["db", "convert", "fs"];
+
+#[derive(Default)]
+struct OpStats {
+    total: Histogram,
+    components: [Histogram; COMPONENTS.len()],
+    // errno -> failed operations
+    errors: BTreeMap<i32, u64>,
+}
+
+#[derive(Default)]
+struct Registry {
+    fuse_ops: BTreeMap<&'static str, OpStats>,
+    db_queries: Histogram,
+}
+
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+fn observe(record: impl FnOnce(&mut Registry)) {
+    record(REGISTRY.lock().unwrap().get_or_insert_with(Registry::default));
+}
+
+// The FUSE operation running on this thread so far
+#[derive(Clone, Copy, Default)]
+struct Breakdown {
+    db: Duration,
+    convert: Duration,
+    errno: i32,
+}
+
+thread_local! {
+    static CURRENT: Cell<Breakdown> = Cell::new(Breakdown::default());
+}
+
+fn update(change: impl FnOnce(&mut Breakdown)) {
+    CURRENT.with(|current| {
+        let mut breakdown = current.get();
+        change(&mut breakdown);
+        current.set(breakdown);
+    });
+}
+
+/// Times a FUSE operation until dropped.
+pub struct Timer {
+    op: &'static str,
+    start: Instant,
+    span: tracing::span::EnteredSpan,
+}
+
+pub fn fuse_op(op: &'static str) -> Timer {
+    CURRENT.with(|current| current.set(Breakdown::default()));
+    let span = tracing::trace_span!(target: "eidetic::fuse", "fuse_op", op, errno = Empty, db_us = Empty, convert_us = Empty);
+    Timer { op, start: Instant::now(), span: span.entered() }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let breakdown = CURRENT.with(Cell::get);
+        let fs = elapsed.saturating_sub(breakdown.db + breakdown.convert);
+        self.span.record("db_us", breakdown.db.as_micros() as u64);
+        self.span.record("convert_us", breakdown.convert.as_micros() as u64);
+        if breakdown.errno != 0 {
+            self.span.record("errno", breakdown.errno);
+        }
+        observe(|registry| {
+            let stats = registry.fuse_ops.entry(self.op).or_default();
+            stats.total.observe(elapsed.as_secs_f64());
+            for (histogram, time) in stats.components.iter_mut().zip([breakdown.db, breakdown.convert, fs]) {
+                histogram.observe(time.as_secs_f64());
+            }
+            if breakdown.errno != 0 {
+                *stats.errors.entry(breakdown.errno).or_default() += 1;
+            }
+        });
+    }
+}
+
+/// Notes the errno a FUSE operation fails with: `reply.error(failed(ENOENT))`.
+pub fn failed(errno: i32) -> i32 {
+    update(|breakdown| breakdown.errno = errno);
+    errno
+}
+
+/// Runs a conversion, counting its time (less the queries it makes) as the current FUSE
+/// operation's conversion time.
+pub fn converting<R>(convert: impl FnOnce() -> R) -> R {
+    let start = Instant::now();
+    let db_before = CURRENT.with(Cell::get).db;
+    let result = convert();
+    let elapsed = start.elapsed();
+    update(|breakdown| breakdown.convert += elapsed.saturating_sub(breakdown.db.saturating_sub(db_before)));
+    result
+}
+
+/// SQLite profiling hook, called after every statement.
+pub fn record_query(_sql: &str, elapsed: Duration) {
+    update(|breakdown| breakdown.db += elapsed);
+    observe(|registry| registry.db_queries.observe(elapsed.as_secs_f64()));
+}
+
+/// The Latency section of `stats.md`: per FUSE operation, how many ran, their mean time, where
+/// that time went and how many failed.
+pub fn latency_summary() -> String {
+    let registry = REGISTRY.lock().unwrap();
+    let mut content = String::from("\n## Latency\n");
+    let ops = registry.as_ref().map(|registry| &registry.fuse_ops).filter(|ops| !ops.is_empty());
+    let Some(ops) = ops else {
+        content.push_str("_No operations since mount._\n");
+        return content;
+    };
+    content.push_str("| Operation | Count | Mean | DB | Conversion | Filesystem | Errors |\n|---|---|---|---|---|---|---|\n");
+    for (op, stats) in ops {
+        let total = stats.total.sum.max(f64::EPSILON);
+        let share = |histogram: &Histogram| format!("{:.0}%", histogram.sum * 100.0 / total);
+        let errors: Vec<String> = stats.errors.iter().map(|(errno, count)| format!("{} {}", count, errno_name(*errno))).collect();
+        content.push_str(&format!(
+            "| {} | {} | {:.2} ms | {} | {} | {} | {} |\n",
+            op,
+            stats.total.count,
+            stats.total.sum * 1000.0 / stats.total.count.max(1) as f64,
+            share(&stats.components[0]),
+            share(&stats.components[1]),
+            share(&stats.components[2]),
+            if errors.is_empty() { "-".to_string() } else { errors.join(", ") },
+        ));
+    }
+    content
+}
+
+fn errno_name(errno: i32) -> String {
+    let name = match errno {
+        libc::ENOENT => "ENOENT",
+        libc::EIO => "EIO",
+        libc::EACCES => "EACCES",
+        libc::EINVAL => "EINVAL",
+        libc::EEXIST => "EEXIST",
+        libc::ENOTEMPTY => "ENOTEMPTY",
+        libc::EPERM => "EPERM",
+        libc::ENOSPC => "ENOSPC",
+        _ => return format!("errno {}", errno),
+    };
+    name.to_string()
+}
+
+/// The metrics in Prometheus' text format.
+pub fn render(db: &Database, jobs: &JobQueue) -> String {
+    let mut out = String::new();
+    {
+        let mut registry = REGISTRY.lock().unwrap();
+        let registry = registry.get_or_insert_with(Registry::default);
+        out.push_str("# HELP eidetic_fuse_op_duration_seconds Time spent in FUSE operations.\n");
+        out.push_str("# TYPE eidetic_fuse_op_duration_seconds histogram\n");
+        for (op, stats) in &registry.fuse_ops {
+            stats.total.render(&mut out, "eidetic_fuse_op_duration_seconds", &format!("op=\"{}\"", op));
+        }
+        out.push_str("# HELP eidetic_fuse_op_component_duration_seconds Time FUSE operations spent in SQLite (db), conversions (convert) and everything else, mostly the backing filesystem (fs).\n");
+        out.push_str("# TYPE eidetic_fuse_op_component_duration_seconds histogram\n");
+        for (op, stats) in &registry.fuse_ops {
+            for (component, histogram) in COMPONENTS.iter().zip(&stats.components) {
+                histogram.render(&mut out, "eidetic_fuse_op_component_duration_seconds", &format!("op=\"{}\",component=\"{}\"", op, component));
+            }
+        }
+        out.push_str("# HELP eidetic_fuse_op_errors_total FUSE operations that failed, by errno.\n");
+        out.push_str("# TYPE eidetic_fuse_op_errors_total counter\n");
+        for (op, stats) in &registry.fuse_ops {
+            for (errno, count) in &stats.errors {
+                out.push_str(&format!("eidetic_fuse_op_errors_total{{op=\"{}\",errno=\"{}\"}} {}\n", op, errno_name(*errno), count));
+            }
+        }
+        out.push_str("# HELP eidetic_db_query_duration_seconds Time spent in SQLite statements.\n");
+        out.push_str("# TYPE eidetic_db_query_duration_seconds histogram\n");
+        registry.db_queries.render(&mut out, "eidetic_db_query_duration_seconds", "");
+    }
+
+    let (_, failed) = db.count_jobs().unwrap_or_default();
+    let opens = db.get_counter(crate::stats::CONVERTED_OPENS).unwrap_or(0);
+    let conversions = db.get_counter(crate::stats::CONVERSIONS).unwrap_or(0);
+    let mut gauge = |name: &str, kind: &str, help: &str, value: f64| {
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+    };
+    gauge("eidetic_jobs_pending", "gauge", "Analysis jobs waiting in the worker's queue.", jobs.pending() as f64);
+    gauge("eidetic_jobs_paused", "gauge", "1 while background analysis is paused.", if jobs.is_paused() { 1.0 } else { 0.0 });
+    gauge("eidetic_jobs_failed", "gauge", "Recently failed analysis jobs.", failed as f64);
+    gauge("eidetic_indexed_files", "gauge", "Files analyzed at least once.", db.count_indexed_files().unwrap_or(0) as f64);
+    gauge("eidetic_converted_opens_total", "counter", "Opens of converted and translated files.", opens as f64);
+    gauge("eidetic_conversions_total", "counter", "Renderings made for the conversion cache.", conversions as f64);
+    if opens > 0 {
+        gauge("eidetic_conversion_cache_hit_ratio", "gauge", "Share of converted file opens served from the cache.", opens.saturating_sub(conversions) as f64 / opens as f64);
+    }
+    out
+}
+
+pub fn start(config: &MetricsConfig, source_path: PathBuf, jobs: JobQueue) {
+    if let Some(listen) = config.listen.clone() {
+        let (source_path, jobs) = (source_path.clone(), jobs.clone());
+        thread::spawn(move || {
+            if let Err(e) = serve(&listen, &source_path, &jobs) {
+                error!(%listen, error = %e, "metrics endpoint failed");
+            }
+        });
+    }
+    if let Some(textfile) = config.textfile.clone() {
+        thread::spawn(move || {
+            let Some(db) = open_db(&source_path) else { return };
+            loop {
+                // Written whole and renamed, so the collector never reads half a file
+                let partial = textfile.with_extension("prom.part");
+                let written = std::fs::write(&partial, render(&db, &jobs)).and_then(|_| std::fs::rename(&partial, &textfile));
+                if let Err(e) = written {
+                    warn!(?textfile, error = %e, "failed to write metrics textfile");
+                }
+                thread::sleep(TEXTFILE_INTERVAL);
+            }
+        });
+    }
+}
+
+fn open_db(source_path: &Path) -> Option<Database> {
+    Database::new(source_path.join(".eidetic.db")).map_err(|e| error!(error = %e, "failed to open DB")).ok()
+}
+
+// A minimal HTTP server: GET /metrics, one request per connection
+fn serve(listen: &str, source_path: &Path, jobs: &JobQueue) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen)?;
+    let Some(db) = open_db(source_path) else { return Ok(()) };
+    info!("serving http://{}/metrics", listen);
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let mut request_line = String::new();
+        if BufReader::new(&stream).read_line(&mut request_line).is_err() {
+            continue;
+        }
+        let response = match request_line.split_whitespace().nth(1) {
+            Some("/metrics") => {
+                let body = render(&db, jobs);
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+            }
+            _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+        };
+        let _ = stream.write_all(response.as_bytes());
+    }
+    Ok(())
+}
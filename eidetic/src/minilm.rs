@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert;
+
+// Sentence Embeddings
+// all-MiniLM-L6-v2, a sentence-transformer small enough to run on the CPU through candle,
+// turns each chunk into a 384-dimensional vector: the encoder's token outputs averaged,
+// then L2-normalized, as sentence-transformers does. Like the T5 summarizer it's only ever
+// downloaded by `eidetic model download`; without it the embedding stage stays off.
+
+/// Name under ~/.eidetic/models/ and in `eidetic model list`.
+pub const MODEL_NAME: &str = "all-minilm-l6-v2";
+
+/// Tokens per chunk, [CLS] and [SEP] included; the model was trained on 256.
+const MAX_TOKENS: usize = 256;
+
+/// Longer "words" (hashes, base64) are a single [UNK], as in BERT.
+const MAX_WORD_CHARS: usize = 100;
+
+/// Uncased WordPiece tokenizer read from a Hugging Face `tokenizer.json`.
+struct Tokenizer {
+    ids: HashMap<String, u32>,
+    unk_id: u32,
+    cls_id: u32,
+    sep_id: u32,
+}
+
+impl Tokenizer {
+    fn load(path: &Path) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_reader(std::fs::File::open(path)?)?;
+        let model = &json["model"];
+        if model["type"] != "WordPiece" {
+            return Err(anyhow!("Unsupported tokenizer type {}", model["type"]));
+        }
+        let vocab = model["vocab"].as_object().ok_or_else(|| anyhow!("Tokenizer has no vocab"))?;
+        let ids: HashMap<String, u32> = vocab.iter()
+            .filter_map(|(piece, id)| Some((piece.clone(), id.as_u64()? as u32)))
+            .collect();
+        let special = |token: &str| ids.get(token).copied().ok_or_else(|| anyhow!("Tokenizer has no {}", token));
+        Ok(Self { unk_id: special("[UNK]")?, cls_id: special("[CLS]")?, sep_id: special("[SEP]")?, ids })
+    }
+
+    // "[CLS] word ##piece ... [SEP]", cut at MAX_TOKENS
+    fn encode(&self, text: &str) -> Vec<u32> {
+        let mut ids = vec![self.cls_id];
+        let lowered = text.to_lowercase();
+        for word in words(&lowered) {
+            if ids.len() >= MAX_TOKENS - 1 {
+                break;
+            }
+            ids.extend(self.word_pieces(word));
+        }
+        ids.truncate(MAX_TOKENS - 1);
+        ids.push(self.sep_id);
+        ids
+    }
+
+    // Greedy longest-match-first, continuations prefixed with "##"
+    fn word_pieces(&self, word: &str) -> Vec<u32> {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() > MAX_WORD_CHARS {
+            return vec![self.unk_id];
+        }
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let found = (start + 1..=chars.len()).rev().find_map(|end| {
+                let piece: String = chars[start..end].iter().collect();
+                let piece = if start > 0 { format!("##{}", piece) } else { piece };
+                self.ids.get(&piece).map(|id| (end, *id))
+            });
+            match found {
+                Some((end, id)) => {
+                    pieces.push(id);
+                    start = end;
+                }
+                // A word with any unknown part is one [UNK]
+                None => return vec![self.unk_id],
+            }
+        }
+        pieces
+    }
+}
+
+// Whitespace separates words and every punctuation mark or symbol is a word of its own
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    for chunk in text.split_whitespace() {
+        let mut start = 0;
+        for (i, c) in chunk.char_indices() {
+            if !c.is_alphanumeric() {
+                if start < i {
+                    words.push(&chunk[start..i]);
+                }
+                words.push(&chunk[i..i + c.len_utf8()]);
+                start = i + c.len_utf8();
+            }
+        }
+        if start < chunk.len() {
+            words.push(&chunk[start..]);
+        }
+    }
+    words
+}
+
+/// Embeds text chunks with all-MiniLM-L6-v2.
+pub struct Embedder {
+    model: bert::BertModel,
+    tokenizer: Tokenizer,
+}
+
+impl Embedder {
+    /// Stored with every vector, so vectors from another model are never compared with these.
+    pub const MODEL: &'static str = "all-MiniLM-L6-v2";
+
+    pub fn new() -> Result<Self> {
+        if !crate::t5::is_downloaded(MODEL_NAME) {
+            return Err(anyhow!("{} isn't downloaded (run `eidetic model download {}`)", MODEL_NAME, MODEL_NAME));
+        }
+        let dir = crate::t5::models_dir().join(MODEL_NAME);
+        let config: bert::Config = serde_json::from_reader(std::fs::File::open(dir.join("config.json"))?)?;
+        let tokenizer = Tokenizer::load(&dir.join("tokenizer.json"))?;
+        // Safety: the weights file is ours and isn't modified while mapped
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DType::F32, &Device::Cpu)? };
+        let model = bert::BertModel::load(vb, &config)?;
+        Ok(Self { model, tokenizer })
+    }
+
+    pub fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let ids = self.tokenizer.encode(text);
+        let device = Device::Cpu;
+        let input = Tensor::new(ids.as_slice(), &device)?.unsqueeze(0)?;
+        let token_types = input.zeros_like()?;
+        // (1, tokens, 384) -> mean over the tokens
+        let output = self.model.forward(&input, &token_types, None)?;
+        let mut vector = output.mean(1)?.squeeze(0)?.to_vec1::<f32>()?;
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+        Ok(vector)
+    }
+}
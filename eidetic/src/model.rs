@@ -1,34 +1,105 @@
-use candle_core::{Tensor, Device};
-use candle_transformers::models::t5;
-use anyhow::Result;
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use crate::config::{AiBackend, AiConfig};
+
+// AI Backends
+// Summaries come from whichever backend `[ai]` in the config picks: the built-in heuristic
+// (default, no setup), a downloaded T5 run in-process (t5.rs), a local Ollama, or any
+// OpenAI-compatible chat endpoint (OpenAI, llama.cpp's llama-server, vLLM, LM Studio, ...).
+// The same backend answers the questions written to a directory's `.ask` file and
+// translates documents (`notes.de.txt` from `notes.txt`).
+
+/// Something that can summarize a document, answer questions about some context and translate.
+pub trait Backend: Send + Sync {
+    fn summarize(&self, text: &str) -> Result<String>;
+    fn ask(&self, context: &str, question: &str) -> Result<String>;
+    /// `language` is the target's English name, e.g. "German"
+    fn translate(&self, text: &str, language: &str) -> Result<String>;
+}
+
+/// Longest document excerpt sent to an LLM; most local models have small context windows.
+const MAX_PROMPT_CHARS: usize = 12_000;
+
+const SUMMARY_PROMPT: &str = "Summarize the following document in two or three sentences. Reply with the summary only.\n\n";
+
+fn summary_prompt(text: &str) -> String {
+    let excerpt: String = text.chars().take(MAX_PROMPT_CHARS).collect();
+    format!("{}{}", SUMMARY_PROMPT, excerpt)
+}
+
+/// Characters translated per request; longer documents go in pieces.
+const TRANSLATE_CHUNK_CHARS: usize = 4000;
+
+fn translate_prompt(text: &str, language: &str) -> String {
+    format!("Translate the following text into {}. Keep the formatting (Markdown, line breaks). Reply with the translation only.\n\n{}", language, text)
+}
+
+fn ask_prompt(context: &str, question: &str) -> String {
+    let excerpt: String = context.chars().take(MAX_PROMPT_CHARS).collect();
+    format!("Answer the question using only the files below. If they don't contain the answer, say so.\n\n{}\n\nQuestion: {}", excerpt, question)
+}
 
 pub struct Summarizer {
-    // In a real production app, we would hold the loaded model here.
-    // For this demonstration/prototype, we will simulate the behavior
-    // or use a very lightweight approach if possible.
-    // Full T5 loading requires significant memory and model file download strategies
-    // which are out of scope for a "first pass" production ready local FS without
-    // explicit user instruction to download 500MB+ files.
-    //
-    // However, to fulfill the promise of "AI Integration", we will setup the structure.
+    backend: Box<dyn Backend>,
 }
 
 impl Summarizer {
-    pub fn new() -> Result<Self> {
-        Ok(Self {})
+    pub fn new(config: &AiConfig) -> Result<Self> {
+        let client = || reqwest::blocking::Client::builder().timeout(Duration::from_secs(config.timeout_secs)).build();
+        let backend: Box<dyn Backend> = match config.backend {
+            AiBackend::Heuristic => Box::new(Heuristic),
+            AiBackend::T5 => Box::new(crate::t5::T5::new(config.model.as_deref().unwrap_or(crate::t5::DEFAULT_MODEL))),
+            AiBackend::Ollama => Box::new(Ollama {
+                client: client()?,
+                url: config.url.clone().unwrap_or_else(|| "http://localhost:11434".to_string()),
+                model: config.model.clone().context("[ai] model is required for the ollama backend")?,
+            }),
+            AiBackend::Openai | AiBackend::LlamaCpp => {
+                let default_url = if config.backend == AiBackend::LlamaCpp { "http://localhost:8080/v1" } else { "https://api.openai.com/v1" };
+                Box::new(OpenAi {
+                    client: client()?,
+                    url: config.url.clone().unwrap_or_else(|| default_url.to_string()),
+                    // llama-server serves whatever model it was started with and ignores this
+                    model: config.model.clone().unwrap_or_else(|| "default".to_string()),
+                    api_key: config.api_key.clone().or_else(|| std::env::var("OPENAI_API_KEY").ok()),
+                })
+            }
+        };
+        Ok(Self { backend })
     }
 
     pub fn summarize(&self, text: &str) -> Result<String> {
-        // Real implementation would:
-        // 1. Tokenize text
-        // 2. Run encoder
-        // 3. Generate tokens
-        // 4. Decode
-        
-        // For now, let's implement a heuristic summarizer to prove the pipeline works
-        // without crashing the users machine downloading models unexpectedly.
-        
-        let sentences: Vec<&str> = text.split(|c| c == '.' || c == '!' || c == '?').collect();
+        self.backend.summarize(text)
+    }
+
+    pub fn ask(&self, context: &str, question: &str) -> Result<String> {
+        self.backend.ask(context, question)
+    }
+
+    /// Translates in pieces of a few thousand characters so long documents fit the model's context.
+    pub fn translate(&self, text: &str, language: &str) -> Result<String> {
+        let pieces: Result<Vec<String>> = chunk_text(text, TRANSLATE_CHUNK_CHARS)
+            .into_iter()
+            .map(|(_, chunk)| self.backend.translate(chunk, language))
+            .collect();
+        Ok(pieces?.join("\n\n") + "\n")
+    }
+}
+
+/// Too common to tell sentences (or topics) apart
+pub const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "are", "was", "were", "has", "have", "does", "did",
+    "what", "when", "where", "which", "who", "why", "how", "there", "about", "into", "you", "your", "our",
+];
+
+/// First and last sentence. Needs no model at all.
+pub struct Heuristic;
+
+impl Backend for Heuristic {
+    fn summarize(&self, text: &str) -> Result<String> {
+        // Skip the empty fragment after a trailing period and PDF layout whitespace
+        let sentences: Vec<&str> = text.split(['.', '!', '?']).map(str::trim).filter(|s| !s.is_empty()).collect();
         let summary = if sentences.len() > 3 {
              format!("{}... {}", sentences[0].trim(), sentences.last().unwrap_or(&"").trim())
         } else {
@@ -37,4 +108,144 @@ impl Summarizer {
         
         Ok(format!("[AI-Verified] {}", summary))
     }
+
+    // The sentences sharing the most words with the question
+    fn ask(&self, context: &str, question: &str) -> Result<String> {
+        let words = |text: &str| -> std::collections::HashSet<String> {
+            text.split(|c: char| !c.is_alphanumeric())
+                .filter(|w| w.chars().count() > 2)
+                .map(str::to_lowercase)
+                .filter(|w| !STOPWORDS.contains(&w.as_str()))
+                .collect()
+        };
+        let asked = words(question);
+        let mut scored: Vec<(usize, &str)> = context.split(['.', '!', '?', '\n'])
+            .map(str::trim)
+            .filter(|s| !s.is_empty() && !s.starts_with("```"))
+            .map(|s| (words(s).intersection(&asked).count(), s))
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        if scored.is_empty() {
+            return Ok("Nothing in this directory seems to mention that.".to_string());
+        }
+        Ok(scored.iter().take(3).map(|(_, s)| format!("{}.", s)).collect::<Vec<_>>().join(" "))
+    }
+
+    fn translate(&self, _text: &str, _language: &str) -> Result<String> {
+        Err(anyhow!("Translation needs a model; set `backend` under [ai] in the config"))
+    }
+}
+
+/// Ollama's native API (`ollama serve`).
+pub struct Ollama {
+    client: reqwest::blocking::Client,
+    url: String,
+    model: String,
+}
+
+impl Ollama {
+    fn complete(&self, prompt: String) -> Result<String> {
+        crate::http::ensure_reachable(&self.url)?;
+        let body = json!({ "model": self.model, "prompt": prompt, "stream": false });
+        let response: Value = self.client.post(format!("{}/api/generate", self.url.trim_end_matches('/')))
+            .json(&body)
+            .send()
+            .with_context(|| format!("Ollama at {}", self.url))?
+            .error_for_status()?
+            .json()?;
+        reply_text(&response["response"])
+    }
+}
+
+impl Backend for Ollama {
+    fn summarize(&self, text: &str) -> Result<String> {
+        self.complete(summary_prompt(text))
+    }
+
+    fn ask(&self, context: &str, question: &str) -> Result<String> {
+        self.complete(ask_prompt(context, question))
+    }
+
+    fn translate(&self, text: &str, language: &str) -> Result<String> {
+        self.complete(translate_prompt(text, language))
+    }
+}
+
+/// `/chat/completions` of an OpenAI-compatible server.
+pub struct OpenAi {
+    client: reqwest::blocking::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl OpenAi {
+    fn complete(&self, prompt: String) -> Result<String> {
+        crate::http::ensure_reachable(&self.url)?;
+        let body = json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt }],
+        });
+        let mut request = self.client.post(format!("{}/chat/completions", self.url.trim_end_matches('/'))).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response: Value = request.send()
+            .with_context(|| format!("AI endpoint {}", self.url))?
+            .error_for_status()?
+            .json()?;
+        reply_text(&response["choices"][0]["message"]["content"])
+    }
+}
+
+impl Backend for OpenAi {
+    fn summarize(&self, text: &str) -> Result<String> {
+        self.complete(summary_prompt(text))
+    }
+
+    fn ask(&self, context: &str, question: &str) -> Result<String> {
+        self.complete(ask_prompt(context, question))
+    }
+
+    fn translate(&self, text: &str, language: &str) -> Result<String> {
+        self.complete(translate_prompt(text, language))
+    }
+}
+
+fn reply_text(value: &Value) -> Result<String> {
+    value.as_str()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Empty or malformed reply from the AI backend"))
+}
+
+/// Splits text into chunks of about `max_chars`, preferring paragraph and then word
+/// boundaries. Returns (byte offset, chunk) pairs.
+pub fn chunk_text(text: &str, max_chars: usize) -> Vec<(usize, &str)> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        // Separators between chunks belong to neither
+        let rest = text[start..].trim_start();
+        start = text.len() - rest.len();
+        if rest.is_empty() {
+            break;
+        }
+        // Byte offset of the max_chars-th character, or the end
+        let limit = rest.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(rest.len());
+        let end = if limit == rest.len() {
+            limit
+        } else {
+            let window = &rest[..limit];
+            window.rfind("\n\n").filter(|i| *i > 0)
+                .or_else(|| window.rfind(char::is_whitespace).filter(|i| *i > 0))
+                .unwrap_or(limit)
+        };
+        chunks.push((start, rest[..end].trim_end()));
+        start += end;
+    }
+    chunks
 }
@@ -0,0 +1,176 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use anyhow::Result;
+
+// Music Tags
+// Artist / album / title from ID3 (v2.2-2.4, falling back to v1) and Vorbis comments
+// (FLAC, Ogg Vorbis, Opus). Only the first MB of a file is read.
+
+/// Formats read_track understands.
+pub const MUSIC_FORMATS: &[&str] = &["mp3", "flac", "ogg", "oga", "opus"];
+
+const READ_LIMIT: u64 = 1 << 20;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TrackInfo {
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Tags of an audio file, or None if it has none.
+pub fn read_track(path: &Path) -> Result<Option<TrackInfo>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut data = Vec::new();
+    (&mut file).take(READ_LIMIT).read_to_end(&mut data)?;
+
+    let mut info = if data.starts_with(b"ID3") {
+        id3v2(&data)
+    } else if data.starts_with(b"fLaC") {
+        flac(&data)
+    } else if data.starts_with(b"OggS") {
+        ogg(&data)
+    } else {
+        TrackInfo::default()
+    };
+
+    // ID3v1 lives in the last 128 bytes
+    if info.artist.is_none() && info.title.is_none() && file.seek(SeekFrom::End(-128)).is_ok() {
+        let mut tail = [0u8; 128];
+        if file.read_exact(&mut tail).is_ok() && tail.starts_with(b"TAG") {
+            let field = |range: std::ops::Range<usize>| latin1(&tail[range]);
+            info = TrackInfo { title: field(3..33), artist: field(33..63), album: field(63..93) };
+        }
+    }
+    Ok((info != TrackInfo::default()).then_some(info))
+}
+
+fn id3v2(data: &[u8]) -> TrackInfo {
+    let mut info = TrackInfo::default();
+    let Some(header) = data.get(..10) else { return info };
+    let version = header[3];
+    let end = (10 + syncsafe(&header[6..10])).min(data.len());
+    // v2.2 has 3-byte frame ids and sizes, v2.3/2.4 have 4 plus two flag bytes
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    let mut pos = 10;
+    // Extended header (v2.3/2.4 only), rarely used
+    if version > 2 && header[5] & 0x40 != 0 {
+        let Some(size) = data.get(10..14) else { return info };
+        pos += if version == 4 { syncsafe(size) } else { u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize + 4 };
+    }
+
+    while pos + header_len <= end {
+        let id = &data[pos..pos + id_len];
+        if id[0] == 0 {
+            break; // padding
+        }
+        let size_bytes = &data[pos + id_len..pos + id_len * 2];
+        let size = match version {
+            2 => u32::from_be_bytes([0, size_bytes[0], size_bytes[1], size_bytes[2]]) as usize,
+            4 => syncsafe(size_bytes),
+            _ => u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]) as usize,
+        };
+        let body_start = pos + header_len;
+        let Some(body) = data.get(body_start..body_start + size) else { break };
+        let slot = match id {
+            b"TPE1" | b"TP1" => Some(&mut info.artist),
+            b"TALB" | b"TAL" => Some(&mut info.album),
+            b"TIT2" | b"TT2" => Some(&mut info.title),
+            _ => None,
+        };
+        if let Some(slot) = slot {
+            *slot = id3_text(body);
+        }
+        pos = body_start + size;
+    }
+    info
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().take(4).fold(0, |acc, b| (acc << 7) | (*b as usize & 0x7f))
+}
+
+// Text frame: one encoding byte, then the text (several values are NUL-separated; keep the first)
+fn id3_text(body: &[u8]) -> Option<String> {
+    let (&encoding, text) = body.split_first()?;
+    let text = match encoding {
+        0 => latin1(text)?,
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                _ => (encoding == 2, text),
+            };
+            let units: Vec<u16> = text.chunks_exact(2)
+                .map(|c| if big_endian { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+                .take_while(|u| *u != 0)
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text.split(|b| *b == 0).next().unwrap_or_default()).to_string(),
+    };
+    clean(&text)
+}
+
+fn latin1(bytes: &[u8]) -> Option<String> {
+    clean(&bytes.iter().take_while(|b| **b != 0).map(|b| *b as char).collect::<String>())
+}
+
+fn clean(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn flac(data: &[u8]) -> TrackInfo {
+    let mut pos = 4;
+    while let Some(header) = data.get(pos..pos + 4) {
+        let last = header[0] & 0x80 != 0;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        // Block type 4 is VORBIS_COMMENT
+        if header[0] & 0x7f == 4 {
+            return data.get(pos + 4..pos + 4 + len).map(vorbis_comments).unwrap_or_default();
+        }
+        if last {
+            break;
+        }
+        pos += 4 + len;
+    }
+    TrackInfo::default()
+}
+
+// The comment header is the second Ogg packet; it normally fits in the first pages we read
+fn ogg(data: &[u8]) -> TrackInfo {
+    for magic in [&b"\x03vorbis"[..], &b"OpusTags"[..]] {
+        if let Some(at) = data.windows(magic.len()).position(|w| w == magic) {
+            return vorbis_comments(&data[at + magic.len()..]);
+        }
+    }
+    TrackInfo::default()
+}
+
+// vendor string, then a count of "KEY=value" strings, all length-prefixed little-endian
+fn vorbis_comments(block: &[u8]) -> TrackInfo {
+    let mut info = TrackInfo::default();
+    let read_u32 = |pos: usize| block.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    let Some(vendor_len) = read_u32(0) else { return info };
+    let mut pos = 4 + vendor_len;
+    let Some(count) = read_u32(pos) else { return info };
+    pos += 4;
+    for _ in 0..count {
+        let Some(len) = read_u32(pos) else { break };
+        let Some(comment) = block.get(pos + 4..pos + 4 + len) else { break };
+        pos += 4 + len;
+        let comment = String::from_utf8_lossy(comment);
+        let Some((key, value)) = comment.split_once('=') else { continue };
+        let slot = match key.to_uppercase().as_str() {
+            "ARTIST" => &mut info.artist,
+            "ALBUM" => &mut info.album,
+            "TITLE" => &mut info.title,
+            _ => continue,
+        };
+        if slot.is_none() {
+            *slot = clean(value);
+        }
+    }
+    info
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+// Rename Suggestions
+// Cameras, scanners and browsers hand out names like IMG_2031.png, scan0004.pdf or
+// document(3).pdf. For those the worker proposes something descriptive (the document's
+// title line, or a photo's capture date and camera); `.magic/rename_suggestions/` lists the
+// proposals and mv-ing one onto its original applies it. Nothing is renamed on its own.
+
+/// Words that say nothing about the content. A name made of only these, digits and separators is generic.
+const GENERIC_WORDS: &[&str] = &[
+    "img", "image", "dsc", "dscn", "dscf", "dcim", "pxl", "photo", "pic", "picture", "screenshot", "screen",
+    "shot", "at", "scan", "document", "doc", "file", "untitled", "download", "new", "unnamed", "copy",
+];
+
+/// Words kept from a title line.
+const MAX_WORDS: usize = 8;
+
+/// Whether `name` is a camera/scanner/browser default worth suggesting a replacement for.
+pub fn is_generic(name: &str) -> bool {
+    let stem = Path::new(name).file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+    // "document(3)", "IMG_2031 copy", "Screenshot 2024-01-02 at 10.00.00"
+    stem.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .all(|word| GENERIC_WORDS.contains(&word))
+}
+
+/// A descriptive name for a file from its text: the first line that reads like a title.
+pub fn from_text(original: &Path, text: &str) -> Option<String> {
+    let title = text.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| line.chars().filter(|c| c.is_alphabetic()).count() >= 3)?;
+    let words: Vec<String> = title.split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric() || "-&'".contains(*c)).collect::<String>())
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .take(MAX_WORDS)
+        .collect();
+    with_extension(original, &words.join(" "))
+}
+
+/// A photo's name from its EXIF capture date ("2024-05-03 12:00:00") and camera.
+pub fn from_photo(original: &Path, taken: Option<&str>, camera: Option<&str>) -> Option<String> {
+    let date = taken.filter(|t| t.len() >= 10).map(|t| &t[..10])?;
+    let name = match camera {
+        Some(camera) => format!("{} {}", date, camera.replace('/', "_")),
+        None => date.to_string(),
+    };
+    with_extension(original, &name)
+}
+
+fn with_extension(original: &Path, stem: &str) -> Option<String> {
+    let stem = stem.trim();
+    if stem.is_empty() {
+        return None;
+    }
+    Some(match original.extension() {
+        Some(ext) => format!("{}.{}", stem, ext.to_string_lossy()),
+        None => stem.to_string(),
+    })
+}
@@ -0,0 +1,77 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::config::NotificationsConfig;
+use tracing::warn;
+
+// Desktop Notifications
+// Optional popups for what the daemon does without being asked: tags the worker added to a
+// file, files the sensitive data scanner flagged, and organizer moves. Off by default. A first
+// scan touches every file, so popups are at least MIN_INTERVAL apart; the ones held back in
+// between are counted into the next ("…and 12 more"). Each popup is sent from a thread of
+// its own: organizer moves happen inside a FUSE call, which mustn't wait on D-Bus.
+
+const MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+struct Pacing {
+    last_shown: Option<Instant>,
+    held_back: usize,
+}
+
+// Shared by the worker's and the mount's notifiers, so both count against the same pace
+static PACING: Mutex<Pacing> = Mutex::new(Pacing { last_shown: None, held_back: 0 });
+
+pub struct Notifier {
+    config: NotificationsConfig,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationsConfig) -> Self {
+        Self { config: config.clone() }
+    }
+
+    /// After the worker analyzed `path`: the tags it didn't have before, and whether it was just flagged.
+    pub fn analyzed(&self, path: &str, new_tags: &[String], flagged: bool) {
+        if flagged && self.config.sensitive {
+            self.show("Sensitive data found", &format!("{} was tagged sensitive", path));
+        } else if !new_tags.is_empty() && self.config.tags {
+            self.show("Tagged", &format!("{}: {}", path, new_tags.join(", ")));
+        }
+    }
+
+    pub fn moved(&self, from: &str, to: &str) {
+        if self.config.moves {
+            self.show("Moved", &format!("{} → {}", from, to));
+        }
+    }
+
+    fn show(&self, summary: &str, body: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        let held_back = {
+            let mut pacing = PACING.lock().unwrap();
+            if pacing.last_shown.is_some_and(|at| at.elapsed() < MIN_INTERVAL) {
+                pacing.held_back += 1;
+                return;
+            }
+            pacing.last_shown = Some(Instant::now());
+            std::mem::take(&mut pacing.held_back)
+        };
+        let body = match held_back {
+            0 => body.to_string(),
+            n => format!("{}\n…and {} more", body, n),
+        };
+        let summary = summary.to_string();
+        thread::spawn(move || {
+            let shown = notify_rust::Notification::new()
+                .appname("Eidetic")
+                .summary(&summary)
+                .body(&body)
+                .show();
+            if let Err(e) = shown {
+                warn!(summary = %summary, error = %e, "desktop notification failed");
+            }
+        });
+    }
+}
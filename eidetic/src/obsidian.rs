@@ -0,0 +1,147 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Result};
+use crate::db::Database;
+
+// Obsidian Vault Export
+// `eidetic export --obsidian <dir>` copies the Markdown notes into a vault with what the
+// worker learned added: tags in the YAML frontmatter (merged with the note's own, entity
+// tags like `person:alice-smith` become nested `person/alice-smith`), the summary as a
+// callout at the top and the notes linking to it at the bottom. Summarized documents get a
+// companion note, `paper.pdf.md`. Sync is one-way: the vault copies are rewritten on every
+// export, so edit the notes in the Source folder.
+
+/// Heading of the backlinks section added to each note.
+const BACKLINKS_HEADING: &str = "## Backlinks (Eidetic)";
+
+/// Writes the vault to `out_dir`; returns a one-line summary.
+pub fn export(db: &Database, source_path: &Path, out_dir: &Path) -> Result<String> {
+    if out_dir.starts_with(source_path) {
+        bail!("Export to a folder outside the Source folder, or the vault gets indexed too");
+    }
+    let (mut notes, mut documents) = (0, 0);
+    for file in db.get_indexed_files()? {
+        let Some(source) = db.get_path(file.inode)? else { continue };
+        let summary = db.get_summary(file.inode)?;
+        let tags: Vec<String> = file.tags.iter().map(|tag| tag_name(tag)).collect();
+        let (frontmatter, body, target) = if crate::links::is_note(&file.name) {
+            let Ok(markdown) = fs::read_to_string(source_path.join(&source)) else { continue };
+            let (frontmatter, body) = split_frontmatter(&markdown);
+            notes += 1;
+            (frontmatter.to_string(), body.to_string(), source.clone())
+        } else if summary.is_some() {
+            let link = format!("[{}](<file://{}>)", file.name, source_path.join(&source).display());
+            documents += 1;
+            (String::new(), format!("# {}\n\n{}\n", file.name, link), format!("{}.md", source))
+        } else {
+            continue;
+        };
+
+        let mut content = with_tags(&frontmatter, &tags);
+        if let Some(summary) = summary {
+            content.push_str("> [!summary] Summary (Eidetic)\n");
+            for line in summary.lines() {
+                content.push_str(&format!("> {}\n", line));
+            }
+            content.push('\n');
+        }
+        content.push_str(body.trim_end());
+        content.push('\n');
+        let backlinks = backlinks(db, file.inode, &source)?;
+        if !backlinks.is_empty() {
+            content.push_str(&format!("\n{}\n", BACKLINKS_HEADING));
+            for note in backlinks {
+                content.push_str(&format!("- [[{}]]\n", note));
+            }
+        }
+
+        let path = out_dir.join(&target);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    if notes + documents == 0 {
+        bail!("Nothing to export: no notes or summaries in the index yet");
+    }
+    Ok(format!("Exported {} notes and {} document summaries to {}\n", notes, documents, out_dir.display()))
+}
+
+// "lang:de" -> "lang/de"; Obsidian tags are letters, digits, `_`, `-` and `/` for nesting
+fn tag_name(tag: &str) -> String {
+    tag.chars()
+        .map(|c| match c {
+            ':' => '/',
+            c if c.is_alphanumeric() || c == '_' || c == '-' || c == '/' => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+// (YAML between the `---` lines without them, the rest)
+fn split_frontmatter(markdown: &str) -> (&str, &str) {
+    let Some(rest) = markdown.strip_prefix("---\n").or_else(|| markdown.strip_prefix("---\r\n")) else {
+        return ("", markdown);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return (&rest[..offset], &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    ("", markdown)
+}
+
+// The frontmatter block with `tags` replaced by the note's own tags plus `tags`
+fn with_tags(frontmatter: &str, tags: &[String]) -> String {
+    let mut kept = Vec::new();
+    let mut all: BTreeSet<String> = tags.iter().cloned().collect();
+    let mut in_tags = false;
+    for line in frontmatter.lines() {
+        if let Some(value) = line.strip_prefix("tags:") {
+            in_tags = true;
+            // `tags: [a, b]` or `tags: a, b`
+            all.extend(value.trim().trim_matches(['[', ']']).split(',').map(|tag| tag.trim().trim_matches(['"', '\'']).trim_start_matches('#').to_string()).filter(|tag| !tag.is_empty()));
+            continue;
+        }
+        if in_tags {
+            // `  - a` items of a block list
+            if let Some(item) = line.trim_start().strip_prefix('-') {
+                all.insert(item.trim().trim_matches(['"', '\'']).trim_start_matches('#').to_string());
+                continue;
+            }
+            in_tags = false;
+        }
+        kept.push(line);
+    }
+    if kept.is_empty() && all.is_empty() {
+        return String::new();
+    }
+    let mut content = String::from("---\n");
+    for line in kept {
+        content.push_str(line);
+        content.push('\n');
+    }
+    if !all.is_empty() {
+        content.push_str("tags:\n");
+        for tag in all {
+            content.push_str(&format!("  - {}\n", tag));
+        }
+    }
+    content.push_str("---\n");
+    content
+}
+
+// Names of the notes linking to the file at `rel_path`, as Obsidian links them
+fn backlinks(db: &Database, inode: u64, rel_path: &str) -> Result<Vec<String>> {
+    let mut names: Vec<String> = db.get_linking_notes(&crate::links::targets_of(rel_path))?
+        .into_iter()
+        .filter(|source| *source != inode)
+        .filter_map(|source| db.get_path(source).ok().flatten())
+        .map(|path| path.rsplit_once('.').map_or(path.as_str(), |(stem, _)| stem).to_string())
+        .collect();
+    names.sort();
+    Ok(names)
+}
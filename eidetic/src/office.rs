@@ -0,0 +1,304 @@
+use std::io::Read;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+
+// Office Documents -> Markdown
+// DOCX and ODT are zip files with an XML body. We only need text and coarse structure
+// (headings, lists, bold/italic, tables), so a small tag scanner does instead of a full XML parser.
+
+pub fn to_markdown(source: &Path) -> Result<String> {
+    let ext = source.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(source)?)?;
+    let entry = match ext.as_str() {
+        "docx" => "word/document.xml",
+        "odt" => "content.xml",
+        _ => return Err(anyhow!("Not an office document: {:?}", source)),
+    };
+    let mut xml = String::new();
+    archive.by_name(entry)
+        .map_err(|_| anyhow!("{:?} has no {}", source, entry))?
+        .read_to_string(&mut xml)?;
+    Ok(if ext == "docx" { docx(&xml) } else { odt(&xml) })
+}
+
+/// A piece of XML as the tag scanner sees it (also used for feeds, see feeds.rs).
+pub enum Token<'a> {
+    Start { name: &'a str, attrs: &'a str, empty: bool },
+    End(&'a str),
+    Text(String),
+}
+
+pub fn tokens(xml: &str) -> Vec<Token<'_>> {
+    let mut out = Vec::new();
+    let mut rest = xml;
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            out.push(Token::Text(unescape(rest)));
+            break;
+        };
+        if open > 0 {
+            out.push(Token::Text(unescape(&rest[..open])));
+        }
+        rest = &rest[open..];
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            out.push(Token::Text(cdata[..end].to_string()));
+            rest = cdata.get(end + 3..).unwrap_or("");
+            continue;
+        }
+        let close = if rest.starts_with("<!--") { rest.find("-->").map(|i| i + 2) } else { rest.find('>') };
+        let Some(close) = close else { break };
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            out.push(Token::End(name.trim()));
+            continue;
+        }
+        let empty = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        out.push(Token::Start { name, attrs, empty });
+    }
+    out
+}
+
+pub fn attr(attrs: &str, key: &str) -> Option<String> {
+    let start = attrs.find(&format!("{}=", key))? + key.len() + 1;
+    let quote = attrs[start..].chars().next()?;
+    let value = &attrs[start + 1..];
+    Some(unescape(&value[..value.find(quote)?]))
+}
+
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else { break };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity.strip_prefix("#x").and_then(|h| u32::from_str_radix(h, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Accumulates paragraphs and table rows into Markdown.
+#[derive(Default)]
+struct Writer {
+    out: String,
+    paragraph: String,
+    heading: usize,
+    list_level: Option<usize>,
+    row: Option<Vec<String>>,
+    rows_in_table: usize,
+}
+
+impl Writer {
+    fn text(&mut self, text: &str) {
+        match self.row.as_mut().and_then(|cells| cells.last_mut()) {
+            Some(cell) => cell.push_str(text),
+            None => self.paragraph.push_str(text),
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        if let Some(cell) = self.row.as_mut().and_then(|cells| cells.last_mut()) {
+            // Several paragraphs in one cell share the cell's line
+            if !cell.is_empty() && !cell.ends_with(' ') {
+                cell.push(' ');
+            }
+            return;
+        }
+        let text = std::mem::take(&mut self.paragraph);
+        let text = text.trim();
+        if !text.is_empty() {
+            if self.heading > 0 {
+                self.out.push_str(&format!("{} {}\n\n", "#".repeat(self.heading.min(6)), text));
+            } else if let Some(level) = self.list_level {
+                self.out.push_str(&format!("{}- {}\n", "  ".repeat(level), text));
+            } else {
+                self.out.push_str(&format!("{}\n\n", text));
+            }
+        }
+        self.heading = 0;
+        self.list_level = None;
+    }
+
+    fn start_row(&mut self) {
+        self.row = Some(Vec::new());
+    }
+
+    fn start_cell(&mut self) {
+        if let Some(cells) = self.row.as_mut() {
+            cells.push(String::new());
+        }
+    }
+
+    fn end_row(&mut self) {
+        let Some(cells) = self.row.take() else { return };
+        let cells: Vec<String> = cells.iter().map(|c| c.trim().replace('|', "\\|")).collect();
+        self.out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        if self.rows_in_table == 0 {
+            self.out.push_str(&format!("|{}\n", " --- |".repeat(cells.len())));
+        }
+        self.rows_in_table += 1;
+    }
+
+    fn end_table(&mut self) {
+        self.rows_in_table = 0;
+        self.out.push('\n');
+    }
+
+    // Lists end without a blank line, separate them from what follows
+    fn finish(mut self) -> String {
+        self.end_paragraph();
+        let mut out = String::new();
+        let mut previous_was_item = false;
+        for line in self.out.lines() {
+            let is_item = line.trim_start().starts_with("- ");
+            if previous_was_item && !is_item && !line.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(line);
+            out.push('\n');
+            previous_was_item = is_item;
+        }
+        out.trim_end().to_string() + "\n"
+    }
+}
+
+fn docx(xml: &str) -> String {
+    let mut writer = Writer::default();
+    let mut in_text = false;
+    let mut in_run_props = false;
+    let (mut bold, mut italic) = (false, false);
+
+    // `<w:b/>` switches on, `<w:b w:val="0"/>` off
+    let toggle = |attrs: &str| !matches!(attr(attrs, "w:val").as_deref(), Some("0") | Some("false"));
+
+    for token in tokens(xml) {
+        match token {
+            Token::Start { name, attrs, empty } => match name {
+                "w:p" if !empty => {}
+                "w:p" => writer.end_paragraph(),
+                "w:pStyle" => {
+                    let style = attr(attrs, "w:val").unwrap_or_default().to_lowercase();
+                    if style == "title" {
+                        writer.heading = 1;
+                    } else if let Some(level) = style.strip_prefix("heading").and_then(|l| l.parse().ok()) {
+                        writer.heading = level;
+                    }
+                }
+                "w:numPr" => writer.list_level = Some(writer.list_level.unwrap_or(0)),
+                "w:ilvl" => writer.list_level = attr(attrs, "w:val").and_then(|l| l.parse().ok()),
+                "w:r" => {
+                    bold = false;
+                    italic = false;
+                }
+                "w:rPr" => in_run_props = !empty,
+                "w:b" if in_run_props => bold = toggle(attrs),
+                "w:i" if in_run_props => italic = toggle(attrs),
+                "w:t" => in_text = !empty,
+                "w:tab" => writer.text("\t"),
+                "w:br" | "w:cr" => writer.text("\n"),
+                "w:tr" => writer.start_row(),
+                "w:tc" => writer.start_cell(),
+                _ => {}
+            },
+            Token::End(name) => match name {
+                "w:p" => writer.end_paragraph(),
+                "w:rPr" => in_run_props = false,
+                "w:t" => in_text = false,
+                "w:tr" => writer.end_row(),
+                "w:tbl" => writer.end_table(),
+                _ => {}
+            },
+            Token::Text(text) if in_text && !text.is_empty() => {
+                let marker = match (bold, italic) {
+                    (true, true) => "***",
+                    (true, false) => "**",
+                    (false, true) => "*",
+                    (false, false) => "",
+                };
+                // Markers only hug non-space text, otherwise Markdown ignores them
+                if marker.is_empty() || text.trim().is_empty() {
+                    writer.text(&text);
+                } else {
+                    let leading = &text[..text.len() - text.trim_start().len()];
+                    let trailing = &text[text.trim_end().len()..];
+                    writer.text(&format!("{}{}{}{}{}", leading, marker, text.trim(), marker, trailing));
+                }
+            }
+            Token::Text(_) => {}
+        }
+    }
+    writer.finish()
+}
+
+fn odt(xml: &str) -> String {
+    let mut writer = Writer::default();
+    let mut list_depth = 0usize;
+    let mut in_body = false;
+
+    for token in tokens(xml) {
+        match token {
+            Token::Start { name, attrs, empty } => match name {
+                "office:body" => in_body = true,
+                "text:h" => {
+                    writer.heading = attr(attrs, "text:outline-level").and_then(|l| l.parse().ok()).unwrap_or(1);
+                    if empty {
+                        writer.end_paragraph();
+                    }
+                }
+                "text:p" if empty => writer.end_paragraph(),
+                "text:list" if !empty => list_depth += 1,
+                "text:list-item" => writer.list_level = Some(list_depth.saturating_sub(1)),
+                "text:s" => {
+                    let count = attr(attrs, "text:c").and_then(|c| c.parse().ok()).unwrap_or(1);
+                    writer.text(&" ".repeat(count));
+                }
+                "text:tab" => writer.text("\t"),
+                "text:line-break" => writer.text("\n"),
+                "table:table-row" => writer.start_row(),
+                "table:table-cell" => writer.start_cell(),
+                _ => {}
+            },
+            Token::End(name) => match name {
+                "text:h" | "text:p" => writer.end_paragraph(),
+                "text:list" => list_depth = list_depth.saturating_sub(1),
+                "table:table-row" => writer.end_row(),
+                "table:table" => writer.end_table(),
+                _ => {}
+            },
+            Token::Text(text) if in_body => writer.text(&text),
+            Token::Text(_) => {}
+        }
+    }
+    writer.finish()
+}
@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use s3::creds::Credentials;
+use s3::{Bucket, Region};
+use crate::config::S3Config;
+use crate::db::Database;
+use tracing::{error, info, warn};
+
+// Offsite History
+// With `[s3] bucket` set, every version history snapshot is copied to an S3-compatible bucket
+// (AWS, MinIO, Backblaze, …), so versions survive losing the disk. `.eidetic/history` stays
+// the write-through cache: snapshots are taken there as before and uploaded in the background
+// every UPLOAD_INTERVAL, and once uploaded the oldest local copies are deleted beyond
+// `cache_mb`. Keys name the file and the time, `<prefix>history/<path>/<unix time>`, so the
+// bucket is readable without the database and `eidetic history-pull` can bring everything
+// back onto a fresh disk.
+
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn start(config: &S3Config, source_path: PathBuf) {
+    if config.bucket.is_none() {
+        return;
+    }
+    let config = config.clone();
+    thread::spawn(move || {
+        let (bucket, runtime) = match connect(&config) {
+            Ok(connected) => connected,
+            Err(e) => {
+                error!(error = %e, "S3 history upload disabled");
+                return;
+            }
+        };
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        loop {
+            // Offline, snapshots stay in the local cache until they can be uploaded
+            if crate::http::offline() {
+                thread::sleep(UPLOAD_INTERVAL);
+                continue;
+            }
+            let uploaded = upload(&db, &bucket, &runtime, &config.prefix);
+            if uploaded > 0 {
+                info!(uploaded, "snapshots uploaded to S3");
+                trim(&db, &source_path.join(".eidetic/history"), config.cache_mb);
+            }
+            thread::sleep(UPLOAD_INTERVAL);
+        }
+    });
+}
+
+// The bucket, and a runtime to drive its requests from this synchronous code
+fn connect(config: &S3Config) -> Result<(Box<Bucket>, tokio::runtime::Runtime)> {
+    let name = config.bucket.as_deref().context("No [s3] bucket configured")?;
+    let region = match &config.endpoint {
+        Some(endpoint) => Region::Custom { region: config.region.clone(), endpoint: endpoint.clone() },
+        None => config.region.parse()?,
+    };
+    // Without keys in the config, $AWS_ACCESS_KEY_ID and $AWS_SECRET_ACCESS_KEY
+    let credentials = Credentials::new(config.access_key.as_deref(), config.secret_key.as_deref(), None, None, None)?;
+    let bucket = Bucket::new(name, region, credentials)?.with_path_style();
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    Ok((bucket, runtime))
+}
+
+fn key(prefix: &str, path: &str, timestamp: i64) -> String {
+    format!("{}history/{}/{}", prefix, path, timestamp)
+}
+
+// Uploads the snapshots not in the bucket yet; returns how many were
+fn upload(db: &Database, bucket: &Bucket, runtime: &tokio::runtime::Runtime, prefix: &str) -> usize {
+    let mut uploaded = 0;
+    for (id, inode, timestamp, snapshot) in db.history_to_upload().unwrap_or_default() {
+        // Deleted since (trimmed copies are always uploaded already)
+        let Ok(content) = std::fs::read(&snapshot) else { continue };
+        let path = db.get_path(inode).ok().flatten()
+            .unwrap_or_else(|| format!("unknown/{}", Path::new(&snapshot).file_name().unwrap_or_default().to_string_lossy()));
+        match runtime.block_on(bucket.put_object(key(prefix, &path, timestamp), &content)) {
+            Ok(_) => {
+                let _ = db.set_history_uploaded(id);
+                uploaded += 1;
+            }
+            Err(e) => {
+                // Offline or misconfigured; the next round retries
+                warn!(%snapshot, error = %e, "S3 upload failed");
+                break;
+            }
+        }
+    }
+    uploaded
+}
+
+// Deletes the oldest uploaded local copies until the history folder fits in `cache_mb`
+fn trim(db: &Database, history_dir: &Path, cache_mb: u64) {
+    if cache_mb == 0 {
+        return;
+    }
+    let mut total: u64 = std::fs::read_dir(history_dir).into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    for snapshot in db.uploaded_history().unwrap_or_default() {
+        if total <= cache_mb * 1024 * 1024 {
+            break;
+        }
+        if let Ok(metadata) = std::fs::metadata(&snapshot) {
+            if std::fs::remove_file(&snapshot).is_ok() {
+                total = total.saturating_sub(metadata.len());
+            }
+        }
+    }
+}
+
+/// Downloads the snapshots missing locally (all, or those of files under `under`), e.g. onto
+/// a new disk or after the cache trimmed them. Returns a report of what was fetched.
+pub fn pull(config: &S3Config, db: &Database, source_path: &Path, under: Option<&str>) -> Result<String> {
+    crate::http::ensure_online()?;
+    let (bucket, runtime) = connect(config)?;
+    let history_dir = source_path.join(".eidetic/history");
+    std::fs::create_dir_all(&history_dir)?;
+    let listing = runtime.block_on(bucket.list(format!("{}history/{}", config.prefix, under.unwrap_or("")), None))?;
+
+    let (mut fetched, mut present) = (0, 0);
+    for object in listing.into_iter().flat_map(|page| page.contents) {
+        let Some(rest) = object.key.strip_prefix(&format!("{}history/", config.prefix)) else { continue };
+        let Some((path, timestamp)) = rest.rsplit_once('/').and_then(|(path, time)| Some((path, time.parse::<i64>().ok()?))) else { continue };
+        if path.starts_with("unknown/") {
+            continue;
+        }
+        let inode = db.alloc_path(path)?;
+        let recorded = db.history_at(inode, timestamp)?;
+        if recorded.as_ref().is_some_and(|snapshot| Path::new(snapshot).exists()) {
+            present += 1;
+            continue;
+        }
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let local = match &recorded {
+            Some(snapshot) => PathBuf::from(snapshot),
+            None => history_dir.join(format!("{}_{}_{}", inode, timestamp, name)),
+        };
+        let response = runtime.block_on(bucket.get_object(&object.key))?;
+        std::fs::write(&local, response.bytes()).with_context(|| format!("Failed to write {:?}", local))?;
+        if recorded.is_none() {
+            db.restore_history(inode, timestamp, &local.to_string_lossy())?;
+        }
+        fetched += 1;
+    }
+    Ok(format!("Downloaded {} snapshot(s), {} already here\n", fetched, present))
+}
@@ -0,0 +1,243 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use crate::db::Database;
+
+// Organizer Rules
+// ~/.eidetic/organize.toml lists where files belong. Every condition a rule sets (tag,
+// extension, name, content) has to match; the first matching rule's destination, a folder
+// template like "Finance/{year}/", becomes a proposal in `.magic/proposals/`. Without the
+// file the single built-in rule applies: names containing "invoice" go to a Finance/
+// folder next to them.
+//
+//   [[rule]]
+//   tag = "finance"
+//   extension = ["pdf", "txt"]
+//   name = "invoice|receipt"          # regex, case-insensitive
+//   content = "total|amount due"      # regex over the first MAX_CONTENT bytes of text files
+//   destination = "Finance/{year}/"
+//
+// Templates may use {dir} (the file's current folder), {name}, {stem}, {ext}, and {year},
+// {month}, {day} from the photo's capture date or else the file's modification time.
+// Accepted moves are logged (`.magic/organizer_log.md`) and `eidetic organize undo` puts
+// files back where they came from.
+
+/// Bytes of a text file the `content` condition looks at.
+const MAX_CONTENT: u64 = 1024 * 1024;
+
+const DEFAULT_RULES: &str = r#"
+[[rule]]
+name = "invoice"
+destination = "{dir}/Finance/"
+"#;
+
+#[derive(Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    rule: Vec<RawRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    tag: Option<String>,
+    #[serde(default)]
+    extension: Vec<String>,
+    name: Option<String>,
+    content: Option<String>,
+    destination: String,
+}
+
+struct Rule {
+    tag: Option<String>,
+    extensions: Vec<String>,
+    name: Option<Regex>,
+    content: Option<Regex>,
+    destination: String,
+}
+
+#[derive(Default)]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    pub fn path() -> PathBuf {
+        crate::config::Config::path().with_file_name("organize.toml")
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Self::parse(DEFAULT_RULES);
+        }
+        let text = std::fs::read_to_string(&path)?;
+        Self::parse(&text).with_context(|| format!("Invalid organizer rules {:?}", path))
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let file: RuleFile = toml::from_str(text)?;
+        let regex = |pattern: &str| RegexBuilder::new(pattern).case_insensitive(true).build();
+        let rules = file.rule.into_iter()
+            .map(|raw| Ok(Rule {
+                tag: raw.tag,
+                extensions: raw.extension.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect(),
+                name: raw.name.as_deref().map(regex).transpose()?,
+                content: raw.content.as_deref().map(regex).transpose()?,
+                destination: raw.destination,
+            }))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self(rules))
+    }
+
+    /// Source-relative path the file at `rel_path` should move to, if a rule wants it elsewhere.
+    pub fn destination(&self, db: &Database, inode: u64, rel_path: &str, path: &Path) -> Option<String> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let tags = db.get_file_tags(inode).unwrap_or_default();
+        // Read once, and only if a rule asks for it
+        let mut content: Option<Option<String>> = None;
+
+        let rule = self.0.iter().find(|rule| {
+            rule.tag.as_ref().is_none_or(|tag| tags.contains(tag))
+                && (rule.extensions.is_empty() || rule.extensions.contains(&ext))
+                && rule.name.as_ref().is_none_or(|re| re.is_match(&name))
+                && rule.content.as_ref().is_none_or(|re| {
+                    content.get_or_insert_with(|| read_text(path)).as_deref().is_some_and(|text| re.is_match(text))
+                })
+        })?;
+
+        let dir = rel_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        // "{dir}/Finance/" is relative: a file already in a Finance folder stays put
+        if let Some(relative) = rule.destination.strip_prefix("{dir}") {
+            let folder = normalize(&render(relative, db, inode, rel_path, path));
+            if !folder.is_empty() && (dir == folder || dir.ends_with(&format!("/{}", folder))) {
+                return None;
+            }
+        }
+        let destination = normalize(&format!("{}/{}", render(&rule.destination, db, inode, rel_path, path), name));
+        (destination != rel_path && !destination.split('/').any(|part| part == "..")).then_some(destination)
+    }
+}
+
+// Without empty and "." components
+fn normalize(path: &str) -> String {
+    path.split('/').filter(|part| !part.is_empty() && *part != ".").collect::<Vec<_>>().join("/")
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    use std::io::Read;
+    let mut bytes = Vec::new();
+    std::fs::File::open(path).ok()?.take(MAX_CONTENT).read_to_end(&mut bytes).ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn render(template: &str, db: &Database, inode: u64, rel_path: &str, path: &Path) -> String {
+    let (year, month, day) = file_date(db, inode, path);
+    template
+        .replace("{dir}", rel_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or(""))
+        .replace("{name}", &path.file_name().unwrap_or_default().to_string_lossy())
+        .replace("{stem}", &path.file_stem().unwrap_or_default().to_string_lossy())
+        .replace("{ext}", &path.extension().unwrap_or_default().to_string_lossy())
+        .replace("{year}", &format!("{:04}", year))
+        .replace("{month}", &format!("{:02}", month))
+        .replace("{day}", &format!("{:02}", day))
+}
+
+// Capture date of a photo ("2024-05-03 12:00:00"), else the modification date
+fn file_date(db: &Database, inode: u64, path: &Path) -> (i64, u32, u32) {
+    let taken = db.get_photo_taken(inode).ok().flatten().and_then(|taken| {
+        let mut parts = taken.get(..10)?.split('-').map(|p| p.parse::<i64>().ok());
+        Some((parts.next()??, parts.next()?? as u32, parts.next()?? as u32))
+    });
+    taken.unwrap_or_else(|| {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
+        let secs = modified.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        civil_date(secs.div_euclid(86400))
+    })
+}
+
+// (year, month, day) of a day count since 1970-01-01, after Howard Hinnant's civil_from_days
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Markdown list of the organizer's moves, newest first.
+pub fn log(db: &Database) -> String {
+    let moves = db.organizer_moves(0).unwrap_or_default();
+    let mut content = String::from("# Organizer Log\n\n");
+    if moves.is_empty() {
+        content.push_str("_No files moved yet._\n");
+        return content;
+    }
+    for m in moves {
+        let undone = m.undone.map(|when| format!(" (undone {})", when)).unwrap_or_default();
+        content.push_str(&format!("- {} `{}` -> `{}`{}\n", m.moved, m.from, m.to, undone));
+    }
+    content
+}
+
+/// Moves files back to where they were before the organizer moved them: every move since
+/// `since` (unix time), or only the latest one. Returns a Markdown report.
+pub fn undo(db: &Database, source_path: &Path, since: Option<i64>) -> String {
+    let moves = db.organizer_moves(since.unwrap_or(0)).unwrap_or_default();
+    let moves: Vec<_> = moves.into_iter().filter(|m| m.undone.is_none()).take(if since.is_some() { usize::MAX } else { 1 }).collect();
+    if moves.is_empty() {
+        return "Nothing to undo.\n".to_string();
+    }
+    let mut content = String::from("# Undo\n\n");
+    for m in moves {
+        let outcome = match db.get_path(m.inode) {
+            Ok(Some(current)) if current == m.to => {
+                let (from, to) = (source_path.join(&m.from), source_path.join(&m.to));
+                if from.exists() {
+                    Err(format!("{} exists again", m.from))
+                } else {
+                    let moved = from.parent().map_or(Ok(()), std::fs::create_dir_all)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|_| db.move_to_path(m.inode, &m.from, || std::fs::rename(&to, &from)));
+                    moved.map_err(|e| e.to_string())
+                }
+            }
+            Ok(Some(current)) => Err(format!("moved again since, now at {}", current)),
+            _ => Err("deleted since".to_string()),
+        };
+        match outcome {
+            Ok(()) => {
+                let _ = db.mark_move_undone(m.id);
+                // Don't propose the same move again
+                let _ = db.set_proposal(m.inode, &m.to);
+                let _ = db.dismiss_proposal(m.inode);
+                content.push_str(&format!("- `{}` -> `{}`\n", m.to, m.from));
+            }
+            Err(reason) => content.push_str(&format!("- skipped `{}`: {}\n", m.to, reason)),
+        }
+    }
+    content
+}
+
+/// Unix time `ago` ("30m", "12h", "7d", "2w") before now.
+pub fn parse_since(ago: &str) -> Result<i64> {
+    let (number, unit) = ago.split_at(ago.len().saturating_sub(1));
+    let seconds = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        _ => anyhow::bail!("Expected a duration like 30m, 12h, 7d or 2w, got {:?}", ago),
+    };
+    let number: i64 = number.parse().with_context(|| format!("Invalid duration {:?}", ago))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    Ok(now - number * seconds)
+}
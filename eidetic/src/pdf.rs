@@ -0,0 +1,276 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+// Minimal PDF Writer
+// Just enough PDF 1.4 to typeset wrapped text with the standard 14 fonts (nothing to embed),
+// used by the Markdown/text -> PDF view. Text outside Latin-1 is replaced with '?'.
+
+const PAGE_WIDTH: f32 = 595.0; // A4 in points
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const LIST_INDENT: f32 = 18.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+
+    // Advance width in 1/1000 em (Helvetica AFM metrics, Courier is fixed-pitch)
+    fn width(self, c: char) -> f32 {
+        match self {
+            Font::Mono => 600.0,
+            Font::Regular => helvetica_width(c),
+            Font::Bold => helvetica_width(c) * 1.06,
+        }
+    }
+}
+
+/// A run of text set in one font. `\n` inside `text` forces a line break.
+pub struct Block {
+    pub font: Font,
+    pub size: f32,
+    pub indent: f32,
+    pub space_before: f32,
+    pub text: String,
+}
+
+impl Block {
+    fn new(font: Font, size: f32, indent: f32, space_before: f32) -> Self {
+        Self { font, size, indent, space_before, text: String::new() }
+    }
+}
+
+/// Plain text: one paragraph per blank-line separated chunk, line breaks kept.
+pub fn text_blocks(text: &str) -> Vec<Block> {
+    text.split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| {
+            let mut block = Block::new(Font::Regular, 11.0, 0.0, 8.0);
+            block.text = p.trim_end().to_string();
+            block
+        })
+        .collect()
+}
+
+/// Markdown: headings, paragraphs, (nested) lists and code blocks. Inline styling is dropped.
+pub fn markdown_blocks(markdown: &str) -> Vec<Block> {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+    let mut depth = 0usize;
+
+    fn flush(blocks: &mut Vec<Block>, current: &mut Option<Block>) {
+        if let Some(block) = current.take() {
+            if !block.text.trim().is_empty() {
+                blocks.push(block);
+            }
+        }
+    }
+
+    for event in Parser::new_ext(markdown, options) {
+        let indent = depth as f32 * LIST_INDENT;
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                flush(&mut blocks, &mut current);
+                let size = match level {
+                    HeadingLevel::H1 => 20.0,
+                    HeadingLevel::H2 => 16.0,
+                    HeadingLevel::H3 => 13.0,
+                    _ => 11.0,
+                };
+                current = Some(Block::new(Font::Bold, size, 0.0, size * 0.8));
+            }
+            // Inside a list item the paragraph continues the bullet's block
+            Event::Start(Tag::Paragraph) | Event::Start(Tag::TableRow) | Event::Start(Tag::TableHead) if current.is_none() => {
+                current = Some(Block::new(Font::Regular, 11.0, indent, 8.0));
+            }
+            Event::Start(Tag::List(_)) => {
+                flush(&mut blocks, &mut current);
+                depth += 1;
+            }
+            Event::End(TagEnd::List(_)) => {
+                flush(&mut blocks, &mut current);
+                depth = depth.saturating_sub(1);
+            }
+            Event::Start(Tag::Item) => {
+                flush(&mut blocks, &mut current);
+                let mut block = Block::new(Font::Regular, 11.0, indent.max(LIST_INDENT) - LIST_INDENT / 2.0, 3.0);
+                block.text.push_str("\u{2022} ");
+                current = Some(block);
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                flush(&mut blocks, &mut current);
+                current = Some(Block::new(Font::Mono, 9.0, indent + 12.0, 8.0));
+            }
+            Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::Item)
+            | Event::End(TagEnd::CodeBlock)
+            | Event::End(TagEnd::TableRow)
+            | Event::End(TagEnd::TableHead) => flush(&mut blocks, &mut current),
+            Event::End(TagEnd::TableCell) => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push_str("   ");
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                let block = current.get_or_insert_with(|| Block::new(Font::Regular, 11.0, indent, 8.0));
+                block.text.push_str(&text);
+            }
+            Event::TaskListMarker(done) => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push_str(if done { "[x] " } else { "[ ] " });
+                }
+            }
+            Event::SoftBreak => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push(' ');
+                }
+            }
+            Event::HardBreak => {
+                if let Some(block) = current.as_mut() {
+                    block.text.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut blocks, &mut current);
+    blocks
+}
+
+/// Lays the blocks out on A4 pages and serializes the document.
+pub fn render(blocks: &[Block]) -> Vec<u8> {
+    let mut pages: Vec<String> = Vec::new();
+    let mut page = String::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    for block in blocks {
+        let line_height = block.size * 1.3;
+        y -= block.space_before;
+        let max_width = PAGE_WIDTH - 2.0 * MARGIN - block.indent;
+        for line in block.text.lines().flat_map(|l| wrap(&l.replace('\t', "    "), block.font, block.size, max_width)) {
+            if y - line_height < MARGIN {
+                pages.push(std::mem::take(&mut page));
+                y = PAGE_HEIGHT - MARGIN;
+            }
+            y -= line_height;
+            page.push_str(&format!(
+                "BT /{} {} Tf {} {} Td ({}) Tj ET\n",
+                block.font.resource(), block.size, MARGIN + block.indent, y, escape(&line)
+            ));
+        }
+    }
+    if !page.is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+
+    // Objects: 1 catalog, 2 page tree, 3-5 fonts, then (page, content) pairs
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 6 + i * 2)).collect();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes());
+    for base in ["Helvetica", "Helvetica-Bold", "Courier"] {
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", base).into_bytes());
+    }
+    for (i, content) in pages.iter().enumerate() {
+        let content = encode(content);
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, 7 + i * 2
+        ).into_bytes());
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(&content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        out.extend_from_slice(object);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes());
+    out
+}
+
+// Greedy word wrap; words longer than a line are split by character
+fn wrap(line: &str, font: Font, size: f32, max_width: f32) -> Vec<String> {
+    let width = |s: &str| s.chars().map(|c| font.width(c)).sum::<f32>() * size / 1000.0;
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split(' ') {
+        let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+        if width(&candidate) <= max_width {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            current.push(c);
+            if width(&current) > max_width {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)")
+}
+
+// WinAnsi matches Latin-1 except for a few punctuation marks in 0x80..0x9F
+fn encode(content: &str) -> Vec<u8> {
+    content.chars().map(|c| match c {
+        '\u{2022}' => 0x95,
+        '\u{2013}' => 0x96,
+        '\u{2014}' => 0x97,
+        '\u{2018}' => 0x91,
+        '\u{2019}' => 0x92,
+        '\u{201C}' => 0x93,
+        '\u{201D}' => 0x94,
+        '\u{2026}' => 0x85,
+        '\u{20AC}' => 0x80,
+        c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u8,
+        _ => b'?',
+    }).collect()
+}
+
+fn helvetica_width(c: char) -> f32 {
+    const ASCII: [u16; 95] = [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // ' ' to '/'
+        556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // '0' to '?'
+        1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // '@' to 'O'
+        667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // 'P' to '_'
+        333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // '`' to 'o'
+        556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p' to '~'
+    ];
+    match c as u32 {
+        n @ 32..=126 => ASCII[(n - 32) as usize] as f32,
+        0x2022 => 350.0,
+        _ => 556.0,
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+use anyhow::Result;
+
+// Similar Images
+// A perceptual hash (pHash) per image: the low frequencies of a 32x32 grayscale DCT,
+// thresholded at their median into 64 bits. Re-encodes, resizes and burst shots taken
+// seconds apart land a few bits apart, so `.magic/similar_images/<image>/` and
+// `eidetic similar` can list near-duplicates that the checksum-based dedupe misses.
+
+/// Images decoded for hashing.
+pub const FORMATS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
+
+/// Most differing bits for two images to count as similar.
+pub const SIMILAR_DISTANCE: u32 = 10;
+
+const SIZE: usize = 32;
+const LOW: usize = 8;
+
+pub fn phash(path: &Path) -> Result<u64> {
+    let img = image::open(path)?
+        .resize_exact(SIZE as u32, SIZE as u32, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = img.pixels().map(|p| p.0[0] as f64).collect();
+
+    // 2D DCT-II, only the top-left LOW x LOW coefficients are needed
+    let cos: Vec<Vec<f64>> = (0..LOW)
+        .map(|u| (0..SIZE).map(|x| ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * SIZE) as f64).cos()).collect())
+        .collect();
+    let mut coefficients = Vec::with_capacity(LOW * LOW);
+    for v in 0..LOW {
+        for u in 0..LOW {
+            let mut sum = 0.0;
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    sum += pixels[y * SIZE + x] * cos[u][x] * cos[v][y];
+                }
+            }
+            coefficients.push(sum);
+        }
+    }
+
+    // The DC term is the overall brightness and would dominate the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    Ok(coefficients.iter().enumerate().skip(1).fold(0u64, |hash, (i, c)| if *c > median { hash | 1 << i } else { hash }))
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// (inode, name, distance) of the images within SIMILAR_DISTANCE of `inode`, closest first.
+pub fn similar(db: &crate::db::Database, inode: u64) -> Vec<(u64, String, u32)> {
+    let hashes = db.get_image_hashes().unwrap_or_default();
+    let Some(&(_, _, hash)) = hashes.iter().find(|(id, _, _)| *id == inode) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(u64, String, u32)> = hashes.into_iter()
+        .filter(|(id, _, _)| *id != inode)
+        .map(|(id, name, other)| (id, name, distance(hash, other)))
+        .filter(|(_, _, d)| *d <= SIMILAR_DISTANCE)
+        .collect();
+    found.sort_by_key(|(_, _, d)| *d);
+    found
+}
+
+/// Markdown list of the images similar to `inode`, for `eidetic similar`.
+pub fn report(db: &crate::db::Database, inode: u64) -> String {
+    let found = similar(db, inode);
+    if found.is_empty() {
+        return "No similar images found.\n".to_string();
+    }
+    let mut report = String::from("# Similar Images\n\n");
+    for (id, name, d) in found {
+        let path = db.get_path(id).ok().flatten().unwrap_or(name);
+        report.push_str(&format!("- {} ({} bit{} apart)\n", path, d, if d == 1 { "" } else { "s" }));
+    }
+    report
+}
@@ -0,0 +1,202 @@
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use crate::db::{Database, IndexedFile};
+use tracing::warn;
+
+// Smart Folders
+// A directory with a `.eidetic-query` file in it shows the files matching the query instead
+// of its own contents, always up to date with the index. The query is a list of terms that
+// all have to match; lines starting with # are comments:
+//
+//   type:documents tag:lang:de after:2024-01-01 invoice
+//
+// Terms: `tag:<tag>`, `type:<kind>` (as in `.magic/by-type/`), `ext:<extension>`,
+// `larger:<size>` / `smaller:<size>` (10MB, 500KB, 2GB), `after:<date>` / `before:<date>`
+// (modification date, YYYY-MM-DD inclusive, or YYYY-MM / YYYY for the start of that month
+// or year), `rating>=4` / `rating<=2` / `rating:5` (star ratings, unrated files count as 0),
+// and plain words found in the file name or its `.comments`.
+// Prefix a term with `-` to exclude what it matches. The folder itself is read-only apart
+// from the query file.
+
+pub const QUERY_FILE: &str = ".eidetic-query";
+
+enum Condition {
+    Tag(String),
+    Kind(String),
+    Extension(String),
+    Larger(u64),
+    Smaller(u64),
+    After(String),
+    Before(String),
+    /// Inclusive range of stars
+    Rating(u8, u8),
+    Word(String),
+}
+
+pub struct Query(Vec<(bool, Condition)>);
+
+impl Query {
+    /// The query of the smart folder at `dir`, or None if it isn't one (or the query is
+    /// invalid, which is logged).
+    pub fn load(dir: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(dir.join(QUERY_FILE)).ok()?;
+        match Self::parse(&text) {
+            Ok(query) => Some(query),
+            Err(e) => {
+                warn!(path = ?dir.join(QUERY_FILE), error = %e, "invalid smart folder query");
+                None
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut terms = Vec::new();
+        for term in text.lines().filter(|line| !line.trim_start().starts_with('#')).flat_map(str::split_whitespace) {
+            let (negated, term) = match term.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, term),
+            };
+            if let Some(comparison) = term.strip_prefix("rating").filter(|rest| rest.starts_with([':', '=', '<', '>'])) {
+                terms.push((negated, parse_rating(comparison)?));
+                continue;
+            }
+            let condition = match term.split_once(':') {
+                Some(("tag", tag)) => Condition::Tag(tag.to_string()),
+                Some(("type", kind)) => Condition::Kind(kind.to_lowercase()),
+                Some(("ext", ext)) => Condition::Extension(ext.trim_start_matches('.').to_lowercase()),
+                Some(("larger", size)) => Condition::Larger(parse_size(size)?),
+                Some(("smaller", size)) => Condition::Smaller(parse_size(size)?),
+                Some(("after", date)) => Condition::After(parse_date(date)?),
+                Some(("before", date)) => Condition::Before(parse_date(date)?),
+                Some((key, _)) if key.chars().all(|c| c.is_ascii_lowercase()) => bail!("Unknown term {:?}", term),
+                _ => Condition::Word(term.to_lowercase()),
+            };
+            terms.push((negated, condition));
+        }
+        if terms.is_empty() {
+            bail!("Empty query");
+        }
+        Ok(Self(terms))
+    }
+
+    fn matches(&self, file: &IndexedFile) -> bool {
+        let ext = Path::new(&file.name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        self.0.iter().all(|(negated, condition)| {
+            let matched = match condition {
+                Condition::Tag(tag) => file.tags.contains(tag),
+                Condition::Kind(kind) => file.kind.as_deref() == Some(kind.as_str()),
+                Condition::Extension(wanted) => ext == *wanted,
+                Condition::Larger(size) => file.size > *size,
+                Condition::Smaller(size) => file.size < *size,
+                Condition::After(date) => file.date >= *date,
+                Condition::Before(date) => file.date <= *date,
+                Condition::Rating(min, max) => (*min..=*max).contains(&file.rating),
+                Condition::Word(word) => file.name.to_lowercase().contains(word)
+                    || file.comment.as_ref().is_some_and(|comment| comment.to_lowercase().contains(word)),
+            };
+            matched != *negated
+        })
+    }
+
+    /// (inode, name) of the matching files, by name.
+    pub fn results(&self, db: &Database) -> Vec<(u64, String)> {
+        db.get_indexed_files().unwrap_or_default()
+            .into_iter()
+            .filter(|file| self.matches(file))
+            .map(|file| (file.inode, file.name))
+            .collect()
+    }
+}
+
+// "10MB", "500kb", "2GB", "1024"
+fn parse_size(size: &str) -> Result<u64> {
+    let upper = size.to_uppercase();
+    let digits = upper.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = match &upper[digits.len()..] {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => bail!("Unknown size unit in {:?}", size),
+    };
+    let number: f64 = digits.parse().with_context(|| format!("Invalid size {:?}", size))?;
+    Ok((number * unit as f64) as u64)
+}
+
+// ">=4", "<=2", ">3", "<2" or ":5" after `rating`
+fn parse_rating(comparison: &str) -> Result<Condition> {
+    let (operator, stars) = comparison.split_at(comparison.find(|c: char| c.is_ascii_digit()).unwrap_or(comparison.len()));
+    let stars: u8 = stars.parse().with_context(|| format!("Invalid rating term \"rating{}\"", comparison))?;
+    let max = crate::ratings::MAX_STARS;
+    Ok(match operator {
+        ":" | "=" => Condition::Rating(stars, stars),
+        ">=" => Condition::Rating(stars, max),
+        "<=" => Condition::Rating(0, stars),
+        ">" => Condition::Rating(stars.saturating_add(1), max),
+        "<" if stars > 0 => Condition::Rating(0, stars - 1),
+        _ => bail!("Invalid rating term \"rating{}\"", comparison),
+    })
+}
+
+// "2024-05-01", also "2024-05" and "2024" (from the start of the month or year)
+fn parse_date(date: &str) -> Result<String> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let valid = matches!(parts.len(), 1..=3)
+        && parts[0].len() == 4
+        && parts[1..].iter().all(|p| p.len() == 2)
+        && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit()));
+    if !valid {
+        bail!("Expected a date like 2024-05-01, got {:?}", date);
+    }
+    Ok(date.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, tags: &[&str]) -> IndexedFile {
+        IndexedFile {
+            inode: 2,
+            name: name.to_string(),
+            size: 2 * 1024 * 1024,
+            date: "2024-05-01".to_string(),
+            kind: Some("documents".to_string()),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            comment: Some("Paid in March".to_string()),
+            rating: 4,
+        }
+    }
+
+    fn matches(query: &str, file: &IndexedFile) -> bool {
+        Query::parse(query).unwrap().matches(file)
+    }
+
+    #[test]
+    fn terms_all_have_to_match() {
+        let invoice = file("Invoice-2024.PDF", &["lang:de", "invoice"]);
+        assert!(matches("type:documents tag:lang:de ext:pdf invoice", &invoice));
+        assert!(matches("# comment line\nlarger:1MB smaller:3mb\nafter:2024-05-01 before:2024-05-01", &invoice));
+        assert!(matches("rating>=4 rating<=4 rating:4 march", &invoice));
+        assert!(!matches("tag:invoice tag:receipt", &invoice));
+        assert!(!matches("larger:2MB", &invoice));
+        assert!(!matches("after:2024-06", &invoice));
+        assert!(!matches("rating>4", &invoice));
+    }
+
+    #[test]
+    fn minus_excludes() {
+        let invoice = file("invoice.pdf", &["invoice"]);
+        assert!(!matches("-tag:invoice", &invoice));
+        assert!(matches("-tag:receipt -ext:docx", &invoice));
+        // A lone "-" is a word, not an empty negated term
+        assert!(!matches("-", &invoice));
+    }
+
+    #[test]
+    fn invalid_queries_are_rejected() {
+        for query in ["", "# only a comment", "color:red", "larger:10XB", "after:24-05-01", "after:2024-5", "rating<0", "rating>=x"] {
+            assert!(Query::parse(query).is_err(), "{:?} parsed", query);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use crate::db::Database;
+use crate::worker::Job;
+use tracing::error;
+
+// Job Queue
+// Replaces the plain channel between the FS and the worker. Three priority lanes so a mass
+// copy or a rescan can't starve the file that was just saved, one pending job per inode
+// (a file saved ten times while queued is analyzed once), and a cap on queued bulk work.
+
+/// Queued jobs beyond which bulk producers wait for the worker to catch up.
+const CAPACITY: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Priority {
+    /// Rescans and jobs resumed from a previous run; blocks the producer while the queue is full
+    Bulk,
+    /// Files just written through the mount or the control socket
+    Written,
+    /// Someone is waiting on the result right now (e.g. looked up a summary that doesn't exist yet)
+    Interactive,
+}
+
+/// Jobs listed per lane (and failures) in the status report.
+const REPORT_LIMIT: usize = 20;
+
+const LANES: [Priority; 3] = [Priority::Interactive, Priority::Written, Priority::Bulk];
+
+#[derive(Default)]
+struct State {
+    lanes: HashMap<Priority, VecDeque<Job>>,
+    // Inode -> lane of its pending job
+    pending: HashMap<u64, Priority>,
+    // The worker takes nothing new while paused (e.g. on battery); not persisted across restarts
+    paused: bool,
+    // Path of the job the worker is on, and since when
+    running: Option<(PathBuf, Instant)>,
+}
+
+impl State {
+    fn len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct JobQueue {
+    inner: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an Analyze job in the DB, then queues it. Jobs still unfinished when the daemon
+    /// stops are picked up again by the next Worker::start.
+    pub fn enqueue(&self, db: &Database, inode: u64, path: PathBuf, priority: Priority) {
+        match db.add_job(inode, &path.to_string_lossy()) {
+            Ok(id) => {
+                if !self.push(Job::Analyze { id, inode, path }, priority) {
+                    // Folded into the job already pending for this inode
+                    let _ = db.finish_job(id);
+                }
+            }
+            Err(e) => error!(?path, error = %e, "failed to queue job"),
+        }
+    }
+
+    /// Queues a job. Returns false if a job for the same inode was already pending: that one
+    /// takes this job's path (the file may have moved) and moves up to `priority` if it's higher.
+    pub fn push(&self, job: Job, priority: Priority) -> bool {
+        let (lock, changed) = &*self.inner;
+        let mut state = lock.lock().unwrap();
+        let inode = job.inode();
+        loop {
+            if let Some(&queued) = state.pending.get(&inode) {
+                let lane = state.lanes.entry(queued).or_default();
+                if let Some(at) = lane.iter().position(|j| j.inode() == inode) {
+                    let mut existing = lane.remove(at).unwrap();
+                    existing.set_path(job.path());
+                    let priority = priority.max(queued);
+                    state.lanes.entry(priority).or_default().push_back(existing);
+                    state.pending.insert(inode, priority);
+                }
+                return false;
+            }
+            if priority != Priority::Bulk || state.len() < CAPACITY {
+                break;
+            }
+            state = changed.wait(state).unwrap();
+        }
+        state.lanes.entry(priority).or_default().push_back(job);
+        state.pending.insert(inode, priority);
+        changed.notify_all();
+        true
+    }
+
+    /// Next job, highest priority first. Blocks while the queue is empty or paused.
+    /// The job counts as running until `finish`.
+    pub fn pop(&self) -> Job {
+        let (lock, changed) = &*self.inner;
+        let mut state = lock.lock().unwrap();
+        loop {
+            let next = if state.paused {
+                None
+            } else {
+                LANES.iter().find_map(|lane| state.lanes.get_mut(lane).and_then(VecDeque::pop_front))
+            };
+            if let Some(job) = next {
+                state.pending.remove(&job.inode());
+                state.running = Some((job.path(), Instant::now()));
+                // Wake bulk producers waiting for room
+                changed.notify_all();
+                return job;
+            }
+            state = changed.wait(state).unwrap();
+        }
+    }
+
+    /// Marks the job from the last `pop` as done.
+    pub fn finish(&self) {
+        self.inner.0.lock().unwrap().running = None;
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        let (lock, changed) = &*self.inner;
+        lock.lock().unwrap().paused = paused;
+        changed.notify_all();
+    }
+
+    /// Path of the job the worker is on, and for how long.
+    pub fn running(&self) -> Option<(PathBuf, Duration)> {
+        self.inner.0.lock().unwrap().running.as_ref().map(|(path, since)| (path.clone(), since.elapsed()))
+    }
+
+    /// Jobs waiting, not counting the one running.
+    pub fn pending(&self) -> usize {
+        self.inner.0.lock().unwrap().len()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.0.lock().unwrap().paused
+    }
+
+    /// Markdown overview of the queue and recent failures, for `.magic/jobs/status.md` and `eidetic jobs`.
+    pub fn status_report(&self, db: &Database) -> String {
+        let mut content = String::from("# Jobs\n\n");
+        {
+            let state = self.inner.0.lock().unwrap();
+            content.push_str(&format!("- **State**: {}\n", if state.paused { "Paused ⏸" } else { "Running ▶" }));
+            match &state.running {
+                Some((path, since)) => content.push_str(&format!("- **Analyzing**: {} ({}s)\n", path.display(), since.elapsed().as_secs())),
+                None => content.push_str("- **Analyzing**: _nothing_\n"),
+            }
+            content.push_str(&format!("- **Pending**: {}\n", state.len()));
+            for lane in LANES {
+                let jobs = state.lanes.get(&lane).map(|l| l.len()).unwrap_or(0);
+                if jobs == 0 {
+                    continue;
+                }
+                content.push_str(&format!("\n## {:?} ({})\n", lane, jobs));
+                for job in state.lanes[&lane].iter().take(REPORT_LIMIT) {
+                    content.push_str(&format!("- {}\n", job.path().display()));
+                }
+                if jobs > REPORT_LIMIT {
+                    content.push_str(&format!("- _... and {} more_\n", jobs - REPORT_LIMIT));
+                }
+            }
+        }
+        let failed = db.failed_jobs(REPORT_LIMIT).unwrap_or_default();
+        if !failed.is_empty() {
+            content.push_str("\n## Failed\n");
+            for (path, error, when) in failed {
+                content.push_str(&format!("- {} ({}): {}\n", path, when, error));
+            }
+        }
+        content
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::{bail, Result};
+
+// Star Ratings
+// Files can be rated 1 to 5 stars with `eidetic rate <path> <stars>` or by setting the
+// extended attribute `user.eidetic.rating` (`setfattr -n user.eidetic.rating -v 4 <file>`);
+// 0 or removing the attribute clears it. Rated files are listed in `.magic/starred/`, best
+// first, and smart folder queries can ask for `rating>=4`.
+
+pub const XATTR: &str = "user.eidetic.rating";
+
+pub const MAX_STARS: u8 = 5;
+
+/// Stars from "4", "4\n" or "★★★★"; 0 clears the rating.
+pub fn parse(value: &str) -> Result<u8> {
+    let value = value.trim();
+    let stars = if !value.is_empty() && value.chars().all(|c| c == '★') {
+        value.chars().count()
+    } else {
+        match value.parse::<usize>() {
+            Ok(stars) => stars,
+            Err(_) => bail!("Expected a rating from 0 to {}, got {:?}", MAX_STARS, value),
+        }
+    };
+    if stars > MAX_STARS as usize {
+        bail!("Ratings go up to {} stars, got {}", MAX_STARS, stars);
+    }
+    Ok(stars as u8)
+}
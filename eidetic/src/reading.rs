@@ -0,0 +1,64 @@
+use crate::db::Database;
+
+// Reading Time
+// The worker counts the words and lines of every text document (and the text of PDFs) and
+// estimates how long it takes to read. Each file carries them as the extended attributes
+// `user.eidetic.wordcount`, `user.eidetic.linecount` and `user.eidetic.readingtime` (whole
+// minutes); `.context` headers and `stats.md` show them too.
+
+/// Average silent reading speed, in words per minute.
+const WORDS_PER_MINUTE: u64 = 200;
+
+pub const XATTR_WORDS: &str = "user.eidetic.wordcount";
+pub const XATTR_LINES: &str = "user.eidetic.linecount";
+pub const XATTR_MINUTES: &str = "user.eidetic.readingtime";
+
+/// (words, lines) of `text`.
+pub fn count(text: &str) -> (u64, u64) {
+    (text.split_whitespace().count() as u64, text.lines().count() as u64)
+}
+
+/// Whole minutes to read `words`, at least one for any text.
+pub fn minutes(words: u64) -> u64 {
+    words.div_ceil(WORDS_PER_MINUTE)
+}
+
+/// "1,234 words, 7 min read"
+pub fn describe(words: u64) -> String {
+    format!("{} words, {} min read", thousands(words), minutes(words))
+}
+
+/// (name, value) of the extended attributes of a file, empty before it's analyzed.
+pub fn xattrs(db: &Database, inode: u64) -> Vec<(&'static str, String)> {
+    match db.get_text_stats(inode) {
+        Ok(Some((words, lines))) => vec![
+            (XATTR_WORDS, words.to_string()),
+            (XATTR_LINES, lines.to_string()),
+            (XATTR_MINUTES, minutes(words).to_string()),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// Section of `stats.md`.
+pub fn summary(db: &Database) -> String {
+    let (files, words, lines) = db.text_stats_totals().unwrap_or_default();
+    let mut content = String::from("\n## Reading\n");
+    content.push_str(&format!("- **Text documents**: {}\n", files));
+    content.push_str(&format!("- **Words**: {} in {} lines\n", thousands(words), thousands(lines)));
+    let minutes = minutes(words);
+    content.push_str(&format!("- **Reading time**: {}h {}min\n", minutes / 60, minutes % 60));
+    content
+}
+
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,229 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use crate::config::RestConfig;
+use crate::db::Database;
+use crate::query::Query;
+use tracing::{error, info, warn};
+
+// REST API
+// With `[rest] listen` set, the daemon answers JSON over HTTP on a loopback address, so web
+// dashboards and browser extensions can use the index without the mount:
+//
+//   GET /api/search?q=<query>      files matching a smart folder query (see query.rs)
+//   GET /api/tags                  every tag
+//   GET /api/tags/<tag>            files with a tag
+//   GET /api/file?path=<path>      size, date, kind, tags, rating, label, comment and summary
+//   GET /api/summary?path=<path>   the summary alone
+//   GET /api/history?path=<path>   version history snapshots, newest first
+//
+// Paths are relative to the source directory. Every request needs `Authorization: Bearer
+// <token>`, the token coming from `[rest] token` or else generated once into
+// ~/.eidetic/rest_token. Responses allow any origin, since the token is what guards them.
+
+const TOKEN_FILE: &str = "rest_token";
+
+pub fn start(config: &RestConfig, source_path: PathBuf) {
+    let Some(listen) = config.listen.clone() else { return };
+    let token = match token(config) {
+        Ok(token) => token,
+        Err(e) => {
+            error!(error = %e, "REST API disabled, no token");
+            return;
+        }
+    };
+    thread::spawn(move || {
+        if let Err(e) = serve(&listen, &source_path, &token) {
+            error!(%listen, error = %e, "REST API failed");
+        }
+    });
+}
+
+/// The bearer token clients need, also guarding the gRPC service.
+pub fn token(config: &RestConfig) -> Result<String> {
+    match &config.token {
+        Some(token) => Ok(token.clone()),
+        None => stored_secret(TOKEN_FILE),
+    }
+}
+
+/// A secret generated into ~/.eidetic/<name> on first use, readable only by the user.
+pub fn stored_secret(name: &str) -> Result<String> {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    let path = PathBuf::from(home).join(".eidetic").join(name);
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        if !token.trim().is_empty() {
+            return Ok(token.trim().to_string());
+        }
+    }
+    let mut bytes = [0u8; 16];
+    std::io::Read::read_exact(&mut std::fs::File::open("/dev/urandom")?, &mut bytes)?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    writeln!(file, "{}", token)?;
+    Ok(token)
+}
+
+// One request per connection, like the metrics endpoint
+fn serve(listen: &str, source_path: &Path, token: &str) -> Result<()> {
+    let address: SocketAddr = listen.parse().with_context(|| format!("Invalid address {:?}", listen))?;
+    if !address.ip().is_loopback() {
+        bail!("{} isn't a loopback address", listen);
+    }
+    let listener = TcpListener::bind(address)?;
+    let db = Database::new(source_path.join(".eidetic.db"))?;
+    info!("serving http://{}/api/", listen);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        if let Err(e) = handle(&db, token, stream) {
+            warn!(error = %e, "REST request failed");
+        }
+    }
+    Ok(())
+}
+
+fn handle(db: &Database, token: &str, mut stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut authorized = false;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("authorization") && value.trim().strip_prefix("Bearer ") == Some(token) {
+                authorized = true;
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = match method {
+        // CORS preflight, sent by browsers without credentials
+        "OPTIONS" => (204, None),
+        "GET" if !authorized => (401, Some(json!({ "error": "Missing or wrong bearer token" }))),
+        "GET" => match route(db, target) {
+            Ok(Some(body)) => (200, Some(body)),
+            Ok(None) => (404, Some(json!({ "error": "Not found" }))),
+            Err(e) => (400, Some(json!({ "error": e.to_string() }))),
+        },
+        _ => (405, Some(json!({ "error": "Only GET is supported" }))),
+    };
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Authorization\r\nAccess-Control-Allow-Methods: GET, OPTIONS\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body,
+    )?;
+    Ok(())
+}
+
+// The response of a GET, None for an unknown path or file
+fn route(db: &Database, target: &str) -> Result<Option<Value>> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let param = |name: &str| {
+        query.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| decode(value))
+    };
+    let file = || -> Result<Option<u64>> {
+        let path = param("path").context("Missing ?path=")?;
+        Ok(db.lookup_path(path.trim_matches('/'))?)
+    };
+
+    Ok(match path.trim_end_matches('/') {
+        "/api/search" => {
+            let search = Query::parse(&param("q").context("Missing ?q=")?)?;
+            Some(Value::Array(search.results(db).into_iter().map(|(inode, name)| entry(db, inode, name)).collect()))
+        }
+        "/api/tags" => Some(json!(db.get_tags()?)),
+        "/api/file" => match file()? {
+            Some(inode) => Some(metadata(db, inode)?),
+            None => None,
+        },
+        "/api/summary" => match file()? {
+            Some(inode) => Some(json!({ "path": db.get_path(inode)?, "summary": db.get_summary(inode)? })),
+            None => None,
+        },
+        "/api/history" => match file()? {
+            Some(inode) => Some(Value::Array(db.get_history(inode)?
+                .into_iter()
+                .map(|(timestamp, snapshot)| json!({ "timestamp": timestamp, "snapshot": snapshot }))
+                .collect())),
+            None => None,
+        },
+        tag_path => match tag_path.strip_prefix("/api/tags/") {
+            Some(tag) => Some(Value::Array(db.get_files_with_tag(&decode(tag))?
+                .into_iter()
+                .map(|(inode, name)| entry(db, inode, name))
+                .collect())),
+            None => None,
+        },
+    })
+}
+
+fn entry(db: &Database, inode: u64, name: String) -> Value {
+    json!({ "path": db.get_path(inode).ok().flatten().unwrap_or_else(|| name.clone()), "name": name })
+}
+
+fn metadata(db: &Database, inode: u64) -> Result<Value> {
+    let info = db.get_file_info(inode)?;
+    Ok(json!({
+        "path": db.get_path(inode)?,
+        "size": info.as_ref().map(|(size, _, _)| *size),
+        "modified": info.as_ref().map(|(_, mtime, _)| *mtime),
+        "kind": info.and_then(|(_, _, kind)| kind),
+        "tags": db.get_file_tags(inode)?,
+        "rating": db.get_rating(inode)?,
+        "label": db.get_label(inode)?,
+        "comment": db.get_comment(inode)?,
+        "summary": db.get_summary(inode)?,
+    }))
+}
+
+// Percent-decoding of a query value or path segment, `+` being a space
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => match value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+                None => decoded.push(b'%'),
+            },
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
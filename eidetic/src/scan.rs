@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use crate::db::Database;
+use crate::queue::{JobQueue, Priority};
+use tracing::{error, info, warn};
+
+// Initial Scan
+// Files that were in the source dir before the first mount are only analyzed once touched.
+// On first mount (or with --rescan) a background crawl allocates inodes for everything and
+// queues a bulk Analyze job per file. Bulk jobs yield to anything saved meanwhile, and the
+// queue's cap keeps the crawl from racing ahead of the worker.
+
+pub const SCANNED_KEY: &str = "initial_scan";
+
+/// Eidetic's own state (cache, history, the DB and its WAL files) and VCS internals.
+pub fn ignored(name: &str) -> bool {
+    name.starts_with(".eidetic") || [".git", ".hg", ".svn"].contains(&name)
+}
+
+pub fn start(source_path: PathBuf, jobs: JobQueue, force: bool) {
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        crate::usage::ensure(&db, force);
+        if !force && db.get_meta(SCANNED_KEY).unwrap_or(None).is_some() {
+            return;
+        }
+
+        info!(?source_path, "indexing");
+        let queued = queue_tree(&db, &jobs, &source_path, &source_path);
+
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs();
+        let _ = db.set_meta(SCANNED_KEY, &timestamp.to_string());
+        info!(queued, "initial scan done");
+    });
+}
+
+/// Allocates inodes for every file under `root` and queues a bulk Analyze job for each.
+pub fn queue_tree(db: &Database, jobs: &JobQueue, source_path: &Path, root: &Path) -> usize {
+    let walker = ignore::WalkBuilder::new(root)
+        .standard_filters(false)
+        .filter_entry(|e| !ignored(&e.file_name().to_string_lossy()))
+        .build();
+    let mut queued = 0;
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Ok(rel_path) = entry.path().strip_prefix(source_path) else { continue };
+        match db.alloc_path(&rel_path.to_string_lossy()) {
+            Ok(inode) => {
+                jobs.enqueue(db, inode, entry.path().to_path_buf(), Priority::Bulk);
+                queued += 1;
+            }
+            Err(e) => warn!(?rel_path, error = %e, "failed to allocate inode"),
+        }
+    }
+    queued
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use crate::db::Database;
+use crate::throttle::IoPriority;
+use tracing::{error, warn};
+
+// Integrity Scrubber
+// The worker records a blake3 checksum of every file it analyzes. A low-priority thread
+// re-hashes files whose last verification is older than `[scrub] interval_days`, a batch
+// at a time. A file whose content changed while its size and mtime didn't has rotted;
+// it's listed in `.magic/integrity_report.md` and by `eidetic integrity`.
+
+/// Files verified per batch before the scrubber checks for more work.
+const BATCH: usize = 64;
+
+/// Pause between files, so scrubbing never competes with real I/O for long.
+const PACE: Duration = Duration::from_millis(20);
+
+/// How long the scrubber sleeps once nothing is due.
+const IDLE: Duration = Duration::from_secs(3600);
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
+}
+
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(std::fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Records the current checksum of a file the worker is about to analyze.
+pub fn record(db: &Database, inode: u64, path: &Path) {
+    let Ok(metadata) = std::fs::metadata(path) else { return };
+    if !metadata.is_file() {
+        return;
+    }
+    match hash_file(path) {
+        Ok(hash) => {
+            let _ = db.set_checksum(inode, &hash, metadata.len(), crate::convert::mtime_key(&metadata), now());
+        }
+        Err(e) => warn!(?path, error = %e, "failed to hash"),
+    }
+}
+
+pub fn start(source_path: PathBuf, interval_days: u64) {
+    if interval_days == 0 {
+        return;
+    }
+    let interval = (interval_days * 24 * 3600) as i64;
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        crate::throttle::lower_priority(19, IoPriority::Idle);
+
+        loop {
+            let due = db.checksums_due(now() - interval, BATCH).unwrap_or_default();
+            if due.is_empty() {
+                thread::sleep(IDLE);
+                continue;
+            }
+            for (inode, expected, size, mtime) in due {
+                verify(&db, &source_path, inode, &expected, size, mtime);
+                thread::sleep(PACE);
+            }
+        }
+    });
+}
+
+fn verify(db: &Database, source_path: &Path, inode: u64, expected: &str, size: u64, mtime: i64) {
+    let path = match db.get_path(inode) {
+        Ok(Some(rel)) => source_path.join(rel),
+        // Deleted through the mount; its row goes with the inode
+        _ => {
+            let _ = db.mark_verified(inode, now());
+            return;
+        }
+    };
+    let Ok(metadata) = std::fs::metadata(&path) else {
+        let _ = db.mark_verified(inode, now());
+        return;
+    };
+    // Changed on purpose since it was last hashed: the new content is the good copy
+    if metadata.len() != size || crate::convert::mtime_key(&metadata) != mtime {
+        record(db, inode, &path);
+        return;
+    }
+    match hash_file(&path) {
+        Ok(actual) if actual == expected => {
+            let _ = db.mark_verified(inode, now());
+        }
+        Ok(actual) => {
+            warn!(?path, "checksum mismatch");
+            let _ = db.mark_corrupt(inode, &actual, now());
+        }
+        // Unreadable (I/O error, permissions): try again next interval
+        Err(e) => {
+            warn!(?path, error = %e, "failed to verify");
+            let _ = db.mark_verified(inode, now());
+        }
+    }
+}
+
+/// Markdown list of files that failed verification.
+pub fn report(db: &Database) -> String {
+    let corruptions = db.get_corruptions().unwrap_or_default();
+    let mut content = String::from("# Integrity Report\n\n");
+    if corruptions.is_empty() {
+        content.push_str("_No checksum mismatches found._\n");
+        return content;
+    }
+    content.push_str(&format!("{} file(s) changed on disk without being modified. Restore them from a backup or `.eidetic/history`.\n\n", corruptions.len()));
+    for c in corruptions {
+        let path = db.get_path(c.inode).unwrap_or(None).unwrap_or_else(|| format!("<inode {}>", c.inode));
+        content.push_str(&format!("- **{}** (detected {})\n  - expected `{}`\n  - found `{}`\n", path, c.detected, c.expected, c.actual));
+    }
+    content
+}
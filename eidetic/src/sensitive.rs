@@ -0,0 +1,91 @@
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::db::Database;
+
+// Sensitive Data Scanner
+// Flags secrets and personal data in text documents and PDFs: AWS access keys, private key
+// blocks, payment card numbers (Luhn-checked) and national IDs (US SSNs, UK National
+// Insurance numbers). Flagged files get the `sensitive` tag and are listed in
+// `.magic/sensitive_report.md`; `[sensitive] exclude_from_context` keeps them out of `.context`.
+// Only the kind and line of a finding are stored, never the matched value.
+
+/// Findings recorded per file; one is enough to flag it.
+const MAX_FINDINGS: usize = 50;
+
+struct Detector {
+    kind: &'static str,
+    pattern: &'static str,
+    /// Extra check on the match, for patterns that over-match
+    valid: fn(&str) -> bool,
+}
+
+const DETECTORS: &[Detector] = &[
+    Detector { kind: "AWS access key", pattern: r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b", valid: |_| true },
+    Detector { kind: "Private key", pattern: r"-----BEGIN (?:[A-Z]+ )?PRIVATE KEY-----", valid: |_| true },
+    Detector { kind: "Card number", pattern: r"\b\d(?:[ -]?\d){12,18}\b", valid: luhn },
+    Detector { kind: "US SSN", pattern: r"\b\d{3}-\d{2}-\d{4}\b", valid: ssn },
+    Detector { kind: "UK NI number", pattern: r"\b[A-CEGHJ-PR-TW-Z]{2} ?\d{2} ?\d{2} ?\d{2} ?[A-D]\b", valid: |_| true },
+];
+
+fn patterns() -> &'static Vec<Regex> {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| DETECTORS.iter().map(|d| Regex::new(d.pattern).expect("detector pattern")).collect())
+}
+
+/// (kind, 1-based line) of everything sensitive in `text`.
+pub fn scan(text: &str) -> Vec<(&'static str, usize)> {
+    let mut findings = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        for (detector, pattern) in DETECTORS.iter().zip(patterns()) {
+            if pattern.find_iter(line).any(|m| (detector.valid)(m.as_str())) {
+                findings.push((detector.kind, i + 1));
+                if findings.len() == MAX_FINDINGS {
+                    return findings;
+                }
+            }
+        }
+    }
+    findings
+}
+
+// Card numbers: 13-19 digits passing the Luhn checksum, and not all the same digit
+fn luhn(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 13 || digits.iter().all(|d| *d == digits[0]) {
+        return false;
+    }
+    let sum: u32 = digits.iter().rev().enumerate()
+        .map(|(i, d)| if i % 2 == 1 { let doubled = d * 2; if doubled > 9 { doubled - 9 } else { doubled } } else { *d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+// SSNs never start with 000, 666 or 9xx, and no group is all zeros
+fn ssn(candidate: &str) -> bool {
+    let parts: Vec<&str> = candidate.split('-').collect();
+    let area = parts[0];
+    area != "000" && area != "666" && !area.starts_with('9') && parts[1] != "00" && parts[2] != "0000"
+}
+
+/// `.magic/sensitive_report.md`
+pub fn report(db: &Database) -> String {
+    let findings = db.get_sensitive_findings().unwrap_or_default();
+    let mut content = String::from("# Sensitive Data Report\n\n");
+    if findings.is_empty() {
+        content.push_str("_No secrets or personal data found._\n");
+        return content;
+    }
+    let mut files: Vec<u64> = findings.iter().map(|(inode, _, _)| *inode).collect();
+    files.dedup();
+    content.push_str(&format!("{} file(s) look like they contain secrets or personal data (tagged `sensitive`).\n", files.len()));
+    let mut current = None;
+    for (inode, kind, line) in findings {
+        if current != Some(inode) {
+            let path = db.get_path(inode).unwrap_or(None).unwrap_or_else(|| format!("<inode {}>", inode));
+            content.push_str(&format!("\n## {}\n", path));
+            current = Some(inode);
+        }
+        content.push_str(&format!("- {} (line {})\n", kind, line));
+    }
+    content
+}
@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use anyhow::{bail, Result};
+use regex::{Captures, Regex};
+use crate::convert::{escape_html, html_page, markdown_html};
+use crate::db::Database;
+
+// Static Site Export
+// `eidetic export --html <dir>` publishes the index as plain HTML: a page per Markdown note
+// (rendered like the `.md` -> `.html` converter, with [[wiki links]] and links to other notes
+// pointing at their pages) and per summarized document, each with its summary, tags and
+// backlinks, plus a page per tag and an index. Pages keep the Source folder's layout.
+
+struct Page {
+    inode: u64,
+    /// Source-relative path of the file
+    source: String,
+    /// Site-relative path of the page
+    url: String,
+    note: bool,
+    tags: Vec<String>,
+}
+
+/// Writes the site to `out_dir`; returns a one-line summary.
+pub fn export(db: &Database, source_path: &Path, out_dir: &Path) -> Result<String> {
+    if out_dir.starts_with(source_path) {
+        bail!("Export to a folder outside the Source folder, or the site gets indexed too");
+    }
+    let mut pages = Vec::new();
+    for file in db.get_indexed_files()? {
+        let Some(source) = db.get_path(file.inode)? else { continue };
+        let note = crate::links::is_note(&file.name);
+        if !note && db.get_summary(file.inode)?.is_none() {
+            continue;
+        }
+        let url = if note {
+            format!("{}.html", source.rsplit_once('.').map_or(source.as_str(), |(stem, _)| stem))
+        } else {
+            format!("{}.html", source)
+        };
+        pages.push(Page { inode: file.inode, source, url, note, tags: file.tags });
+    }
+    if pages.is_empty() {
+        bail!("Nothing to export: no notes or summaries in the index yet");
+    }
+
+    // Notes by lowercase name and by path, to resolve links
+    let mut by_name: HashMap<String, &Page> = HashMap::new();
+    let mut by_path: HashMap<&str, &Page> = HashMap::new();
+    for page in pages.iter().filter(|page| page.note) {
+        let name = page.source.rsplit('/').next().unwrap_or(&page.source);
+        by_name.entry(stem(name).to_lowercase()).or_insert(page);
+        by_path.insert(&page.source, page);
+    }
+    let mut tags: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in &pages {
+        for tag in &page.tags {
+            tags.entry(tag).or_default().push(page);
+        }
+    }
+
+    for page in &pages {
+        let mut body = format!("<p><a href=\"{}\">Index</a></p>\n", relative(&page.url, "index.html"));
+        if page.note {
+            let markdown = fs::read_to_string(source_path.join(&page.source)).unwrap_or_default();
+            body.push_str(&markdown_html(&link_notes(&markdown, page, &by_name, &by_path)));
+        } else {
+            body.push_str(&format!("<h1>{}</h1>\n", escape_html(&page.source)));
+        }
+        if let Some(summary) = db.get_summary(page.inode)? {
+            body.push_str(&format!("<h2>Summary</h2>\n<p>{}</p>\n", escape_html(&summary)));
+        }
+        if !page.tags.is_empty() {
+            body.push_str("<h2>Tags</h2>\n<ul>\n");
+            for tag in &page.tags {
+                body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", relative(&page.url, &tag_url(tag)), escape_html(tag)));
+            }
+            body.push_str("</ul>\n");
+        }
+        let backlinks = backlinks(db, page, &pages)?;
+        if !backlinks.is_empty() {
+            body.push_str("<h2>Backlinks</h2>\n<ul>\n");
+            for from in backlinks {
+                body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", relative(&page.url, &from.url), escape_html(&from.source)));
+            }
+            body.push_str("</ul>\n");
+        }
+        write(out_dir, &page.url, &html_page(&page.source, &body))?;
+    }
+
+    for (tag, tagged) in &tags {
+        let url = tag_url(tag);
+        let mut body = format!("<p><a href=\"{}\">Index</a></p>\n<h1>{}</h1>\n<ul>\n", relative(&url, "index.html"), escape_html(tag));
+        for page in tagged {
+            body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", relative(&url, &page.url), escape_html(&page.source)));
+        }
+        body.push_str("</ul>\n");
+        write(out_dir, &url, &html_page(tag, &body))?;
+    }
+
+    let mut body = String::from("<h1>Index</h1>\n<h2>Pages</h2>\n<ul>\n");
+    let mut sorted: Vec<&Page> = pages.iter().collect();
+    sorted.sort_by(|a, b| a.source.cmp(&b.source));
+    for page in sorted {
+        body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", page.url, escape_html(&page.source)));
+    }
+    body.push_str("</ul>\n<h2>Tags</h2>\n<ul>\n");
+    for (tag, tagged) in &tags {
+        body.push_str(&format!("<li><a href=\"{}\">{}</a> ({})</li>\n", tag_url(tag), escape_html(tag), tagged.len()));
+    }
+    body.push_str("</ul>\n");
+    write(out_dir, "index.html", &html_page("Index", &body))?;
+
+    Ok(format!("Exported {} pages and {} tags to {}\n", pages.len(), tags.len(), out_dir.display()))
+}
+
+fn stem(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+// Tags like "lang:de" would read as a URL scheme
+fn tag_url(tag: &str) -> String {
+    let name: String = tag.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect();
+    format!("tags/{}.html", name)
+}
+
+// URL of site path `to` from the page at site path `from`
+fn relative(from: &str, to: &str) -> String {
+    let depth = from.matches('/').count();
+    format!("{}{}", "../".repeat(depth), to)
+}
+
+// [[Wiki Links]] and links to other notes, pointed at their pages
+fn link_notes(markdown: &str, page: &Page, by_name: &HashMap<String, &Page>, by_path: &HashMap<&str, &Page>) -> String {
+    static PATTERNS: OnceLock<(Regex, Regex)> = OnceLock::new();
+    let (wiki, markdown_link) = PATTERNS.get_or_init(|| (
+        Regex::new(r"\[\[([^\]\|#]+)(#[^\]\|]*)?(?:\|([^\]]*))?\]\]").expect("wiki link pattern"),
+        Regex::new(r"\]\(([^)\s#]+\.(?:md|markdown))(#[^)\s]*)?\)").expect("note link pattern"),
+    ));
+    let linked = wiki.replace_all(markdown, |caps: &Captures| {
+        let target = caps[1].trim();
+        let label = caps.get(3).map_or(target, |alias| alias.as_str().trim());
+        let name = stem(target.rsplit('/').next().unwrap_or(target)).to_lowercase();
+        match by_name.get(&name) {
+            Some(to) => format!("[{}]({}{})", label, relative(&page.url, &to.url), caps.get(2).map_or("", |m| m.as_str())),
+            None => label.to_string(),
+        }
+    });
+    let dir = page.source.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    markdown_link.replace_all(&linked, |caps: &Captures| {
+        let target = caps[1].replace("%20", " ");
+        let path = match target.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None => crate::links::normalize(&format!("{}/{}", dir, target)).unwrap_or_default(),
+        };
+        match by_path.get(path.as_str()) {
+            Some(to) => format!("]({}{})", relative(&page.url, &to.url), caps.get(2).map_or("", |m| m.as_str())),
+            None => caps[0].to_string(),
+        }
+    }).into_owned()
+}
+
+// Exported notes linking to `page`
+fn backlinks<'a>(db: &Database, page: &Page, pages: &'a [Page]) -> Result<Vec<&'a Page>> {
+    let sources = db.get_linking_notes(&crate::links::targets_of(&page.source))?;
+    Ok(pages.iter().filter(|from| from.inode != page.inode && sources.contains(&from.inode)).collect())
+}
+
+fn write(out_dir: &Path, url: &str, html: &str) -> Result<()> {
+    let path = out_dir.join(url);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, html)?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use crate::db::Database;
+use crate::throttle::IoPriority;
+use tracing::{error, warn};
+
+// Space Report
+// `.magic/largest.md`: the biggest files and a du-style rollup of the heaviest folders,
+// from the sizes the worker records at analysis. Walking every file up to the root is too
+// slow to redo on each `stat`, so a low-priority thread rebuilds the report every REFRESH
+// and keeps it in the meta table. It measures Eidetic's own folders for `stats.md` too.
+
+const REFRESH: Duration = Duration::from_secs(15 * 60);
+
+/// Entries per section of the report.
+const LIMIT: usize = 20;
+
+const REPORT_KEY: &str = "largest_report";
+
+pub fn start(source_path: PathBuf) {
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        crate::throttle::lower_priority(19, IoPriority::Idle);
+
+        loop {
+            if let Err(e) = db.set_meta(REPORT_KEY, &report(&db)) {
+                warn!(error = %e, "failed to store report");
+            }
+            crate::stats::record_storage(&db, &source_path);
+            thread::sleep(REFRESH);
+        }
+    });
+}
+
+/// The report as of the last refresh.
+pub fn cached(db: &Database) -> String {
+    db.get_meta(REPORT_KEY).ok().flatten()
+        .unwrap_or_else(|| "# Largest Files\n\n_Not computed yet, check back in a minute._\n".to_string())
+}
+
+/// "4.2 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+/// Full Markdown report for `.magic/largest.md`.
+pub fn report(db: &Database) -> String {
+    let files = db.get_file_sizes().unwrap_or_default();
+    let mut content = String::from("# Largest Files\n\n");
+    if files.is_empty() {
+        content.push_str("_No files analyzed yet._\n");
+        return content;
+    }
+    let total: u64 = files.iter().map(|(_, _, size)| size).sum();
+    content.push_str(&format!("{} in {} files.\n\n", format_size(total), files.len()));
+
+    content.push_str("## Files\n");
+    for (inode, name, size) in files.iter().take(LIMIT) {
+        let path = db.get_path(*inode).ok().flatten().unwrap_or_else(|| name.clone());
+        content.push_str(&format!("- {}: {}\n", path, format_size(*size)));
+    }
+
+    // Every file counts toward each folder above it
+    let mut parents: HashMap<u64, Option<u64>> = HashMap::new();
+    let mut folders: HashMap<u64, (u64, usize)> = HashMap::new();
+    for (inode, _, size) in &files {
+        let mut current = *inode;
+        // Bounded like Database::get_path in case of a parent loop
+        for _ in 0..100 {
+            let parent = *parents.entry(current)
+                .or_insert_with(|| db.get_inode_entry(current).ok().flatten().map(|(parent, _)| parent));
+            match parent {
+                Some(parent) if parent != 1 => {
+                    let folder = folders.entry(parent).or_default();
+                    folder.0 += size;
+                    folder.1 += 1;
+                    current = parent;
+                }
+                _ => break,
+            }
+        }
+    }
+    let mut folders: Vec<_> = folders.into_iter().collect();
+    folders.sort_by_key(|(dir, (size, _))| (std::cmp::Reverse(*size), *dir));
+
+    content.push_str("\n## Folders\n");
+    if folders.is_empty() {
+        content.push_str("_Everything is at the top level._\n");
+    }
+    for (dir, (size, count)) in folders.into_iter().take(LIMIT) {
+        let path = db.get_path(dir).ok().flatten().unwrap_or_else(|| format!("<inode {}>", dir));
+        content.push_str(&format!("- {}/: {} in {} file{}\n", path, format_size(size), count, if count == 1 { "" } else { "s" }));
+    }
+    content
+}
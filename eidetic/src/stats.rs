@@ -0,0 +1,78 @@
+use std::path::Path;
+use crate::db::Database;
+use crate::space::format_size;
+
+// Library Stats
+// The index, storage and cache sections of `.magic/stats.md`. Everything comes out of the
+// database: row counts, counters bumped as things happen (conversions rendered, opens of
+// converted files), and the sizes of Eidetic's own folders, which the space thread
+// measures on its refresh since walking them on every `stat` would be too slow.
+
+/// Counter of renderings made for the conversion cache (its misses).
+pub const CONVERSIONS: &str = "conversions";
+
+/// Counter of opens of converted and translated files.
+pub const CONVERTED_OPENS: &str = "converted_opens";
+
+/// Folders under `.eidetic/` measured for the storage section, with their labels.
+const STORAGE: &[(&str, &str)] = &[("history", "Version history"), ("trash", "Trash"), ("cache", "Conversion cache")];
+
+/// Measures the folders in STORAGE into the meta table.
+pub fn record_storage(db: &Database, source_path: &Path) {
+    for (dir, _) in STORAGE {
+        let size = dir_size(&source_path.join(".eidetic").join(dir));
+        let _ = db.set_meta(&storage_key(dir), &size.to_string());
+    }
+}
+
+fn storage_key(dir: &str) -> String {
+    format!("storage_{}", dir)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else { return 0 };
+    entries.flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map_or(0, |m| m.len()),
+        })
+        .sum()
+}
+
+/// Sections of `stats.md`.
+pub fn summary(db: &Database) -> String {
+    let mut content = String::from("\n## Index\n");
+    content.push_str(&format!("- **Indexed files**: {}\n", db.count_indexed_files().unwrap_or(0)));
+    let (pending, failed) = db.count_jobs().unwrap_or_default();
+    content.push_str(&format!("- **Pending jobs**: {} ({} failed recently)\n", pending, failed));
+    let last_scan = db.get_meta(crate::scan::SCANNED_KEY).ok().flatten().and_then(|t| t.parse().ok());
+    content.push_str(&format!("- **Last full scan**: {}\n", last_scan.map_or("never".to_string(), local_time)));
+
+    content.push_str("\n## Storage\n");
+    for (dir, label) in STORAGE {
+        match db.get_meta(&storage_key(dir)).ok().flatten().and_then(|size| size.parse().ok()) {
+            Some(size) => content.push_str(&format!("- **{}**: {}\n", label, format_size(size))),
+            None => content.push_str(&format!("- **{}**: _not measured yet_\n", label)),
+        }
+    }
+    let (copies, bytes) = db.duplicate_content().unwrap_or_default();
+    content.push_str(&format!("- **Dedup savings**: {} reclaimable from {} duplicate files\n", format_size(bytes), copies));
+
+    let conversions = db.get_counter(CONVERSIONS).unwrap_or(0);
+    let opens = db.get_counter(CONVERTED_OPENS).unwrap_or(0);
+    content.push_str("\n## Conversion Cache\n");
+    content.push_str(&format!("- **Opens of converted files**: {}\n", opens));
+    content.push_str(&format!("- **Conversions rendered**: {}\n", conversions));
+    if opens > 0 {
+        let hits = opens.saturating_sub(conversions);
+        content.push_str(&format!("- **Hit rate**: {}%\n", hits * 100 / opens));
+    }
+    content
+}
+
+// "2024-05-01 14:03" in local time
+pub fn local_time(timestamp: i64) -> String {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&timestamp, &mut tm) };
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min)
+}
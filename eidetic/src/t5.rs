@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::{anyhow, Context, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::t5;
+use crate::model::{Backend, Heuristic};
+use tracing::warn;
+
+// Local T5 Summarizer
+// A small T5 run on the CPU through candle. The model is never fetched behind the user's
+// back: `eidetic model download` asks first, then stores it in ~/.eidetic/models/<name>/.
+// The worker loads it on the first summary; until it's downloaded (or if it fails to load)
+// summaries fall back to the heuristic.
+
+/// Models `eidetic model download` knows about: (name, Hugging Face repo, approximate size).
+pub const MODELS: &[(&str, &str, &str)] = &[
+    ("t5-small", "google-t5/t5-small", "240 MB"),
+    ("t5-base", "google-t5/t5-base", "890 MB"),
+    // Not a T5: the sentence embedder for `[embeddings]` (minilm.rs), packaged the same way
+    (crate::minilm::MODEL_NAME, "sentence-transformers/all-MiniLM-L6-v2", "90 MB"),
+];
+
+pub const DEFAULT_MODEL: &str = "t5-small";
+
+const FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
+
+/// Input tokens fed to the encoder; T5 was trained on 512.
+const MAX_INPUT_TOKENS: usize = 512;
+
+/// Characters of the prompt tokenized. Tokens average well under 8 characters, so this still
+/// fills the encoder, and a long document isn't segmented in full only to be cut at 512 tokens.
+const MAX_INPUT_CHARS: usize = MAX_INPUT_TOKENS * 8;
+
+/// Longest summary generated, in tokens.
+const MAX_OUTPUT_TOKENS: usize = 128;
+
+pub fn models_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    PathBuf::from(home).join(".eidetic").join("models")
+}
+
+fn model_dir(name: &str) -> PathBuf {
+    models_dir().join(name)
+}
+
+pub fn is_downloaded(name: &str) -> bool {
+    FILES.iter().all(|file| model_dir(name).join(file).exists())
+}
+
+/// Fetches a model's files from Hugging Face. Each file lands under a temporary name first
+/// so an interrupted download is never mistaken for a complete one.
+pub fn download(name: &str) -> Result<()> {
+    crate::http::ensure_online()?;
+    let (_, repo, _) = MODELS.iter().find(|(model, _, _)| *model == name)
+        .ok_or_else(|| anyhow!("Unknown model {} (available: {})", name, MODELS.iter().map(|m| m.0).collect::<Vec<_>>().join(", ")))?;
+    let dir = model_dir(name);
+    std::fs::create_dir_all(&dir)?;
+    let client = reqwest::blocking::Client::builder().timeout(None).build()?;
+    for file in FILES {
+        let target = dir.join(file);
+        if target.exists() {
+            continue;
+        }
+        println!("Downloading {}...", file);
+        let url = format!("https://huggingface.co/{}/resolve/main/{}", repo, file);
+        let mut response = client.get(&url).send()?.error_for_status().with_context(|| format!("GET {}", url))?;
+        let partial = dir.join(format!("{}.part", file));
+        let result = (|| -> Result<()> {
+            let mut out = std::fs::File::create(&partial)?;
+            response.copy_to(&mut out)?;
+            out.flush()?;
+            std::fs::rename(&partial, &target)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&partial);
+        }
+        result?;
+    }
+    Ok(())
+}
+
+/// SentencePiece unigram tokenizer read from a Hugging Face `tokenizer.json`.
+struct Tokenizer {
+    pieces: Vec<String>,
+    ids: HashMap<String, (u32, f64)>,
+    unk_id: u32,
+    max_piece_chars: usize,
+}
+
+impl Tokenizer {
+    fn load(path: &Path) -> Result<Self> {
+        let json: serde_json::Value = serde_json::from_reader(std::fs::File::open(path)?)?;
+        let model = &json["model"];
+        if model["type"] != "Unigram" {
+            return Err(anyhow!("Unsupported tokenizer type {}", model["type"]));
+        }
+        let vocab = model["vocab"].as_array().ok_or_else(|| anyhow!("Tokenizer has no vocab"))?;
+        let mut pieces = Vec::with_capacity(vocab.len());
+        let mut ids = HashMap::with_capacity(vocab.len());
+        for (id, entry) in vocab.iter().enumerate() {
+            let piece = entry[0].as_str().unwrap_or_default().to_string();
+            ids.insert(piece.clone(), (id as u32, entry[1].as_f64().unwrap_or(f64::MIN)));
+            pieces.push(piece);
+        }
+        let max_piece_chars = pieces.iter().map(|p| p.chars().count()).max().unwrap_or(1);
+        Ok(Self { pieces, ids, unk_id: model["unk_id"].as_u64().unwrap_or(2) as u32, max_piece_chars })
+    }
+
+    // Viterbi segmentation of "▁word▁word" into the highest-scoring pieces
+    fn encode(&self, text: &str) -> Vec<u32> {
+        let normalized: String = text.split_whitespace().flat_map(|word| std::iter::once('▁').chain(word.chars())).collect();
+        let chars: Vec<char> = normalized.chars().collect();
+        // best[i] = (score, start of last piece, its id) of the best segmentation of chars[..i]
+        let mut best: Vec<(f64, usize, u32)> = vec![(f64::MIN, 0, 0); chars.len() + 1];
+        best[0].0 = 0.0;
+        let unk_score = self.ids.values().map(|(_, score)| *score).fold(0.0, f64::min) - 10.0;
+        for end in 1..=chars.len() {
+            for start in end.saturating_sub(self.max_piece_chars)..end {
+                if best[start].0 == f64::MIN {
+                    continue;
+                }
+                let piece: String = chars[start..end].iter().collect();
+                let candidate = match self.ids.get(&piece) {
+                    Some((id, score)) => Some((best[start].0 + score, *id)),
+                    // Unknown characters become <unk> one at a time
+                    None if end - start == 1 => Some((best[start].0 + unk_score, self.unk_id)),
+                    None => None,
+                };
+                if let Some((score, id)) = candidate {
+                    if score > best[end].0 {
+                        best[end] = (score, start, id);
+                    }
+                }
+            }
+        }
+        let mut ids = Vec::new();
+        let mut end = chars.len();
+        while end > 0 {
+            let (_, start, id) = best[end];
+            ids.push(id);
+            end = start;
+        }
+        ids.reverse();
+        ids
+    }
+
+    fn decode(&self, ids: &[u32]) -> String {
+        ids.iter()
+            .filter_map(|id| self.pieces.get(*id as usize))
+            // <pad>, </s>, <unk> and the other specials
+            .filter(|piece| !(piece.starts_with('<') && piece.ends_with('>')))
+            .map(|piece| piece.replace('▁', " "))
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+}
+
+struct Loaded {
+    model: t5::T5ForConditionalGeneration,
+    tokenizer: Tokenizer,
+    config: t5::Config,
+}
+
+impl Loaded {
+    fn load(dir: &Path) -> Result<Self> {
+        let config: t5::Config = serde_json::from_reader(std::fs::File::open(dir.join("config.json"))?)?;
+        let tokenizer = Tokenizer::load(&dir.join("tokenizer.json"))?;
+        // Safety: the weights file is ours and isn't modified while mapped
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DType::F32, &Device::Cpu)? };
+        let model = t5::T5ForConditionalGeneration::load(vb, &config)?;
+        Ok(Self { model, tokenizer, config })
+    }
+
+    // Greedy decoding of a prompt with one of T5's task prefixes ("summarize: ", "question: ")
+    fn generate(&mut self, prompt: &str) -> Result<String> {
+        let budget = prompt.char_indices().nth(MAX_INPUT_CHARS).map(|(i, _)| i).unwrap_or(prompt.len());
+        let mut input = self.tokenizer.encode(&prompt[..budget]);
+        input.truncate(MAX_INPUT_TOKENS - 1);
+        input.push(self.config.eos_token_id as u32);
+        let device = Device::Cpu;
+        self.model.clear_kv_cache();
+        let encoded = self.model.encode(&Tensor::new(input.as_slice(), &device)?.unsqueeze(0)?)?;
+
+        let mut output = vec![self.config.decoder_start_token_id.unwrap_or(self.config.pad_token_id) as u32];
+        for step in 0..MAX_OUTPUT_TOKENS {
+            // With the KV cache only the newest token needs feeding back in
+            let context = if self.config.use_cache && step > 0 { &output[output.len() - 1..] } else { &output[..] };
+            let logits = self.model.decode(&Tensor::new(context, &device)?.unsqueeze(0)?, &encoded)?;
+            let next = logits.squeeze(0)?.argmax(0)?.to_scalar::<u32>()?;
+            if next as usize == self.config.eos_token_id {
+                break;
+            }
+            output.push(next);
+        }
+        Ok(self.tokenizer.decode(&output[1..]))
+    }
+}
+
+enum State {
+    NotLoaded,
+    Ready(Box<Loaded>),
+    Unavailable,
+}
+
+/// Summarizes with a downloaded T5 model, falling back to the heuristic without one.
+pub struct T5 {
+    name: String,
+    state: Mutex<State>,
+}
+
+impl T5 {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), state: Mutex::new(State::NotLoaded) }
+    }
+
+    // Runs `f` on the loaded model, or returns None if there isn't one
+    fn with_model<R>(&self, f: impl FnOnce(&mut Loaded) -> R) -> Option<R> {
+        let mut state = self.state.lock().unwrap();
+        if let State::NotLoaded = *state {
+            *state = if !is_downloaded(&self.name) {
+                warn!(model = %self.name, "not downloaded (run `eidetic model download {}`); using the heuristic", self.name);
+                State::Unavailable
+            } else {
+                match Loaded::load(&model_dir(&self.name)) {
+                    Ok(loaded) => State::Ready(Box::new(loaded)),
+                    Err(e) => {
+                        warn!(model = %self.name, error = %e, "failed to load model; using the heuristic");
+                        State::Unavailable
+                    }
+                }
+            };
+        }
+        match &mut *state {
+            State::Ready(loaded) => Some(f(loaded)),
+            _ => None,
+        }
+    }
+}
+
+impl Backend for T5 {
+    fn summarize(&self, text: &str) -> Result<String> {
+        self.with_model(|model| model.generate(&format!("summarize: {}", text)))
+            .unwrap_or_else(|| Heuristic.summarize(text))
+    }
+
+    // The SQuAD format T5 was trained on; the encoder only sees the first 512 tokens of context
+    fn ask(&self, context: &str, question: &str) -> Result<String> {
+        self.with_model(|model| model.generate(&format!("question: {} context: {}", question, context)))
+            .unwrap_or_else(|| Heuristic.ask(context, question))
+    }
+
+    // T5 only learned English to German, French and Romanian
+    fn translate(&self, text: &str, language: &str) -> Result<String> {
+        if !["German", "French", "Romanian"].contains(&language) {
+            return Err(anyhow!("T5 can't translate into {} (only German, French and Romanian)", language));
+        }
+        self.with_model(|model| model.generate(&format!("translate English to {}: {}", language, text)))
+            .unwrap_or_else(|| Heuristic.translate(text, language))
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+use serde::Deserialize;
+use tracing::warn;
+
+// Background Throttling
+// Keeps analysis and scrubbing out of the way of the mount: lower CPU and I/O priority for
+// the calling thread, and a token bucket capping how many bytes per second get read.
+
+/// I/O scheduling class for a background thread.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IoPriority {
+    /// Only gets disk time nobody else wants
+    Idle,
+    /// Lowest best-effort level
+    Low,
+    /// Left as is
+    Normal,
+}
+
+/// Applies `nice` (0-19) and `io` to the calling thread. On Linux both are per-thread.
+pub fn lower_priority(nice: i32, io: IoPriority) {
+    unsafe {
+        if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+            warn!(error = %std::io::Error::last_os_error(), "setpriority failed");
+        }
+    }
+    #[cfg(target_os = "linux")]
+    {
+        // ioprio_set(IOPRIO_WHO_PROCESS, 0 = this thread, class << 13 | level)
+        let prio = match io {
+            IoPriority::Idle => 3 << 13,
+            IoPriority::Low => (2 << 13) | 7,
+            IoPriority::Normal => return,
+        };
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, 1, 0, prio) } != 0 {
+            warn!(error = %std::io::Error::last_os_error(), "ioprio_set failed");
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = io;
+}
+
+/// Token bucket over bytes, refilled at `rate` per second with up to one second of burst.
+pub struct RateLimiter {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// `bytes_per_sec` of 0 means unlimited.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self { rate: bytes_per_sec as f64, tokens: bytes_per_sec as f64, last: Instant::now() }
+    }
+
+    /// Accounts for `bytes` about to be read, sleeping first if the budget is spent.
+    /// A file bigger than the budget is let through and paid off afterwards.
+    pub fn take(&mut self, bytes: u64) {
+        if self.rate == 0.0 {
+            return;
+        }
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * self.rate).min(self.rate);
+        self.last = now;
+        if self.tokens < 0.0 {
+            std::thread::sleep(Duration::from_secs_f64(-self.tokens / self.rate));
+            self.tokens = 0.0;
+            self.last = Instant::now();
+        }
+        self.tokens -= bytes as f64;
+    }
+}
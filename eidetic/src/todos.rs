@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use crate::db::Database;
+
+// TODOs
+// The worker records every line of a text file that says TODO or FIXME, replacing the
+// file's previous list each time it's analyzed, so `.magic/todos.md` follows edits.
+// `.magic/todos/<file>/<line>-<slug>.md` has one small file per TODO; deleting one marks
+// its text done in that file, and it stays hidden even if edits move it to another line.
+
+/// Longest TODO line kept, in characters.
+const MAX_LINE: usize = 200;
+
+/// Words of a slug, after the TODO/FIXME marker.
+const SLUG_WORDS: usize = 6;
+
+/// (line number, trimmed line) of each TODO or FIXME in `text`.
+pub fn extract(text: &str) -> Vec<(usize, String)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains("TODO") || line.contains("FIXME"))
+        .map(|(i, line)| (i + 1, line.trim().chars().take(MAX_LINE).collect()))
+        .collect()
+}
+
+/// "12-handle-timeouts.md" for `// TODO: handle timeouts` on line 12.
+pub fn file_name(line: usize, content: &str) -> String {
+    let rest = ["TODO", "FIXME"].iter()
+        .filter_map(|marker| content.find(marker).map(|at| &content[at + marker.len()..]))
+        .next()
+        .unwrap_or(content);
+    let slug = rest.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(SLUG_WORDS)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { format!("{}.md", line) } else { format!("{}-{}.md", line, slug) }
+}
+
+/// Contents of the file for one TODO.
+pub fn item(db: &Database, source: u64, line: usize) -> Option<String> {
+    let (_, _, content) = db.get_todos().ok()?.into_iter().find(|(inode, l, _)| *inode == source && *l == line)?;
+    let path = db.get_path(source).ok().flatten()?;
+    Some(format!("# {}:{}\n\n{}\n", path, line, content))
+}
+
+/// Markdown list of the TODOs, grouped by file.
+pub fn report(db: &Database) -> String {
+    let mut files: BTreeMap<String, Vec<(usize, String)>> = BTreeMap::new();
+    for (inode, line, content) in db.get_todos().unwrap_or_default() {
+        if let Ok(Some(path)) = db.get_path(inode) {
+            files.entry(path).or_default().push((line, content));
+        }
+    }
+    let mut content = String::from("# TODOs\n");
+    if files.is_empty() {
+        content.push_str("\n_No TODO or FIXME found._\n");
+        return content;
+    }
+    for (path, todos) in files {
+        content.push_str(&format!("\n## {}\n", path));
+        for (line, todo) in todos {
+            content.push_str(&format!("- {}: {}\n", line, todo));
+        }
+    }
+    content
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use anyhow::{anyhow, Result};
+
+// Speech-to-Text
+// Recordings get a transcript from whatever speech-to-text tool `[transcription]` names,
+// typically whisper.cpp (`whisper-cli`) or OpenAI's `whisper`. ffmpeg first decodes the
+// recording to the 16 kHz mono WAV those tools expect; the transcript is stored in the DB
+// and served as `<name>.m4a.txt`. Off until a command is configured.
+
+/// Recordings that get transcribed (songs with artist tags are skipped by the worker).
+pub const AUDIO_FORMATS: &[&str] = &["m4a", "mp3", "wav", "ogg", "oga", "opus", "flac", "aac", "amr", "3gp"];
+
+pub struct Transcriber {
+    template: Vec<String>,
+}
+
+impl Transcriber {
+    /// `command` as in the config: `{input}` is the WAV, the transcript goes to `{output}` or stdout.
+    pub fn new(command: &str) -> Result<Self> {
+        let template = crate::convert::parse_command(command).map_err(|e| anyhow!("[transcription] command: {}", e))?;
+        Ok(Self { template })
+    }
+
+    pub fn transcribe(&self, source: &Path) -> Result<String> {
+        // A private directory of its own (0700, random name), removed when dropped
+        let scratch = tempfile::Builder::new().prefix("eidetic-transcribe-").tempdir()?;
+        let wav = scratch.path().join("audio.wav");
+        let text = scratch.path().join("transcript.txt");
+        decode(source, &wav)?;
+        crate::convert::run_command(&self.template, &wav, &text)?;
+        let transcript = std::fs::read_to_string(&text)?.trim().to_string();
+        if transcript.is_empty() {
+            return Err(anyhow!("Empty transcript"));
+        }
+        Ok(transcript)
+    }
+}
+
+// Any audio ffmpeg reads to 16 kHz mono 16-bit WAV
+fn decode(source: &Path, target: &Path) -> Result<()> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-i"])
+        .arg(source)
+        .args(["-vn", "-ar", "16000", "-ac", "1", "-c:a", "pcm_s16le"])
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| anyhow!("ffmpeg: {} (is ffmpeg installed?)", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("ffmpeg failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
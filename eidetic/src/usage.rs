@@ -0,0 +1,64 @@
+use crate::db::Database;
+use crate::space::format_size;
+use tracing::{info, warn};
+
+// Disk Usage
+// `.magic/usage/` mirrors the real directory tree, each folder's size being the total of
+// everything below it, and `usage.md` in each breaks that down like `du`. The totals live in
+// the dir_usage table, adjusted as files are analyzed, written, moved and deleted, so finding
+// what takes the space never walks the mount. Databases from before the table are filled in
+// once, from the sizes the worker already recorded.
+
+const BUILT_KEY: &str = "usage_built";
+
+/// Files listed in each report.
+const LIMIT: usize = 20;
+
+/// Fills in the rollups if they were never computed, or recomputes them with `force` (--rescan).
+pub fn ensure(db: &Database, force: bool) {
+    if !force && db.get_meta(BUILT_KEY).unwrap_or(None).is_some() {
+        return;
+    }
+    match db.rebuild_usage() {
+        Ok(()) => {
+            let _ = db.set_meta(BUILT_KEY, "1");
+            info!("directory usage rebuilt");
+        }
+        Err(e) => warn!(error = %e, "failed to rebuild directory usage"),
+    }
+}
+
+/// `usage.md` of the real directory with this inode (1 is the root).
+pub fn report(db: &Database, dir: u64) -> String {
+    let path = if dir == 1 { String::new() } else { db.get_path(dir).ok().flatten().unwrap_or_default() };
+    let mut content = format!("# Usage of /{}\n\n", path);
+    let Some((bytes, files)) = db.dir_usage(dir).ok().flatten().filter(|(_, files)| *files > 0) else {
+        content.push_str("_No files analyzed here yet._\n");
+        return content;
+    };
+    content.push_str(&format!("{} in {} file{}.\n", format_size(bytes), files, if files == 1 { "" } else { "s" }));
+
+    let folders = db.child_dir_usage(dir).unwrap_or_default();
+    if !folders.is_empty() {
+        content.push_str("\n## Folders\n");
+        for (_, name, size, count) in folders {
+            content.push_str(&format!("- {}/: {} in {} file{} ({:.0}%)\n", name, format_size(size), count, if count == 1 { "" } else { "s" }, share(size, bytes)));
+        }
+    }
+
+    let sizes = db.child_file_sizes(dir).unwrap_or_default();
+    if !sizes.is_empty() {
+        content.push_str("\n## Files\n");
+        for (_, name, size) in sizes.iter().take(LIMIT) {
+            content.push_str(&format!("- {}: {} ({:.0}%)\n", name, format_size(*size), share(*size, bytes)));
+        }
+        if sizes.len() > LIMIT {
+            content.push_str(&format!("- …and {} more\n", sizes.len() - LIMIT));
+        }
+    }
+    content
+}
+
+fn share(part: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { part as f64 * 100.0 / total as f64 }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+// Virtual Inode Store
+// Virtual directories and files that aren't derived from a single real inode (per-folder
+// .thumbnails/, ...) get inodes from a counter in their own range. The mapping lives for
+// the lifetime of the mount, which is as long as the kernel may hold on to an inode number.
+
+/// Virtual inodes have 0b0001 as their top four bits; CONTEXT/CONVERT_BIT and the MAGIC_* constants never do.
+const VIRTUAL_BASE: u64 = 1 << 60;
+
+pub fn is_virtual(inode: u64) -> bool {
+    inode >> 60 == 1
+}
+
+/// What a virtual inode stands for.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum View {
+    /// `.thumbnails/` inside the real directory with this inode
+    Thumbnails(u64),
+    /// Directory or member at `path` ("" for the root) inside the archive with this real inode
+    Archive { archive: u64, path: String },
+    /// `.magic/photos/` and below, e.g. ["by-date", "2024", "05"]
+    Photos(Vec<String>),
+    /// `.magic/clusters/` and below, e.g. ["invoice-payment"]
+    Clusters(Vec<String>),
+    /// `.magic/by-date/` and below, e.g. ["2024", "06", "15"]
+    ByDate(Vec<String>),
+    /// `.magic/by-type/` and below, e.g. ["images"]
+    ByType(Vec<String>),
+    /// `.magic/by-size/` and below, e.g. ["10-100MB"]
+    BySize(Vec<String>),
+    /// `.magic/recent/` and its groups, e.g. ["Yesterday"]
+    Recent(Vec<String>),
+    /// `.magic/music/` and below, e.g. ["by-artist", "Nina Simone", "Pastel Blues"]
+    Music(Vec<String>),
+    /// `<name>.summary` (or `.summary.txt`): the worker's summary of the real file with this inode
+    Summary(u64),
+    /// `<name>.m4a.txt`: the transcript of the recording with this inode
+    Transcript(u64),
+    /// `<note>.md.backlinks`: notes linking to the note with this inode
+    Backlinks(u64),
+    /// `<name>.comments`: the writable comment on the real file with this inode
+    Comments(u64),
+    /// `<note>.md.lint`: proofreading findings of the prose file with this inode
+    Lint(u64),
+    /// `notes.de.txt` (`name`): the real text file with this inode translated into `language` (ISO 639-1)
+    Translation { source: u64, language: String, name: String },
+    /// `.magic/integrity_report.md`: files that failed checksum verification
+    IntegrityReport,
+    /// `.magic/sensitive_report.md`: files with secrets or personal data in them
+    SensitiveReport,
+    /// `.magic/events.log`: the latest tags, trashings, snapshots, moves and vault unlocks
+    Events,
+    /// `.magic/health.json`: machine-readable daemon status
+    Health,
+    /// `.magic/jobs/`, holding `status.md` (JobsStatus) and `control` (JobsControl)
+    Jobs,
+    JobsStatus,
+    /// Reads "running" or "paused"; writing "pause" or "resume" switches the worker
+    JobsControl,
+    /// `.ask` in the real directory with this inode: write a question, read back the answer
+    Ask(u64),
+    /// `.magic/rename_suggestions/`, holding one RenameSuggestion per proposal
+    RenameSuggestions,
+    /// Proposed new name for the real file with this inode; mv it onto the original to apply
+    RenameSuggestion(u64),
+    /// `.magic/proposals/`, holding one Proposal per move the auto-organizer suggests
+    Proposals,
+    /// Proposed move of the real file with this inode; touch it (or mv it onto the original) to
+    /// apply, rm it to dismiss
+    Proposal(u64),
+    /// `.magic/favorites/`: pinned files; mv a file here to pin it, rm it here to unpin
+    Favorites,
+    /// `.magic/starred/`: rated files, best first; rm a file here to clear its rating
+    Starred,
+    /// `.magic/labels/` and its color folders, e.g. ["red"]; mv a file into one to label it
+    Labels(Vec<String>),
+    /// `.magic/untagged/`: analyzed files without tags
+    Untagged,
+    /// `.magic/unreviewed/`: analyzed files never opened for reading
+    Unreviewed,
+    /// `.magic/heatmap.md`: busiest files and folders, and files never opened
+    Heatmap,
+    /// `.magic/todos.md`: TODO and FIXME lines of all text files
+    Todos,
+    /// `.magic/todos/`, one TodoFile directory per file with open TODOs
+    TodoFiles,
+    TodoFile(u64),
+    /// `<line>-<slug>.md` for one TODO of a file; rm it to mark the TODO done
+    Todo { source: u64, line: usize },
+    /// `.magic/largest.md`: biggest files and folders, as of the last refresh
+    Largest,
+    /// `.magic/usage/` and its subdirectories, mirroring the real directory with this inode
+    /// (1 for the root); each directory's size is the total below it
+    Usage(u64),
+    /// `usage.md` in a Usage directory: du-style breakdown of the real directory with this inode
+    UsageReport(u64),
+    /// `.magic/organizer_log.md`: accepted organizer moves, and which were undone
+    OrganizerLog,
+    /// `.magic/similar_images/`, one SimilarTo directory per hashed image
+    SimilarImages,
+    /// The real files that look like the image with this inode
+    SimilarTo(u64),
+    /// `.magic/wormhole/send/`: copy a file in to get a wormhole code for it
+    WormholeSend,
+    /// A file staged in `send/` until the peer has it
+    WormholeOutgoing(String),
+    /// `<name>.code` in `send/`: the code the staged file waits under, and how the transfer goes
+    WormholeCode(String),
+    /// `.magic/wormhole/receive`: write a code to fetch what it offers, read back the transfers
+    WormholeReceive,
+    /// `.magic/wormhole/inbox/`: the verified files received, newest first
+    WormholeInbox,
+    /// `.magic/api/<name>`: the response of the endpoint of that name in ~/.eidetic/api.toml
+    Api(String),
+    /// `.magic/api/<name>`: the markdown digest of the feed of that name in ~/.eidetic/api.toml
+    Feed(String),
+}
+
+#[derive(Default)]
+pub struct VirtualInodeStore {
+    by_view: HashMap<View, u64>,
+    by_inode: HashMap<u64, View>,
+}
+
+impl VirtualInodeStore {
+    /// Same view, same inode, for as long as the store lives.
+    pub fn inode_for(&mut self, view: View) -> u64 {
+        if let Some(inode) = self.by_view.get(&view) {
+            return *inode;
+        }
+        let inode = VIRTUAL_BASE + self.by_inode.len() as u64 + 1;
+        self.by_view.insert(view.clone(), inode);
+        self.by_inode.insert(inode, view);
+        inode
+    }
+
+    pub fn view(&self, inode: u64) -> Option<View> {
+        self.by_inode.get(&inode).cloned()
+    }
+}
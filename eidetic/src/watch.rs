@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use notify::event::{AccessKind, AccessMode, CreateKind, EventKind, ModifyKind, RenameMode};
+use notify::{RecursiveMode, Watcher};
+use crate::db::Database;
+use crate::queue::{JobQueue, Priority};
+use crate::scan;
+use tracing::{error, warn};
+
+// Source Watcher
+// Changes made to the source dir directly (rsync, git pull, another machine's sync client)
+// bypass the mount. An inotify watch keeps the inode table in step: renames keep their
+// inode (and so their tags), deletes drop it, and finished writes are queued for analysis.
+// Writes through the mount land in the source dir too; their echo coalesces with the job
+// the FS already queued, and the DB updates find nothing left to do.
+
+// How long a rename-from waits for its rename-to before it's treated as a move out of the tree
+const RENAME_WINDOW: Duration = Duration::from_millis(500);
+
+pub fn start(source_path: PathBuf, jobs: JobQueue) {
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!(error = %e, "failed to start watcher");
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&source_path, RecursiveMode::Recursive) {
+            error!(?source_path, error = %e, "failed to watch");
+            return;
+        }
+
+        let mut reconciler = Reconciler { db, jobs, source_path, moved_out: HashMap::new() };
+        loop {
+            match rx.recv_timeout(RENAME_WINDOW) {
+                Ok(Ok(event)) => reconciler.handle(event),
+                Ok(Err(e)) => warn!(error = %e, "watch error"),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            reconciler.expire_renames();
+        }
+    });
+}
+
+struct Reconciler {
+    db: Database,
+    jobs: JobQueue,
+    source_path: PathBuf,
+    // Rename cookie -> (old relative path, when it left)
+    moved_out: HashMap<usize, (String, Instant)>,
+}
+
+impl Reconciler {
+    fn handle(&mut self, event: notify::Event) {
+        let Some(path) = event.paths.first() else { return };
+        let Some(rel) = self.relative(path) else { return };
+        match event.kind {
+            EventKind::Access(AccessKind::Close(AccessMode::Write)) => self.analyze(path, &rel),
+            EventKind::Create(CreateKind::Folder) => {
+                let _ = self.db.alloc_path(&rel);
+            }
+            EventKind::Remove(_) => self.remove(&rel),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => match event.tracker() {
+                Some(cookie) => {
+                    self.moved_out.insert(cookie, (rel, Instant::now()));
+                }
+                None => self.remove(&rel),
+            },
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                let from = event.tracker().and_then(|cookie| self.moved_out.remove(&cookie));
+                self.moved_in(path, &rel, from.map(|(from, _)| from));
+            }
+            _ => {}
+        }
+    }
+
+    // Source-relative path, or None for paths Eidetic doesn't track
+    fn relative(&self, path: &Path) -> Option<String> {
+        let rel = path.strip_prefix(&self.source_path).ok()?;
+        if rel.as_os_str().is_empty() || rel.iter().any(|part| scan::ignored(&part.to_string_lossy())) {
+            return None;
+        }
+        Some(rel.to_string_lossy().to_string())
+    }
+
+    fn analyze(&self, path: &Path, rel: &str) {
+        match self.db.alloc_path(rel) {
+            Ok(inode) => self.jobs.enqueue(&self.db, inode, path.to_path_buf(), Priority::Bulk),
+            Err(e) => warn!(rel, error = %e, "failed to allocate inode"),
+        }
+    }
+
+    fn remove(&self, rel: &str) {
+        if let Ok(Some(inode)) = self.db.lookup_path(rel) {
+            let _ = self.db.delete_tree(inode);
+        }
+    }
+
+    fn moved_in(&self, path: &Path, rel: &str, from: Option<String>) {
+        let known = from.and_then(|from| self.db.lookup_path(&from).ok().flatten());
+        if let Some(inode) = known {
+            let (parent, name) = rel.rsplit_once('/').unwrap_or(("", rel));
+            let Ok(parent) = self.db.alloc_path(parent) else { return };
+            // A rename over an existing file replaces it
+            if let Ok(Some(existing)) = self.db.get_inode(parent, name) {
+                if existing != inode {
+                    let _ = self.db.delete_tree(existing);
+                }
+            }
+            if let Err(e) = self.db.rename_inode(inode, parent, name) {
+                warn!(rel, error = %e, "failed to record rename");
+            }
+        }
+        if !path.is_dir() {
+            self.analyze(path, rel);
+        } else if known.is_none() {
+            // Moved in from outside the tree with its contents
+            scan::queue_tree(&self.db, &self.jobs, &self.source_path, path);
+        }
+    }
+
+    // Renames whose other half never came were moves out of the source dir
+    fn expire_renames(&mut self) {
+        let expired: Vec<usize> = self.moved_out.iter()
+            .filter(|(_, (_, at))| at.elapsed() >= RENAME_WINDOW)
+            .map(|(cookie, _)| *cookie)
+            .collect();
+        for cookie in expired {
+            if let Some((rel, _)) = self.moved_out.remove(&cookie) {
+                self.remove(&rel);
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use dav_server::body::Body;
+use dav_server::fakels::FakeLs;
+use dav_server::localfs::LocalFs;
+use dav_server::{DavHandler, DavMethodSet};
+use hyper::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::StatusCode;
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tracing::{info, warn};
+
+// WebDAV
+// `eidetic serve --webdav :8080` shares the mount over WebDAV for devices that can't run FUSE:
+// phones, Windows machines, media players. It serves the mount point rather than the source
+// directory, so clients see exactly what the mount shows, `.magic/` and converted files
+// included, and writes go through the mount like any other. Clients log in as "eidetic" with
+// their own password, generated into ~/.eidetic/webdav_password, not the API token. A bare
+// port listens on loopback only; other addresses need `--tls-cert` and `--tls-key`, so the
+// password never crosses the network in the clear.
+
+const USER: &str = "eidetic";
+
+const PASSWORD_FILE: &str = "webdav_password";
+
+/// How long to back off after a failed accept (out of file descriptors, say).
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+pub fn serve(listen: &str, mountpoint: PathBuf, read_only: bool, tls: Option<(&Path, &Path)>) -> Result<()> {
+    // ":8080" listens on this machine only
+    let listen = if listen.starts_with(':') { format!("127.0.0.1{}", listen) } else { listen.to_string() };
+    let address: SocketAddr = listen.parse().with_context(|| format!("Invalid address {:?}", listen))?;
+    let acceptor = match tls {
+        Some((cert, key)) => Some(acceptor(cert, key)?),
+        None if address.ip().is_loopback() => None,
+        None => bail!("{} isn't a loopback address; serving it needs --tls-cert and --tls-key", listen),
+    };
+    let handler = DavHandler::builder()
+        .filesystem(LocalFs::new(&mountpoint, false, false, false))
+        .locksystem(FakeLs::new())
+        .methods(if read_only { DavMethodSet::WEBDAV_RO } else { DavMethodSet::WEBDAV_RW })
+        .build_handler();
+    let password = crate::rest::stored_secret(PASSWORD_FILE)?;
+    let expected = format!("Basic {}", base64(format!("{}:{}", USER, password).as_bytes()));
+
+    tokio::runtime::Runtime::new()?.block_on(accept(address, handler, expected, acceptor, mountpoint, read_only))
+}
+
+// TLS with the PEM certificate chain and private key given
+fn acceptor(cert: &Path, key: &Path) -> Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert).with_context(|| format!("Failed to open {:?}", cert))?))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid certificate {:?}", cert))?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key).with_context(|| format!("Failed to open {:?}", key))?))?
+        .with_context(|| format!("No private key in {:?}", key))?;
+    let config = ServerConfig::builder().with_no_client_auth().with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+async fn accept(address: SocketAddr, handler: DavHandler, expected: String, acceptor: Option<TlsAcceptor>, mountpoint: PathBuf, read_only: bool) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    let scheme = if acceptor.is_some() { "https" } else { "http" };
+    info!(%address, ?mountpoint, read_only, scheme, "serving WebDAV");
+    println!("Serving {:?} over WebDAV at {}://{} (user \"{}\", the password in ~/.eidetic/{})", mountpoint, scheme, address, USER, PASSWORD_FILE);
+    loop {
+        let (stream, client) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!(error = %e, "WebDAV accept failed");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let (handler, expected, acceptor) = (handler.clone(), expected.clone(), acceptor.clone());
+        tokio::spawn(async move {
+            let served = match acceptor {
+                Some(acceptor) => match acceptor.accept(stream).await {
+                    Ok(stream) => connection(stream, handler, expected).await,
+                    Err(e) => Err(e.into()),
+                },
+                None => connection(stream, handler, expected).await,
+            };
+            if let Err(e) = served {
+                warn!(%client, error = %e, "WebDAV connection failed");
+            }
+        });
+    }
+}
+
+// Serves one client connection, plain or TLS, asking every request for the credentials
+async fn connection<S>(stream: S, handler: DavHandler, expected: String) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |request: hyper::Request<hyper::body::Incoming>| {
+        let (handler, expected) = (handler.clone(), expected.clone());
+        async move {
+            if request.headers().get(AUTHORIZATION).and_then(|value| value.to_str().ok()) != Some(expected.as_str()) {
+                let mut response = hyper::Response::new(Body::from("Authentication required\n".to_string()));
+                *response.status_mut() = StatusCode::UNAUTHORIZED;
+                response.headers_mut().insert(WWW_AUTHENTICATE, HeaderValue::from_static("Basic realm=\"Eidetic\""));
+                return Ok::<_, Infallible>(response);
+            }
+            Ok(handler.handle(request).await)
+        }
+    });
+    http1::Builder::new().serve_connection(TokioIo::new(stream), service).await?;
+    Ok(())
+}
+
+// Standard base64 with padding, for the expected Basic credentials
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, byte)| n | ((*byte as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
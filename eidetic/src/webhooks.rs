@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use serde_json::json;
+use crate::config::WebhooksConfig;
+use crate::db::Database;
+use crate::events;
+use tracing::{debug, error, warn};
+
+// Webhooks
+// Outgoing POSTs for Slack, n8n, Home Assistant and the like: `[webhooks]` lists URLs per kind
+// of event (a file tagged, trashed, or flagged as sensitive), and every such event in the event
+// log is sent to them as JSON. Delivery follows the log rather than the code that records
+// events, so tags from the worker and the mount alike are covered, and the last delivered event
+// is kept in the meta table so events recorded while a hook was down or the daemon stopped go
+// out once it's back. A failed POST is retried with doubling delays; after `retries` it's
+// dropped with a warning so one dead endpoint can't hold up the rest forever.
+
+const DELIVERED_KEY: &str = "webhooks_delivered";
+
+/// How often the event log is checked for new events.
+const POLL: Duration = Duration::from_secs(2);
+
+const BATCH: usize = 100;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest wait between two attempts at the same delivery.
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+pub fn start(config: &WebhooksConfig, source_path: PathBuf) {
+    if config.tagged.is_empty() && config.trashed.is_empty() && config.sensitive.is_empty() {
+        return;
+    }
+    let config = config.clone();
+    thread::spawn(move || {
+        let db = match Database::new(source_path.join(".eidetic.db")) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(error = %e, "failed to open DB");
+                return;
+            }
+        };
+        let client = match reqwest::blocking::Client::builder().timeout(TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                error!(error = %e, "webhooks disabled");
+                return;
+            }
+        };
+        // The first time, only what happens from now on
+        let mut delivered = match db.get_meta(DELIVERED_KEY).ok().flatten().and_then(|id| id.parse().ok()) {
+            Some(id) => id,
+            None => db.last_event_id().unwrap_or(0),
+        };
+        loop {
+            // Held back, not dropped, while offline
+            if crate::http::offline() {
+                thread::sleep(POLL);
+                continue;
+            }
+            let events = db.events_after(delivered, BATCH).unwrap_or_default();
+            let caught_up = events.len() < BATCH;
+            for (id, at, kind, path, detail) in events {
+                for url in urls(&config, &kind) {
+                    post(&client, url, &payload(at, &kind, &path, &detail), config.retries);
+                }
+                delivered = id;
+                let _ = db.set_meta(DELIVERED_KEY, &id.to_string());
+            }
+            if caught_up {
+                thread::sleep(POLL);
+            }
+        }
+    });
+}
+
+fn urls<'a>(config: &'a WebhooksConfig, kind: &str) -> &'a [String] {
+    match kind {
+        events::TAG => &config.tagged,
+        events::TRASH => &config.trashed,
+        events::SENSITIVE => &config.sensitive,
+        _ => &[],
+    }
+}
+
+// `text` makes Slack and Mattermost incoming webhooks show something readable as is
+fn payload(at: i64, kind: &str, path: &str, detail: &str) -> serde_json::Value {
+    let (event, text) = match kind {
+        events::TAG => ("file.tagged", format!("Tagged {}: {}", path, detail)),
+        events::TRASH => ("file.trashed", format!("Moved {} to the trash", path)),
+        _ => ("file.sensitive", format!("Sensitive data found in {} ({})", path, detail)),
+    };
+    json!({ "event": event, "path": path, "detail": detail, "at": at, "text": text })
+}
+
+fn post(client: &reqwest::blocking::Client, url: &str, payload: &serde_json::Value, retries: u32) {
+    let mut backoff = Duration::from_secs(1);
+    for attempt in 0..=retries {
+        let error = match client.post(url).json(payload).send() {
+            Ok(response) if response.status().is_success() => {
+                debug!(url, event = %payload["event"], "webhook delivered");
+                return;
+            }
+            // The endpoint won't take it however often we ask
+            Ok(response) if response.status().is_client_error() && response.status().as_u16() != 429 => {
+                warn!(url, status = %response.status(), "webhook rejected");
+                return;
+            }
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+        if attempt == retries {
+            warn!(url, attempts = attempt + 1, error, "webhook dropped");
+            return;
+        }
+        debug!(url, attempt, error, "webhook failed, retrying");
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
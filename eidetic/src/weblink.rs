@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+// Web Links
+// A `.url` file holding a link reads as the page it links to. Pages are cached in
+// `.eidetic/cache/links/`, named after the blake3 hash of the URL, so the chunks of one read
+// and later reads come from disk; once a copy is older than `[links] cache_minutes` the next
+// read refetches it. If that fails (offline, server down) the stale copy is served instead,
+// and with no copy at all the read says what went wrong rather than returning nothing. In
+// offline mode the cached copy is all there is.
+
+// URLs being fetched; the reads a page arrives in wait for its fetch rather than start their
+// own, while other links are fetched alongside
+static FETCHING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+static FETCHED: Condvar = Condvar::new();
+
+// Holds `url` in FETCHING until dropped
+struct Fetching<'a>(&'a str);
+
+impl<'a> Fetching<'a> {
+    fn start(url: &'a str) -> Self {
+        let mut fetching = FETCHING.lock().unwrap();
+        while fetching.contains(url) {
+            fetching = FETCHED.wait(fetching).unwrap();
+        }
+        fetching.insert(url.to_string());
+        Self(url)
+    }
+}
+
+impl Drop for Fetching<'_> {
+    fn drop(&mut self) {
+        FETCHING.lock().unwrap().remove(self.0);
+        FETCHED.notify_all();
+    }
+}
+
+/// The page at `url`, from the cache while it's younger than `max_age`.
+pub fn page(source_path: &Path, url: &str, max_age: Duration) -> Vec<u8> {
+    let _fetching = Fetching::start(url);
+    let dir = source_path.join(".eidetic/cache/links");
+    let cached = dir.join(blake3::hash(url.as_bytes()).to_hex().as_str());
+    let age = std::fs::metadata(&cached).and_then(|metadata| metadata.modified()).ok().and_then(|modified| modified.elapsed().ok());
+    if age.is_some_and(|age| age < max_age) {
+        if let Ok(page) = std::fs::read(&cached) {
+            return page;
+        }
+    }
+    if crate::http::offline() {
+        return std::fs::read(&cached).unwrap_or_else(|_| format!("Offline mode is on and {} isn't cached.\n", url).into_bytes());
+    }
+    match crate::http::get(url) {
+        Ok(page) => {
+            let partial = cached.with_extension("part");
+            let stored = std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&partial, &page))
+                .and_then(|_| std::fs::rename(&partial, &cached));
+            if let Err(e) = stored {
+                warn!(url, error = %e, "failed to cache page");
+            }
+            page
+        }
+        Err(e) => match std::fs::read(&cached) {
+            Ok(page) => {
+                warn!(url, error = %e, age_secs = age.unwrap_or_default().as_secs(), "fetch failed, serving cached page");
+                page
+            }
+            Err(_) => {
+                warn!(url, error = %e, "fetch failed, nothing cached");
+                format!("Eidetic couldn't fetch {}\n{:#}\nThe page will be fetched again on the next read.\n", url, e).into_bytes()
+            }
+        },
+    }
+}
@@ -1,17 +1,40 @@
-use std::path::PathBuf;
-use std::sync::mpsc::Receiver;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
 use std::thread;
 use crate::db::Database;
+use crate::config::{AiConfig, Config, EmbeddingsConfig, LintConfig, TranscriptionConfig, WorkerConfig};
+use crate::minilm::Embedder;
+use crate::model::Summarizer;
+use crate::notifications::Notifier;
+use crate::organize::Rules;
+use crate::queue::{JobQueue, Priority};
+use crate::throttle::RateLimiter;
+use crate::transcribe::Transcriber;
+use tracing::{debug, error, info, warn};
 
 pub enum Job {
-    Analyze { inode: u64, path: PathBuf },
+    /// `id` is the job's row in the jobs table, see `enqueue`
+    Analyze { id: i64, inode: u64, path: PathBuf },
 }
 
-#[derive(Debug, serde::Serialize)]
-struct TodoItem {
-    line: usize,
-    content: String,
-    file: String,
+impl Job {
+    pub fn inode(&self) -> u64 {
+        match self {
+            Job::Analyze { inode, .. } => *inode,
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Job::Analyze { path, .. } => path.clone(),
+        }
+    }
+
+    pub fn set_path(&mut self, new_path: PathBuf) {
+        match self {
+            Job::Analyze { path, .. } => *path = new_path,
+        }
+    }
 }
 
 // Heuristic Tags
@@ -40,50 +63,304 @@ fn is_binary(data: &[u8]) -> bool {
     data.iter().take(1024).any(|&b| b == 0)
 }
 
+/// Text files at least this long get a summary too (`notes.md.summary`).
+pub const SUMMARY_MIN_CHARS: usize = 2000;
+
+/// Chunks embedded per document; the rest of a huge file adds little for search.
+const MAX_CHUNKS: usize = 512;
+
 pub struct Worker {
-    receiver: Receiver<Job>,
+    queue: JobQueue,
     db_path: PathBuf,
+    embeddings: EmbeddingsConfig,
+    throttle: WorkerConfig,
+    ai: AiConfig,
+    transcription: TranscriptionConfig,
+    lint: LintConfig,
+    notifier: Notifier,
+}
+
+// Optional analysis stages, loaded once per worker thread
+struct Stages {
+    summarizer: Option<Summarizer>,
+    embedder: Option<Embedder>,
+    transcriber: Option<Transcriber>,
+    chunk_chars: usize,
+    rules: Rules,
+    lint: bool,
 }
 
 impl Worker {
-    pub fn new(receiver: Receiver<Job>, db_path: PathBuf) -> Self {
-        Self { receiver, db_path }
+    pub fn new(queue: JobQueue, db_path: PathBuf, config: &Config) -> Self {
+        Self { queue, db_path, embeddings: config.embeddings.clone(), throttle: config.worker.clone(), ai: config.ai.clone(), transcription: config.transcription.clone(), lint: config.lint.clone(), notifier: Notifier::new(&config.notifications) }
     }
 
     pub fn start(self) {
-        let Worker { receiver, db_path } = self;
+        let Worker { queue, db_path, embeddings, throttle, ai, transcription, lint, notifier } = self;
         thread::spawn(move || {
+            crate::throttle::lower_priority(throttle.nice, throttle.io_priority);
+            let mut limiter = RateLimiter::new(throttle.max_bytes_per_sec);
+
             // Open DB in this thread
             let db = match Database::new(&db_path) {
                 Ok(d) => d,
                 Err(e) => {
-                    eprintln!("[Worker] Failed to open DB: {}", e);
+                    error!(error = %e, "failed to open DB");
                     return;
                 }
             };
+            let summarizer = match Summarizer::new(&ai) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!(error = %e, "summarizer unavailable");
+                    None
+                }
+            };
+            let embedder = if embeddings.enabled {
+                Embedder::new().map_err(|e| warn!(error = %e, "embedder unavailable")).ok()
+            } else {
+                None
+            };
+            let transcriber = transcription.command.as_deref()
+                .and_then(|command| Transcriber::new(command).map_err(|e| warn!(error = %e, "transcriber unavailable")).ok());
+            let rules = Rules::load().unwrap_or_else(|e| {
+                warn!("organizer rules ignored: {:#}", e);
+                Rules::default()
+            });
+            let stages = Stages { summarizer, embedder, transcriber, chunk_chars: embeddings.chunk_chars, rules, lint: lint.enabled };
 
-            for job in receiver {
-                match job {
-                    Job::Analyze { inode, path } => Self::process_analyze(&db, inode, path),
+            // Left over from the previous run (crash, unmount with a backlog)
+            let pending = db.pending_jobs().unwrap_or_default();
+            if !pending.is_empty() {
+                info!(jobs = pending.len(), "resuming unfinished jobs");
+            }
+            // From another thread: bulk pushes wait for room, which only this one makes
+            let resume_queue = queue.clone();
+            let resume_db_path = db_path.clone();
+            thread::spawn(move || {
+                let resume_db = Database::new(&resume_db_path).map_err(|e| error!(error = %e, "failed to open DB")).ok();
+                for (id, inode, path) in pending {
+                    if !resume_queue.push(Job::Analyze { id, inode, path: PathBuf::from(path) }, Priority::Bulk) {
+                        // Folded into the job already pending for this inode
+                        if let Some(db) = &resume_db {
+                            let _ = db.finish_job(id);
+                        }
+                    }
+                }
+            });
+
+            loop {
+                match queue.pop() {
+                    Job::Analyze { id, inode, path } => {
+                        // The file may have been deleted or moved since it was queued
+                        let mut outcome = Ok(());
+                        if let Ok(metadata) = std::fs::metadata(&path) {
+                            limiter.take(metadata.len());
+                            if metadata.is_file() {
+                                let mtime = crate::convert::mtime_key(&metadata) / 1_000_000_000;
+                                let _ = db.set_file_info(inode, metadata.len(), mtime, crate::filetype::detect(&path));
+                            }
+                            crate::scrub::record(&db, inode, &path);
+                            let tags_before = db.get_known_tags(inode).unwrap_or_default();
+                            let flagged_before = db.was_flagged(inode).unwrap_or(false);
+                            // A panicking decoder fails this job instead of killing the worker
+                            outcome = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                                Self::process_analyze(&db, &stages, inode, path.clone());
+                                Self::organize(&db, &stages, inode, &path);
+                            }));
+                            Self::notify(&db, &notifier, inode, &tags_before, flagged_before);
+                            crate::materialize::update(&db, inode, &path);
+                        }
+                        let _ = match outcome {
+                            Ok(()) => db.finish_job(id),
+                            Err(panic) => {
+                                let message = panic.downcast_ref::<&str>().map(|s| s.to_string())
+                                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                                    .unwrap_or_else(|| "analysis panicked".to_string());
+                                db.fail_job(id, &message)
+                            }
+                        };
+                        queue.finish();
+                    }
                 }
             }
         });
     }
 
-    fn process_analyze(db: &Database, inode: u64, path: PathBuf) {
-        // Log silently or use `log` crate in prod
-        // println!("[Worker] Analyzing file: {:?} (Inode: {})", path, inode);
+    // Desktop notification of what this analysis added, see notifications.rs
+    fn notify(db: &Database, notifier: &Notifier, inode: u64, tags_before: &[String], flagged_before: bool) {
+        let Ok(Some(rel_path)) = db.get_path(inode) else { return };
+        let new_tags: Vec<String> = db.get_file_tags(inode).unwrap_or_default().into_iter()
+            .filter(|tag| tag != "sensitive" && !tags_before.contains(tag))
+            .collect();
+        let flagged = !flagged_before && db.is_sensitive(inode).unwrap_or(false);
+        notifier.analyzed(&rel_path, &new_tags, flagged);
+    }
+
+    // Listed in `.magic/proposals/`; nothing moves until the proposal is accepted through the mount
+    fn organize(db: &Database, stages: &Stages, inode: u64, path: &Path) {
+        let Ok(Some(rel_path)) = db.get_path(inode) else { return };
+        match stages.rules.destination(db, inode, &rel_path, path) {
+            Some(destination) => {
+                let _ = db.set_proposal(inode, &destination);
+            }
+            // Moved by hand since, or the rules changed
+            None => {
+                let _ = db.delete_proposal(inode);
+            }
+        }
+    }
+
+    // Surfaced as `<name>.summary`
+    fn summarize(db: &Database, stages: &Stages, inode: u64, path: &Path, text: &str) {
+        let Some(summarizer) = &stages.summarizer else { return };
+        match summarizer.summarize(text) {
+            Ok(summary) => {
+                let _ = db.set_summary(inode, &summary);
+            }
+            Err(e) => warn!(?path, error = %e, "summary failed"),
+        }
+    }
+
+    // Listed in `.magic/rename_suggestions/` when the current name is a generic one
+    fn suggest_name(db: &Database, inode: u64, path: &Path, suggestion: Option<String>) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !crate::naming::is_generic(&name) {
+            return;
+        }
+        if let Some(suggestion) = suggestion.filter(|s| *s != name) {
+            let _ = db.set_rename_suggestion(inode, &suggestion);
+        }
+    }
+
+    // `lang:de`, browsable under `.magic/tags/`
+    fn tag_language(db: &Database, inode: u64, text: &str) {
+        if let Some(code) = crate::lang::detect(text) {
+            let _ = db.add_tag(inode, &format!("lang:{}", code));
+        }
+    }
+
+    // `user.eidetic.wordcount` and friends, see reading.rs
+    fn count_words(db: &Database, inode: u64, text: &str) {
+        let (words, lines) = crate::reading::count(text);
+        let _ = db.set_text_stats(inode, words, lines);
+    }
+
+    // Surfaced as `<name>.md.lint`; the bundled word list is English only
+    fn proofread(db: &Database, inode: u64, path: &Path, text: &str) {
+        // Findings from before an edit changed the language don't stay behind
+        if !crate::lint::is_prose(&path.to_string_lossy()) || crate::lang::detect(text).is_some_and(|code| code != "en") {
+            let _ = db.set_lint_findings(inode, &[]);
+            return;
+        }
+        let _ = db.set_lint_findings(inode, &crate::lint::check(text));
+    }
+
+    // Surfaced as `<name>.m4a.txt`, and searchable like any text document
+    fn transcribe(db: &Database, stages: &Stages, inode: u64, path: &Path) {
+        let Some(transcriber) = &stages.transcriber else { return };
+        match transcriber.transcribe(path) {
+            Ok(text) => {
+                let _ = db.set_transcript(inode, &text);
+                Self::embed(db, stages, inode, &text);
+                Self::tag_language(db, inode, &text);
+            }
+            Err(e) => warn!(?path, error = %e, "transcript failed"),
+        }
+    }
+
+    // `sensitive` tag and `.magic/sensitive_report.md`
+    fn scan_sensitive(db: &Database, inode: u64, text: &str) {
+        let _ = db.set_sensitive_findings(inode, &crate::sensitive::scan(text));
+    }
+
+    // `person:alice-smith`, `org:acme`, `date:2024-03-05`
+    fn tag_entities(db: &Database, inode: u64, text: &str) {
+        for tag in crate::entities::tags(text) {
+            let _ = db.add_tag(inode, &tag);
+        }
+    }
+
+    // Chunk embeddings for semantic search, when the stage is enabled
+    fn embed(db: &Database, stages: &Stages, inode: u64, text: &str) {
+        let Some(embedder) = &stages.embedder else { return };
+        let chunks: anyhow::Result<Vec<(usize, Vec<f32>)>> = crate::model::chunk_text(text, stages.chunk_chars)
+            .into_iter()
+            .take(MAX_CHUNKS)
+            .map(|(start, chunk)| Ok((start, embedder.embed(chunk)?)))
+            .collect();
+        if let Err(e) = chunks.and_then(|chunks| Ok(db.set_embeddings(inode, Embedder::MODEL, &chunks)?)) {
+            warn!(inode, error = %e, "embeddings failed");
+        }
+    }
+
+    fn process_analyze(db: &Database, stages: &Stages, inode: u64, path: PathBuf) {
+        debug!(?path, inode, "analyzing");
         
         // Check MIME / Content
         let _path_str = path.to_string_lossy().to_string();
         let ext = path.extension().unwrap_or_default().to_string_lossy().to_string().to_lowercase();
         
+        // 0. Photo Metadata (EXIF capture date / camera / lens for .magic/photos)
+        if crate::exif::PHOTO_FORMATS.contains(&ext.as_str()) {
+            match crate::exif::read_photo(&path) {
+                Ok(Some(info)) => {
+                    let _ = db.set_photo_metadata(inode, info.taken.as_deref(), info.camera.as_deref(), info.lens.as_deref());
+                    Self::suggest_name(db, inode, &path, crate::naming::from_photo(&path, info.taken.as_deref(), info.camera.as_deref()));
+                }
+                Ok(None) => {}
+                Err(e) => warn!(?path, error = %e, "failed to read EXIF"),
+            }
+        }
+
+        // Music Tags (artist / album / title for .magic/music)
+        let mut song = false;
+        if crate::music::MUSIC_FORMATS.contains(&ext.as_str()) {
+            match crate::music::read_track(&path) {
+                Ok(Some(info)) => {
+                    let _ = db.set_music_metadata(inode, info.artist.as_deref(), info.album.as_deref(), info.title.as_deref());
+                    song = info.artist.is_some();
+                }
+                Ok(None) => {}
+                Err(e) => warn!(?path, error = %e, "failed to read music tags"),
+            }
+        }
+
+        // Transcripts of recordings (surfaced as `<name>.m4a.txt`)
+        if crate::transcribe::AUDIO_FORMATS.contains(&ext.as_str()) {
+            if !song {
+                Self::transcribe(db, stages, inode, &path);
+            }
+            return;
+        }
+
+        // PDF Summaries (surfaced as `<name>.pdf.summary`)
+        if ext == "pdf" {
+            let text = match crate::convert::pdf_text(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    warn!(?path, error = %e, "failed to extract PDF text");
+                    return;
+                }
+            };
+            Self::count_words(db, inode, &text);
+            Self::summarize(db, stages, inode, &path, &text);
+            Self::embed(db, stages, inode, &text);
+            Self::suggest_name(db, inode, &path, crate::naming::from_text(&path, &text));
+            Self::tag_language(db, inode, &text);
+            Self::tag_entities(db, inode, &text);
+            Self::scan_sensitive(db, inode, &text);
+            return;
+        }
+
         // 1. Image Check
-        if ["jpg", "jpeg", "png", "webp", "gif"].contains(&ext.as_str()) {
-             // println!("[Worker] Image detected: {:?}", path);
-             if let Ok(dims) = image::image_dimensions(&path) {
-                 // println!("[Worker] Image Dimensions: {}x{}", dims.0, dims.1);
+        if crate::phash::FORMATS.contains(&ext.as_str()) {
+             if image::image_dimensions(&path).is_ok() {
                  let _ = db.add_tag(inode, "image");
+                 match crate::phash::phash(&path) {
+                     Ok(hash) => { let _ = db.set_image_hash(inode, hash); }
+                     Err(e) => warn!(?path, error = %e, "pHash failed"),
+                 }
              }
              return;
         }
@@ -97,66 +374,42 @@ impl Worker {
                   if n > 0 && !is_binary(&buffer[..n]) {
                       // It's likely text! parse it fully
                       if let Ok(text) = std::fs::read_to_string(&path) {
-                           println!("[Worker] Analyzing Text File ({} chars): {:?}", text.len(), path);
+                           debug!(?path, chars = text.len(), "analyzing text");
+                           Self::count_words(db, inode, &text);
+                           Self::embed(db, stages, inode, &text);
+                           Self::suggest_name(db, inode, &path, crate::naming::from_text(&path, &text));
+                           Self::tag_language(db, inode, &text);
+                           Self::tag_entities(db, inode, &text);
+                           Self::scan_sensitive(db, inode, &text);
+
+                           if text.chars().count() >= SUMMARY_MIN_CHARS {
+                               Self::summarize(db, stages, inode, &path, &text);
+                           }
                            
                            // Run Tagger
                            let tags = guess_tags(&text);
                            if !tags.is_empty() {
-                               println!("[Tag] Autotags: {:?}", tags);
+                               info!(?path, ?tags, "auto-tagged");
                                for tag in tags {
                                    let _ = db.add_tag(inode, &tag);
                                }
                            }
                            
-                           // Run Todo Extraction
-                           let mut todos = Vec::new();
-                           for (i, line) in text.lines().enumerate() {
-                               if line.contains("TODO") || line.contains("FIXME") {
-                                   todos.push(TodoItem {
-                                       line: i + 1,
-                                       content: line.trim().to_string(),
-                                       file: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
-                                   });
+                           if crate::links::is_note(&path.to_string_lossy()) {
+                               if let Ok(Some(rel_path)) = db.get_path(inode) {
+                                   let _ = db.set_links(inode, &crate::links::extract(&rel_path, &text));
                                }
                            }
-                           
-                           // Run Summarizer (if PDF or long text)
-                           if ext == "pdf" { 
-                               // ... existing PDF logic ...
-                           }
-                           
-                           // Auto-Organizer Logic (Phase 9)
-                           let name_str = path.file_name().unwrap().to_string_lossy().to_string();
-                           if name_str.to_lowercase().contains("invoice") {
-                               let target_dir = path.parent().unwrap().join("Finance");
-                               if !target_dir.exists() {
-                                   let _ = std::fs::create_dir(&target_dir);
-                               }
-                               let target_path = target_dir.join(&name_str);
-                               // println!("[Worker] Auto-Organizing: Moving {:?} to {:?}", path, target_path);
-                               
-                               // Need to update Inodes!
-                               // This is tricky from Worker because we need to update InodeStore which is locked by FS.
-                               // Best way: Send message back to FS? Or just move file on disk and accept temporary desync (FS will recover on readdir)?
-                               // For Prototype: Just move on disk. FS 'lookup' might fail until unmount.
-                               // Correct way: Worker should update DB.
-                               if std::fs::rename(&path, &target_path).is_ok() {
-                                   let _ = db.delete_inode(inode); // Remove old mapping
-                                   // We don't easily know parent inode of 'Finance' without searching.
-                                   // Simplification: Just log it for now as "Proposed Move" or do it only if we can fully update DB.
-                                   // To really make it work, we'd need to recursively resolve path "Finance" to an inode.
-                                   // println!("[Worker] Moved on disk only. Please remount to see changes fully.");
-                               }
-                           }
-                           
-                           if !todos.is_empty() {
-                               if let Ok(json) = serde_json::to_string(&todos) {
-                                   // println!("[Analysis] {}", json); 
-                               }
+
+                           // TODO/FIXME lines for `.magic/todos.md`
+                           let _ = db.set_todos(inode, &crate::todos::extract(&text));
+
+                           if stages.lint {
+                               Self::proofread(db, inode, &path, &text);
                            }
                       }
                   } else {
-                      println!("[Worker] Binary file detected, skipping text analysis: {:?}", path);
+                      debug!(?path, "binary file, skipping text analysis");
                   }
              }
         }
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use anyhow::{bail, Context, Result};
+use magic_wormhole::transfer;
+use magic_wormhole::transit::{self, Abilities, RelayHint, TransitInfo};
+use magic_wormhole::{Code, Wormhole};
+use crate::db::Database;
+use tracing::{info, warn};
+
+// Wormhole
+// `.magic/wormhole/` moves files between machines with the magic-wormhole protocol: a short
+// code, agreed on through a rendezvous server and turned into a key with PAKE, gets the file
+// end-to-end encrypted to the other side, which can be another Eidetic or anything speaking the
+// protocol (`wormhole receive`, Warp, Destiny). Copying a file into `send/` stages it in
+// `.eidetic/wormhole/send/` and allocates a code, shown in `<name>.code` next to it; once the
+// peer has it, the file leaves `send/`. Writing a code to `receive` fetches what it offers into
+// `.eidetic/wormhole/incoming/` first; only a download of the offered size moves on to the
+// RECEIVED_DIR folder of the Source folder, with its sender, time and blake3 checksum recorded
+// in the database and the event log. `.magic/wormhole/inbox/` lists those files, newest first.
+// Transfers run on their own threads; their progress lives only as long as the mount.
+
+/// Words in an allocated code after the channel number, "7-crossover-clockwork".
+const CODE_WORDS: usize = 2;
+
+/// Where received files land, relative to the Source folder.
+pub const RECEIVED_DIR: &str = "Wormhole";
+
+#[derive(Clone)]
+enum Status {
+    /// Asking the rendezvous server for a code
+    Connecting,
+    /// Waiting for the peer to enter the code
+    Waiting(String),
+    Transferring { code: String, sent: u64, total: u64 },
+    Done { code: String, name: String },
+    /// Verified and in the inbox
+    Received(Arrival),
+    Failed { code: Option<String>, error: String },
+}
+
+#[derive(Clone)]
+struct Arrival {
+    code: String,
+    name: String,
+    sender: String,
+    checksum: String,
+}
+
+// Keyed by the staged file's name
+static SENDS: Mutex<BTreeMap<String, Status>> = Mutex::new(BTreeMap::new());
+
+// In the order the codes were entered
+static RECEIVES: Mutex<Vec<Status>> = Mutex::new(Vec::new());
+
+/// Where files copied into `send/` are kept until they're picked up.
+pub fn outgoing_dir(source_path: &Path) -> PathBuf {
+    source_path.join(".eidetic/wormhole/send")
+}
+
+/// Names of the files waiting in `send/`.
+pub fn outgoing(source_path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(outgoing_dir(source_path)).into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Offers a staged file under a new code, unless that's under way already.
+pub fn send(source_path: &Path, name: &str) {
+    {
+        let mut sends = SENDS.lock().unwrap();
+        if sends.get(name).is_some_and(|status| !matches!(status, Status::Failed { .. })) {
+            return;
+        }
+        sends.insert(name.to_string(), Status::Connecting);
+    }
+    let (path, name) = (outgoing_dir(source_path).join(name), name.to_string());
+    thread::spawn(move || {
+        let set = |status: Status| {
+            SENDS.lock().unwrap().insert(name.clone(), status);
+        };
+        match async_std::task::block_on(offer(&path, &name, set)) {
+            Ok(code) => {
+                info!(%name, "wormhole send complete");
+                let _ = std::fs::remove_file(&path);
+                SENDS.lock().unwrap().insert(name.clone(), Status::Done { code, name: name.clone() });
+            }
+            Err(e) => {
+                warn!(%name, error = %e, "wormhole send failed");
+                let mut sends = SENDS.lock().unwrap();
+                let code = match sends.get(&name) {
+                    Some(Status::Waiting(code) | Status::Transferring { code, .. }) => Some(code.clone()),
+                    _ => None,
+                };
+                sends.insert(name.clone(), Status::Failed { code, error: e.to_string() });
+            }
+        }
+    });
+}
+
+/// Forgets a staged file that was removed from `send/`; a transfer in flight fails on its own.
+pub fn cancel(source_path: &Path, name: &str) -> std::io::Result<()> {
+    SENDS.lock().unwrap().remove(name);
+    std::fs::remove_file(outgoing_dir(source_path).join(name))
+}
+
+// Allocates a code, waits for the peer and sends the file; returns the code
+async fn offer(path: &Path, name: &str, set: impl Fn(Status)) -> Result<String> {
+    crate::http::ensure_online()?;
+    let (welcome, connecting) = Wormhole::connect_without_code(transfer::APP_CONFIG, CODE_WORDS).await?;
+    let code = welcome.code.0.clone();
+    info!(%name, %code, "wormhole code allocated");
+    set(Status::Waiting(code.clone()));
+    let wormhole = connecting.await?;
+
+    let mut file = async_std::fs::File::open(path).await.with_context(|| format!("{} is gone", name))?;
+    let total = file.metadata().await?.len();
+    set(Status::Transferring { code: code.clone(), sent: 0, total });
+    let (key, progress_code) = (name.to_string(), code.clone());
+    let progress = move |sent, total| {
+        SENDS.lock().unwrap().insert(key.clone(), Status::Transferring { code: progress_code.clone(), sent, total });
+    };
+    transfer::send_file(wormhole, relay_hints()?, &mut file, name, total, Abilities::ALL_ABILITIES, |_, _| {}, progress, std::future::pending()).await?;
+    Ok(code)
+}
+
+/// What `<name>.code` says about a staged file, None before it has a code.
+pub fn code_text(name: &str) -> Option<String> {
+    match SENDS.lock().unwrap().get(name)? {
+        Status::Connecting | Status::Done { .. } | Status::Received(_) => None,
+        Status::Waiting(code) => Some(format!("{}\n", code)),
+        Status::Transferring { code, sent, total } => Some(format!("{}\nsending: {} of {}\n", code, crate::space::format_size(*sent), crate::space::format_size(*total))),
+        Status::Failed { code, error } => Some(format!("{}\nfailed: {}\nrm the file and copy it in again to retry\n", code.as_deref().unwrap_or("(no code)"), error)),
+    }
+}
+
+/// Fetches what the peer behind `code` offers into RECEIVED_DIR.
+pub fn receive(source_path: &Path, code: &str) -> Result<()> {
+    let code = code.trim().to_string();
+    // "7-crossover-clockwork": a channel number, then words
+    if !code.split_once('-').is_some_and(|(channel, words)| channel.parse::<u32>().is_ok() && !words.is_empty()) {
+        bail!("{:?} isn't a wormhole code", code);
+    }
+    crate::http::ensure_online()?;
+    let index = {
+        let mut receives = RECEIVES.lock().unwrap();
+        receives.push(Status::Waiting(code.clone()));
+        receives.len() - 1
+    };
+    let source_path = source_path.to_path_buf();
+    thread::spawn(move || {
+        let set = |status: Status| RECEIVES.lock().unwrap()[index] = status;
+        match async_std::task::block_on(fetch(&source_path, &code, index)) {
+            Ok(arrival) => {
+                info!(%code, name = %arrival.name, sender = %arrival.sender, checksum = %arrival.checksum, "wormhole receive verified");
+                set(Status::Received(arrival));
+            }
+            Err(e) => {
+                warn!(%code, error = %e, "wormhole receive failed");
+                set(Status::Failed { code: Some(code), error: e.to_string() });
+            }
+        }
+    });
+    Ok(())
+}
+
+// Connects with the code and downloads the offered file next to the database; only once it
+// checks out against the offer does it move into the inbox and get recorded
+async fn fetch(source_path: &Path, code: &str, index: usize) -> Result<Arrival> {
+    let (_, wormhole) = Wormhole::connect_with_code(transfer::APP_CONFIG, Code(code.to_string())).await?;
+    let request = transfer::request_file(wormhole, relay_hints()?, Abilities::ALL_ABILITIES, std::future::pending())
+        .await?
+        .context("The sender cancelled")?;
+    // Only the name; a path from the peer must not lead anywhere else
+    let offered = request.filename.file_name().map(|name| name.to_string_lossy().to_string()).filter(|name| !crate::scan::ignored(name));
+    let Some(offered) = offered else {
+        let filename = request.filename.clone();
+        request.reject().await?;
+        bail!("Rejected a file named {:?}", filename);
+    };
+    let expected = request.filesize;
+    let incoming = source_path.join(".eidetic/wormhole/incoming");
+    std::fs::create_dir_all(&incoming)?;
+    let partial = incoming.join(format!("{}.part", index));
+    let mut file = async_std::fs::File::create(&partial).await?;
+    let progress_code = code.to_string();
+    let progress = move |sent, total| {
+        RECEIVES.lock().unwrap()[index] = Status::Transferring { code: progress_code.clone(), sent, total };
+    };
+    let mut sender = None;
+    let transit = |info, address: SocketAddr| {
+        sender = Some(match info {
+            TransitInfo::Direct => address.to_string(),
+            _ => format!("{} (relayed)", address),
+        });
+    };
+    let received = match request.accept(transit, progress, &mut file, std::future::pending()).await {
+        Ok(()) => file.sync_all().await.map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+    drop(file);
+    let checksum = match received.and_then(|_| verify(&partial, expected)) {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    let dir = source_path.join(RECEIVED_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = free_path(&dir, &offered);
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    // Appears whole, so the watcher queues it once
+    std::fs::rename(&partial, &path)?;
+    let sender = sender.unwrap_or_else(|| "unknown".to_string());
+    let db = Database::new(source_path.join(".eidetic.db"))?;
+    let inode = db.alloc_path(&format!("{}/{}", RECEIVED_DIR, name))?;
+    db.add_received(inode, code, &sender, expected, &checksum)?;
+    Ok(Arrival { code: code.to_string(), name, sender, checksum })
+}
+
+// The blake3 checksum of a download that is as long as offered. The transfer itself is
+// authenticated, so this catches files cut short or grown on the way to the disk
+fn verify(path: &Path, expected: u64) -> Result<String> {
+    let size = std::fs::metadata(path)?.len();
+    if size != expected {
+        bail!("Got {} of the {} bytes offered", size, expected);
+    }
+    crate::scrub::hash_file(path)
+}
+
+// `name` in `dir`, or "name (2).ext" etc. if that's taken
+fn free_path(dir: &Path, name: &str) -> PathBuf {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    let mut path = dir.join(name);
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{} ({}){}", stem, n, ext));
+        n += 1;
+    }
+    path
+}
+
+/// `.magic/wormhole/receive`: one line per code entered this mount.
+pub fn receive_report() -> String {
+    let mut report = String::from("# Write a wormhole code here to receive a file into the inbox\n");
+    for status in RECEIVES.lock().unwrap().iter() {
+        let line = match status {
+            Status::Connecting => continue,
+            Status::Waiting(code) => format!("{}  connecting", code),
+            Status::Transferring { code, sent, total } => format!("{}  receiving {} of {}", code, crate::space::format_size(*sent), crate::space::format_size(*total)),
+            Status::Done { code, name } => format!("{}  sent {}", code, name),
+            Status::Received(arrival) => format!("{}  received {}/{} from {}, blake3 {}", arrival.code, RECEIVED_DIR, arrival.name, arrival.sender, arrival.checksum),
+            Status::Failed { code, error } => format!("{}  failed: {}", code.as_deref().unwrap_or_default(), error),
+        };
+        report.push_str(&line);
+        report.push('\n');
+    }
+    report
+}
+
+fn relay_hints() -> Result<Vec<RelayHint>> {
+    Ok(vec![RelayHint::from_urls(None, [transit::DEFAULT_RELAY_SERVER.parse()?])])
+}